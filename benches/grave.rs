@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use mcts::games::breakthrough::Breakthrough;
+use mcts::strategies::mcts::{backprop, select, simulate, SearchConfig, Strategy, TreeSearch};
+use mcts::strategies::Search;
+
+type G = Breakthrough<6, 4>;
+
+// Uniform playouts, kept short, so that the cost of selection dominates
+#[derive(Clone, Default)]
+struct Grave;
+
+impl Strategy<G> for Grave {
+    type Select = select::Rave;
+    type Simulate = simulate::Uniform;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+}
+
+fn select_loop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grave");
+    for threshold in [0, 10, 100] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threshold),
+            &threshold,
+            |b, &threshold| {
+                let mut ts = TreeSearch::<G, Grave>::default().config(
+                    SearchConfig::default()
+                        .max_iterations(5000)
+                        .max_playout_depth(4)
+                        .expand_threshold(0)
+                        .select(select::Rave::default().threshold(threshold))
+                        .seed(0),
+                );
+                b.iter(|| {
+                    ts.choose_action(&Default::default());
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, select_loop);
+criterion_main!(benches);
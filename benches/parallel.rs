@@ -0,0 +1,30 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use mcts::games::breakthrough::Breakthrough;
+use mcts::strategies::mcts::{strategy, SearchConfig, TreeParallel, TreeSearch};
+use mcts::strategies::Search;
+
+type Game = Breakthrough<6, 6>;
+
+fn bench_threads(c: &mut Criterion, threads: usize) {
+    c.bench_function(&format!("tree_parallel/breakthrough/{threads}"), |b| {
+        b.iter(|| {
+            let mut ts = TreeSearch::<Game, strategy::Ucb1>::default().config(
+                SearchConfig::default()
+                    .max_iterations(4000)
+                    .tree_parallel(TreeParallel::default().threads(threads))
+                    .seed(0),
+            );
+            ts.choose_action(&Default::default())
+        })
+    });
+}
+
+fn tree_parallel(c: &mut Criterion) {
+    for threads in [1, 2, 4] {
+        bench_threads(c, threads);
+    }
+}
+
+criterion_group!(benches, tree_parallel);
+criterion_main!(benches);
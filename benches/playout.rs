@@ -0,0 +1,54 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use mcts::game::Game;
+use mcts::games::breakthrough::Breakthrough;
+use mcts::games::ttt::TicTacToe;
+use mcts::strategies::mcts::simulate::{Batched, SimulateStrategy, Uniform};
+use mcts::strategies::mcts::TreeStats;
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+
+fn bench_game<G: Game, S: SimulateStrategy<G>>(c: &mut Criterion, name: &str) {
+    let stats = TreeStats::<G>::default();
+    let mut simulate = S::default();
+    let mut rng = SmallRng::seed_from_u64(0);
+    c.bench_function(name, |b| {
+        b.iter(|| simulate.playout(G::S::default(), usize::MAX, &stats, 0, &mut rng))
+    });
+}
+
+// Picking one random legal action, by generating them all or by sampling
+fn bench_sample<G: Game>(c: &mut Criterion, name: &str) {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut state = G::S::default();
+    let mut actions = Vec::new();
+    for _ in 0..4 {
+        actions.clear();
+        G::generate_actions(&state, &mut actions);
+        state = G::apply(state, &actions[rng.gen_range(0..actions.len())]);
+    }
+    c.bench_function(&format!("sample/{name}/generate"), |b| {
+        b.iter(|| {
+            actions.clear();
+            G::generate_actions(&state, &mut actions);
+            let word = rng.gen::<u32>();
+            actions[((word as u64 * actions.len() as u64) >> 32) as usize].clone()
+        })
+    });
+    c.bench_function(&format!("sample/{name}/sample_action"), |b| {
+        b.iter(|| G::sample_action(&state, rng.gen()))
+    });
+}
+
+fn playout(c: &mut Criterion) {
+    bench_game::<TicTacToe, Uniform>(c, "playout/ttt/uniform");
+    bench_game::<TicTacToe, Batched>(c, "playout/ttt/batched");
+    bench_game::<Breakthrough<6, 4>, Uniform>(c, "playout/breakthrough/uniform");
+    bench_game::<Breakthrough<6, 4>, Batched>(c, "playout/breakthrough/batched");
+    bench_sample::<TicTacToe>(c, "ttt");
+    bench_sample::<Breakthrough<6, 4>>(c, "breakthrough");
+}
+
+criterion_group!(benches, playout);
+criterion_main!(benches);
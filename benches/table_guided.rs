@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use mcts::games::traffic_lights::TrafficLights;
+use mcts::strategies::mcts::{backprop, select, simulate, SearchConfig, Strategy, TreeSearch};
+use mcts::strategies::Search;
+
+type G = TrafficLights;
+
+// TrafficLights transposes heavily, so the table is dense and most playout
+// positions have a node to consult
+#[derive(Clone, Default)]
+struct Guided;
+
+impl Strategy<G> for Guided {
+    type Select = select::Ucb1;
+    type Simulate = simulate::TableGuided<G>;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+}
+
+fn table_guided(c: &mut Criterion) {
+    let mut group = c.benchmark_group("table_guided");
+    for probability in [0., 0.5, 1.] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(probability),
+            &probability,
+            |b, &probability| {
+                let mut ts = TreeSearch::<G, Guided>::default().config(
+                    SearchConfig::default()
+                        .max_iterations(5000)
+                        .use_transpositions(true)
+                        .simulate(simulate::TableGuided::new().probability(probability))
+                        .seed(0),
+                );
+                b.iter(|| {
+                    ts.choose_action(&Default::default());
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, table_guided);
+criterion_main!(benches);
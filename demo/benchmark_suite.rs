@@ -0,0 +1,99 @@
+/// Play a fixed set of matchups and compare the results with the baselines
+/// stored in `benchmarks/baselines.json`, exiting with an error when playing
+/// strength has regressed significantly.
+///
+///     cargo run --release --bin benchmark-suite
+///     cargo run --release --bin benchmark-suite -- --update
+///
+/// See `mcts::benchmark`.
+use clap::Parser;
+use std::path::PathBuf;
+
+use mcts::benchmark::{self, Baselines, Matchup, Outcome, Status};
+use mcts::game::Game;
+use mcts::games::breakthrough::Breakthrough;
+use mcts::games::traffic_lights::TrafficLights;
+use mcts::games::ttt::TicTacToe;
+use mcts::tournament::ManifestError;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[arg(long, default_value = "benchmarks/baselines.json")]
+    baselines: PathBuf,
+
+    /// Record the results as the new baselines
+    #[arg(long)]
+    update: bool,
+
+    /// Only run matchups whose id contains this
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// The z statistic beyond which a change is significant
+    #[arg(long, default_value_t = 1.96)]
+    threshold: f64,
+}
+
+// The benchmark matrix: (game, candidate, opponent, iterations)
+const MATCHUPS: &[(&str, &str, &str, usize)] = &[
+    ("ttt", "ucb1_mast", "ucb1", 1000),
+    ("ttt", "rave_mast_dm", "ucb1", 1000),
+    ("traffic_lights", "ucb1_tuned", "ucb1", 1000),
+    ("traffic_lights", "ucb1_dm", "ucb1", 1000),
+    ("breakthrough", "ucb1_mast", "ucb1", 1000),
+    ("breakthrough", "rave_mast_dm", "ucb1", 1000),
+];
+
+fn play<G: Game>(matchup: &Matchup) -> Result<Outcome, ManifestError>
+where
+    G::S: std::fmt::Display,
+{
+    matchup.play::<G>(&G::S::default())
+}
+
+fn main() -> Result<(), ManifestError> {
+    color_backtrace::install();
+    let args = Args::parse();
+    let mut baselines = if args.baselines.exists() {
+        Baselines::load(&args.baselines)?
+    } else {
+        Baselines::default()
+    };
+
+    let mut regressions = 0;
+    for &(game, candidate, opponent, iterations) in MATCHUPS {
+        let matchup = Matchup::new(game, candidate, opponent, iterations);
+        if args
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !matchup.id.contains(filter))
+        {
+            continue;
+        }
+        let outcome = match game {
+            "ttt" => play::<TicTacToe>(&matchup)?,
+            "traffic_lights" => play::<TrafficLights>(&matchup)?,
+            "breakthrough" => play::<Breakthrough<6, 4>>(&matchup)?,
+            game => unreachable!("unknown game: {game}"),
+        };
+
+        let comparison =
+            benchmark::compare(outcome.clone(), baselines.get(&matchup.id), args.threshold);
+        println!("{comparison}");
+        regressions += (comparison.status == Status::Regressed) as usize;
+        if args.update {
+            baselines.update(outcome);
+        }
+    }
+
+    if args.update {
+        baselines.write(&args.baselines)?;
+        println!("updated {}", args.baselines.display());
+    } else if regressions > 0 {
+        eprintln!("{regressions} regressions");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
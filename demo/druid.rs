@@ -132,4 +132,8 @@ fn main() {
         &<Druid as Game>::S::default(),
         Verbosity::Verbose,
     );
+
+    if cfg!(feature = "profile-playouts") {
+        eprintln!("{}", mcts::strategies::mcts::profile::report());
+    }
 }
@@ -0,0 +1,13 @@
+//! Play Gonnect over the text protocol of `mcts::engine` on stdin and stdout.
+
+use mcts::engine::Engine;
+use mcts::games::gonnect::Gonnect;
+use mcts::strategies::mcts::{strategy, SearchConfig};
+
+fn main() -> std::io::Result<()> {
+    let config = SearchConfig::new()
+        .name("mcts[ucb1]")
+        .max_time(std::time::Duration::from_secs(5));
+    let mut engine = Engine::<Gonnect<8>, strategy::Ucb1>::new(config);
+    engine.run(std::io::stdin().lock(), std::io::stdout())
+}
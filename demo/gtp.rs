@@ -0,0 +1,47 @@
+//! Play Atari Go or Gonnect over GTP on stdin and stdout, for example from
+//! GoGui:
+//!
+//!     gogui -program "cargo run --release --bin gtp -- --game gonnect" -size 8
+use clap::Parser;
+use std::time::Duration;
+
+use mcts::games::atarigo::AtariGo;
+use mcts::games::gonnect::Gonnect;
+use mcts::gtp::{Gtp, GtpGame};
+use mcts::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// atarigo or gonnect
+    #[arg(long, default_value = "gonnect")]
+    game: String,
+
+    /// Seconds per move
+    #[arg(long, default_value_t = 5.)]
+    time: f64,
+}
+
+fn play<G: GtpGame>(time: f64) -> std::io::Result<()>
+where
+    G::S: std::fmt::Display,
+{
+    let search = TreeSearch::<G, strategy::Ucb1>::new().config(
+        SearchConfig::new()
+            .name("mcts[ucb1]")
+            .max_time(Duration::from_secs_f64(time)),
+    );
+    Gtp::new(search).run(std::io::stdin().lock(), std::io::stdout())
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    match args.game.as_str() {
+        "atarigo" => play::<AtariGo<8>>(args.time),
+        "gonnect" => play::<Gonnect<8>>(args.time),
+        game => {
+            eprintln!("unknown game: {game}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 /// This benchmark utility is intended to be invoke by
 /// [SMAC3](https://github.com/automl/SMAC3) for hyperparameter optimization. We
 /// Could do a grid search, but we'll try to do something smarter to save time.
+/// See `mcts::tune` for a tuner which runs without SMAC.
 use clap::Parser;
 use mcts::strategies::mcts::select::RaveSchedule;
 use mcts::strategies::mcts::select::RaveUcb;
@@ -62,6 +63,10 @@ struct Args {
     #[arg(long)]
     q_init: String,
 
+    /// The value for a `Fixed` q_init, or the reduction for `ParentMinus`
+    #[arg(long)]
+    fpu: Option<f64>,
+
     #[arg(long)]
     final_action: String,
 
@@ -89,6 +94,14 @@ struct Args {
 
 ////////////////////////////////////////////////////////////////////////////////////////
 
+fn q_init(args: &Args) -> QInit {
+    match (args.q_init.as_str(), args.fpu) {
+        ("Fixed", Some(value)) => QInit::Fixed(value),
+        ("ParentMinus", Some(reduction)) => QInit::ParentMinus(reduction),
+        (q_init, _) => QInit::from_str(q_init).unwrap(),
+    }
+}
+
 #[derive(Copy, Clone, Default)]
 struct CandidateStrategy<FinalAction: SelectStrategy<G>>(PhantomData<FinalAction>);
 
@@ -131,7 +144,7 @@ impl<FinalAction: SelectStrategy<G>> CandidateStrategy<FinalAction> {
             _ => unreachable!(),
         };
         Self::config()
-            .q_init(QInit::from_str(args.q_init.as_str()).unwrap())
+            .q_init(q_init(args))
             .use_transpositions(true)
             .select(select::Rave::new(args.threshold.unwrap(), schedule, ucb))
             .simulate(
@@ -0,0 +1,85 @@
+/// Scaffold a new game from `games::template`, with conformance tests and a
+/// demo binary, and register it with the crate.
+///
+///     cargo run --bin new_game -- dots_and_boxes
+///
+/// This writes `src/games/dots_and_boxes.rs` and `demo/dots_and_boxes.rs`,
+/// declares the module in `src/games/mod.rs`, and adds the binary to
+/// `Cargo.toml`. Existing files are never overwritten.
+use clap::Parser;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcts::games::template::{scaffold, Scaffold};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// The module name, in lower snake case
+    name: String,
+
+    /// The root of the crate
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+}
+
+fn create(path: &Path, contents: &str) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .and_then(|mut file| io::Write::write_all(&mut file, contents.as_bytes()))
+}
+
+// Insert the module declaration among the others, in order
+fn declare_module(path: &Path, name: &str) -> io::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let declaration = format!("pub mod {name};");
+    let mut lines = source.lines().map(String::from).collect::<Vec<_>>();
+    let position = lines
+        .iter()
+        .position(|line| !line.starts_with("pub mod ") || *line > declaration)
+        .unwrap_or(lines.len());
+    lines.insert(position, declaration);
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+fn register_binary(path: &Path, name: &str) -> io::Result<()> {
+    let mut manifest = fs::read_to_string(path)?;
+    let entry = format!("[[bin]]\nname = \"{name}\"\npath = \"demo/{name}.rs\"\n\n");
+    // After the last binary, or at the end
+    let position = manifest
+        .rfind("[[bin]]")
+        .and_then(|last| manifest[last..].find("\n\n").map(|end| last + end + 2))
+        .unwrap_or(manifest.len());
+    manifest.insert_str(position, &entry);
+    fs::write(path, manifest)
+}
+
+fn write(root: &Path, scaffold: &Scaffold) -> io::Result<()> {
+    let name = &scaffold.name;
+    create(&root.join(format!("src/games/{name}.rs")), &scaffold.module)?;
+    create(&root.join(format!("demo/{name}.rs")), &scaffold.demo)?;
+    declare_module(&root.join("src/games/mod.rs"), name)?;
+    register_binary(&root.join("Cargo.toml"), name)
+}
+
+fn main() {
+    color_backtrace::install();
+    let args = Args::parse();
+
+    let Some(scaffold) = scaffold(&args.name) else {
+        eprintln!("invalid name: {} (expected lower snake case)", args.name);
+        std::process::exit(1);
+    };
+    if let Err(err) = write(&args.root, &scaffold) {
+        eprintln!("failed to scaffold {}: {err}", args.name);
+        std::process::exit(1);
+    }
+
+    println!(
+        "created games::{} ({}); run `cargo test games::{}` and `cargo run --bin {}`",
+        scaffold.name, scaffold.type_name, scaffold.name, scaffold.name
+    );
+}
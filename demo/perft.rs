@@ -0,0 +1,70 @@
+/// Count the states reachable at each depth from the initial state, to check
+/// a game's move generator against known counts.
+///
+///     cargo run --release --bin perft -- --game ttt --depth 6 --unique
+///
+/// With `--divide`, the count at the final depth is also broken down by the
+/// first action.
+use clap::Parser;
+use std::time::Instant;
+
+use mcts::game::Game;
+use mcts::games::breakthrough::Breakthrough;
+use mcts::games::druid::Druid;
+use mcts::games::traffic_lights::TrafficLights;
+use mcts::games::ttt::TicTacToe;
+use mcts::util::{perft, perft_divide, perft_unique};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[arg(long)]
+    game: String,
+
+    #[arg(long, default_value_t = 4)]
+    depth: usize,
+
+    /// Also count distinct states, by zobrist hash
+    #[arg(long)]
+    unique: bool,
+
+    /// Break down the final count by the first action
+    #[arg(long)]
+    divide: bool,
+}
+
+fn run<G: Game>(args: &Args) {
+    let init = G::S::default();
+    for depth in 1..=args.depth {
+        let start = Instant::now();
+        let count = perft::<G>(&init, depth);
+        let unique = if args.unique {
+            format!(", {} unique", perft_unique::<G>(&init, depth))
+        } else {
+            String::new()
+        };
+        println!("depth {depth}: {count}{unique} ({:?})", start.elapsed());
+    }
+
+    if args.divide {
+        for (action, count) in perft_divide::<G>(&init, args.depth) {
+            println!("{}: {count}", G::notation(&init, &action));
+        }
+    }
+}
+
+fn main() {
+    color_backtrace::install();
+    let args = Args::parse();
+
+    match args.game.as_str() {
+        "ttt" => run::<TicTacToe>(&args),
+        "traffic_lights" => run::<TrafficLights>(&args),
+        "breakthrough" => run::<Breakthrough<6, 4>>(&args),
+        "druid" => run::<Druid>(&args),
+        game => {
+            eprintln!("unknown game: {game}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::time::Duration;
 use mcts::game::Game;
 use mcts::games::nim;
 use mcts::games::ttt;
+use mcts::strategies::bandit::{self, FlatBandit};
 use mcts::strategies::flat_mc::FlatMonteCarloStrategy;
 use mcts::strategies::mcts::backprop;
 use mcts::strategies::mcts::node::QInit;
@@ -47,6 +48,7 @@ fn ucd() {
             .name("mcts[ucb1]+ucd")
             .max_iterations(10_000)
             .use_transpositions(true)
+            .ucd(mcts::strategies::mcts::Ucd::default())
             .q_init(QInit::Infinity)
             .select(select::Ucb1::with_c(0.01f64.sqrt())),
     );
@@ -58,6 +60,7 @@ fn ucd() {
             .max_iterations(10_000)
             .expand_threshold(1)
             .use_transpositions(true)
+            .ucd(mcts::strategies::mcts::Ucd::default())
             .q_init(QInit::Infinity)
             .select(select::Ucb1::with_c(0.01f64.sqrt())),
     );
@@ -193,6 +196,86 @@ fn ucd() {
     );
 }
 
+fn amaf_update() {
+    use mcts::games::traffic_lights::TrafficLights;
+    use mcts::strategies::mcts::BackpropFlags;
+    use mcts::strategies::mcts::{MOVER, PLAYOUT, TREE};
+
+    let make = |name: &str, amaf_update: usize| {
+        let ts: TreeSearch<TrafficLights, strategy::RaveMastDm> = TreeSearch::new().config(
+            SearchConfig::new()
+                .name(name)
+                .expand_threshold(1)
+                .max_iterations(10_000)
+                .q_init(QInit::Win)
+                .select(
+                    select::Rave::default()
+                        .threshold(916)
+                        .ucb(select::RaveUcb::None)
+                        .schedule(select::RaveSchedule::MinMSE { bias: 0.711333 })
+                        .amaf_update(BackpropFlags(amaf_update)),
+                )
+                .simulate(
+                    simulate::DecisiveMove::new()
+                        .inner(simulate::EpsilonGreedy::with_epsilon(0.70)),
+                ),
+        );
+        AnySearch::new(ts)
+    };
+
+    let mut strats = vec![
+        make("rave[tree+playout]", TREE | PLAYOUT),
+        make("rave[tree]", TREE),
+        make("rave[playout]", PLAYOUT),
+        make("rave[tree+playout+mover]", TREE | PLAYOUT | MOVER),
+    ];
+
+    _ = round_robin_multiple::<TrafficLights, AnySearch<_>>(
+        &mut strats,
+        100,
+        &Default::default(),
+        mcts::util::Verbosity::Verbose,
+    );
+}
+
+fn stats_decay() {
+    use mcts::games::breakthrough::Breakthrough;
+    type G = Breakthrough<6, 4>;
+
+    let make = |gamma: f64| {
+        let ts: TreeSearch<G, strategy::RaveMastDm> = TreeSearch::new().config(
+            SearchConfig::new()
+                .name(&format!("rave+mast[decay={gamma}]"))
+                .expand_threshold(1)
+                .max_iterations(5_000)
+                .stats_decay(gamma)
+                .q_init(QInit::Infinity)
+                .select(
+                    select::Rave::default()
+                        .ucb(select::RaveUcb::Ucb1 {
+                            exploration_constant: 0.305949,
+                        })
+                        .threshold(600)
+                        .schedule(select::RaveSchedule::MinMSE { bias: 4.313335 }),
+                )
+                .simulate(
+                    simulate::DecisiveMove::new()
+                        .inner(simulate::EpsilonGreedy::with_epsilon(0.29739)),
+                ),
+        );
+        AnySearch::new(ts)
+    };
+
+    let mut strats = vec![make(1.), make(0.5), make(0.)];
+
+    _ = round_robin_multiple::<G, AnySearch<_>>(
+        &mut strats,
+        100,
+        &Default::default(),
+        mcts::util::Verbosity::Verbose,
+    );
+}
+
 fn traffic_lights() {
     use mcts::games::traffic_lights::TrafficLights;
 
@@ -291,6 +374,41 @@ fn breakthrough() {
     self_play(ts);
 }
 
+/// Ucb1 with and without Breakthrough domain knowledge at equal time.
+fn breakthrough_knowledge() {
+    use mcts::games::breakthrough::{Breakthrough, Knowledge};
+    type G = Breakthrough<6, 6>;
+
+    #[derive(Clone, Default)]
+    struct Ucb1Knowledge;
+
+    impl Strategy<G> for Ucb1Knowledge {
+        type Select = select::Ucb1;
+        type Simulate = Knowledge;
+        type Backprop = backprop::Classic;
+        type FinalAction = select::RobustChild;
+
+        fn friendly_name() -> String {
+            "ucb1+knowledge".into()
+        }
+    }
+
+    let max_time = Duration::from_millis(100);
+    let ucb1: TreeSearch<G, strategy::Ucb1> =
+        TreeSearch::new().config(SearchConfig::new().max_time(max_time));
+    let knowledge: TreeSearch<G, Ucb1Knowledge> =
+        TreeSearch::new().config(SearchConfig::new().max_time(max_time));
+
+    let mut strats = vec![AnySearch::new(ucb1), AnySearch::new(knowledge)];
+
+    _ = round_robin_multiple::<G, AnySearch<_>>(
+        &mut strats,
+        20,
+        &Default::default(),
+        mcts::util::Verbosity::Verbose,
+    );
+}
+
 fn atarigo() {
     use mcts::games::atarigo::AtariGo;
 
@@ -376,10 +494,8 @@ fn expansion_test() {
 
 fn ucb_test() {
     let mut flat = NimFlatMC::new();
-    let mut ucb1 = NimFlatMC::new();
+    let mut ucb1 = FlatBandit::<Nim>::new(bandit::Ucb1::with_c(100f64.sqrt())).samples(50000);
     flat.samples_per_move = 5000;
-    ucb1.samples_per_move = 5000;
-    ucb1.ucb1 = Some(100f64.sqrt());
 
     flat.set_friendly_name("classic");
     ucb1.set_friendly_name("ucb1");
@@ -568,11 +684,14 @@ fn main() {
     traffic_lights();
     knightthrough();
     breakthrough();
+    breakthrough_knowledge();
     gonnect();
     atarigo();
     expansion_test();
     ucb_test();
     ucd();
+    amaf_update();
+    stats_decay();
 
     demo_mcts();
     demo_nim();
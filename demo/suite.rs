@@ -0,0 +1,86 @@
+/// Evaluate every position in a suite file with a fixed search budget and
+/// report the chosen move, value and principal variation for each.
+///
+///     cargo run --release --bin suite -- --game ttt --positions ttt.suite --iterations 10000
+///
+/// See `mcts::suite` for the file format.
+use clap::Parser;
+use std::path::PathBuf;
+
+use mcts::game::Game;
+use mcts::games::breakthrough::Breakthrough;
+use mcts::games::traffic_lights::TrafficLights;
+use mcts::games::ttt::TicTacToe;
+use mcts::suite::{self, Evaluation, SuiteError};
+use mcts::tournament::Participant;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[arg(long)]
+    game: String,
+
+    #[arg(long)]
+    positions: PathBuf,
+
+    #[arg(long, default_value = "ucb1")]
+    preset: String,
+
+    #[arg(long)]
+    iterations: Option<usize>,
+
+    #[arg(long)]
+    time_ms: Option<u64>,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+fn run<G: Game>(args: &Args) -> Result<Vec<Evaluation>, SuiteError>
+where
+    G::S: std::fmt::Display,
+{
+    let positions = suite::load::<G>(&args.positions, &G::S::default())?;
+    let participant = Participant {
+        name: args.preset.clone(),
+        preset: args.preset.clone(),
+        seed: Some(args.seed),
+        max_iterations: args.iterations,
+        max_time_ms: args.time_ms,
+        ..Default::default()
+    };
+    suite::run_participant(&positions, &participant)
+}
+
+fn main() -> Result<(), SuiteError> {
+    color_backtrace::install();
+    let args = Args::parse();
+
+    let evaluations = match args.game.as_str() {
+        "ttt" => run::<TicTacToe>(&args)?,
+        "traffic_lights" => run::<TrafficLights>(&args)?,
+        "breakthrough" => run::<Breakthrough<6, 4>>(&args)?,
+        game => {
+            eprintln!("unknown game: {game}");
+            std::process::exit(1);
+        }
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&evaluations).unwrap());
+    } else {
+        evaluations
+            .iter()
+            .for_each(|evaluation| println!("{evaluation}"));
+        let (correct, total) = suite::score(&evaluations);
+        if total > 0 {
+            println!("{correct}/{total} correct");
+        }
+    }
+
+    Ok(())
+}
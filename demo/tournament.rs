@@ -0,0 +1,60 @@
+/// Run a round robin tournament from a manifest file and write out the
+/// resolved results manifest.
+///
+///     cargo run --release --bin tournament -- --game ttt --manifest m.json --output out.json
+///
+/// Only gonnect supports `"scoring": "margin"` in the manifest.
+use clap::Parser;
+use std::path::PathBuf;
+
+use mcts::game::Game;
+use mcts::games::breakthrough::Breakthrough;
+use mcts::games::gonnect;
+use mcts::games::traffic_lights::TrafficLights;
+use mcts::games::ttt::TicTacToe;
+use mcts::tournament::{self, Manifest, ManifestError, ResultsManifest};
+use mcts::util::Verbosity;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[arg(long)]
+    game: String,
+
+    #[arg(long)]
+    manifest: PathBuf,
+
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+fn run<G: Game>(game: &str, manifest: &Manifest) -> Result<ResultsManifest, ManifestError>
+where
+    G::S: std::fmt::Display,
+{
+    tournament::run::<G>(game, manifest, &Default::default(), Verbosity::Verbose)
+}
+
+fn main() -> Result<(), ManifestError> {
+    color_backtrace::install();
+    let args = Args::parse();
+    let manifest = Manifest::load(&args.manifest)?;
+
+    let results = match args.game.as_str() {
+        "ttt" => run::<TicTacToe>(&args.game, &manifest)?,
+        "traffic_lights" => run::<TrafficLights>(&args.game, &manifest)?,
+        "breakthrough" => run::<Breakthrough<6, 4>>(&args.game, &manifest)?,
+        "gonnect" => run::<gonnect::Gonnect<5>>(&args.game, &manifest)?,
+        game => {
+            eprintln!("unknown game: {game}");
+            std::process::exit(1);
+        }
+    };
+
+    match args.output {
+        Some(path) => results.write(&path)?,
+        None => println!("{}", results.to_json()?),
+    }
+
+    Ok(())
+}
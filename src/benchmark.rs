@@ -0,0 +1,275 @@
+//! Playing strength regression checks against stored baselines.
+//!
+//! A benchmark is a fixed list of matchups, each a candidate preset playing a
+//! reference opponent on some game with a fixed iteration budget. The result
+//! of each matchup is compared with the result recorded in a baselines file,
+//! and flagged when the candidate's score is significantly lower or higher
+//! under a two-proportion z-test. Seeds are fixed, so an unchanged tree
+//! reproduces its baselines exactly.
+//!
+//! ```json
+//! {
+//!   "results": [
+//!     { "id": "ttt/ucb1_mast-vs-ucb1/1000", "wins": 14, "losses": 26, "draws": 0 }
+//!   ]
+//! }
+//! ```
+
+use crate::game::Game;
+use crate::tournament::{self, Manifest, ManifestError, Participant};
+use crate::util::Verbosity;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// A candidate against a reference opponent, both with the same budget.
+#[derive(Clone, Debug)]
+pub struct Matchup {
+    pub id: String,
+    pub candidate: Participant,
+    pub opponent: Participant,
+    // Each round plays one game with each color
+    pub rounds: usize,
+}
+
+impl Matchup {
+    pub fn new(game: &str, candidate: &str, opponent: &str, iterations: usize) -> Self {
+        let participant = |preset: &str, seed| Participant {
+            name: preset.into(),
+            preset: preset.into(),
+            seed: Some(seed),
+            max_iterations: Some(iterations),
+            ..Default::default()
+        };
+        Self {
+            id: format!("{game}/{candidate}-vs-{opponent}/{iterations}"),
+            candidate: participant(candidate, 1),
+            opponent: participant(opponent, 2),
+            rounds: 20,
+        }
+    }
+
+    pub fn rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Play the matchup from `init`.
+    pub fn play<G>(&self, init: &G::S) -> Result<Outcome, ManifestError>
+    where
+        G: Game,
+        G::S: fmt::Display,
+    {
+        let manifest = Manifest {
+            rounds: self.rounds,
+            format: Default::default(),
+            concurrency: None,
+            scoring: Default::default(),
+            variety: None,
+            openings: None,
+            participants: vec![self.candidate.clone(), self.opponent.clone()],
+        };
+        let results = tournament::run::<G>(&self.id, &manifest, init, Verbosity::Silent)?;
+        let candidate = &results.results[0];
+        Ok(Outcome {
+            id: self.id.clone(),
+            wins: candidate.wins,
+            losses: candidate.losses,
+            draws: candidate.draws,
+        })
+    }
+}
+
+/// The candidate's record in one matchup.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub id: String,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Outcome {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// The fraction of points won, counting draws as half.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "+{} -{} ={} ({:.1}%)",
+            self.wins,
+            self.losses,
+            self.draws,
+            100. * self.score()
+        )
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baselines {
+    pub results: Vec<Outcome>,
+}
+
+impl Baselines {
+    pub fn from_json(json: &str) -> Result<Self, ManifestError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, ManifestError> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ManifestError> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Outcome> {
+        self.results.iter().find(|outcome| outcome.id == id)
+    }
+
+    /// Replace the baseline for the outcome's matchup, or add it.
+    pub fn update(&mut self, outcome: Outcome) {
+        match self.results.iter_mut().find(|o| o.id == outcome.id) {
+            Some(baseline) => *baseline = outcome,
+            None => self.results.push(outcome),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    // No baseline was recorded
+    New,
+    Unchanged,
+    Improved,
+    Regressed,
+}
+
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub current: Outcome,
+    pub baseline: Option<Outcome>,
+    // Positive when the current score is higher
+    pub z: f64,
+    pub status: Status,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}: {}", self.status, self.current.id, self.current)?;
+        if let Some(baseline) = &self.baseline {
+            write!(f, ", baseline {baseline}, z {:.2}", self.z)?;
+        }
+        Ok(())
+    }
+}
+
+/// The two-proportion z statistic of the current score against the baseline.
+pub fn z_score(current: &Outcome, baseline: &Outcome) -> f64 {
+    let (n1, n2) = (current.games() as f64, baseline.games() as f64);
+    if n1 == 0. || n2 == 0. {
+        return 0.;
+    }
+    let diff = current.score() - baseline.score();
+    let pooled = (current.score() * n1 + baseline.score() * n2) / (n1 + n2);
+    let se = (pooled * (1. - pooled) * (1. / n1 + 1. / n2)).sqrt();
+    if se == 0. {
+        if diff == 0. {
+            0.
+        } else {
+            diff.signum() * f64::INFINITY
+        }
+    } else {
+        diff / se
+    }
+}
+
+/// Compare an outcome with its baseline, if any. A change is significant when
+/// the z statistic exceeds `threshold` in magnitude.
+pub fn compare(current: Outcome, baseline: Option<&Outcome>, threshold: f64) -> Comparison {
+    let Some(baseline) = baseline else {
+        return Comparison {
+            current,
+            baseline: None,
+            z: 0.,
+            status: Status::New,
+        };
+    };
+    let z = z_score(&current, baseline);
+    let status = if z <= -threshold {
+        Status::Regressed
+    } else if z >= threshold {
+        Status::Improved
+    } else {
+        Status::Unchanged
+    };
+    Comparison {
+        current,
+        baseline: Some(baseline.clone()),
+        z,
+        status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::TicTacToe;
+
+    fn outcome(wins: usize, losses: usize, draws: usize) -> Outcome {
+        Outcome {
+            id: "m".into(),
+            wins,
+            losses,
+            draws,
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = outcome(30, 10, 0);
+        assert_eq!(baseline.score(), 0.75);
+        assert_eq!(z_score(&baseline, &baseline), 0.);
+
+        let status = |current| compare(current, Some(&baseline), 1.96).status;
+        assert_eq!(status(outcome(28, 12, 0)), Status::Unchanged);
+        assert_eq!(status(outcome(15, 25, 0)), Status::Regressed);
+        assert_eq!(status(outcome(40, 0, 0)), Status::Improved);
+        assert_eq!(compare(outcome(0, 0, 1), None, 1.96).status, Status::New);
+
+        let mut baselines = Baselines::default();
+        baselines.update(outcome(1, 0, 0));
+        baselines.update(outcome(0, 1, 0));
+        assert_eq!(baselines.results.len(), 1);
+        let json = baselines.to_json().unwrap();
+        assert_eq!(Baselines::from_json(&json).unwrap(), baselines);
+    }
+
+    #[test]
+    fn test_matchup() {
+        let matchup = Matchup::new("ttt", "ucb1_mast", "ucb1", 100).rounds(2);
+        assert_eq!(matchup.id, "ttt/ucb1_mast-vs-ucb1/100");
+        let outcome = matchup.play::<TicTacToe>(&Default::default()).unwrap();
+        assert_eq!(outcome.games(), 4);
+        // Fixed seeds reproduce the outcome
+        assert_eq!(
+            matchup.play::<TicTacToe>(&Default::default()).unwrap(),
+            outcome
+        );
+    }
+}
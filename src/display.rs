@@ -44,6 +44,123 @@ where
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+
+/// How `StateDiff` marks the cells which changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffStyle {
+    // Highlight with ANSI colors instead of brackets
+    pub color: bool,
+}
+
+impl DiffStyle {
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Colors when stdout is a terminal and `NO_COLOR` is not set.
+    pub fn from_env() -> Self {
+        use std::io::IsTerminal;
+        Self {
+            color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
+
+const HIGHLIGHT: &str = "\x1b[1;33m";
+const RESET: &str = "\x1b[0m";
+
+/// Displays `after`, marking the characters which differ from the display of
+/// `before` at the same line and column. For boards, these are the cells
+/// changed by the last move.
+///
+/// Without colors, a changed character is bracketed by overwriting the spaces
+/// on either side of it, as in `1 .[X]. .`, which keeps the board's layout.
+/// Lines with changes that cannot be bracketed this way end with `  <`.
+pub struct StateDiff<'a, T: fmt::Display> {
+    pub before: Option<&'a T>,
+    pub after: &'a T,
+    pub style: DiffStyle,
+}
+
+impl<'a, T: fmt::Display> StateDiff<'a, T> {
+    pub fn new(before: Option<&'a T>, after: &'a T, style: DiffStyle) -> Self {
+        Self {
+            before,
+            after,
+            style,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for StateDiff<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let after = self.after.to_string();
+        let Some(before) = self.before else {
+            return write!(f, "{after}");
+        };
+        let before = before.to_string();
+        let mut before_lines = before.lines();
+        for line in after.split_inclusive('\n') {
+            let (line, newline) = match line.strip_suffix('\n') {
+                Some(line) => (line, "\n"),
+                None => (line, ""),
+            };
+            let old = before_lines
+                .next()
+                .unwrap_or("")
+                .chars()
+                .collect::<Vec<_>>();
+            let new = line.chars().collect::<Vec<_>>();
+            let changed = (0..new.len())
+                .map(|i| old.get(i) != Some(&new[i]) && !new[i].is_whitespace())
+                .collect::<Vec<_>>();
+            if self.style.color {
+                write_colored(f, &new, &changed)?;
+            } else {
+                write_bracketed(f, &new, &changed)?;
+            }
+            write!(f, "{newline}")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_colored(f: &mut fmt::Formatter<'_>, line: &[char], changed: &[bool]) -> fmt::Result {
+    for (c, changed) in line.iter().zip(changed) {
+        if *changed {
+            write!(f, "{HIGHLIGHT}{c}{RESET}")?;
+        } else {
+            write!(f, "{c}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_bracketed(f: &mut fmt::Formatter<'_>, line: &[char], changed: &[bool]) -> fmt::Result {
+    let mut out = line.to_vec();
+    let mut unmarked = false;
+    for i in (0..line.len()).filter(|i| changed[*i]) {
+        let open = i > 0 && out[i - 1] == ' ';
+        let close = i + 1 == line.len() || out[i + 1] == ' ';
+        if open && close {
+            out[i - 1] = '[';
+            match out.get_mut(i + 1) {
+                Some(c) => *c = ']',
+                None => out.push(']'),
+            }
+        } else {
+            unmarked = true;
+        }
+    }
+    write!(f, "{}", out.into_iter().collect::<String>())?;
+    if unmarked {
+        write!(f, "  <")?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod example {
     use super::*;
@@ -77,4 +194,34 @@ mod example {
 
         println!("{}", RectangularBoardDisplay(&example_board));
     }
+
+    #[test]
+    fn test_state_diff() {
+        struct Text(&'static str);
+
+        impl fmt::Display for Text {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        let before = Text("2 . . .\n1 . X .\n");
+        let after = Text("2 . O .\n1 . X O\n");
+        let diff = |before, style| StateDiff::new(before, &after, style).to_string();
+
+        assert_eq!(diff(None, DiffStyle::default()), after.0);
+        assert_eq!(
+            diff(Some(&before), DiffStyle::default()),
+            "2 .[O].\n1 . X[O]\n"
+        );
+        assert_eq!(
+            diff(Some(&before), DiffStyle::default().color(true)),
+            format!("2 . {HIGHLIGHT}O{RESET} .\n1 . X {HIGHLIGHT}O{RESET}\n")
+        );
+        // Changes without room for brackets mark the line instead
+        assert_eq!(
+            StateDiff::new(Some(&Text("ab")), &Text("ac"), DiffStyle::default()).to_string(),
+            "ac  <"
+        );
+    }
 }
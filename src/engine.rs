@@ -0,0 +1,373 @@
+//! A simple text protocol for driving a `TreeSearch` from a GUI or match
+//! runner, modeled on UCI and UGI. Commands are read one per line:
+//!
+//! ```text
+//! ugi                               reply with `id name <name>` and `ugiok`
+//! isready                           reply with `readyok`
+//! newgame                           clear the tree and return to the start
+//! position startpos [moves <m>...]  the start position and the moves since
+//! go [nodes <n>] [movetime <ms>]    search in the background
+//!    [p1time <ms>] [p2time <ms>]
+//!    [p1inc <ms>] [p2inc <ms>]
+//!    [infinite]
+//! stop                              end the search early
+//! quit                              stop searching and exit
+//! ```
+//!
+//! A search reports `info iterations <n> time <ms> value <v> pv <m>...` every
+//! `info_interval` iterations and at the end, followed by `bestmove <m>`.
+//! Moves are read with `Game::parse_action` and written with
+//! `Game::notation`, so the notation of a move must not contain whitespace.
+//!
+//! `go` without limits keeps those of the configuration. `movetime 0` asks for
+//! as short a search as will choose a move: just enough iterations to expand
+//! the root. The clock of the player to move (`p1` for the first player) is
+//! used with `TimeControl`.
+//! Other commands wait for a running search to finish.
+
+use crate::game::{Game, PlayerIndex};
+use crate::strategies::mcts::{SearchConfig, Strategy, TimeControl, TreeSearch};
+use crate::timer::StopHandle;
+
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+type Output<W> = Arc<Mutex<W>>;
+
+fn send<W: Write>(out: &Mutex<W>, line: &str) {
+    let mut out = out.lock().unwrap();
+    // The other side may have gone away, in which case the next read ends
+    // the loop
+    _ = writeln!(out, "{line}").and_then(|_| out.flush());
+}
+
+pub struct Engine<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    G::S: std::fmt::Display,
+{
+    pub config: SearchConfig<G, S>,
+    // Iterations between info lines
+    pub info_interval: usize,
+    state: G::S,
+    // The search, unless it is running on its own thread
+    search: Option<TreeSearch<G, S>>,
+    running: Option<(StopHandle, JoinHandle<TreeSearch<G, S>>)>,
+}
+
+impl<G, S> Engine<G, S>
+where
+    G: Game + 'static,
+    S: Strategy<G> + 'static,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+    TreeSearch<G, S>: Send,
+{
+    pub fn new(config: SearchConfig<G, S>) -> Self {
+        Self {
+            search: Some(TreeSearch::new().config(config.clone())),
+            config,
+            info_interval: 10_000,
+            state: G::S::default(),
+            running: None,
+        }
+    }
+
+    pub fn info_interval(mut self, info_interval: usize) -> Self {
+        self.info_interval = info_interval;
+        self
+    }
+
+    /// Read commands from `input` until `quit` or the end of input, writing
+    /// replies to `output`. A search still running at the end of input is
+    /// left to finish.
+    pub fn run<W: Write + Send + 'static>(
+        &mut self,
+        input: impl BufRead,
+        output: W,
+    ) -> io::Result<()> {
+        let out = Arc::new(Mutex::new(output));
+        for line in input.lines() {
+            let line = line?;
+            let mut tokens = line.split_whitespace();
+            let Some(command) = tokens.next() else {
+                continue;
+            };
+            let args = tokens.collect::<Vec<_>>();
+            match command {
+                "ugi" => {
+                    send(&out, &format!("id name {}", self.config.name));
+                    send(&out, "ugiok");
+                }
+                "isready" => send(&out, "readyok"),
+                "newgame" => {
+                    self.wait();
+                    self.search = Some(TreeSearch::new().config(self.config.clone()));
+                    self.state = G::S::default();
+                }
+                "position" => {
+                    self.wait();
+                    if let Err(err) = self.set_position(&args) {
+                        send(&out, &format!("info string {err}"));
+                    }
+                }
+                "go" => {
+                    self.wait();
+                    if let Err(err) = self.go(&args, &out) {
+                        send(&out, &format!("info string {err}"));
+                    }
+                }
+                "stop" => self.stop(),
+                "quit" => {
+                    self.stop();
+                    return Ok(());
+                }
+                _ => send(&out, &format!("info string unknown command: {command}")),
+            }
+        }
+        self.wait();
+        Ok(())
+    }
+
+    /// The current position.
+    pub fn state(&self) -> &G::S {
+        &self.state
+    }
+
+    fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves = match args {
+            ["startpos"] => &[][..],
+            ["startpos", "moves", moves @ ..] => moves,
+            _ => return Err(format!("invalid position: {}", args.join(" "))),
+        };
+        let mut state = G::S::default();
+        for input in moves {
+            let action = (!G::is_terminal(&state))
+                .then(|| G::parse_action(&state, input))
+                .flatten()
+                .ok_or_else(|| format!("invalid move: {input}"))?;
+            state = G::apply(state, &action);
+        }
+        self.state = state;
+        Ok(())
+    }
+
+    // The configuration for a search with the limits given to `go`
+    fn limits(&self, args: &[&str]) -> Result<SearchConfig<G, S>, String> {
+        let mut config = self.config.clone();
+        if !args.is_empty() {
+            config.max_iterations = usize::MAX;
+            config.max_time = Duration::default();
+            config.time_control = None;
+        }
+
+        let mut clock = [None; 2];
+        let mut increment = [Duration::default(); 2];
+        let mut instant = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if *arg == "infinite" {
+                continue;
+            }
+            let value = args
+                .next()
+                .and_then(|value| value.parse::<u64>().ok())
+                .ok_or_else(|| format!("invalid value for {arg}"))?;
+            let millis = Duration::from_millis(value);
+            match *arg {
+                "nodes" => config.max_iterations = value as usize,
+                "movetime" => {
+                    // A zero duration would leave the search unbounded
+                    instant = value == 0;
+                    config.max_time = millis;
+                }
+                "p1time" => clock[0] = Some(millis),
+                "p2time" => clock[1] = Some(millis),
+                "p1inc" => increment[0] = millis,
+                "p2inc" => increment[1] = millis,
+                _ => return Err(format!("unknown go argument: {arg}")),
+            }
+        }
+
+        if instant {
+            let iterations = config.expand_threshold as usize + 1;
+            config.max_iterations = config.max_iterations.min(iterations);
+        }
+
+        let player = G::player_to_move(&self.state).to_index();
+        if let Some(remaining) = clock.get(player).copied().flatten() {
+            let mut control = self
+                .config
+                .time_control
+                .unwrap_or(TimeControl::new(remaining, increment[player]))
+                .remaining(remaining);
+            control.increment = increment[player];
+            config.time_control = Some(control);
+        }
+        config
+            .validate()
+            .map_err(|err| format!("invalid search config: {err}"))?;
+        Ok(config)
+    }
+
+    fn go<W: Write + Send + 'static>(
+        &mut self,
+        args: &[&str],
+        out: &Output<W>,
+    ) -> Result<(), String> {
+        if G::is_terminal(&self.state) {
+            send(out, "bestmove none");
+            return Err("the game is over".into());
+        }
+        let mut config = self.limits(args)?;
+
+        // Report progress in the notation of the root position
+        let root = self.state.clone();
+        let progress_out = out.clone();
+        config = config.on_progress(self.info_interval, move |progress| {
+            let mut state = root.clone();
+            let pv = progress
+                .pv()
+                .iter()
+                .map(|action| {
+                    let notation = G::notation(&state, action);
+                    state = G::apply(state.clone(), action);
+                    notation
+                })
+                .collect::<Vec<_>>();
+            send(
+                &progress_out,
+                &format!(
+                    "info iterations {} time {} value {:.3} pv {}",
+                    progress.iterations,
+                    progress.elapsed.as_millis(),
+                    progress.value,
+                    pv.join(" ")
+                ),
+            );
+        });
+
+        let mut search = self.search.take().unwrap();
+        search.config = config;
+        let handle = search.stop_handle();
+        // Drop any stop which arrived after the previous search ended
+        handle.reset();
+        let state = self.state.clone();
+        let out = out.clone();
+        let thread = std::thread::spawn(move || {
+            let result = search.search(&state);
+            send(
+                &out,
+                &format!("bestmove {}", G::notation(&state, &result.action)),
+            );
+            search
+        });
+        self.running = Some((handle, thread));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some((handle, _)) = &self.running {
+            handle.stop();
+        }
+        self.wait();
+    }
+
+    // Wait for a running search to finish
+    fn wait(&mut self) {
+        if let Some((_, thread)) = self.running.take() {
+            self.search = Some(thread.join().expect("search thread panicked"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::template::Template;
+    use crate::strategies::mcts::strategy;
+
+    type E = Engine<Template, strategy::Ucb1>;
+
+    fn run(engine: &mut E, input: &str) -> Vec<String> {
+        let out = Arc::new(Mutex::new(Vec::<u8>::new()));
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        engine.run(input.as_bytes(), Shared(out.clone())).unwrap();
+        let out = out.lock().unwrap();
+        String::from_utf8(out.clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_engine() {
+        let mut engine = E::new(SearchConfig::default().name("test").seed(0)).info_interval(100);
+        let lines = run(
+            &mut engine,
+            "ugi\nisready\nposition startpos moves a1 b1\ngo nodes 250\n",
+        );
+        assert_eq!(lines[..3], ["id name test", "ugiok", "readyok"]);
+        let info = &lines[3..lines.len() - 1];
+        assert_eq!(info.len(), 3);
+        assert!(info[0].starts_with("info iterations 100 time "));
+        assert!(info[2].starts_with("info iterations 250 time "));
+        let best = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+        assert!(Template::parse_action(engine.state(), best).is_some());
+
+        // An infinite search runs until stopped
+        let lines = run(&mut engine, "go infinite\nstop\nisready\n");
+        assert!(lines[lines.len() - 2].starts_with("bestmove "));
+        assert_eq!(lines.last().unwrap(), "readyok");
+
+        let lines = run(
+            &mut engine,
+            "position startpos moves a1 a1\nposition sideways\ngo nodes\nfoo\n",
+        );
+        assert_eq!(
+            lines,
+            [
+                "info string invalid move: a1",
+                "info string invalid position: sideways",
+                "info string invalid value for nodes",
+                "info string unknown command: foo",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let engine = E::new(SearchConfig::default().max_iterations(1000));
+        let config = engine.limits(&[]).unwrap();
+        assert_eq!(config.max_iterations, 1000);
+
+        let config = engine.limits(&["nodes", "50", "movetime", "20"]).unwrap();
+        assert_eq!(config.max_iterations, 50);
+        assert_eq!(config.max_time, Duration::from_millis(20));
+
+        // No time at all is the shortest search, not an unbounded one
+        let config = engine.limits(&["movetime", "0"]).unwrap();
+        assert_eq!(config.max_iterations, config.expand_threshold as usize + 1);
+        assert_eq!(config.max_time, Duration::default());
+        let engine = E::new(SearchConfig::default().expand_threshold(3));
+        let config = engine.limits(&["movetime", "0", "nodes", "100"]).unwrap();
+        assert_eq!(config.max_iterations, 4);
+
+        assert_eq!(
+            engine.limits(&["movetime", "soon"]).err().unwrap(),
+            "invalid value for movetime"
+        );
+    }
+}
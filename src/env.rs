@@ -0,0 +1,244 @@
+//! A step/reset environment over any `Game`, in the style of OpenAI gym, so
+//! that reinforcement learning code can train agents on the crate's games.
+//!
+//! Games are multi-player, so rewards are reported for every player, indexed
+//! by `PlayerIndex`, rather than as a single scalar. Rewards are zero until the
+//! episode ends, at which point they are the game's terminal utilities. Games
+//! which provide dense action indices (see `Game::max_actions`) can be driven
+//! through a discrete action space with `step_index` and `action_mask`.
+//!
+//! A trained agent is plugged back into the search through
+//! `SimulateStrategy`: its value estimate via `evaluate`, and its policy via
+//! `select_move`.
+
+use crate::game::{Game, PlayerIndex};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvError {
+    // The episode has ended; call `reset`
+    Done,
+    // The action is not legal in the current state
+    IllegalAction(String),
+    // The index is outside of the game's dense action space
+    InvalidIndex(usize),
+}
+
+impl std::fmt::Display for EnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvError::Done => write!(f, "the episode has ended"),
+            EnvError::IllegalAction(action) => write!(f, "illegal action: {action}"),
+            EnvError::InvalidIndex(index) => write!(f, "invalid action index: {index}"),
+        }
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+/// The outcome of a single `step`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// The reward for each player for this step
+    pub rewards: Vec<f64>,
+    /// The game reached a terminal state
+    pub done: bool,
+    /// The episode was cut short by `Env::max_steps` without ending the game
+    pub truncated: bool,
+}
+
+#[derive(Clone)]
+pub struct Env<G: Game> {
+    initial: G::S,
+    state: G::S,
+    actions: Vec<G::A>,
+    steps: usize,
+    max_steps: usize,
+    finished: bool,
+}
+
+impl<G: Game> Default for Env<G> {
+    fn default() -> Self {
+        Self::new(G::S::default())
+    }
+}
+
+impl<G: Game> Env<G> {
+    /// An environment whose episodes start from the given state.
+    pub fn new(initial: G::S) -> Self {
+        let mut env = Self {
+            state: initial.clone(),
+            initial,
+            actions: Vec::new(),
+            steps: 0,
+            max_steps: usize::MAX,
+            finished: false,
+        };
+        env.reset();
+        env
+    }
+
+    /// Truncate episodes after the given number of steps.
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Start a new episode and return the initial observation.
+    pub fn reset(&mut self) -> &G::S {
+        self.state = self.initial.clone();
+        self.steps = 0;
+        self.refresh();
+        &self.state
+    }
+
+    /// The current state.
+    pub fn observation(&self) -> &G::S {
+        &self.state
+    }
+
+    /// The index of the player to act.
+    pub fn player(&self) -> usize {
+        G::player_to_move(&self.state).to_index()
+    }
+
+    /// The legal actions in the current state. Empty once the episode is over.
+    pub fn legal_actions(&self) -> &[G::A] {
+        &self.actions
+    }
+
+    /// The number of discrete actions, or zero if the game does not provide
+    /// dense action indices.
+    pub fn action_space(&self) -> usize {
+        G::max_actions()
+    }
+
+    /// Which indices in `0..action_space()` are legal in the current state.
+    pub fn action_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; G::max_actions()];
+        for action in &self.actions {
+            mask[G::action_index(&self.state, action)] = true;
+        }
+        mask
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.finished
+    }
+
+    /// Apply an action for the player to act.
+    pub fn step(&mut self, action: &G::A) -> Result<Step, EnvError> {
+        if self.finished {
+            return Err(EnvError::Done);
+        }
+        if !self.actions.contains(action) {
+            return Err(EnvError::IllegalAction(G::notation(&self.state, action)));
+        }
+
+        self.state = G::apply(self.state.clone(), action);
+        self.steps += 1;
+        self.refresh();
+
+        let done = G::is_terminal(&self.state);
+        let rewards = if done {
+            G::compute_utilities(&self.state)
+        } else {
+            vec![0.; G::num_players()]
+        };
+        Ok(Step {
+            rewards,
+            done,
+            truncated: self.finished && !done,
+        })
+    }
+
+    /// Apply the action with the given dense index.
+    pub fn step_index(&mut self, index: usize) -> Result<Step, EnvError> {
+        if index >= G::max_actions() {
+            return Err(EnvError::InvalidIndex(index));
+        }
+        if self.finished {
+            return Err(EnvError::Done);
+        }
+        // Games need only map legal indices back to actions
+        if !self
+            .actions
+            .iter()
+            .any(|action| G::action_index(&self.state, action) == index)
+        {
+            return Err(EnvError::IllegalAction(format!("index {index}")));
+        }
+        let action = G::action_from_index(&self.state, index);
+        self.step(&action)
+    }
+
+    fn refresh(&mut self) {
+        self.actions.clear();
+        self.finished = G::is_terminal(&self.state) || self.steps >= self.max_steps;
+        if !self.finished {
+            G::generate_actions(&self.state, &mut self.actions);
+            self.finished = self.actions.is_empty();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{Move, TicTacToe};
+
+    #[test]
+    fn test_env() {
+        let mut env = Env::<TicTacToe>::default();
+        assert_eq!(env.legal_actions().len(), 9);
+        assert_eq!(env.action_space(), 9);
+        assert!(env.action_mask().iter().all(|legal| *legal));
+
+        // X takes the top row while O plays below
+        for (i, index) in [0, 3, 1, 4].into_iter().enumerate() {
+            assert_eq!(env.player(), i % 2);
+            let step = env.step_index(index).unwrap();
+            assert_eq!(step.rewards, vec![0., 0.]);
+            assert!(!step.done);
+        }
+        assert!(!env.action_mask()[0]);
+        assert_eq!(
+            env.step(&Move(0)),
+            Err(EnvError::IllegalAction(TicTacToe::notation(
+                env.observation(),
+                &Move(0)
+            )))
+        );
+        assert_eq!(env.step_index(9), Err(EnvError::InvalidIndex(9)));
+
+        let step = env.step(&Move(2)).unwrap();
+        assert!(step.done && !step.truncated);
+        assert_eq!(step.rewards, vec![1., -1.]);
+        assert!(env.legal_actions().is_empty());
+        assert_eq!(env.step(&Move(8)), Err(EnvError::Done));
+
+        env.reset();
+        assert!(!env.is_done());
+        assert_eq!(env.legal_actions().len(), 9);
+
+        let mut env = Env::<TicTacToe>::default().max_steps(1);
+        let step = env.step(&Move(4)).unwrap();
+        assert!(step.truncated && !step.done);
+        assert_eq!(env.step(&Move(0)), Err(EnvError::Done));
+    }
+
+    #[test]
+    fn test_step_index_illegal() {
+        use crate::games::breakthrough::Breakthrough;
+
+        // Index 0 would step off the board from the corner
+        let mut env = Env::<Breakthrough<8, 8>>::default();
+        assert!(!env.action_mask()[0]);
+        assert_eq!(
+            env.step_index(0),
+            Err(EnvError::IllegalAction("index 0".into()))
+        );
+
+        let index = env.action_mask().iter().position(|legal| *legal).unwrap();
+        assert!(env.step_index(index).is_ok());
+    }
+}
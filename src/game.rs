@@ -1,5 +1,5 @@
 use rand::rngs::SmallRng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 // Refers to a player index. Expectation is that these values
 // are small and monotonically increasing. Stored as a usize for ease
@@ -8,6 +8,65 @@ pub trait PlayerIndex {
     fn to_index(&self) -> usize;
 }
 
+impl PlayerIndex for usize {
+    fn to_index(&self) -> usize {
+        *self
+    }
+}
+
+/// A player, identified by its index in turn order. Unlike a bare `usize`,
+/// moving between players goes through `next`, which wraps at the number of
+/// players, or `opponent`, which checks that the game has two players.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct PlayerId(pub usize);
+
+impl PlayerId {
+    pub fn of<P: PlayerIndex>(player: &P) -> Self {
+        Self(player.to_index())
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// The player who moves after this one.
+    pub fn next(self, num_players: usize) -> Self {
+        debug_assert!(self.0 < num_players);
+        Self((self.0 + 1) % num_players)
+    }
+
+    /// The other player in a two player game.
+    pub fn opponent(self) -> Self {
+        assert!(self.0 < 2, "opponent is only defined for two players");
+        Self(1 - self.0)
+    }
+
+    /// Every player in turn order.
+    pub fn all(num_players: usize) -> impl Iterator<Item = PlayerId> {
+        (0..num_players).map(PlayerId)
+    }
+}
+
+impl PlayerIndex for PlayerId {
+    fn to_index(&self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for PlayerId {
+    fn from(index: usize) -> Self {
+        Self(index)
+    }
+}
+
+impl From<PlayerId> for usize {
+    fn from(player: PlayerId) -> Self {
+        player.0
+    }
+}
+
 // A proxy trait to simplify some implementation.
 //
 // NOTE: the `Hash` requirement is less strong than the Zobrist requirement for
@@ -18,6 +77,39 @@ pub trait Action: Clone + Eq + std::hash::Hash + std::fmt::Debug + Serialize + S
 // Blanket implementation
 impl<T: Clone + Eq + std::hash::Hash + std::fmt::Debug + Serialize + Sync + Send> Action for T {}
 
+// How terminal utilities are shaped. Every game supports `WinLoss`. Games which
+// support `Margin` scale the winner's utility by the margin of victory, which
+// gives the search a richer signal than a bare win or loss.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    #[default]
+    WinLoss,
+    Margin,
+}
+
+// Search settings a game recommends in place of the strategy defaults. Unset
+// fields leave the strategy's value unchanged. See `Game::recommended_config_hints`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConfigHints {
+    // Multiplies the select strategy's exploration constant
+    pub exploration_scale: Option<f64>,
+    pub max_playout_depth: Option<usize>,
+    pub expand_threshold: Option<u32>,
+}
+
+// Dense indices drawn by the default `Game::sample_action` before giving up
+const SAMPLE_ATTEMPTS: usize = 8;
+
+// A fresh random word from a used one, for another attempt (murmur3's finalizer)
+#[inline(always)]
+fn mix32(word: u32) -> u32 {
+    let mut x = word.wrapping_add(0x9e37_79b9);
+    x = (x ^ (x >> 16)).wrapping_mul(0x85eb_ca6b);
+    x = (x ^ (x >> 13)).wrapping_mul(0xc2b2_ae35);
+    x ^ (x >> 16)
+}
+
 pub trait Game: Sized + Clone + Sync + Send {
     /// The type representing the state of your game. Ideally, this
     /// should be as small as possible and have a cheap Clone or Copy
@@ -60,6 +152,24 @@ pub trait Game: Sized + Clone + Sync + Send {
         state
     }
 
+    /// Determinize `state` as seen by `observer`, resampling only what that
+    /// player cannot see. Used by `ismcts::MoIsmcts`, which searches from the
+    /// searching player's information set. The default treats every hidden
+    /// detail as hidden from everyone.
+    #[allow(unused_variables)]
+    fn determinize_for(state: Self::S, observer: PlayerId, rng: &mut SmallRng) -> Self::S {
+        Self::determinize(state, rng)
+    }
+
+    /// `action`, played in `state`, as `observer` sees it, or `None` if the
+    /// observer only learns that a move was made, such as a card played face
+    /// down. Moves which look the same share a node in the observer's tree
+    /// under `ismcts::MoIsmcts`.
+    #[allow(unused_variables)]
+    fn observe_action(state: &Self::S, action: &Self::A, observer: PlayerId) -> Option<Self::A> {
+        Some(action.clone())
+    }
+
     /// Assuming a zero-sum game, the player who has won.
     fn winner(state: &Self::S) -> Option<Self::P>;
 
@@ -70,8 +180,8 @@ pub trait Game: Sized + Clone + Sync + Send {
     //
     // NOTE: this is too expensive. Maybe `rank(S) -> Vec<f64>`
     fn rank(state: &Self::S, player_index: usize) -> f64 {
-        match Self::winner(state) {
-            Some(w) if w.to_index() == player_index => 1.,
+        match Self::winner_id(state) {
+            Some(w) if w == PlayerId(player_index) => 1.,
             Some(_) => 2.,
             None => 1.5,
         }
@@ -87,6 +197,21 @@ pub trait Game: Sized + Clone + Sync + Send {
         2
     }
 
+    /// `player_to_move` as a `PlayerId`.
+    fn player_id(state: &Self::S) -> PlayerId {
+        PlayerId::of(&Self::player_to_move(state))
+    }
+
+    /// `winner` as a `PlayerId`.
+    fn winner_id(state: &Self::S) -> Option<PlayerId> {
+        Self::winner(state).map(|winner| PlayerId::of(&winner))
+    }
+
+    /// Every player in turn order.
+    fn players() -> impl Iterator<Item = PlayerId> {
+        PlayerId::all(Self::num_players())
+    }
+
     /// Move notation for a given move relative to a given state.
     #[allow(unused)]
     fn notation(state: &Self::S, action: &Self::A) -> String {
@@ -95,7 +220,7 @@ pub trait Game: Sized + Clone + Sync + Send {
 
     #[inline]
     fn get_reward(init: &Self::S, term: &Self::S) -> f64 {
-        Self::compute_utilities(term)[Self::player_to_move(init).to_index()]
+        Self::compute_utilities(term)[Self::player_id(init).index()]
     }
 
     #[allow(unused_variables)]
@@ -103,6 +228,27 @@ pub trait Game: Sized + Clone + Sync + Send {
         unimplemented!();
     }
 
+    /// An exclusive upper bound on the values returned by `action_index`. A
+    /// value of zero indicates that the game does not provide dense action
+    /// indices.
+    fn max_actions() -> usize {
+        0
+    }
+
+    /// A stable index for an action in the range `0..max_actions()`. This
+    /// allows per-action data (policies, MAST tables, etc.) to be stored in
+    /// dense arrays rather than hash maps keyed by action.
+    #[allow(unused_variables)]
+    fn action_index(state: &Self::S, action: &Self::A) -> usize {
+        unimplemented!();
+    }
+
+    /// The inverse of `action_index` for the given state.
+    #[allow(unused_variables)]
+    fn action_from_index(state: &Self::S, index: usize) -> Self::A {
+        unimplemented!();
+    }
+
     // #[inline]
     // fn rank_to_util(rank: f64, num_players: usize) -> f64 {
     //     let n = num_players as f64;
@@ -114,13 +260,27 @@ pub trait Game: Sized + Clone + Sync + Send {
     //     }
     // }
 
+    /// The outcome of a terminal state for the first player of a two-player
+    /// zero-sum game. Games which only produce a scalar reward can implement
+    /// this instead of `compute_utilities`, which then gives the second player
+    /// the negated reward.
+    #[allow(unused_variables)]
+    fn zero_sum_reward(state: &Self::S) -> Option<f64> {
+        None
+    }
+
     #[inline]
     fn compute_utilities(state: &Self::S) -> Vec<f64> {
-        let winner = Self::winner(state).map(|p| p.to_index());
-        (0..Self::num_players())
-            .map(|i| match winner {
+        if let Some(reward) = Self::zero_sum_reward(state) {
+            debug_assert_eq!(Self::num_players(), 2);
+            return vec![reward, -reward];
+        }
+
+        let winner = Self::winner_id(state);
+        Self::players()
+            .map(|player| match winner {
                 None => 0.,
-                Some(w) if w == i => 1.,
+                Some(w) if w == player => 1.,
                 _ => -1.,
             })
             .collect()
@@ -136,6 +296,43 @@ pub trait Game: Sized + Clone + Sync + Send {
         //     .collect()
     }
 
+    /// The utilities of a non-terminal state, used when a playout is cut short
+    /// by `max_playout_depth`. Games which can estimate the outcome of an
+    /// unfinished game (material, captures, distance to a goal) should do so
+    /// here. `None` falls back to `compute_utilities`, which by default scores
+    /// a game without a winner as a draw.
+    #[allow(unused_variables)]
+    fn utilities_nonterminal(state: &Self::S) -> Option<Vec<f64>> {
+        None
+    }
+
+    /// A player with a decisive advantage in a non-terminal state, if one is
+    /// cheaply recognized, such as an unassailable material lead. Playouts
+    /// under `simulate::Mercy` end early with this player as the winner, which
+    /// saves playing out long, already decided endgames.
+    #[allow(unused_variables)]
+    fn playout_cutoff(state: &Self::S) -> Option<Self::P> {
+        None
+    }
+
+    /// Prior probabilities for `actions`, the legal actions in `state` in the
+    /// order of `generate_actions`, for strategies which weigh exploration by a
+    /// policy, such as `select::Puct`. The weights need not be normalized.
+    /// `None` gives every action the same prior.
+    #[allow(unused_variables)]
+    fn action_priors(state: &Self::S, actions: &[Self::A]) -> Option<Vec<f64>> {
+        None
+    }
+
+    /// Whether two actions are the same move in `state`. Actions which carry
+    /// state-dependent detail (e.g., the stones a placement captures) should
+    /// compare only the part which identifies the move. This is used to carry
+    /// statistics over when a node's edges are revalidated.
+    #[allow(unused_variables)]
+    fn actions_equal_modulo_state(state: &Self::S, a: &Self::A, b: &Self::A) -> bool {
+        a == b
+    }
+
     /// A canonical representation of the state. Many board games exhibit some
     /// form of symmetry. Canonicalizing the state will enable the engine to
     /// leverage those symmetries.
@@ -143,10 +340,154 @@ pub trait Game: Sized + Clone + Sync + Send {
         state
     }
 
+    /// `action` in `state` as the same move in `canonical_representation(state)`.
+    /// Games which canonicalize their states map the action through the same
+    /// symmetry.
+    #[allow(unused_variables)]
+    fn canonical_action(state: &Self::S, action: &Self::A) -> Self::A {
+        action.clone()
+    }
+
+    /// The inverse of `canonical_action`: an action in the canonical
+    /// representation of `state` as the same move in `state`.
+    #[allow(unused_variables)]
+    fn action_from_canonical(state: &Self::S, action: &Self::A) -> Self::A {
+        action.clone()
+    }
+
+    /// Search settings which suit this game better than the strategy defaults.
+    /// These are applied by `SearchConfig::default_for`.
+    fn recommended_config_hints() -> ConfigHints {
+        ConfigHints::default()
+    }
+
+    /// `state` with its terminal utilities shaped by `scoring`, or `None` if
+    /// the game does not support it. Every game supports `Scoring::WinLoss`.
+    fn with_scoring(state: Self::S, scoring: Scoring) -> Option<Self::S> {
+        (scoring == Scoring::WinLoss).then_some(state)
+    }
+
+    /// An upper bound on the number of moves remaining in the game, if one is
+    /// cheaply known. This is used to decide when the remaining game is small
+    /// enough to solve exactly.
+    #[allow(unused_variables)]
+    fn estimated_remaining_moves(state: &Self::S) -> Option<usize> {
+        None
+    }
+
     /// A zobrist hash is expected to be cheap and precomputed upon move
     /// application.
     #[allow(unused_variables)]
     fn zobrist_hash(state: &Self::S) -> u64 {
         0
     }
+
+    /// True if `zobrist_hash` is implemented and the legal actions depend only
+    /// on the state, so that nodes can be shared between transpositions. Used
+    /// by `Preset::Strong` to decide whether to enable transpositions.
+    fn supports_transpositions() -> bool {
+        false
+    }
+
+    /// Choose a legal action uniformly at random, using `word` as the source of
+    /// randomness, without generating the full list. Used to speed up
+    /// `simulate::Batched` playouts. `None` means the game does not support
+    /// it, or gave up.
+    ///
+    /// By default, dense action indices (see `max_actions`) are drawn until
+    /// `is_legal_index` accepts one, for a few attempts. Games with a compact
+    /// set of legal moves can override this to sample them directly.
+    fn sample_action(state: &Self::S, word: u32) -> Option<Self::A> {
+        let n = Self::max_actions() as u64;
+        let mut word = word;
+        for _ in 0..SAMPLE_ATTEMPTS {
+            let index = ((word as u64 * n) >> 32) as usize;
+            if Self::is_legal_index(state, index)? {
+                return Some(Self::action_from_index(state, index));
+            }
+            word = mix32(word);
+        }
+        None
+    }
+
+    /// Whether the action with the dense index `index`, below `max_actions`,
+    /// is legal in `state`. Games which can tell without generating the legal
+    /// actions implement this to support the default `sample_action`. `None`
+    /// means the game does not support it.
+    #[allow(unused_variables)]
+    fn is_legal_index(state: &Self::S, index: usize) -> Option<bool> {
+        None
+    }
+
+    /// Split the state into independent components, each a state of this game
+    /// with the same player to move, such that every move in the whole is a
+    /// move in exactly one component. An empty result means the state does not
+    /// decompose. Used by `strategies::decompose::DecomposedSearch`.
+    #[allow(unused_variables)]
+    fn decompose(state: &Self::S) -> Vec<Self::S> {
+        vec![]
+    }
+
+    /// Map an action in the component at `index` of `decompose(state)` to the
+    /// same move in `state`. The default suits games whose actions do not
+    /// depend on the component, e.g. board coordinates.
+    #[allow(unused_variables)]
+    fn lift_action(state: &Self::S, index: usize, action: &Self::A) -> Self::A {
+        action.clone()
+    }
+
+    /// True if any state of the game is simultaneous (see `is_simultaneous`).
+    /// Proven wins and losses assume that one player moves at a time, so the
+    /// search does not propagate them in such games.
+    fn has_simultaneous_moves() -> bool {
+        false
+    }
+
+    /// Whether every player chooses a move at once in `state`. The actions of
+    /// a simultaneous state are joint actions, each combining one move per
+    /// player, which `player_move` takes apart. Used by `select::Duct`.
+    #[allow(unused_variables)]
+    fn is_simultaneous(state: &Self::S) -> bool {
+        false
+    }
+
+    /// The index of `player`'s own move within the joint `action` of a
+    /// simultaneous state. Joint actions with the same move for a player share
+    /// that player's statistics under `select::Duct`.
+    #[allow(unused_variables)]
+    fn player_move(state: &Self::S, action: &Self::A, player: usize) -> usize {
+        unimplemented!();
+    }
+}
+
+/// A game in which all players move at once, described by each player's own
+/// moves. `games::wrappers::Simultaneous` turns it into a `Game` with joint
+/// actions. A player with nothing to decide on a turn should be given a single
+/// move, e.g. a pass.
+pub trait SimultaneousGame: Sized + Clone + Sync + Send {
+    type S: Clone + Default + std::fmt::Debug + Sized + Sync + Send + Eq + std::fmt::Display;
+
+    /// One player's move.
+    type M: Action;
+
+    fn num_players() -> usize {
+        2
+    }
+
+    /// The moves available to `player` in `state`. Not invoked if
+    /// `is_terminal` returns `true`.
+    fn player_moves(state: &Self::S, player: PlayerId, moves: &mut Vec<Self::M>);
+
+    /// Apply the players' moves, in player order.
+    fn apply(state: Self::S, moves: &[Self::M]) -> Self::S;
+
+    fn is_terminal(state: &Self::S) -> bool;
+
+    /// The utility of each player in a terminal state.
+    fn utilities(state: &Self::S) -> Vec<f64>;
+
+    #[allow(unused_variables)]
+    fn zobrist_hash(state: &Self::S) -> u64 {
+        0
+    }
 }
@@ -4,8 +4,10 @@ use super::bitboard;
 use super::bitboard::BitBoard;
 use crate::display::RectangularBoard;
 use crate::display::RectangularBoardDisplay;
+use crate::game::ConfigHints;
 use crate::game::Game;
 use crate::game::PlayerIndex;
+use crate::gtp::{GtpGame, Vertex};
 
 use serde::Serialize;
 use std::fmt;
@@ -35,15 +37,67 @@ impl PlayerIndex for Player {
 #[derive(Clone, Copy, Serialize, Debug, Hash, PartialEq, Eq)]
 pub struct Move(u8, u64);
 
+/// Rule variations. The board size is given by `N`, and may be up to 8x8.
+/// Suicide is never allowed. If the player to move has no legal moves the game
+/// ends, and is decided by the number of stones captured.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+pub struct Rules {
+    // The number of stones a player must capture to win, at least 1
+    pub capture_target: u8,
+    // Allow moves which leave the moving group with a single liberty
+    pub allow_self_atari: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            capture_target: 1,
+            allow_self_atari: true,
+        }
+    }
+}
+
+impl Rules {
+    pub fn capture_target(mut self, capture_target: u8) -> Self {
+        assert!(capture_target > 0, "capture_target must be at least 1");
+        self.capture_target = capture_target;
+        self
+    }
+
+    pub fn allow_self_atari(mut self, allow_self_atari: bool) -> Self {
+        self.allow_self_atari = allow_self_atari;
+        self
+    }
+}
+
 #[derive(Clone, Copy, Serialize, Debug, Default, PartialEq, Eq)]
 pub struct State<const N: usize> {
     black: BitBoard<N, N>,
     white: BitBoard<N, N>,
     turn: Player,
-    winner: bool,
+    winner: Option<Player>,
+    captures: [u8; 2],
+    rules: Rules,
+    // The player to move has no legal moves, found once per move in `apply`
+    stalemate: bool,
 }
 
 impl<const N: usize> State<N> {
+    pub fn with_rules(rules: Rules) -> Self {
+        assert!(
+            rules.capture_target > 0,
+            "capture_target must be at least 1"
+        );
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    pub fn captures(&self, player: Player) -> u8 {
+        self.captures[player.to_index()]
+    }
+
     #[inline(always)]
     fn occupied(&self) -> BitBoard<N, N> {
         self.black | self.white
@@ -70,11 +124,29 @@ impl<const N: usize> State<N> {
 
     #[inline]
     fn valid(&self, index: usize) -> (bool, BitBoard<N, N>) {
-        bitboard::check_go_move::<N, N>(
-            self.player(self.turn),
-            self.player(self.turn.next()),
-            index,
-        )
+        let player = self.player(self.turn);
+        let opponent = self.player(self.turn.next());
+        let (valid, will_capture) = bitboard::check_go_move::<N, N>(player, opponent, index);
+        if !valid || self.rules.allow_self_atari || !will_capture.is_empty() {
+            return (valid, will_capture);
+        }
+
+        let player = player | BitBoard::from_index(index);
+        let liberties = player.flood4(index).adjacency_mask() & !(player | opponent);
+        (liberties.count_ones() > 1, will_capture)
+    }
+
+    fn moves(&self, actions: &mut Vec<Move>) {
+        for index in !self.occupied() {
+            let (valid, will_capture) = self.valid(index);
+            if valid {
+                actions.push(Move(index as u8, will_capture.get_raw()))
+            }
+        }
+    }
+
+    fn has_moves(&self) -> bool {
+        (!self.occupied()).any(|index| self.valid(index).0)
     }
 
     #[inline]
@@ -92,10 +164,13 @@ impl<const N: usize> State<N> {
                 self.black = opponent & !BitBoard::new(action.1);
             }
         }
-        if action.1 > 0 {
-            self.winner = true;
+        let captures = &mut self.captures[self.turn.to_index()];
+        *captures = captures.saturating_add(action.1.count_ones() as u8);
+        if *captures >= self.rules.capture_target {
+            self.winner = Some(self.turn);
         } else {
             self.turn = self.turn.next();
+            self.stalemate = !self.has_moves();
         }
 
         *self
@@ -115,14 +190,13 @@ impl<const N: usize> Game for AtariGo<N> {
     }
 
     fn generate_actions(state: &State<N>, actions: &mut Vec<Move>) {
-        for index in !state.occupied() {
-            let (mut valid, will_capture) = state.valid(index);
-            actions.push(Move(index as u8, will_capture.get_raw()))
+        if state.winner.is_none() {
+            state.moves(actions);
         }
     }
 
     fn is_terminal(state: &State<N>) -> bool {
-        state.winner
+        state.winner.is_some() || state.stalemate
     }
 
     fn player_to_move(state: &State<N>) -> Player {
@@ -130,13 +204,37 @@ impl<const N: usize> Game for AtariGo<N> {
     }
 
     fn winner(state: &State<N>) -> Option<Player> {
-        if state.winner {
-            Some(state.turn)
-        } else {
-            None
+        state.winner.or_else(|| {
+            use std::cmp::Ordering::*;
+            match state.captures[0].cmp(&state.captures[1]) {
+                Greater => Some(Player::Black),
+                Less => Some(Player::White),
+                Equal => None,
+            }
+        })
+    }
+
+    fn playout_cutoff(state: &State<N>) -> Option<Player> {
+        // A lead of at least two stones and half the capture target is rarely
+        // overturned in random play
+        let lead = state.captures[0] as i32 - state.captures[1] as i32;
+        let margin = lead.unsigned_abs();
+        let decisive = margin >= 2 && 2 * margin >= state.rules.capture_target as u32;
+        match lead.signum() {
+            1 if decisive => Some(Player::Black),
+            -1 if decisive => Some(Player::White),
+            _ => None,
         }
     }
 
+    fn utilities_nonterminal(state: &State<N>) -> Option<Vec<f64>> {
+        // The capture lead as a fraction of the target, worth at most half a
+        // win since `winner` would otherwise award the game to any lead.
+        let lead = state.captures[0] as f64 - state.captures[1] as f64;
+        let utility = 0.5 * (lead / state.rules.capture_target as f64).clamp(-1., 1.);
+        Some(vec![utility, -utility])
+    }
+
     fn notation(state: &Self::S, action: &Self::A) -> String {
         const COL_NAMES: &[u8] = b"ABCDEFGH";
         let (row, col) = BitBoard::<N, N>::to_coord(action.0 as usize);
@@ -146,6 +244,32 @@ impl<const N: usize> Game for AtariGo<N> {
     fn num_players() -> usize {
         2
     }
+
+    // The capture set is derived from the position
+    fn actions_equal_modulo_state(_state: &State<N>, a: &Move, b: &Move) -> bool {
+        a.0 == b.0
+    }
+
+    fn recommended_config_hints() -> ConfigHints {
+        // With a capture target above one, stones can be recaptured forever
+        ConfigHints {
+            max_playout_depth: Some(4 * N * N),
+            ..Default::default()
+        }
+    }
+}
+impl<const N: usize> GtpGame for AtariGo<N> {
+    const BOARD_SIZE: usize = N;
+
+    fn vertex(action: &Move) -> Vertex {
+        let (row, col) = BitBoard::<N, N>::to_coord(action.0 as usize);
+        Vertex::Point { row, col }
+    }
+
+    // The capture lead
+    fn score(state: &State<N>) -> f64 {
+        state.captures[0] as f64 - state.captures[1] as f64
+    }
 }
 
 impl<const N: usize> RectangularBoard for State<N> {
@@ -178,5 +302,96 @@ mod tests {
     #[test]
     fn test_atarigo() {
         random_play::<AtariGo<7>>();
+        random_play::<AtariGo<5>>();
+        random_play::<AtariGo<8>>();
+    }
+
+    #[test]
+    fn test_stalemate() {
+        use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+        // The flag kept by `apply` matches a scan for legal moves
+        let mut rng = SmallRng::seed_from_u64(0);
+        let rules = Rules::default().capture_target(8).allow_self_atari(false);
+        for _ in 0..20 {
+            let mut state = State::<5>::with_rules(rules);
+            let mut actions = vec![];
+            while state.winner.is_none() && !state.stalemate {
+                assert!(state.has_moves());
+                actions.clear();
+                AtariGo::generate_actions(&state, &mut actions);
+                state = AtariGo::apply(state, &actions[rng.gen_range(0..actions.len())]);
+            }
+            assert!(AtariGo::is_terminal(&state));
+            assert!(state.winner.is_some() || !state.has_moves());
+        }
+    }
+
+    fn play<const N: usize>(mut state: State<N>, moves: &[(usize, usize)]) -> State<N> {
+        for (row, col) in moves {
+            let index = BitBoard::<N, N>::to_index(*row, *col);
+            let mut actions = vec![];
+            AtariGo::generate_actions(&state, &mut actions);
+            let action = actions.iter().find(|m| m.0 as usize == index).unwrap();
+            state = AtariGo::apply(state, action);
+        }
+        state
+    }
+
+    #[test]
+    fn test_capture_target() {
+        // Black captures the white stone in the corner
+        let moves = [(0, 1), (0, 0), (1, 0)];
+
+        let state = play(State::<5>::default(), &moves);
+        assert!(AtariGo::is_terminal(&state));
+        assert_eq!(AtariGo::winner(&state), Some(Player::Black));
+
+        let state = play(
+            State::<5>::with_rules(Rules::default().capture_target(2)),
+            &moves,
+        );
+        assert!(!AtariGo::is_terminal(&state));
+        assert_eq!(state.captures(Player::Black), 1);
+        assert_eq!(AtariGo::player_to_move(&state), Player::White);
+        // Halfway to the target, so a quarter of a win if the playout stops here
+        assert_eq!(
+            AtariGo::utilities_nonterminal(&state),
+            Some(vec![0.25, -0.25])
+        );
+        // But not yet a decisive lead
+        assert_eq!(AtariGo::playout_cutoff(&state), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "capture_target must be at least 1")]
+    fn test_zero_capture_target() {
+        Rules::default().capture_target(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "capture_target must be at least 1")]
+    fn test_zero_capture_target_rules() {
+        State::<5>::with_rules(Rules {
+            capture_target: 0,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_self_atari() {
+        // White at (0, 1) leaves a black move at (0, 0) with one liberty
+        let moves = [(2, 2), (0, 1)];
+        let corner = BitBoard::<5, 5>::to_index(0, 0);
+
+        let has_corner = |state: &State<5>| {
+            let mut actions = vec![];
+            AtariGo::generate_actions(state, &mut actions);
+            actions.iter().any(|m| m.0 as usize == corner)
+        };
+
+        assert!(has_corner(&play(State::<5>::default(), &moves)));
+        let rules = Rules::default().allow_self_atari(false);
+        assert!(!has_corner(&play(State::<5>::with_rules(rules), &moves)));
     }
 }
@@ -10,7 +10,7 @@
 //  - Because we don't want the hidden bids to affect the rollouts we have
 //    to determinize the results.
 
-use crate::game::{Game, PlayerIndex};
+use crate::game::{Game, PlayerId, PlayerIndex};
 use rand::rngs::SmallRng;
 use rand::Rng;
 use serde::Serialize;
@@ -22,7 +22,7 @@ impl PlayerIndex for Piece {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
 pub enum Piece {
     X,
     O,
@@ -37,7 +37,7 @@ impl Piece {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
 pub struct Player {
     chips: u16,
     bid: u16,
@@ -56,7 +56,7 @@ impl Player {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
 pub enum Phase {
     BidX,
     BidO,
@@ -80,7 +80,7 @@ pub enum Move {
 
 const BOARD_LEN: usize = 9;
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
 pub struct BiddingTicTacToe {
     pub board: [Option<Piece>; BOARD_LEN],
     pub x: Player,
@@ -97,15 +97,39 @@ impl Default for BiddingTicTacToe {
 
 impl BiddingTicTacToe {
     pub fn new() -> Self {
+        Self::with_chips(100)
+    }
+
+    pub fn with_chips(chips: u16) -> Self {
         Self {
             board: [None; BOARD_LEN],
-            x: Player { chips: 100, bid: 0 },
-            o: Player { chips: 100, bid: 0 },
+            x: Player { chips, bid: 0 },
+            o: Player { chips, bid: 0 },
             tiebreaker: Piece::O,
             phase: Phase::BidX,
         }
     }
 
+    /// The chips X holds including any hidden bid. Bids are revealed by the
+    /// chip transfer once both players have bid, so during `Phase::BidO` this
+    /// and X's bid are all that O does not observe directly.
+    pub fn x_total(&self) -> u16 {
+        self.x.chips + self.x.bid
+    }
+
+    /// The state as O observes it during `Phase::BidO`, with X's bid folded
+    /// back into X's chips. States with the same information set are equal.
+    pub fn information_set(&self) -> Self {
+        let mut state = *self;
+        if state.phase == Phase::BidO {
+            state.x = Player {
+                chips: self.x_total(),
+                bid: 0,
+            };
+        }
+        state
+    }
+
     fn pick_x(&mut self) {
         self.phase = Phase::PlayX;
         self.o.chips += self.o.bid + self.x.bid;
@@ -264,18 +288,35 @@ impl Game for BiddingTicTacToe {
         state
     }
 
+    // Resamples X's hidden bid with a uniform belief, which is exact when X
+    // bids uniformly at random as in playouts (see `belief_error`). The tree
+    // itself is still built from the true state, so nodes below O's bid see
+    // X's actual bid; use `Belief::sample` with an opponent model at the root,
+    // or search with `ismcts::MoIsmcts`, to avoid that.
     fn determinize(state: Self::S, rng: &mut SmallRng) -> Self::S {
-        let mut state = state;
-        // Not sure this is enough to hide all the bid information. I think
-        // we introduce bias by not modeling simultaneous moves directly. But
-        // this is a start.
         if state.phase == Phase::BidO {
-            let chips = state.x.chips + state.x.bid;
-            let n = rng.gen_range(0..=chips);
-            state.x.chips = n;
-            state.x.bid = chips - n;
+            Belief::uniform(state.x_total()).sample(&state, rng)
+        } else {
+            state
+        }
+    }
+
+    // Only O is ever missing information: X's bid
+    fn determinize_for(state: Self::S, observer: PlayerId, rng: &mut SmallRng) -> Self::S {
+        if observer == PlayerId::of(&Piece::O) {
+            Self::determinize(state, rng)
+        } else {
+            state
+        }
+    }
+
+    // X's bid is hidden from O until the chips change hands
+    fn observe_action(state: &Self::S, m: &Self::A, observer: PlayerId) -> Option<Self::A> {
+        if state.phase == Phase::BidX && observer == PlayerId::of(&Piece::O) {
+            None
+        } else {
+            Some(*m)
         }
-        state
     }
 
     fn notation(_state: &Self::S, m: &Self::A) -> String {
@@ -307,3 +348,171 @@ impl Game for BiddingTicTacToe {
         state.player_to_move()
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// O's belief about X's hidden bid during `Phase::BidO`, as a distribution
+/// over bids `0..=x_total`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Belief {
+    weights: Vec<f64>,
+}
+
+impl Belief {
+    pub fn uniform(x_total: u16) -> Self {
+        Self::from_weights(vec![1.; x_total as usize + 1])
+    }
+
+    /// A belief proportional to the given non-negative weights, indexed by bid.
+    pub fn from_weights(weights: Vec<f64>) -> Self {
+        let total: f64 = weights.iter().sum();
+        assert!(total > 0., "belief weights must not all be zero");
+        Self {
+            weights: weights.into_iter().map(|w| w / total).collect(),
+        }
+    }
+
+    pub fn probability(&self, bid: u16) -> f64 {
+        self.weights.get(bid as usize).copied().unwrap_or(0.)
+    }
+
+    /// Draw a bid from the belief.
+    pub fn draw(&self, rng: &mut SmallRng) -> u16 {
+        let mut r = rng.gen::<f64>();
+        self.weights
+            .iter()
+            .position(|p| {
+                r -= p;
+                r < 0.
+            })
+            .unwrap_or(self.weights.len() - 1) as u16
+    }
+
+    /// Replace X's hidden bid with one drawn from the belief.
+    pub fn sample(&self, state: &BiddingTicTacToe, rng: &mut SmallRng) -> BiddingTicTacToe {
+        debug_assert_eq!(state.phase, Phase::BidO);
+        debug_assert_eq!(self.weights.len(), state.x_total() as usize + 1);
+        let total = state.x_total();
+        let bid = self.draw(rng);
+        let mut state = *state;
+        state.x = Player {
+            chips: total - bid,
+            bid,
+        };
+        state
+    }
+
+    /// The total variation distance between two beliefs.
+    pub fn distance(&self, other: &Belief) -> f64 {
+        let len = self.weights.len().max(other.weights.len());
+        (0..len as u16)
+            .map(|bid| (self.probability(bid) - other.probability(bid)).abs())
+            .sum::<f64>()
+            / 2.
+    }
+}
+
+/// Measure how far a belief about X's hidden bid is from the true distribution
+/// over O's information sets, for games played from
+/// `BiddingTicTacToe::with_chips(chips)`.
+///
+/// X bids according to `x_policy` and every other move is uniformly random.
+/// All other information is public, so an information set reveals the state
+/// X bid from. The true distribution of the hidden bid is then exactly
+/// `x_policy` at that state. `games` games are sampled to find which
+/// information sets are reached and how often. The result is the total
+/// variation distance between `belief` and the truth, averaged over the
+/// information sets and weighted by how often each was reached.
+pub fn belief_error<B, F>(
+    chips: u16,
+    games: usize,
+    x_policy: F,
+    belief: B,
+    rng: &mut SmallRng,
+) -> f64
+where
+    F: Fn(&BiddingTicTacToe) -> Belief,
+    B: Fn(&BiddingTicTacToe) -> Belief,
+{
+    use rustc_hash::FxHashMap;
+
+    let mut reached: FxHashMap<BiddingTicTacToe, usize> = FxHashMap::default();
+    for _ in 0..games {
+        let mut state = BiddingTicTacToe::with_chips(chips);
+        while !BiddingTicTacToe::is_terminal(&state) {
+            if state.phase == Phase::BidO {
+                *reached.entry(state.information_set()).or_default() += 1;
+            }
+            let m = if state.phase == Phase::BidX {
+                Move::Bid(x_policy(&state).draw(rng))
+            } else {
+                let moves = state.gen_moves();
+                moves[rng.gen_range(0..moves.len())]
+            };
+            state.apply(m);
+        }
+    }
+
+    let visits: usize = reached.values().sum();
+    reached
+        .iter()
+        .map(|(info, n)| {
+            let mut bid_state = *info;
+            bid_state.phase = Phase::BidX;
+            let truth = x_policy(&bid_state);
+            belief(info).distance(&truth) * *n as f64 / visits as f64
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_belief_sample() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut state = BiddingTicTacToe::with_chips(3);
+        state.apply(Move::Bid(2));
+
+        let belief = Belief::from_weights(vec![0., 1., 0., 1.]);
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            let sampled = belief.sample(&state, &mut rng);
+            assert_eq!(sampled.x_total(), 3);
+            assert_eq!(sampled.information_set(), state.information_set());
+            counts[sampled.x.bid as usize] += 1;
+        }
+        assert_eq!((counts[0], counts[2]), (0, 0));
+        assert!(counts[1] > 400 && counts[3] > 400);
+
+        // The game's determinization samples uniformly
+        let mut counts = [0; 4];
+        for _ in 0..4000 {
+            let sampled = BiddingTicTacToe::determinize(state, &mut rng);
+            counts[sampled.x.bid as usize] += 1;
+        }
+        assert!(counts.iter().all(|n| (800..1200).contains(n)));
+    }
+
+    #[test]
+    fn test_belief_error() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let uniform = |state: &BiddingTicTacToe| Belief::uniform(state.x_total());
+        let all_in = |state: &BiddingTicTacToe| {
+            let mut weights = vec![0.; state.x_total() as usize + 1];
+            weights[state.x_total() as usize] = 1.;
+            Belief::from_weights(weights)
+        };
+
+        // The uniform belief used by `determinize` is exact against a
+        // uniformly random bidder, as in playouts
+        assert!(belief_error(3, 200, uniform, uniform, &mut rng) < 1e-9);
+
+        // Against an opponent who always bids everything it is biased, while
+        // a belief modelling that opponent is exact
+        assert!(belief_error(3, 200, all_in, uniform, &mut rng) > 0.25);
+        assert!(belief_error(3, 200, all_in, all_in, &mut rng) < 1e-9);
+    }
+}
@@ -85,7 +85,6 @@ impl<const N: usize, const M: usize> Default for BitBoard<N, M> {
 /////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// For the `BitBoard`, iterate over every positition set.
-
 impl<const N: usize, const M: usize> Iterator for BitBoard<N, M> {
     type Item = usize;
 
@@ -651,6 +650,177 @@ impl<const N: usize, const M: usize> BitBoard<N, M> {
     pub fn connects_walls8(self, start: usize, a: Direction, b: Direction) -> bool {
         self.has_connections8(start, Self::wall(a), Self::wall(b))
     }
+
+    /// The fewest cells from `open` which must be added to the set bits to
+    /// form a four-way connection between walls `a` and `b`, or `None` if no
+    /// such connection is possible.
+    pub fn connection_distance4(self, open: Self, a: Direction, b: Direction) -> Option<u32> {
+        let (a, b) = (Self::wall(a), Self::wall(b));
+        let passable = self | open;
+        // Spread through the set bits, which are free to traverse
+        let spread = |mut flood: Self| loop {
+            let temp = flood;
+            flood |= flood.adjacency_mask() & self;
+            if flood == temp {
+                return flood;
+            }
+        };
+
+        let mut reached = spread(a & self);
+        let mut distance = 0;
+        while !reached.intersects(b) {
+            let next = spread(reached | ((reached.adjacency_mask() | a) & passable));
+            if next == reached {
+                return None;
+            }
+            reached = next;
+            distance += 1;
+        }
+        Some(distance)
+    }
+
+    /// The shorter of the north-south and east-west connection distances.
+    pub fn opposite_connection_distance4(self, open: Self) -> Option<u32> {
+        let ns = self.connection_distance4(open, Direction::North, Direction::South);
+        let ew = self.connection_distance4(open, Direction::East, Direction::West);
+        ns.into_iter().chain(ew).min()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The four line orientations used for K-in-a-row detection. Each line is
+/// traversed in the direction given: east, north, north-east and north-west.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum Line {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal,
+}
+
+impl Line {
+    pub const ALL: [Line; 4] = [
+        Line::Horizontal,
+        Line::Vertical,
+        Line::Diagonal,
+        Line::AntiDiagonal,
+    ];
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Line detection
+
+impl<const N: usize, const M: usize> BitBoard<N, M> {
+    /// Shift one step forward along the line.
+    #[inline]
+    pub fn shift_line(self, line: Line) -> Self {
+        match line {
+            Line::Horizontal => self.shift_east(),
+            Line::Vertical => self.shift_north(),
+            Line::Diagonal => self.shift_north().shift_east(),
+            Line::AntiDiagonal => self.shift_north().shift_west(),
+        }
+    }
+
+    /// Shift one step backward along the line.
+    #[inline]
+    pub fn shift_line_back(self, line: Line) -> Self {
+        match line {
+            Line::Horizontal => self.shift_west(),
+            Line::Vertical => self.shift_south(),
+            Line::Diagonal => self.shift_south().shift_west(),
+            Line::AntiDiagonal => self.shift_south().shift_east(),
+        }
+    }
+
+    /// All positions on the line through `index`.
+    pub fn line_mask(index: usize, line: Line) -> Self {
+        let start = Self::from_index(index);
+        let mut mask = start;
+        let (mut fwd, mut back) = (start, start);
+        while !(fwd.is_empty() && back.is_empty()) {
+            fwd = fwd.shift_line(line);
+            back = back.shift_line_back(line);
+            mask |= fwd | back;
+        }
+        mask
+    }
+
+    /// Positions which end a run of at least `n` set bits along the line. The
+    /// run extends backwards from each returned position.
+    #[inline]
+    pub fn n_in_row(self, line: Line, n: usize) -> Self {
+        debug_assert!(n > 0);
+        let mut ends = self;
+        for _ in 1..n {
+            ends = ends.shift_line(line) & self;
+        }
+        ends
+    }
+
+    /// Check for a run of at least `n` set bits in any direction.
+    pub fn has_n_in_row(self, n: usize) -> bool {
+        Line::ALL
+            .iter()
+            .any(|line| !self.n_in_row(*line, n).is_empty())
+    }
+
+    /// Positions in `empty` which would complete a run of at least `n` set
+    /// bits if played. This is the usual winning move test for K-in-a-row
+    /// games, and is suitable for decisive move heuristics.
+    pub fn completing_moves(self, empty: Self, n: usize) -> Self {
+        debug_assert!(n > 0);
+        let mut result = Self::EMPTY;
+        for line in Line::ALL {
+            // back[k] is set where the k positions behind are set, and fwd[k]
+            // where the k positions ahead are set.
+            let mut back = vec![Self::ONES; n];
+            let mut fwd = vec![Self::ONES; n];
+            let (mut b, mut f) = (self, self);
+            for k in 1..n {
+                b = b.shift_line(line);
+                f = f.shift_line_back(line);
+                back[k] = back[k - 1] & b;
+                fwd[k] = fwd[k - 1] & f;
+            }
+            for k in 0..n {
+                result |= back[k] & fwd[n - 1 - k];
+            }
+        }
+        result & empty
+    }
+
+    /// Positions which end a run of exactly `n` set bits along the line where
+    /// the positions on both sides of the run are in `empty`.
+    pub fn open_runs(self, empty: Self, line: Line, n: usize) -> Self {
+        let ends = self.n_in_row(line, n);
+        // Set where the position n steps behind is empty
+        let mut before = empty;
+        for _ in 0..n {
+            before = before.shift_line(line);
+        }
+        ends & before & empty.shift_line_back(line)
+    }
+
+    /// The number of open runs of exactly `n` over all directions.
+    pub fn count_open_runs(self, empty: Self, n: usize) -> u32 {
+        Line::ALL
+            .iter()
+            .map(|line| self.open_runs(empty, *line, n).count_ones())
+            .sum()
+    }
+
+    #[inline]
+    pub fn open_threes(self, empty: Self) -> u32 {
+        self.count_open_runs(empty, 3)
+    }
+
+    #[inline]
+    pub fn open_fours(self, empty: Self) -> u32 {
+        self.count_open_runs(empty, 4)
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////////////////////////
@@ -777,6 +947,92 @@ mod tests {
         assert_eq!(flood, expected);
     }
 
+    #[test]
+    fn test_line_mask() {
+        type B = BitBoard<3, 3>;
+        assert_eq!(B::line_mask(4, Line::Horizontal), B::new(0b000_111_000));
+        assert_eq!(B::line_mask(4, Line::Vertical), B::new(0b010_010_010));
+        assert_eq!(B::line_mask(4, Line::Diagonal), B::new(0b100_010_001));
+        assert_eq!(B::line_mask(4, Line::AntiDiagonal), B::new(0b001_010_100));
+        assert_eq!(B::line_mask(1, Line::Diagonal), B::new(0b000_100_010));
+    }
+
+    #[test]
+    fn test_n_in_row() {
+        type B = BitBoard<3, 3>;
+        assert!(B::new(0b100_010_001).has_n_in_row(3));
+        assert!(B::new(0b001_010_100).has_n_in_row(3));
+        assert!(B::new(0b010_010_010).has_n_in_row(3));
+        assert!(B::new(0b000_000_111).has_n_in_row(3));
+        // Must not wrap around the board edge
+        assert!(!B::new(0b000_011_100).has_n_in_row(3));
+        assert!(!B::new(0b001_100_010).has_n_in_row(3));
+        assert!(B::new(0b001_100_010).has_n_in_row(1));
+    }
+
+    #[test]
+    fn test_completing_moves() {
+        type B = BitBoard<3, 3>;
+        let stones = B::new(0b000_000_101);
+        let empty = B::ONES ^ stones;
+        assert_eq!(stones.completing_moves(empty, 3), B::from_index(1));
+
+        let stones = B::new(0b000_010_001);
+        let empty = B::ONES ^ stones;
+        assert_eq!(stones.completing_moves(empty, 3), B::from_index(8));
+
+        let stones = B::new(0b000_010_001);
+        let empty = B::ONES ^ stones ^ B::from_index(8);
+        assert_eq!(stones.completing_moves(empty, 3), B::EMPTY);
+    }
+
+    #[test]
+    fn test_open_runs() {
+        type B = BitBoard<8, 8>;
+        let three = B::from_coord(3, 2) | B::from_coord(3, 3) | B::from_coord(3, 4);
+        let empty = B::ONES ^ three;
+        assert_eq!(three.open_threes(empty), 1);
+        assert_eq!(three.open_fours(empty), 0);
+
+        // Blocking one end closes the three
+        let blocked = empty ^ B::from_coord(3, 1);
+        assert_eq!(three.open_threes(blocked), 0);
+
+        // A run against the wall is never open
+        let edge = B::from_coord(0, 0) | B::from_coord(1, 0) | B::from_coord(2, 0);
+        assert_eq!(edge.open_threes(B::ONES ^ edge), 0);
+
+        let four = three | B::from_coord(3, 5);
+        let empty = B::ONES ^ four;
+        assert_eq!(four.open_fours(empty), 1);
+        assert_eq!(four.open_threes(empty), 0);
+    }
+
+    #[test]
+    fn test_connection_distance4() {
+        type B = BitBoard<4, 4>;
+        let column = |col| (0..4).fold(B::EMPTY, |acc, row| acc | B::from_coord(row, col));
+
+        // An empty board needs a full line of stones
+        assert_eq!(B::EMPTY.opposite_connection_distance4(B::ONES), Some(4));
+
+        // Stones already placed in a column are free to use
+        let stones = B::from_coord(0, 1) | B::from_coord(2, 1);
+        let empty = B::ONES ^ stones;
+        assert_eq!(stones.opposite_connection_distance4(empty), Some(2));
+
+        // A complete column is connected
+        assert_eq!(column(1).opposite_connection_distance4(!column(1)), Some(0));
+
+        // An opposing wall blocks east-west but not north-south
+        let open = B::ONES ^ column(2);
+        assert_eq!(
+            B::EMPTY.connection_distance4(open, Direction::East, Direction::West),
+            None
+        );
+        assert_eq!(B::EMPTY.opposite_connection_distance4(open), Some(4));
+    }
+
     /////////////////////////////////////////////////////////////////////////////////////////////
 
     use super::super::bitboard_match::*;
@@ -6,7 +6,12 @@ use crate::display::RectangularBoard;
 use crate::display::RectangularBoardDisplay;
 use crate::game::Game;
 use crate::game::PlayerIndex;
+use crate::strategies::mcts::simulate::SimulateStrategy;
+use crate::strategies::mcts::TreeStats;
+use crate::util::random_best;
 
+use rand::rngs::SmallRng;
+use rand::Rng;
 use serde::Serialize;
 use std::fmt;
 
@@ -150,7 +155,8 @@ impl<const N: usize, const M: usize> State<N, M> {
             }
         };
 
-        if player.intersects(goal) {
+        // Reaching the goal row or capturing every opposing piece wins.
+        if player.intersects(goal) || opponent.is_empty() {
             self.winner = true;
         } else {
             self.turn = self.turn.next();
@@ -201,6 +207,197 @@ impl<const N: usize, const M: usize> Game for Breakthrough<N, M> {
     fn num_players() -> usize {
         2
     }
+
+    // Every move advances one row, so an action is identified by its source
+    // and the column offset of the destination.
+    fn max_actions() -> usize {
+        N * M * 3
+    }
+
+    fn action_index(_state: &Self::S, action: &Self::A) -> usize {
+        let (_, src_col) = BitBoard::<N, M>::to_coord(action.src());
+        let (_, dst_col) = BitBoard::<N, M>::to_coord(action.dst());
+        action.src() * 3 + (dst_col + 1 - src_col)
+    }
+
+    fn action_from_index(state: &Self::S, index: usize) -> Self::A {
+        let src = index / 3;
+        let (row, col) = BitBoard::<N, M>::to_coord(src);
+        let row = match state.turn {
+            Player::Black => row - 1,
+            Player::White => row + 1,
+        };
+        let dst = BitBoard::<N, M>::to_index(row, col + index % 3 - 1);
+        Move(src as u8, dst as u8)
+    }
+
+    fn is_legal_index(state: &Self::S, index: usize) -> Option<bool> {
+        let src = index / 3;
+        let player = state.player(state.turn);
+        if state.winner || !player.get(src) {
+            return Some(false);
+        }
+        let (row, col) = BitBoard::<N, M>::to_coord(src);
+        let row = match state.turn {
+            Player::Black => row.checked_sub(1),
+            Player::White => Some(row + 1).filter(|row| *row < N),
+        };
+        let col = (col + index % 3).checked_sub(1).filter(|col| *col < M);
+        let (Some(row), Some(col)) = (row, col) else {
+            return Some(false);
+        };
+        // Straight ahead onto an empty square, or diagonally onto any but our own
+        let blocked = if index % 3 == 1 {
+            state.occupied()
+        } else {
+            player
+        };
+        Some(!blocked.get_at(row, col))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Domain knowledge, following Lorentz and Horey, "Programming Breakthrough".
+
+impl<const N: usize, const M: usize> State<N, M> {
+    #[inline]
+    fn forward(pieces: BitBoard<N, M>, player: Player) -> BitBoard<N, M> {
+        match player {
+            Player::Black => pieces.shift_south(),
+            Player::White => pieces.shift_north(),
+        }
+    }
+
+    /// Squares attacked by the given pieces.
+    #[inline]
+    fn attacks(pieces: BitBoard<N, M>, player: Player) -> BitBoard<N, M> {
+        let forward = Self::forward(pieces, player);
+        forward.shift_east() | forward.shift_west()
+    }
+
+    #[inline]
+    fn goal(player: Player) -> BitBoard<N, M> {
+        match player {
+            Player::Black => BitBoard::wall(bitboard::Direction::South),
+            Player::White => BitBoard::wall(bitboard::Direction::North),
+        }
+    }
+
+    #[inline]
+    fn home(player: Player) -> BitBoard<N, M> {
+        Self::goal(player.next())
+    }
+
+    /// The number of rows a piece at `index` has advanced from its home row.
+    #[inline]
+    fn progress(index: usize, player: Player) -> usize {
+        let (row, _) = BitBoard::<N, M>::to_coord(index);
+        match player {
+            Player::Black => N - 1 - row,
+            Player::White => row,
+        }
+    }
+}
+
+/// A heuristic evaluation of the position in [-1, 1] from the perspective of
+/// `player`. This combines material, advancement, the safety of advanced
+/// pieces, and the number of defenders remaining on the home row.
+pub fn evaluate<const N: usize, const M: usize>(state: &State<N, M>, player: Player) -> f64 {
+    let side = |p: Player| {
+        let pieces = state.player(p);
+        let opponent = state.player(p.next());
+        let defended = State::attacks(pieces, p);
+        let attacked = State::attacks(opponent, p.next());
+
+        let mut score = 10. * pieces.count_ones() as f64;
+        for index in pieces {
+            let progress = State::<N, M>::progress(index, p) as f64;
+            score += progress * progress / N as f64;
+            let square = BitBoard::from_index(index);
+            if square.intersects(attacked) && !square.intersects(defended) {
+                score -= progress;
+            }
+        }
+        score + 3. * (pieces & State::home(p)).count_ones() as f64
+    };
+
+    ((side(player) - side(player.next())) / 20.).tanh()
+}
+
+/// A playout policy using Breakthrough domain knowledge: take wins, capture
+/// pieces which threaten to win, prefer safe captures, and otherwise prefer
+/// moves which improve the evaluation without leaving the piece en prise.
+#[derive(Clone)]
+pub struct Knowledge {
+    pub epsilon: f64,
+}
+
+impl Default for Knowledge {
+    fn default() -> Self {
+        Self { epsilon: 0.1 }
+    }
+}
+
+impl Knowledge {
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    fn score<const N: usize, const M: usize>(state: &State<N, M>, action: &Move) -> f64 {
+        let player = state.turn;
+        let opponent = state.player(player.next());
+        let dst = BitBoard::from_index(action.dst());
+
+        if dst.intersects(State::goal(player)) {
+            return 1000.;
+        }
+
+        let mut child = *state;
+        child.apply(action);
+        let remaining = child.player(player.next());
+        let safe = !dst.intersects(State::attacks(remaining, player.next()));
+
+        if dst.intersects(opponent) {
+            // Anti-decisive: the captured piece was one step from winning
+            let threat = State::forward(State::goal(player.next()), player);
+            if dst.intersects(threat) {
+                return 500.;
+            }
+            return if safe { 100. } else { 20. };
+        }
+
+        let value = evaluate(&child, player);
+        if safe {
+            value
+        } else {
+            value - 10.
+        }
+    }
+}
+
+impl<const N: usize, const M: usize> SimulateStrategy<Breakthrough<N, M>> for Knowledge {
+    fn select_move<'a>(
+        &mut self,
+        state: &State<N, M>,
+        available: &'a [Move],
+        _stats: &TreeStats<Breakthrough<N, M>>,
+        _player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a Move {
+        if rng.gen::<f64>() < self.epsilon {
+            return &available[rng.gen_range(0..available.len())];
+        }
+        let action_scores = available
+            .iter()
+            .map(|action| (Self::score(state, action), action))
+            .collect::<Vec<_>>();
+
+        random_best(&action_scores, rng, |(score, _)| *score)
+            .unwrap()
+            .1
+    }
 }
 
 impl<const N: usize, const M: usize> RectangularBoard for State<N, M> {
@@ -227,6 +424,7 @@ impl<const N: usize, const M: usize> fmt::Display for State<N, M> {
 #[cfg(test)]
 mod tests {
     use crate::util::random_play;
+    use rand_core::SeedableRng;
 
     use super::*;
 
@@ -234,4 +432,82 @@ mod tests {
     fn test_breakthrough() {
         random_play::<Breakthrough<8, 8>>();
     }
+
+    #[test]
+    fn test_capture_all() {
+        type G = Breakthrough<6, 4>;
+        // Black takes White's last piece, which would otherwise leave White
+        // without a move in an unfinished game
+        let state = State::<6, 4> {
+            black: BitBoard::from_coord(3, 1),
+            white: BitBoard::from_coord(2, 2),
+            ..Default::default()
+        };
+        let mut actions = vec![];
+        G::generate_actions(&state, &mut actions);
+        let capture = actions
+            .into_iter()
+            .find(|action| state.white.get(action.1 as usize))
+            .unwrap();
+        let state = G::apply(state, &capture);
+        assert!(G::is_terminal(&state));
+        assert_eq!(G::winner(&state), Some(Player::Black));
+    }
+
+    #[test]
+    fn test_knowledge() {
+        type G = Breakthrough<6, 4>;
+        let init = State::<6, 4>::default();
+        assert_eq!(evaluate(&init, Player::Black), 0.);
+
+        // Losing a piece is bad for the side that lost it
+        let mut state = init;
+        state.white &= !BitBoard::from_index(0);
+        assert!(evaluate(&state, Player::Black) > 0.);
+        assert!(evaluate(&state, Player::White) < 0.);
+
+        // A piece one step from the goal always moves there
+        let mut state = init;
+        state.black = BitBoard::from_coord(1, 1);
+        state.white = BitBoard::from_coord(5, 3);
+        let mut actions = vec![];
+        G::generate_actions(&state, &mut actions);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut policy = Knowledge::default().epsilon(0.);
+        let stats = TreeStats::default();
+        let action = policy.select_move(&state, &actions, &stats, 0, &mut rng);
+        let mut child = state;
+        child.apply(action);
+        assert!(G::is_terminal(&child));
+        assert_eq!(G::winner(&child), Some(Player::Black));
+    }
+
+    #[test]
+    fn test_action_index() {
+        type G = Breakthrough<6, 5>;
+        let mut state = State::default();
+        let mut actions = vec![];
+        let mut seen = vec![false; G::max_actions()];
+        while !G::is_terminal(&state) {
+            actions.clear();
+            G::generate_actions(&state, &mut actions);
+            seen.iter_mut().for_each(|x| *x = false);
+            for action in &actions {
+                let index = G::action_index(&state, action);
+                assert!(index < G::max_actions());
+                assert!(!seen[index]);
+                seen[index] = true;
+                assert_eq!(G::action_from_index(&state, index), *action);
+            }
+            for (index, legal) in seen.iter().enumerate() {
+                assert_eq!(G::is_legal_index(&state, index), Some(*legal));
+            }
+            for word in (0..u32::MAX).step_by(1 << 24) {
+                if let Some(action) = G::sample_action(&state, word) {
+                    assert!(actions.contains(&action));
+                }
+            }
+            state = G::apply(state, &actions[actions.len() / 2]);
+        }
+    }
 }
@@ -53,6 +53,25 @@ impl Game for CountingGame {
         format!("{:?}", m).to_string()
     }
 
+    fn max_actions() -> usize {
+        2
+    }
+
+    fn action_index(_: &Self::S, m: &Self::A) -> usize {
+        match m {
+            Move::Add => 0,
+            Move::Sub => 1,
+        }
+    }
+
+    fn action_from_index(_: &Self::S, index: usize) -> Self::A {
+        match index {
+            0 => Move::Add,
+            1 => Move::Sub,
+            _ => unreachable!(),
+        }
+    }
+
     fn winner(_: &Self::S) -> Option<Unit> {
         Some(Unit)
     }
@@ -534,6 +534,10 @@ impl Game for Druid {
         state.1
     }
 
+    fn supports_transpositions() -> bool {
+        true
+    }
+
     fn apply(mut state: Self::S, m: &Self::A) -> Self::S {
         state.0.apply(*m);
 
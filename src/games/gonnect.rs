@@ -4,8 +4,11 @@ use super::bitboard;
 use super::bitboard::BitBoard;
 use crate::display::RectangularBoard;
 use crate::display::RectangularBoardDisplay;
+use crate::game::ConfigHints;
 use crate::game::Game;
 use crate::game::PlayerIndex;
+use crate::game::Scoring;
+use crate::gtp::{GtpGame, Vertex};
 
 use serde::Serialize;
 use std::fmt;
@@ -50,6 +53,7 @@ pub struct State<const N: usize> {
     turn: Player,
     can_swap: bool,
     winner: bool,
+    scoring: Scoring,
 }
 
 impl<const N: usize> Default for State<N> {
@@ -62,11 +66,29 @@ impl<const N: usize> Default for State<N> {
             turn: Player::default(),
             can_swap: true,
             winner: false,
+            scoring: Scoring::default(),
         }
     }
 }
 
 impl<const N: usize> State<N> {
+    pub fn with_scoring(scoring: Scoring) -> Self {
+        Self {
+            scoring,
+            ..Default::default()
+        }
+    }
+
+    /// How far the player is from connecting, as a fraction of the board
+    /// width. A player with no possible connection is a full width away.
+    pub fn connection_distance(&self, player: Player) -> f64 {
+        let distance = self
+            .player(player)
+            .opposite_connection_distance4(!self.occupied())
+            .map_or(N, |distance| (distance as usize).min(N));
+        distance as f64 / N as f64
+    }
+
     #[inline(always)]
     fn occupied(&self) -> BitBoard<N, N> {
         self.black | self.white
@@ -257,6 +279,77 @@ impl<const N: usize> Game for Gonnect<N> {
     fn num_players() -> usize {
         2
     }
+
+    // The capture set is derived from the position
+    fn actions_equal_modulo_state(_state: &State<N>, a: &Move, b: &Move) -> bool {
+        a.0 == b.0
+    }
+
+    fn recommended_config_hints() -> ConfigHints {
+        // Random playouts can cycle through ko without end
+        ConfigHints {
+            max_playout_depth: Some(4 * N * N),
+            ..Default::default()
+        }
+    }
+
+    fn playout_cutoff(state: &State<N>) -> Option<Player> {
+        // A player walled off from every connection has lost, unless captures
+        // reopen a path, which is rare enough to ignore in playouts
+        let blocked = |player| {
+            state
+                .player(player)
+                .opposite_connection_distance4(!state.occupied())
+                .is_none()
+        };
+        match (blocked(Player::Black), blocked(Player::White)) {
+            (true, false) => Some(Player::White),
+            (false, true) => Some(Player::Black),
+            _ => None,
+        }
+    }
+
+    fn utilities_nonterminal(state: &State<N>) -> Option<Vec<f64>> {
+        // Favor the player closer to connecting, worth at most half a win
+        let utility = 0.5
+            * (state.connection_distance(Player::White) - state.connection_distance(Player::Black));
+        Some(vec![utility, -utility])
+    }
+
+    fn compute_utilities(state: &State<N>) -> Vec<f64> {
+        let Some(winner) = Self::winner(state) else {
+            return vec![0., 0.];
+        };
+        // Under margin scoring a win is worth between 0.5 and 1, depending on
+        // how far the loser was from connecting.
+        let utility = match state.scoring {
+            Scoring::WinLoss => 1.,
+            Scoring::Margin => 0.5 + 0.5 * state.connection_distance(winner.next()),
+        };
+        match winner {
+            Player::Black => vec![utility, -utility],
+            Player::White => vec![-utility, utility],
+        }
+    }
+
+    fn with_scoring(state: State<N>, scoring: Scoring) -> Option<State<N>> {
+        Some(State { scoring, ..state })
+    }
+}
+impl<const N: usize> GtpGame for Gonnect<N> {
+    const BOARD_SIZE: usize = N;
+
+    fn vertex(action: &Move) -> Vertex {
+        match *action {
+            Move::SWAP => Vertex::Swap,
+            // Forced when no placement is legal, ending the game
+            Move::NO_MOVE => Vertex::Pass,
+            _ => {
+                let (row, col) = BitBoard::<N, N>::to_coord(action.0 as usize);
+                Vertex::Point { row, col }
+            }
+        }
+    }
 }
 
 impl<const N: usize> RectangularBoard for State<N> {
@@ -329,6 +422,51 @@ mod tests {
         random_play::<Gonnect<6>>();
     }
 
+    #[test]
+    fn test_margin_scoring() {
+        // Black connects the first column while White needs two more stones
+        let mut state = State::<4>::with_scoring(Scoring::Margin);
+        for (black, white) in [(0, 6), (4, 14), (8, 15)] {
+            state = Gonnect::apply(state, &Move(black, 0));
+            state = Gonnect::apply(state, &Move(white, 0));
+        }
+        // Black is one stone short and White two, a quarter of the width apart
+        let utilities = Gonnect::utilities_nonterminal(&state).unwrap();
+        assert_eq!(utilities, vec![0.125, -0.125]);
+
+        state = Gonnect::apply(state, &Move(12, 0));
+        assert_eq!(Gonnect::winner(&state), Some(Player::Black));
+        assert_eq!(Gonnect::compute_utilities(&state), vec![0.75, -0.75]);
+
+        state = Gonnect::with_scoring(state, Scoring::WinLoss).unwrap();
+        assert_eq!(Gonnect::compute_utilities(&state), vec![1., -1.]);
+    }
+
+    #[test]
+    fn test_playout_cutoff() {
+        assert_eq!(Gonnect::playout_cutoff(&State::<4>::default()), None);
+
+        // White cuts Black off from both pairs of walls, and is a stone short
+        // of connecting at (1, 3)
+        let moves = [
+            ((3, 0), (1, 0)),
+            ((3, 1), (1, 1)),
+            ((2, 0), (1, 2)),
+            ((2, 1), (0, 1)),
+            ((3, 2), (2, 3)),
+            ((0, 2), (3, 3)),
+        ];
+        let mut state = State::<4>::default();
+        for (black, white) in moves {
+            for (row, col) in [black, white] {
+                let index = BitBoard::<4, 4>::to_index(row, col);
+                state = Gonnect::apply(state, &Move(index as u8, 0));
+            }
+        }
+        assert!(!Gonnect::is_terminal(&state));
+        assert_eq!(Gonnect::playout_cutoff(&state), Some(Player::White));
+    }
+
     #[test]
     fn test_gonnect_render() {
         let mut search = TreeSearch::<Gonnect<3>, strategy::Ucb1>::new().config(
@@ -336,6 +474,8 @@ mod tests {
                 .expand_threshold(1)
                 .q_init(QInit::Infinity)
                 .use_transpositions(false)
+                // Playouts on a tiny board can cycle through ko forever
+                .max_playout_depth(200)
                 .max_iterations(20),
         );
         _ = search.choose_action(&State::default());
@@ -4,13 +4,30 @@ use super::bitboard;
 use super::bitboard::BitBoard;
 use crate::display::RectangularBoard;
 use crate::display::RectangularBoardDisplay;
+use crate::game::ConfigHints;
 use crate::game::Game;
 use crate::game::PlayerIndex;
+use crate::strategies::mcts::simulate::SimulateStrategy;
+use crate::strategies::mcts::TreeStats;
+use crate::util::random_best;
 
 use log::Record;
+use rand::rngs::SmallRng;
+use rand::Rng;
 use serde::Serialize;
 use std::fmt;
 
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (2, 1),
+    (2, -1),
+    (-2, 1),
+    (-2, -1),
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+];
+
 #[derive(Copy, Clone, Serialize, Debug, Default, PartialEq, Eq)]
 pub enum Player {
     #[default]
@@ -113,16 +130,7 @@ impl<const N: usize, const M: usize> State<N, M> {
         for src in player {
             let mut knight_moves = BitBoard::EMPTY;
             let (row, col) = BitBoard::<N, M>::to_coord(src);
-            for (dx, dy) in [
-                (2, 1),
-                (2, -1),
-                (-2, 1),
-                (-2, -1),
-                (1, 2),
-                (1, -2),
-                (-1, 2),
-                (-1, -2),
-            ] {
+            for (dx, dy) in KNIGHT_OFFSETS {
                 let (r, c) = (row as isize + dx, col as isize + dy);
                 if r >= 0 && r < N as isize && c >= 0 && c < M as isize {
                     knight_moves |= BitBoard::from_coord(r as usize, c as usize);
@@ -157,7 +165,8 @@ impl<const N: usize, const M: usize> State<N, M> {
             }
         }
 
-        if player.intersects(goal) {
+        // Reaching the goal row or capturing every opposing piece wins.
+        if player.intersects(goal) || opponent.is_empty() {
             self.winner = true;
         } else {
             self.turn = self.turn.next();
@@ -209,6 +218,157 @@ impl<const N: usize, const M: usize> Game for Knightthrough<N, M> {
     fn num_players() -> usize {
         2
     }
+
+    fn recommended_config_hints() -> ConfigHints {
+        // Measured against the default UCB1 constant on 8x8 at 1000 iterations
+        ConfigHints {
+            exploration_scale: Some(0.5),
+            ..Default::default()
+        }
+    }
+
+    fn max_actions() -> usize {
+        N * M * N * M
+    }
+
+    fn action_index(_state: &Self::S, action: &Self::A) -> usize {
+        action.src() * N * M + action.dst()
+    }
+
+    fn action_from_index(_state: &Self::S, index: usize) -> Self::A {
+        Move((index / (N * M)) as u8, (index % (N * M)) as u8)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Race evaluation
+
+/// The minimum number of knight moves from each square to each player's goal
+/// row, ignoring all other pieces.
+#[derive(Clone, Debug)]
+pub struct RaceTable<const N: usize, const M: usize> {
+    distances: [Vec<u8>; 2],
+}
+
+impl<const N: usize, const M: usize> Default for RaceTable<N, M> {
+    fn default() -> Self {
+        let distances = [Player::Black, Player::White].map(|player| {
+            let (_, goal) = State::<N, M>::default().player(player);
+            let mut distances = vec![u8::MAX; N * M];
+            let mut frontier = goal.collect::<Vec<_>>();
+            frontier.iter().for_each(|index| distances[*index] = 0);
+            let mut next = vec![];
+            while !frontier.is_empty() {
+                for index in frontier.drain(..) {
+                    let (row, col) = BitBoard::<N, M>::to_coord(index);
+                    for (dx, dy) in KNIGHT_OFFSETS {
+                        let (r, c) = (row as isize + dx, col as isize + dy);
+                        if r >= 0 && r < N as isize && c >= 0 && c < M as isize {
+                            let neighbor = BitBoard::<N, M>::to_index(r as usize, c as usize);
+                            if distances[neighbor] == u8::MAX {
+                                distances[neighbor] = distances[index] + 1;
+                                next.push(neighbor);
+                            }
+                        }
+                    }
+                }
+                std::mem::swap(&mut frontier, &mut next);
+            }
+            distances
+        });
+        Self { distances }
+    }
+}
+
+impl<const N: usize, const M: usize> RaceTable<N, M> {
+    #[inline]
+    pub fn distance(&self, index: usize, player: Player) -> u8 {
+        self.distances[player.to_index()][index]
+    }
+
+    /// The fewest knight moves any of the player's pieces needs to reach the
+    /// goal.
+    pub fn race(&self, state: &State<N, M>, player: Player) -> u8 {
+        let (pieces, _) = state.player(player);
+        pieces
+            .map(|index| self.distance(index, player))
+            .min()
+            .unwrap_or(u8::MAX)
+    }
+
+    /// Evaluate the race in [-1, 1] from the perspective of `player`. The side
+    /// to move gains a tempo. Interference between pieces is ignored.
+    pub fn evaluate(&self, state: &State<N, M>, player: Player) -> f64 {
+        let plies = |p: Player| {
+            let tempo = if p == state.turn { 1. } else { 0. };
+            2. * self.race(state, p) as f64 - tempo
+        };
+        ((plies(player.next()) - plies(player)) / 4.).tanh()
+    }
+}
+
+/// A playout policy driven by the race evaluation: take wins, capture pieces
+/// which are about to win, and otherwise advance towards the goal.
+#[derive(Clone)]
+pub struct Race<const N: usize, const M: usize> {
+    pub epsilon: f64,
+    table: RaceTable<N, M>,
+}
+
+impl<const N: usize, const M: usize> Default for Race<N, M> {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.2,
+            table: RaceTable::default(),
+        }
+    }
+}
+
+impl<const N: usize, const M: usize> Race<N, M> {
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    fn score(&self, state: &State<N, M>, action: &Move) -> f64 {
+        let player = state.turn;
+        let opponent = player.next();
+        if self.table.distance(action.dst(), player) == 0 {
+            return 1000.;
+        }
+        let (opponents, _) = state.player(opponent);
+        if opponents.get(action.dst()) {
+            // Captures are more valuable the closer the piece was to winning
+            return 100. - self.table.distance(action.dst(), opponent) as f64;
+        }
+        self.table.distance(action.src(), player) as f64
+            - self.table.distance(action.dst(), player) as f64
+    }
+}
+
+impl<const N: usize, const M: usize> SimulateStrategy<Knightthrough<N, M>> for Race<N, M> {
+    fn select_move<'a>(
+        &mut self,
+        state: &State<N, M>,
+        available: &'a [Move],
+        _stats: &TreeStats<Knightthrough<N, M>>,
+        _player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a Move {
+        if rng.gen::<f64>() < self.epsilon {
+            return &available[rng.gen_range(0..available.len())];
+        }
+
+        let action_scores = available
+            .iter()
+            .map(|action| (self.score(state, action), action))
+            .collect::<Vec<_>>();
+
+        random_best(&action_scores, rng, |(score, _)| *score)
+            .unwrap()
+            .1
+    }
 }
 
 impl<const N: usize, const M: usize> RectangularBoard for State<N, M> {
@@ -242,4 +402,73 @@ mod tests {
     fn test_knightthrough() {
         random_play::<Knightthrough<8, 8>>();
     }
+
+    #[test]
+    fn test_race_table() {
+        let table = RaceTable::<8, 8>::default();
+        let state = State::<8, 8>::default();
+        // Black moves south and starts on rows 6 and 7
+        assert_eq!(
+            table.distance(BitBoard::<8, 8>::to_index(0, 3), Player::Black),
+            0
+        );
+        assert_eq!(
+            table.distance(BitBoard::<8, 8>::to_index(2, 3), Player::Black),
+            1
+        );
+        assert_eq!(table.race(&state, Player::Black), 3);
+        assert_eq!(table.race(&state, Player::White), 3);
+        // Black is to move, and so is ahead
+        assert!(table.evaluate(&state, Player::Black) > 0.);
+        assert_eq!(
+            table.evaluate(&state, Player::Black),
+            -table.evaluate(&state, Player::White)
+        );
+    }
+
+    #[test]
+    fn test_race_beats_uct() {
+        use crate::strategies::mcts::TreeSearch;
+        use crate::strategies::mcts::{backprop, select, strategy, SearchConfig, Strategy};
+        use crate::strategies::Search;
+
+        type G = Knightthrough<8, 8>;
+
+        #[derive(Clone, Default)]
+        struct Ucb1Race;
+
+        impl Strategy<G> for Ucb1Race {
+            type Select = select::Ucb1;
+            type Simulate = Race<8, 8>;
+            type Backprop = backprop::Classic;
+            type FinalAction = select::RobustChild;
+        }
+
+        let mut wins = 0;
+        for seed in 0..6 {
+            let race_player = if seed % 2 == 0 {
+                Player::Black
+            } else {
+                Player::White
+            };
+            let mut uct: TreeSearch<G, strategy::Ucb1> =
+                TreeSearch::new().config(SearchConfig::new().max_iterations(200).seed(seed));
+            let mut race: TreeSearch<G, Ucb1Race> =
+                TreeSearch::new().config(SearchConfig::new().max_iterations(200).seed(seed));
+
+            let mut state = State::<8, 8>::default();
+            while !G::is_terminal(&state) {
+                let action = if state.turn == race_player {
+                    race.choose_action(&state)
+                } else {
+                    uct.choose_action(&state)
+                };
+                state = G::apply(state, &action);
+            }
+            if G::winner(&state) == Some(race_player) {
+                wins += 1;
+            }
+        }
+        assert!(wins >= 4, "race policy won {wins} of 6");
+    }
 }
@@ -9,9 +9,11 @@ pub mod knightthrough;
 pub mod nim;
 pub mod null;
 pub mod shibumi;
+pub mod template;
 pub mod traffic_lights;
 pub mod ttt;
 pub mod unit;
+pub mod wrappers;
 
 #[cfg(test)]
 pub mod bitboard_match;
@@ -1,6 +1,8 @@
 use crate::game::{Game, PlayerIndex};
 
-use nimlib::{moves, NimAction, NimGame, NimRule, Split, Stack, TakeSize};
+use nimlib::{moves, NimAction, NimGame, NimRule, PlaceAction, Split, Stack, TakeAction, TakeSize};
+use rustc_hash::FxHasher;
+use std::hash::Hasher;
 
 #[derive(PartialEq, Copy, Clone, Debug, Eq)]
 pub enum Player {
@@ -98,4 +100,52 @@ impl Game for Nim {
     fn player_to_move(state: &Self::S) -> Player {
         state.turn
     }
+
+    fn zobrist_hash(state: &Self::S) -> u64 {
+        let mut hasher = FxHasher::default();
+        for stack in state.game.get_stacks() {
+            hasher.write_u64(stack.0);
+        }
+        hasher.write_usize(state.turn.to_index());
+        hasher.finish()
+    }
+
+    /// Each non-empty stack is a component.
+    fn decompose(state: &Self::S) -> Vec<Self::S> {
+        let stacks = state.game.get_stacks();
+        if stacks.iter().filter(|stack| stack.0 > 0).count() < 2 {
+            return vec![];
+        }
+        stacks
+            .iter()
+            .filter(|stack| stack.0 > 0)
+            .map(|stack| NimState {
+                game: NimGame::new(state.rules.clone(), vec![*stack]),
+                rules: state.rules.clone(),
+                turn: state.turn,
+            })
+            .collect()
+    }
+
+    fn lift_action(state: &Self::S, index: usize, action: &Self::A) -> Self::A {
+        let stack_index = state
+            .game
+            .get_stacks()
+            .iter()
+            .enumerate()
+            .filter(|(_, stack)| stack.0 > 0)
+            .nth(index)
+            .map(|(i, _)| i)
+            .unwrap();
+        match action {
+            NimAction::Take(take) => NimAction::Take(TakeAction {
+                stack_index,
+                ..take.clone()
+            }),
+            NimAction::Place(place) => NimAction::Place(PlaceAction {
+                stack_index,
+                ..place.clone()
+            }),
+        }
+    }
 }
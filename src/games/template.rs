@@ -0,0 +1,335 @@
+//! Template: three in a row on a small board. Players take turns placing a
+//! stone on any empty cell, and the first to complete a line of three wins.
+// scaffold:begin
+//!
+//! This module doubles as the scaffold for new games: `scaffold` renames it
+//! and the `new_game` binary writes the result into the crate. Replace the
+//! rules in `State` and keep the rest as a starting point.
+// scaffold:end
+
+use crate::display::{RectangularBoard, RectangularBoardDisplay};
+use crate::game::{Game, PlayerIndex};
+use crate::zobrist::LazyZobristTable;
+
+use serde::Serialize;
+use std::fmt;
+
+const ROWS: usize = 4;
+const COLS: usize = 4;
+const CELLS: usize = ROWS * COLS;
+const LINE: usize = 3;
+
+// One hash per cell and player
+static HASHES: LazyZobristTable<{ CELLS * 2 }> = LazyZobristTable::new(0x9E37_79B9_7F4A_7C15);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Player {
+    #[default]
+    Black,
+    White,
+}
+
+impl Player {
+    fn next(self) -> Player {
+        match self {
+            Player::Black => Player::White,
+            Player::White => Player::Black,
+        }
+    }
+}
+
+impl PlayerIndex for Player {
+    fn to_index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Place a stone on the cell with this index, in row major order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Move(pub u8);
+
+impl Move {
+    fn row(self) -> usize {
+        self.0 as usize / COLS
+    }
+
+    fn col(self) -> usize {
+        self.0 as usize % COLS
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    board: Vec<Option<Player>>,
+    turn: Player,
+    winner: Option<Player>,
+    hash: u64,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            board: vec![None; CELLS],
+            turn: Player::Black,
+            winner: None,
+            hash: 0,
+        }
+    }
+}
+
+impl State {
+    pub fn get(&self, row: usize, col: usize) -> Option<Player> {
+        self.board[row * COLS + col]
+    }
+
+    fn is_full(&self) -> bool {
+        self.board.iter().all(Option::is_some)
+    }
+
+    // Whether the stone just placed at `m` completes a line
+    fn completes_line(&self, m: Move) -> bool {
+        const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+        let player = self.board[m.0 as usize];
+        DIRECTIONS.iter().any(|(dr, dc)| {
+            let run = |sign: isize| {
+                (1..LINE as isize)
+                    .take_while(|i| {
+                        let row = m.row() as isize + sign * i * dr;
+                        let col = m.col() as isize + sign * i * dc;
+                        (0..ROWS as isize).contains(&row)
+                            && (0..COLS as isize).contains(&col)
+                            && self.get(row as usize, col as usize) == player
+                    })
+                    .count()
+            };
+            1 + run(1) + run(-1) >= LINE
+        })
+    }
+
+    fn apply(&mut self, m: Move) {
+        debug_assert!(self.board[m.0 as usize].is_none());
+        self.board[m.0 as usize] = Some(self.turn);
+        self.hash ^= HASHES.hash(((m.0 as usize) << 1) | self.turn as usize);
+        if self.completes_line(m) {
+            self.winner = Some(self.turn);
+        }
+        self.turn = self.turn.next();
+    }
+}
+
+#[derive(Clone)]
+pub struct Template;
+
+impl Game for Template {
+    type S = State;
+    type A = Move;
+    type P = Player;
+
+    fn apply(mut state: State, m: &Move) -> State {
+        state.apply(*m);
+        state
+    }
+
+    fn generate_actions(state: &State, actions: &mut Vec<Move>) {
+        if state.winner.is_none() {
+            actions.extend(
+                (0..CELLS)
+                    .filter(|i| state.board[*i].is_none())
+                    .map(|i| Move(i as u8)),
+            );
+        }
+    }
+
+    fn is_terminal(state: &State) -> bool {
+        state.winner.is_some() || state.is_full()
+    }
+
+    fn winner(state: &State) -> Option<Player> {
+        state.winner
+    }
+
+    fn player_to_move(state: &State) -> Player {
+        state.turn
+    }
+
+    fn notation(_: &State, m: &Move) -> String {
+        const COL_NAMES: &[u8] = b"ABCDEFGH";
+        format!("{}{}", COL_NAMES[m.col()] as char, m.row() + 1)
+    }
+
+    fn parse_action(state: &State, input: &str) -> Option<Move> {
+        let input = input.trim().to_ascii_uppercase();
+        let mut chars = input.chars();
+        let col = (chars.next()? as usize).checked_sub('A' as usize)?;
+        let row = chars.as_str().parse::<usize>().ok()?.checked_sub(1)?;
+        if row >= ROWS || col >= COLS {
+            return None;
+        }
+        let m = Move((row * COLS + col) as u8);
+        let mut actions = Vec::new();
+        Self::generate_actions(state, &mut actions);
+        actions.contains(&m).then_some(m)
+    }
+
+    fn max_actions() -> usize {
+        CELLS
+    }
+
+    fn action_index(_: &State, m: &Move) -> usize {
+        m.0 as usize
+    }
+
+    fn action_from_index(_: &State, index: usize) -> Move {
+        Move(index as u8)
+    }
+
+    fn estimated_remaining_moves(state: &State) -> Option<usize> {
+        Some(state.board.iter().filter(|cell| cell.is_none()).count())
+    }
+
+    fn zobrist_hash(state: &State) -> u64 {
+        state.hash
+    }
+
+    fn supports_transpositions() -> bool {
+        true
+    }
+}
+
+impl RectangularBoard for State {
+    const NUM_DISPLAY_ROWS: usize = ROWS;
+    const NUM_DISPLAY_COLS: usize = COLS;
+
+    fn display_char_at(&self, row: usize, col: usize) -> char {
+        match self.get(row, col) {
+            None => '.',
+            Some(Player::Black) => 'X',
+            Some(Player::White) => 'O',
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        RectangularBoardDisplay(self).fmt(f)
+    }
+}
+
+// scaffold:begin
+////////////////////////////////////////////////////////////////////////////////
+
+// The demo binary written alongside a scaffolded game
+const DEMO: &str = r#"use mcts::games::template::Template;
+use mcts::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+use mcts::util::self_play;
+
+fn main() {
+    color_backtrace::install();
+    self_play(
+        TreeSearch::<Template, strategy::Ucb1>::new()
+            .config(SearchConfig::new().max_iterations(10_000)),
+    );
+}
+"#;
+
+/// The sources for a new game named `name`, renamed from this module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scaffold {
+    // The module name, and the game type name in upper camel case
+    pub name: String,
+    pub type_name: String,
+    // The contents of `src/games/<name>.rs` and `demo/<name>.rs`
+    pub module: String,
+    pub demo: String,
+}
+
+/// Scaffold a game from this module. `name` must be a lower snake case
+/// identifier.
+pub fn scaffold(name: &str) -> Option<Scaffold> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return None;
+    }
+
+    let type_name = name
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect::<String>();
+    let rename = |source: &str| {
+        source
+            .replace("Template", &type_name)
+            .replace("template", name)
+    };
+
+    // Drop the scaffolding itself from the new module
+    let mut module = String::new();
+    let mut skip = false;
+    for line in include_str!("template.rs").lines() {
+        if line.trim() == "// scaffold:begin" {
+            skip = true;
+        } else if line.trim() == "// scaffold:end" {
+            skip = false;
+        } else if !skip {
+            module.push_str(line);
+            module.push('\n');
+        }
+    }
+
+    Some(Scaffold {
+        name: name.into(),
+        module: rename(&module),
+        demo: rename(DEMO),
+        type_name,
+    })
+}
+// scaffold:end
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{check_conformance, random_play};
+
+    #[test]
+    fn test_template() {
+        random_play::<Template>();
+        check_conformance::<Template>(100, 0, true);
+    }
+
+    #[test]
+    fn test_line() {
+        let mut state = State::default();
+        for text in ["A1", "A2", "B1", "B2"] {
+            let m = Template::parse_action(&state, text).unwrap();
+            state = Template::apply(state, &m);
+        }
+        assert!(!Template::is_terminal(&state));
+        assert_eq!(Template::parse_action(&state, "A1"), None);
+        let m = Template::parse_action(&state, "c1").unwrap();
+        state = Template::apply(state, &m);
+        assert_eq!(Template::winner(&state), Some(Player::Black));
+    }
+
+    // scaffold:begin
+    #[test]
+    fn test_scaffold() {
+        let new = scaffold("dots_and_boxes").unwrap();
+        assert_eq!(new.type_name, "DotsAndBoxes");
+        assert!(new.module.contains("impl Game for DotsAndBoxes {"));
+        assert!(new
+            .demo
+            .contains("use mcts::games::dots_and_boxes::DotsAndBoxes;"));
+        assert!(!new.module.contains("Template"));
+        assert!(!new.module.contains("scaffold"));
+        assert!(scaffold("DotsAndBoxes").is_none());
+        assert!(scaffold("2048").is_none());
+    }
+    // scaffold:end
+}
@@ -3,6 +3,7 @@ use crate::{
     game::{Game, PlayerIndex},
     zobrist::LazyZobristTable,
 };
+use rustc_hash::FxHashMap;
 use serde::Serialize;
 use std::fmt::Display;
 
@@ -250,14 +251,6 @@ impl Game for TrafficLights {
         tmp
     }
 
-    fn get_reward(init: &Self::S, term: &Self::S) -> f64 {
-        let utility = Self::compute_utilities(term)[Self::player_to_move(init).to_index()];
-        if utility < 0. {
-            return utility * 100.;
-        }
-        utility
-    }
-
     fn notation(_state: &Self::S, m: &Self::A) -> String {
         let i = m.index();
         let x = i % 3;
@@ -284,10 +277,98 @@ impl Game for TrafficLights {
     fn zobrist_hash(state: &Self::S) -> u64 {
         state.hash()
     }
+
+    fn supports_transpositions() -> bool {
+        true
+    }
+
+    fn estimated_remaining_moves(state: &Self::S) -> Option<usize> {
+        Some(
+            (0..9)
+                .map(|i| match state.position.get(i) {
+                    None => 3,
+                    Some(Piece::R) => 2,
+                    Some(Piece::Y) => 1,
+                    Some(Piece::G) => 0,
+                })
+                .sum(),
+        )
+    }
+
+    // The move encoding is already dense: a board index and a two bit piece.
+    fn max_actions() -> usize {
+        9 << 2
+    }
+
+    fn action_index(_state: &Self::S, m: &Self::A) -> usize {
+        m.0 as usize
+    }
+
+    fn action_from_index(_state: &Self::S, index: usize) -> Self::A {
+        Move(index as u8)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////
 
+/// An exact solver for TrafficLights. There are no draws, and only 256,208
+/// boards are reachable, so every position can be solved by exhaustive search.
+/// Values are memoized by board; the player to move is implied by the board.
+#[derive(Default)]
+pub struct Solver {
+    values: FxHashMap<u32, bool>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the player to move wins with perfect play. A terminal
+    /// position is a win for the player who made the last move, who is also
+    /// reported as the player to move.
+    pub fn wins(&mut self, state: &HashedPosition) -> bool {
+        if state.position.winner {
+            return true;
+        }
+        if let Some(&value) = self.values.get(&state.position.board) {
+            return value;
+        }
+        let mut actions = Vec::new();
+        state.position.gen_moves(&mut actions);
+        let value = actions.iter().any(|m| self.move_wins(state, m));
+        self.values.insert(state.position.board, value);
+        value
+    }
+
+    /// Returns true if the action wins for the player making it.
+    pub fn move_wins(&mut self, state: &HashedPosition, m: &Move) -> bool {
+        let child = TrafficLights::apply(*state, m);
+        child.position.winner || !self.wins(&child)
+    }
+
+    /// The actions which preserve the game theoretic value of the position:
+    /// the winning actions if there are any, otherwise all of them.
+    pub fn optimal_moves(&mut self, state: &HashedPosition) -> Vec<Move> {
+        let mut actions = Vec::new();
+        state.position.gen_moves(&mut actions);
+        if !self.wins(state) {
+            return actions;
+        }
+        actions.retain(|m| self.move_wins(state, m));
+        actions
+    }
+
+    /// The number of positions solved so far.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rustc_hash::FxHashSet;
@@ -338,6 +419,62 @@ mod tests {
         }
     }
 
+    fn position(board: u32) -> HashedPosition {
+        let plies = (0..9).map(|i| (board >> (i * 2)) & 0b11).sum::<u32>();
+        let turn = if plies % 2 == 0 {
+            Player::First
+        } else {
+            Player::Second
+        };
+        let mut state = HashedPosition::new();
+        state.position = Position {
+            turn,
+            winner: false,
+            board,
+        };
+        state
+    }
+
+    #[test]
+    fn test_solver() {
+        let mut solver = Solver::new();
+
+        // Every opening move wins for the first player
+        let state = HashedPosition::new();
+        assert!(solver.wins(&state));
+        assert_eq!(solver.optimal_moves(&state).len(), 9);
+
+        let state = position(0x4424);
+        assert_eq!(TrafficLights::player_to_move(&state), Player::Second);
+        assert!(solver.wins(&state));
+        assert_eq!(solver.optimal_moves(&state), vec![Move(16)]);
+        assert!(solver.move_wins(&state, &Move(16)));
+        assert!(!solver.move_wins(&state, &Move(0)));
+    }
+
+    // Positions with few winning moves, where the presets have been checked
+    // against the solver.
+    #[test]
+    fn test_presets_find_optimal_moves() {
+        use crate::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+        use crate::strategies::Search;
+
+        let mut solver = Solver::new();
+        for board in [0x4424, 0x3502, 0x1520, 0x1422c, 0x22144] {
+            let state = position(board);
+            let optimal = solver.optimal_moves(&state);
+            assert!(optimal.len() <= 2);
+
+            let mut ucb1 = TreeSearch::<TrafficLights, strategy::Ucb1>::default()
+                .config(SearchConfig::default().max_iterations(1000).seed(0));
+            assert!(optimal.contains(&ucb1.choose_action(&state)));
+
+            let mut tuned = TreeSearch::<TrafficLights, strategy::Ucb1Tuned>::default()
+                .config(SearchConfig::default().max_iterations(1000).seed(0));
+            assert!(optimal.contains(&tuned.choose_action(&state)));
+        }
+    }
+
     fn color_for(piece: Option<Piece>) -> String {
         match piece {
             None => "white",
@@ -434,7 +571,7 @@ mod tests {
         println!("table:");
         println!("{:#?}", ts.table);
         println!("grave:");
-        println!("{:#?}", ts.stats.grave);
+        println!("{:#?}", ts.stats.history.grave);
 
         assert!(ts.table.hits > 0);
         // render::render_trans(&ts);
@@ -1,12 +1,12 @@
 use crate::display::{RectangularBoard, RectangularBoardDisplay};
 use crate::game::{Game, PlayerIndex};
 use crate::zobrist::LazyZobristTable;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 const USE_SYMMETRY: bool = false;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Piece {
     X,
     O,
@@ -27,10 +27,10 @@ impl Piece {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Move(pub u8);
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Serialize, Deserialize)]
 pub struct Position {
     pub turn: Piece,
     pub board: u32,
@@ -174,7 +174,7 @@ const NUM_MOVES: usize = 18;
 
 static HASHES: LazyZobristTable<NUM_MOVES> = LazyZobristTable::new(0xFEAAE62226597B38);
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Serialize, Deserialize)]
 pub struct HashedPosition {
     pub position: Position,
     pub(crate) hashes: [u64; 8],
@@ -187,6 +187,20 @@ impl HashedPosition {
             hashes: [0; 8],
         }
     }
+
+    fn from_position(position: Position) -> Self {
+        let mut hashes = [0; NUM_SYMMETRIES];
+        for i in 0..9 {
+            if let Some(piece) = position.get(i) {
+                let mut symmetries = [0; NUM_SYMMETRIES];
+                sym::index_symmetries(i, &mut symmetries);
+                for (hash, index) in hashes.iter_mut().zip(symmetries) {
+                    *hash ^= HASHES.hash((index << 1) | piece as usize);
+                }
+            }
+        }
+        Self { position, hashes }
+    }
 }
 
 impl Default for HashedPosition {
@@ -260,6 +274,59 @@ impl Game for TicTacToe {
     fn zobrist_hash(state: &Self::S) -> u64 {
         state.hash()
     }
+
+    fn canonical_representation(state: Self::S) -> Self::S {
+        let mut boards = [0; NUM_SYMMETRIES];
+        sym::board_symmetries(state.position.board, &mut boards);
+        let symmetry = sym::canonical_symmetry(state.position.board);
+        HashedPosition::from_position(Position {
+            turn: state.position.turn,
+            board: boards[symmetry],
+        })
+    }
+
+    fn canonical_action(state: &Self::S, action: &Self::A) -> Self::A {
+        let mut symmetries = [0; NUM_SYMMETRIES];
+        sym::index_symmetries(action.0 as usize, &mut symmetries);
+        Move(symmetries[sym::canonical_symmetry(state.position.board)] as u8)
+    }
+
+    fn action_from_canonical(state: &Self::S, action: &Self::A) -> Self::A {
+        let symmetry = sym::canonical_symmetry(state.position.board);
+        Move(sym::invert_symmetry(action.0 as usize, symmetry) as u8)
+    }
+
+    fn supports_transpositions() -> bool {
+        true
+    }
+
+    fn estimated_remaining_moves(state: &Self::S) -> Option<usize> {
+        Some((0..9).filter(|i| state.position.get(*i).is_none()).count())
+    }
+
+    fn max_actions() -> usize {
+        9
+    }
+
+    fn action_index(_state: &Self::S, action: &Self::A) -> usize {
+        action.0 as usize
+    }
+
+    fn action_from_index(_state: &Self::S, index: usize) -> Self::A {
+        Move(index as u8)
+    }
+
+    fn sample_action(state: &Self::S, word: u32) -> Option<Self::A> {
+        // One bit per square, at the low bit of each square's pair
+        const SQUARES: u32 = 0b01_0101_0101_0101_0101;
+        let board = state.position.board;
+        let mut empty = !(board | (board >> 1)) & SQUARES;
+        let k = (word as u64 * empty.count_ones() as u64) >> 32;
+        for _ in 0..k {
+            empty &= empty - 1;
+        }
+        (empty != 0).then(|| Move((empty.trailing_zeros() >> 1) as u8))
+    }
 }
 
 impl RectangularBoard for HashedPosition {
@@ -287,7 +354,7 @@ mod tests {
 
     use super::{HashedPosition, TicTacToe};
     use crate::{
-        game::Game,
+        game::{Game, PlayerId},
         strategies::{
             mcts::{node::QInit, render, strategy, SearchConfig, TreeSearch},
             Search,
@@ -295,6 +362,33 @@ mod tests {
         util::random_play,
     };
 
+    #[test]
+    fn test_player_id() {
+        assert_eq!(PlayerId(0).opponent(), PlayerId(1));
+        assert_eq!(PlayerId(1).opponent(), PlayerId(0));
+        assert_eq!(PlayerId(2).next(3), PlayerId(0));
+        assert_eq!(
+            TicTacToe::players().collect::<Vec<_>>(),
+            vec![PlayerId(0), PlayerId(1)]
+        );
+
+        let mut state = HashedPosition::new();
+        assert_eq!(TicTacToe::player_id(&state), PlayerId(0));
+        for action in [0, 3, 1, 4] {
+            state = TicTacToe::apply(state, &super::Move(action));
+        }
+        assert_eq!(TicTacToe::player_id(&state), PlayerId(0));
+        state = TicTacToe::apply(state, &super::Move(2));
+        assert_eq!(TicTacToe::winner_id(&state), Some(PlayerId(0)));
+        assert_eq!(TicTacToe::compute_utilities(&state), vec![1., -1.]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_player_id_opponent_checked() {
+        PlayerId(2).opponent();
+    }
+
     #[test]
     fn test_ttt() {
         random_play::<TicTacToe>();
@@ -398,4 +492,63 @@ mod tests {
         assert!(ts.table.hits > 0);
         render::render_trans(&ts, &HashedPosition::default());
     }
+
+    #[test]
+    fn test_perft() {
+        use crate::util::{perft, perft_divide, perft_unique};
+
+        let init = HashedPosition::new();
+        let counts = [1, 9, 72, 504, 3024, 15120, 54720, 148176, 200448, 127872];
+        for (depth, count) in counts.into_iter().enumerate() {
+            assert_eq!(perft::<TicTacToe>(&init, depth), count);
+        }
+        assert_eq!(perft::<TicTacToe>(&init, 10), 0);
+        assert_eq!(perft_unique::<TicTacToe>(&init, 4), 756);
+        assert_eq!(perft_unique::<TicTacToe>(&init, 9), 78);
+
+        let divide = perft_divide::<TicTacToe>(&init, 9);
+        assert_eq!(divide.len(), 9);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), 127872);
+    }
+
+    #[test]
+    fn test_sample_action() {
+        use super::Move;
+        use crate::strategies::mcts::simulate::{Batched, EndType, SimulateStrategy};
+        use crate::strategies::mcts::TreeStats;
+        use rand::rngs::SmallRng;
+        use rand_core::SeedableRng;
+
+        let state = [0, 4].into_iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(i))
+        });
+        let mut legal = Vec::new();
+        TicTacToe::generate_actions(&state, &mut legal);
+
+        // Evenly spaced words select each legal move equally often
+        let mut counts = [0; 9];
+        for i in 0..7000u64 {
+            let word = (((2 * i + 1) << 31) / 7000) as u32;
+            let action = TicTacToe::sample_action(&state, word).unwrap();
+            assert!(legal.contains(&action));
+            counts[action.0 as usize] += 1;
+        }
+        assert!(legal.iter().all(|action| counts[action.0 as usize] == 1000));
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let trial = Batched::default().playout(
+            HashedPosition::new(),
+            usize::MAX,
+            &TreeStats::<TicTacToe>::default(),
+            0,
+            &mut rng,
+        );
+        assert!(matches!(trial.status.end_type, Some(EndType::NaturalEnd)));
+        assert!(TicTacToe::is_terminal(&trial.state));
+        assert!(trial
+            .actions
+            .iter()
+            .enumerate()
+            .all(|(i, (_, player))| *player == i % 2));
+    }
 }
@@ -0,0 +1,362 @@
+//! Games built out of other games.
+
+use crate::game::{ConfigHints, Game, PlayerId, SimultaneousGame};
+use std::marker::PhantomData;
+
+/// A best-of-`N` match of a two player game, played as a single game. The
+/// state carries the score; when one game ends the next starts from
+/// `G::S::default()`, and the match ends once a player has won more than half
+/// of the `N` games or all `N` have been played. The match winner is the player
+/// with more wins, so a drawn game counts against neither player.
+///
+/// Searching the match rather than the game lets a strategy weigh a single
+/// game's result by what it means for the match, e.g. playing for a draw
+/// when ahead.
+#[derive(Clone)]
+pub struct MatchOf<G: Game, const N: usize>(PhantomData<G>);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchState<S> {
+    pub game: S,
+    pub wins: [u32; 2],
+    pub played: u32,
+}
+
+impl<S> MatchState<S> {
+    pub fn leader(&self) -> Option<PlayerId> {
+        match self.wins[0].cmp(&self.wins[1]) {
+            std::cmp::Ordering::Greater => Some(PlayerId(0)),
+            std::cmp::Ordering::Less => Some(PlayerId(1)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn is_decided(&self, n: usize) -> bool {
+        self.played as usize >= n || self.wins.iter().any(|w| 2 * *w as usize > n)
+    }
+}
+
+impl<S: std::fmt::Display> std::fmt::Display for MatchState<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "game {} ({}-{})",
+            self.played + 1,
+            self.wins[0],
+            self.wins[1]
+        )?;
+        write!(f, "{}", self.game)
+    }
+}
+
+impl<G: Game, const N: usize> Game for MatchOf<G, N> {
+    type S = MatchState<G::S>;
+    type A = G::A;
+    type P = PlayerId;
+
+    fn apply(mut state: Self::S, action: &Self::A) -> Self::S {
+        state.game = G::apply(state.game, action);
+        if G::is_terminal(&state.game) {
+            if let Some(winner) = G::winner_id(&state.game) {
+                state.wins[winner.index()] += 1;
+            }
+            state.played += 1;
+            if !state.is_decided(N) {
+                state.game = G::S::default();
+            }
+        }
+        state
+    }
+
+    fn generate_actions(state: &Self::S, actions: &mut Vec<Self::A>) {
+        if !Self::is_terminal(state) {
+            G::generate_actions(&state.game, actions);
+        }
+    }
+
+    fn is_terminal(state: &Self::S) -> bool {
+        state.is_decided(N)
+    }
+
+    fn winner(state: &Self::S) -> Option<PlayerId> {
+        state.leader()
+    }
+
+    fn player_to_move(state: &Self::S) -> PlayerId {
+        G::player_id(&state.game)
+    }
+
+    fn num_players() -> usize {
+        debug_assert_eq!(G::num_players(), 2);
+        2
+    }
+
+    fn notation(state: &Self::S, action: &Self::A) -> String {
+        G::notation(&state.game, action)
+    }
+
+    fn parse_action(state: &Self::S, input: &str) -> Option<Self::A> {
+        G::parse_action(&state.game, input)
+    }
+
+    fn max_actions() -> usize {
+        G::max_actions()
+    }
+
+    fn action_index(state: &Self::S, action: &Self::A) -> usize {
+        G::action_index(&state.game, action)
+    }
+
+    fn action_from_index(state: &Self::S, index: usize) -> Self::A {
+        G::action_from_index(&state.game, index)
+    }
+
+    fn actions_equal_modulo_state(state: &Self::S, a: &Self::A, b: &Self::A) -> bool {
+        G::actions_equal_modulo_state(&state.game, a, b)
+    }
+
+    fn recommended_config_hints() -> ConfigHints {
+        G::recommended_config_hints()
+    }
+
+    fn zobrist_hash(state: &Self::S) -> u64 {
+        // Mix in the score so that the same position in different games of the
+        // match hashes differently
+        let score =
+            ((state.played as u64) << 32) | ((state.wins[0] as u64) << 16) | state.wins[1] as u64;
+        G::zobrist_hash(&state.game) ^ score.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn supports_transpositions() -> bool {
+        G::supports_transpositions()
+    }
+
+    fn sample_action(state: &Self::S, word: u32) -> Option<Self::A> {
+        G::sample_action(&state.game, word)
+    }
+
+    fn is_legal_index(state: &Self::S, index: usize) -> Option<bool> {
+        G::is_legal_index(&state.game, index)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A simultaneous move game as a `Game`, in the manner of General Game Playing:
+/// every state is simultaneous, and each action is a joint action holding one
+/// move per player, in player order. The actions of a state are every
+/// combination of the players' moves.
+///
+/// Search it with `strategy::Duct`, which keeps each player's statistics
+/// apart. A sequential select strategy would treat the first player as
+/// choosing the joint action, and so as seeing the other players' moves.
+#[derive(Clone)]
+pub struct Simultaneous<G: SimultaneousGame>(PhantomData<G>);
+
+impl<G: SimultaneousGame> Simultaneous<G> {
+    fn moves(state: &G::S, player: usize) -> Vec<G::M> {
+        let mut moves = vec![];
+        G::player_moves(state, PlayerId(player), &mut moves);
+        moves
+    }
+}
+
+impl<G: SimultaneousGame> Game for Simultaneous<G> {
+    type S = G::S;
+    type A = Vec<G::M>;
+    type P = PlayerId;
+
+    fn apply(state: Self::S, action: &Self::A) -> Self::S {
+        G::apply(state, action)
+    }
+
+    fn generate_actions(state: &Self::S, actions: &mut Vec<Self::A>) {
+        if G::is_terminal(state) {
+            return;
+        }
+        let mut joint = vec![vec![]];
+        for player in 0..G::num_players() {
+            let moves = Self::moves(state, player);
+            joint = joint
+                .into_iter()
+                .flat_map(|prefix: Vec<G::M>| {
+                    moves.iter().map(move |m| {
+                        let mut action = prefix.clone();
+                        action.push(m.clone());
+                        action
+                    })
+                })
+                .collect();
+        }
+        actions.extend(joint);
+    }
+
+    fn is_terminal(state: &Self::S) -> bool {
+        G::is_terminal(state)
+    }
+
+    /// The player with the highest utility, if there is only one.
+    fn winner(state: &Self::S) -> Option<PlayerId> {
+        let utilities = G::utilities(state);
+        let best = utilities.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mut leaders = (0..utilities.len()).filter(|p| utilities[*p] == best);
+        match (leaders.next(), leaders.next()) {
+            (Some(p), None) => Some(PlayerId(p)),
+            _ => None,
+        }
+    }
+
+    // The first player nominally chooses each joint action
+    fn player_to_move(_: &Self::S) -> PlayerId {
+        PlayerId(0)
+    }
+
+    fn num_players() -> usize {
+        G::num_players()
+    }
+
+    fn notation(_: &Self::S, action: &Self::A) -> String {
+        format!("{action:?}")
+    }
+
+    fn compute_utilities(state: &Self::S) -> Vec<f64> {
+        G::utilities(state)
+    }
+
+    fn zobrist_hash(state: &Self::S) -> u64 {
+        G::zobrist_hash(state)
+    }
+
+    fn has_simultaneous_moves() -> bool {
+        true
+    }
+
+    fn is_simultaneous(_: &Self::S) -> bool {
+        true
+    }
+
+    fn player_move(state: &Self::S, action: &Self::A, player: usize) -> usize {
+        Self::moves(state, player)
+            .iter()
+            .position(|m| *m == action[player])
+            .expect("not a legal move")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{Move, TicTacToe};
+    use crate::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+    use crate::strategies::Search;
+    use crate::util::random_play;
+
+    type BestOf3 = MatchOf<TicTacToe, 3>;
+
+    #[test]
+    fn test_match_of() {
+        // X wins along the top row
+        let x_wins = [0, 3, 1, 4, 2];
+
+        let mut state = MatchState::default();
+        for (i, action) in x_wins.iter().enumerate() {
+            assert!(!BestOf3::is_terminal(&state));
+            state = BestOf3::apply(state, &Move(*action));
+            assert_eq!(state.played, (i == 4) as u32);
+        }
+        assert_eq!(state.wins, [1, 0]);
+        assert_eq!(state.game, Default::default());
+        assert!(BestOf3::zobrist_hash(&state) != BestOf3::zobrist_hash(&Default::default()));
+
+        for action in x_wins {
+            state = BestOf3::apply(state, &Move(action));
+        }
+        assert!(BestOf3::is_terminal(&state));
+        assert_eq!(BestOf3::winner_id(&state), Some(PlayerId(0)));
+        assert_eq!(BestOf3::compute_utilities(&state), vec![1., -1.]);
+
+        for _ in 0..10 {
+            random_play::<BestOf3>();
+        }
+
+        let mut ts = TreeSearch::<BestOf3, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(200).seed(0));
+        let state = BestOf3::apply(Default::default(), &Move(4));
+        assert!(ts.choose_action(&state).0 < 9);
+    }
+
+    // A single round of a zero-sum matrix game. The first player's move A
+    // dominates B, and against A the second player does best with Y, for a
+    // draw. The first player would win with A against X.
+    #[derive(Clone)]
+    struct Matrix;
+
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    struct MatrixState(Option<(char, char)>);
+
+    impl std::fmt::Display for MatrixState {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    impl SimultaneousGame for Matrix {
+        type S = MatrixState;
+        type M = char;
+
+        fn player_moves(_: &Self::S, player: PlayerId, moves: &mut Vec<char>) {
+            match player.index() {
+                0 => moves.extend(['A', 'B']),
+                _ => moves.extend(['X', 'Y']),
+            }
+        }
+
+        fn apply(_: Self::S, moves: &[char]) -> Self::S {
+            MatrixState(Some((moves[0], moves[1])))
+        }
+
+        fn is_terminal(state: &Self::S) -> bool {
+            state.0.is_some()
+        }
+
+        fn utilities(state: &Self::S) -> Vec<f64> {
+            let reward = match state.0.unwrap() {
+                ('A', 'X') => 1.,
+                ('A', _) => 0.,
+                _ => -1.,
+            };
+            vec![reward, -reward]
+        }
+    }
+
+    #[test]
+    fn test_simultaneous() {
+        use crate::strategies::mcts::Strategy;
+        type G = Simultaneous<Matrix>;
+
+        let state = MatrixState::default();
+        let mut actions = vec![];
+        G::generate_actions(&state, &mut actions);
+        assert_eq!(actions.len(), 4);
+        assert_eq!(G::player_move(&state, &vec!['B', 'X'], 0), 1);
+        assert_eq!(G::player_move(&state, &vec!['B', 'X'], 1), 0);
+        let draw = G::apply(state.clone(), &vec!['A', 'Y']);
+        assert_eq!(G::winner_id(&draw), None);
+        assert_eq!(G::compute_utilities(&draw), vec![0., 0.]);
+
+        // Each player picks their own move, and they meet at the saddle point
+        let mut ts = TreeSearch::<G, strategy::Duct>::default().config(
+            <strategy::Duct as Strategy<G>>::config()
+                .max_iterations(2000)
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), vec!['A', 'Y']);
+        assert_eq!(ts.root_proof(), None);
+
+        // Treating the joint action as the first player's choice assumes the
+        // second player cooperates
+        let mut ts = TreeSearch::<G, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(2000).seed(0));
+        assert_eq!(ts.choose_action(&state), vec!['A', 'X']);
+    }
+}
@@ -0,0 +1,346 @@
+//! The Go Text Protocol (GTP version 2), for playing the Go-like games from
+//! GoGui, Sabaki and other GTP controllers.
+//!
+//! The supported commands are `protocol_version`, `name`, `version`,
+//! `known_command`, `list_commands`, `quit`, `boardsize`, `clear_board`,
+//! `komi`, `play`, `genmove`, `final_score` and `showboard`. The board size is
+//! fixed by the game type, so `boardsize` only accepts `GtpGame::BOARD_SIZE`,
+//! and `komi` is accepted but ignored. Colors must alternate as the game
+//! requires: a move for the player not to move is illegal. Besides `pass`,
+//! games with a swap rule accept and generate `swap`.
+
+use crate::game::{Game, PlayerIndex};
+use crate::strategies::mcts::{SearchConfig, Strategy, TreeSearch};
+use crate::strategies::Search;
+
+use std::io::{self, BufRead, Write};
+
+/// A point on the board, counted from the bottom left, or a move which
+/// places no stone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vertex {
+    Point { row: usize, col: usize },
+    Pass,
+    Swap,
+}
+
+impl Vertex {
+    // Column letters skip I
+    const COLUMNS: &'static [u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+    /// Parse a GTP vertex such as `C4`, `pass` or `swap`, ignoring case.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.to_ascii_uppercase();
+        match input.as_str() {
+            "PASS" => return Some(Vertex::Pass),
+            "SWAP" => return Some(Vertex::Swap),
+            _ => {}
+        }
+        let col = Self::COLUMNS
+            .iter()
+            .position(|c| input.as_bytes().first() == Some(c))?;
+        let row = input[1..].parse::<usize>().ok()?.checked_sub(1)?;
+        Some(Vertex::Point { row, col })
+    }
+}
+
+impl std::fmt::Display for Vertex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Vertex::Point { row, col } => {
+                write!(f, "{}{}", Self::COLUMNS[*col] as char, row + 1)
+            }
+            Vertex::Pass => write!(f, "pass"),
+            Vertex::Swap => write!(f, "swap"),
+        }
+    }
+}
+
+/// A two player board game which can be played over GTP. The first player is
+/// black.
+pub trait GtpGame: Game {
+    const BOARD_SIZE: usize;
+
+    fn vertex(action: &Self::A) -> Vertex;
+
+    /// Black's lead, for `final_score`. By default a win counts as one point.
+    fn score(state: &Self::S) -> f64 {
+        match Self::winner(state).map(|winner| winner.to_index()) {
+            Some(0) => 1.,
+            Some(_) => -1.,
+            None => 0.,
+        }
+    }
+
+    /// The legal action at `vertex` for the player to move, if any.
+    fn action_at(state: &Self::S, vertex: Vertex) -> Option<Self::A> {
+        if Self::is_terminal(state) {
+            return None;
+        }
+        let mut actions = vec![];
+        Self::generate_actions(state, &mut actions);
+        actions
+            .into_iter()
+            .find(|action| Self::vertex(action) == vertex)
+    }
+}
+
+pub struct Gtp<G, S>
+where
+    G: GtpGame,
+    S: Strategy<G>,
+    G::S: std::fmt::Display,
+{
+    pub search: TreeSearch<G, S>,
+    // The position after `clear_board`
+    initial: G::S,
+    state: G::S,
+}
+
+impl<G, S> Gtp<G, S>
+where
+    G: GtpGame,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    const COMMANDS: &'static [&'static str] = &[
+        "protocol_version",
+        "name",
+        "version",
+        "known_command",
+        "list_commands",
+        "quit",
+        "boardsize",
+        "clear_board",
+        "komi",
+        "play",
+        "genmove",
+        "final_score",
+        "showboard",
+    ];
+
+    pub fn new(search: TreeSearch<G, S>) -> Self {
+        Self {
+            search,
+            initial: G::S::default(),
+            state: G::S::default(),
+        }
+    }
+
+    /// Start from `state` instead of the default, for example to play with
+    /// different rules.
+    pub fn initial_state(mut self, state: G::S) -> Self {
+        self.initial = state.clone();
+        self.state = state;
+        self
+    }
+
+    pub fn state(&self) -> &G::S {
+        &self.state
+    }
+
+    /// Answer commands from `input` on `output` until `quit` or the end of
+    /// input.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            // Comments and control characters other than tabs are dropped
+            let line = line?;
+            let line = line
+                .split('#')
+                .next()
+                .unwrap()
+                .replace('\t', " ")
+                .replace(|c: char| c.is_control(), "");
+            let mut tokens = line.split_whitespace().peekable();
+            let id = tokens
+                .next_if(|token| token.parse::<u64>().is_ok())
+                .unwrap_or("");
+            let Some(command) = tokens.next() else {
+                continue;
+            };
+            let args = tokens.collect::<Vec<_>>();
+            let (status, response) = match self.execute(command, &args) {
+                Ok(response) => ('=', response),
+                Err(err) => ('?', err),
+            };
+            let separator = if response.is_empty() { "" } else { " " };
+            write!(output, "{status}{id}{separator}{response}\n\n")?;
+            output.flush()?;
+            if command == "quit" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match (command, args) {
+            ("protocol_version", []) => Ok("2".into()),
+            ("name", []) => Ok(self.search.friendly_name()),
+            ("version", []) => Ok(env!("CARGO_PKG_VERSION").into()),
+            ("known_command", [name]) => Ok(Self::COMMANDS.contains(name).to_string()),
+            ("list_commands", []) => Ok(Self::COMMANDS.join("\n")),
+            ("quit", []) => Ok(String::new()),
+            ("boardsize", [size]) => match size.parse::<usize>() {
+                Ok(size) if size == G::BOARD_SIZE => Ok(String::new()),
+                Ok(_) => Err("unacceptable size".into()),
+                Err(_) => Err("syntax error".into()),
+            },
+            ("clear_board", []) => {
+                self.state = self.initial.clone();
+                Ok(String::new())
+            }
+            ("komi", [komi]) => komi
+                .parse::<f64>()
+                .map(|_| String::new())
+                .map_err(|_| "syntax error".into()),
+            ("play", [color, vertex]) => {
+                self.check_color(color)?;
+                let vertex = Vertex::parse(vertex).ok_or("syntax error")?;
+                let action = G::action_at(&self.state, vertex).ok_or("illegal move")?;
+                self.state = G::apply(self.state.clone(), &action);
+                Ok(String::new())
+            }
+            ("genmove", [color]) => {
+                self.check_color(color)?;
+                if G::is_terminal(&self.state) {
+                    return Ok("pass".into());
+                }
+                let action = self.search.choose_action(&self.state);
+                self.state = G::apply(self.state.clone(), &action);
+                Ok(G::vertex(&action).to_string())
+            }
+            ("final_score", []) => {
+                let score = G::score(&self.state);
+                Ok(match score {
+                    _ if score > 0. => format!("B+{score}"),
+                    _ if score < 0. => format!("W+{}", -score),
+                    _ => "0".into(),
+                })
+            }
+            ("showboard", []) => Ok(format!("\n{}", self.state)),
+            _ if Self::COMMANDS.contains(&command) => Err("syntax error".into()),
+            _ => Err("unknown command".into()),
+        }
+    }
+
+    // Only the player to move may play
+    fn check_color(&self, color: &str) -> Result<(), String> {
+        let player = match color.to_ascii_lowercase().as_str() {
+            "b" | "black" => 0,
+            "w" | "white" => 1,
+            _ => return Err("syntax error".into()),
+        };
+        if G::player_to_move(&self.state).to_index() == player {
+            Ok(())
+        } else {
+            Err("illegal move".into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::atarigo::AtariGo;
+    use crate::games::gonnect::Gonnect;
+    use crate::strategies::mcts::strategy;
+
+    fn run<G: GtpGame>(gtp: &mut Gtp<G, strategy::Ucb1>, input: &str) -> Vec<String>
+    where
+        G::S: std::fmt::Display,
+    {
+        let mut output = vec![];
+        gtp.run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .split("\n\n")
+            .filter(|response| !response.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn search<G: GtpGame>() -> TreeSearch<G, strategy::Ucb1>
+    where
+        G::S: std::fmt::Display,
+    {
+        TreeSearch::default().config(
+            SearchConfig::default()
+                .name("test")
+                .max_iterations(200)
+                .seed(0),
+        )
+    }
+
+    #[test]
+    fn test_vertex() {
+        for (input, vertex) in [
+            ("a1", Vertex::Point { row: 0, col: 0 }),
+            ("H8", Vertex::Point { row: 7, col: 7 }),
+            ("J3", Vertex::Point { row: 2, col: 8 }),
+            ("PASS", Vertex::Pass),
+            ("swap", Vertex::Swap),
+        ] {
+            assert_eq!(Vertex::parse(input), Some(vertex));
+            assert_eq!(
+                vertex.to_string().to_ascii_uppercase(),
+                input.to_uppercase()
+            );
+        }
+        assert_eq!(Vertex::parse("I1"), None);
+        assert_eq!(Vertex::parse("A0"), None);
+        assert_eq!(Vertex::parse("A"), None);
+    }
+
+    #[test]
+    fn test_gtp() {
+        let mut gtp = Gtp::new(search::<AtariGo<5>>());
+        let responses = run(
+            &mut gtp,
+            "1 protocol_version\nname\nboardsize 19\nboardsize 5\nkomi 6.5\n\
+             known_command genmove\nplay black C3\nplay black D3\nplay white C3\n\
+             play white C4 # comment\nfoo\n",
+        );
+        assert_eq!(
+            responses,
+            [
+                "=1 2",
+                "= test",
+                "? unacceptable size",
+                "=",
+                "=",
+                "= true",
+                "=",
+                "? illegal move",
+                "? illegal move",
+                "=",
+                "? unknown command",
+            ]
+        );
+
+        let responses = run(
+            &mut gtp,
+            "genmove black\nfinal_score\nclear_board\nquit\nname\n",
+        );
+        let vertex = Vertex::parse(responses[0].strip_prefix("= ").unwrap()).unwrap();
+        assert!(matches!(vertex, Vertex::Point { .. }));
+        assert_eq!(responses[1], "= 0");
+        assert_eq!(responses.len(), 4);
+        assert_eq!(gtp.state(), &Default::default());
+
+        // Black captures the white stone at A1
+        let responses = run(
+            &mut gtp,
+            "play b B1\nplay w A1\nplay b A2\nfinal_score\nplay w B2\n",
+        );
+        assert_eq!(responses[3..], ["= B+1", "? illegal move"]);
+
+        let mut gtp = Gtp::new(search::<Gonnect<5>>());
+        let responses = run(&mut gtp, "play b swap\nplay b C3\ngenmove w\nplay w C3\n");
+        assert_eq!(responses[..2], ["? illegal move", "="]);
+        let vertex = Vertex::parse(responses[2].strip_prefix("= ").unwrap()).unwrap();
+        assert_ne!(vertex, Vertex::Point { row: 2, col: 2 });
+        assert_eq!(responses[3], "? illegal move");
+    }
+}
@@ -1,7 +1,15 @@
+pub mod benchmark;
 pub mod display;
+pub mod engine;
+pub mod env;
 pub mod game;
 pub mod games;
+pub mod gtp;
+pub mod sprt;
 pub mod strategies;
+pub mod suite;
 pub mod timer;
+pub mod tournament;
+pub mod tune;
 pub mod util;
 pub mod zobrist;
@@ -0,0 +1,324 @@
+//! Sequential probability ratio tests (SPRT) of a candidate strategy against
+//! a baseline, the usual way to check that a change gains strength without
+//! playing a fixed, large number of games.
+//!
+//! The test weighs H0, that the candidate is `elo0` stronger than the
+//! baseline, against H1, that it is `elo1` stronger, for example `[0, 5]` to
+//! accept a change which gains at least a few Elo. Games are played in pairs
+//! from the same opening with the colors reversed, until the log-likelihood
+//! ratio (LLR) leaves the bounds set by the error rates `alpha` (of accepting
+//! H1 when H0 holds) and `beta` (of accepting H0 when H1 holds).
+//!
+//! ```ignore
+//! let result = Sprt::new()
+//!     .elo_bounds(0., 5.)
+//!     .run::<Game>(&mut candidate, &mut baseline, &init, Verbosity::Verbose);
+//! ```
+//!
+//! The LLR is the normal approximation of the generalized SPRT on the game
+//! results (wins, draws and losses), as in Fishtest. It is unreliable while
+//! almost every game is drawn, since the variance of the results is then
+//! tiny, so games which are drawn with best play want an `opening_plies`.
+
+use crate::game::{Game, PlayerId, PlayerIndex};
+use crate::strategies::{Search, Verdict};
+use crate::util::{elo_difference, AnySearch, HeadToHead, Verbosity};
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SprtDecision {
+    // The candidate is no more than `elo0` stronger
+    AcceptH0,
+    // The candidate is at least `elo1` stronger
+    AcceptH1,
+    // `max_pairs` were played without reaching either bound
+    Inconclusive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+    // Give up after this many game pairs
+    pub max_pairs: usize,
+    // Random moves from the initial state to start each pair from, so that
+    // the pairs are not all alike
+    pub opening_plies: usize,
+    pub seed: u64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.,
+            elo1: 5.,
+            alpha: 0.05,
+            beta: 0.05,
+            max_pairs: 100_000,
+            opening_plies: 0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SprtResult {
+    pub decision: SprtDecision,
+    pub llr: f64,
+    // The LLR at which H0 and H1 are accepted
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    // The candidate's results against the baseline
+    pub result: HeadToHead,
+    pub pairs: usize,
+    // The rating difference implied by the candidate's score
+    pub elo: f64,
+}
+
+impl Sprt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn elo_bounds(mut self, elo0: f64, elo1: f64) -> Self {
+        self.elo0 = elo0;
+        self.elo1 = elo1;
+        self
+    }
+
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    pub fn max_pairs(mut self, max_pairs: usize) -> Self {
+        self.max_pairs = max_pairs;
+        self
+    }
+
+    pub fn opening_plies(mut self, opening_plies: usize) -> Self {
+        self.opening_plies = opening_plies;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The LLR bounds for accepting H0 and H1.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1. - self.alpha)).ln(),
+            ((1. - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The log-likelihood ratio of H1 to H0 given the candidate's results.
+    /// Zero while the results do not vary, as when every game is drawn.
+    pub fn llr(&self, result: &HeadToHead) -> f64 {
+        let games = result.games() as f64;
+        if games == 0. {
+            return 0.;
+        }
+        let score = result.score();
+        let variance = (result.wins as f64 * (1. - score).powi(2)
+            + result.draws as f64 * (0.5 - score).powi(2)
+            + result.losses as f64 * score.powi(2))
+            / games;
+        if variance <= 0. {
+            return 0.;
+        }
+        let expected = |elo: f64| 1. / (1. + 10f64.powf(-elo / 400.));
+        let (s0, s1) = (expected(self.elo0), expected(self.elo1));
+        games * (s1 - s0) * (2. * score - s0 - s1) / (2. * variance)
+    }
+
+    /// Play pairs of games between `candidate` and `baseline` from `init`
+    /// until the test is decided or `max_pairs` is reached.
+    pub fn run<'a, G>(
+        &self,
+        candidate: &mut AnySearch<'a, G>,
+        baseline: &mut AnySearch<'a, G>,
+        init: &G::S,
+        verbose: Verbosity,
+    ) -> SprtResult
+    where
+        G: Game + Clone,
+    {
+        assert!(
+            self.elo0 < self.elo1,
+            "elo0 must be less than elo1 for an SPRT"
+        );
+        assert!(
+            0. < self.alpha && self.alpha < 1. && 0. < self.beta && self.beta < 1.,
+            "alpha and beta must be in (0, 1) for an SPRT"
+        );
+        let (lower_bound, upper_bound) = self.bounds();
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        candidate.prepare(init);
+        baseline.prepare(init);
+
+        let mut result = HeadToHead::default();
+        let mut llr = 0.;
+        let mut pairs = 0;
+        let mut decision = SprtDecision::Inconclusive;
+        while pairs < self.max_pairs {
+            let opening = random_opening::<G>(init, self.opening_plies, &mut rng);
+            for candidate_seat in 0..2 {
+                let mut players = [candidate.clone(), baseline.clone()];
+                players.rotate_left(candidate_seat);
+                match play::<G>(&mut players, opening.clone()) {
+                    None => result.draws += 1,
+                    Some(seat) if seat == candidate_seat => result.wins += 1,
+                    Some(_) => result.losses += 1,
+                }
+            }
+            pairs += 1;
+
+            llr = self.llr(&result);
+            if verbose.verbose() {
+                println!(
+                    "pair {pairs:>6}: {}-{}-{} llr {llr:+.2} ({lower_bound:+.2}, {upper_bound:+.2})",
+                    result.wins, result.losses, result.draws,
+                );
+            }
+            if llr >= upper_bound {
+                decision = SprtDecision::AcceptH1;
+                break;
+            }
+            if llr <= lower_bound {
+                decision = SprtDecision::AcceptH0;
+                break;
+            }
+        }
+
+        SprtResult {
+            decision,
+            llr,
+            lower_bound,
+            upper_bound,
+            result,
+            pairs,
+            elo: elo_difference(result.score()),
+        }
+    }
+}
+
+pub(crate) fn random_opening<G: Game>(init: &G::S, plies: usize, rng: &mut SmallRng) -> G::S {
+    let mut state = init.clone();
+    let mut actions = Vec::new();
+    for _ in 0..plies {
+        if G::is_terminal(&state) {
+            break;
+        }
+        actions.clear();
+        G::generate_actions(&state, &mut actions);
+        state = G::apply(state, &actions[rng.gen_range(0..actions.len())]);
+    }
+    state
+}
+
+// Play out a game, returning the player index of the winner. Resignations and win
+// claims end the game early, as in the round robin.
+pub(crate) fn play<G: Game + Clone>(
+    players: &mut [AnySearch<'_, G>; 2],
+    mut state: G::S,
+) -> Option<usize> {
+    while !G::is_terminal(&state) {
+        let current = G::player_to_move(&state).to_index();
+        let (action, verdict) = players[current].choose_action_with_verdict(&state);
+        match verdict {
+            Some(Verdict::Resign) => return Some(PlayerId(current).opponent().index()),
+            Some(Verdict::ClaimWin) => return Some(current),
+            None => {}
+        }
+        state = G::apply(state, &action);
+    }
+    G::winner(&state).map(|winner| winner.to_index())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::TicTacToe;
+    use crate::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+    use crate::strategies::random::Random;
+
+    fn mcts(seed: u64) -> AnySearch<'static, TicTacToe> {
+        AnySearch::new(
+            TreeSearch::<TicTacToe, strategy::Ucb1>::new()
+                .config(SearchConfig::new().max_iterations(300).seed(seed)),
+        )
+    }
+
+    #[test]
+    fn test_sprt_llr() {
+        let sprt = Sprt::new().elo_bounds(0., 10.);
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 1e-3);
+        assert!((upper - 2.944).abs() < 1e-3);
+
+        let result = |wins, losses, draws| HeadToHead {
+            wins,
+            losses,
+            draws,
+        };
+        assert_eq!(sprt.llr(&result(0, 0, 0)), 0.);
+        assert_eq!(sprt.llr(&result(0, 0, 10)), 0.);
+        // Scoring halfway between the hypotheses is evidence for neither
+        let halfway = sprt.llr(&result(5072, 4928, 0));
+        assert!(halfway.abs() < 0.05, "{halfway}");
+        assert!(sprt.llr(&result(600, 400, 0)) > upper);
+        assert!(sprt.llr(&result(400, 600, 0)) < lower);
+    }
+
+    #[test]
+    fn test_sprt_run() {
+        let sprt = Sprt::new()
+            .elo_bounds(0., 100.)
+            .opening_plies(1)
+            .max_pairs(200);
+        let mut random = AnySearch::new(Random::<TicTacToe>::new().seed(0));
+        let result = sprt.run::<TicTacToe>(
+            &mut mcts(0),
+            &mut random,
+            &Default::default(),
+            Verbosity::Silent,
+        );
+        assert_eq!(result.decision, SprtDecision::AcceptH1);
+        assert!(result.llr >= result.upper_bound);
+        assert_eq!(result.result.games(), 2 * result.pairs);
+        assert!(result.elo > 100.);
+
+        // A strategy is not 100 Elo stronger than itself
+        let random = |seed| AnySearch::new(Random::<TicTacToe>::new().seed(seed));
+        let result = sprt.run::<TicTacToe>(
+            &mut random(1),
+            &mut random(2),
+            &Default::default(),
+            Verbosity::Silent,
+        );
+        assert_eq!(result.decision, SprtDecision::AcceptH0);
+
+        let result = sprt.max_pairs(1).run::<TicTacToe>(
+            &mut mcts(1),
+            &mut mcts(2),
+            &Default::default(),
+            Verbosity::Silent,
+        );
+        assert_eq!(result.decision, SprtDecision::Inconclusive);
+        assert_eq!(result.pairs, 1);
+    }
+}
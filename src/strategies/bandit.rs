@@ -0,0 +1,419 @@
+//! Multi-armed bandits for flat, one ply decisions. A `Bandit` spreads a
+//! budget of samples over a fixed set of arms, choosing the next arm to sample
+//! with a `BanditPolicy`. The UCB policies share their scoring with the MCTS
+//! select strategies of the same name.
+//!
+//! `FlatBandit` uses a bandit as a `Search`, sampling each root action with
+//! random playouts.
+
+use super::mcts::select::{ucb1, ucb1_tuned, VARIANCE_UPPER_BOUND};
+use super::mcts::{validate_epsilon, ConfigError};
+use super::Search;
+use crate::game::Game;
+use crate::util::random_best;
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use rand_distr::{Beta, Distribution};
+use std::marker::PhantomData;
+
+/// The rewards gathered by one arm. Rewards are expected to lie in [0, 1].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Arm {
+    pub visits: u32,
+    pub score: f64,
+    pub score_sq: f64,
+}
+
+impl Arm {
+    pub fn update(&mut self, reward: f64) {
+        self.visits += 1;
+        self.score += reward;
+        self.score_sq += reward * reward;
+    }
+
+    /// The mean reward, or zero when the arm is unvisited.
+    pub fn mean(&self) -> f64 {
+        self.score / (self.visits.max(1) as f64)
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.visits == 0 {
+            return VARIANCE_UPPER_BOUND;
+        }
+        let mean = self.mean();
+        (self.score_sq / self.visits as f64 - mean * mean).max(0.)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Chooses the next arm of a `Bandit` to sample.
+pub trait BanditPolicy: Clone + Sync + Send {
+    /// The index of the arm to sample next. `arms` is never empty.
+    fn select(&mut self, arms: &[Arm], rng: &mut SmallRng) -> usize;
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        Ok(())
+    }
+}
+
+// The first unvisited arm, or else the arm with the highest score
+fn best_arm(arms: &[Arm], rng: &mut SmallRng, score: impl Fn(&Arm) -> f64) -> usize {
+    if let Some(i) = arms.iter().position(|arm| arm.visits == 0) {
+        return i;
+    }
+    let indices = (0..arms.len()).collect::<Vec<_>>();
+    *random_best(&indices, rng, |i| score(&arms[*i])).unwrap()
+}
+
+fn parent_log(arms: &[Arm]) -> f64 {
+    let visits = arms.iter().map(|arm| arm.visits as f64).sum::<f64>();
+    visits.max(1.).ln()
+}
+
+/// Sample each arm in turn, as plain flat Monte Carlo does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RoundRobin;
+
+impl BanditPolicy for RoundRobin {
+    fn select(&mut self, arms: &[Arm], _: &mut SmallRng) -> usize {
+        (0..arms.len()).min_by_key(|i| arms[*i].visits).unwrap()
+    }
+}
+
+/// Upper Confidence Bounds (UCB1). See `mcts::select::Ucb1`.
+#[derive(Clone, Copy, Debug)]
+pub struct Ucb1 {
+    pub exploration_constant: f64,
+}
+
+impl Ucb1 {
+    pub fn with_c(exploration_constant: f64) -> Self {
+        Self {
+            exploration_constant,
+        }
+    }
+}
+
+impl Default for Ucb1 {
+    fn default() -> Self {
+        Self {
+            exploration_constant: 2f64.sqrt(),
+        }
+    }
+}
+
+impl BanditPolicy for Ucb1 {
+    fn select(&mut self, arms: &[Arm], rng: &mut SmallRng) -> usize {
+        let parent_log = parent_log(arms);
+        best_arm(arms, rng, |arm| {
+            ucb1(
+                self.exploration_constant,
+                arm.mean(),
+                parent_log,
+                arm.visits as f64,
+            )
+        })
+    }
+}
+
+/// UCB1-Tuned, which scales exploration by each arm's variance. See
+/// `mcts::select::Ucb1Tuned`.
+#[derive(Clone, Copy, Debug)]
+pub struct Ucb1Tuned {
+    pub exploration_constant: f64,
+}
+
+impl Ucb1Tuned {
+    pub fn with_c(exploration_constant: f64) -> Self {
+        Self {
+            exploration_constant,
+        }
+    }
+}
+
+impl Default for Ucb1Tuned {
+    fn default() -> Self {
+        Self {
+            exploration_constant: 2f64.sqrt(),
+        }
+    }
+}
+
+impl BanditPolicy for Ucb1Tuned {
+    fn select(&mut self, arms: &[Arm], rng: &mut SmallRng) -> usize {
+        let parent_log = parent_log(arms);
+        best_arm(arms, rng, |arm| {
+            ucb1_tuned(
+                self.exploration_constant,
+                arm.mean(),
+                arm.variance(),
+                parent_log / arm.visits as f64,
+            )
+        })
+    }
+}
+
+/// Thompson sampling: sample a mean for each arm from its Beta posterior,
+/// treating rewards as fractional wins, and play the best.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Thompson;
+
+impl BanditPolicy for Thompson {
+    fn select(&mut self, arms: &[Arm], rng: &mut SmallRng) -> usize {
+        let samples = arms
+            .iter()
+            .map(|arm| {
+                let wins = arm.score.clamp(0., arm.visits as f64);
+                let losses = arm.visits as f64 - wins;
+                Beta::new(1. + wins, 1. + losses).unwrap().sample(rng)
+            })
+            .collect::<Vec<_>>();
+        let indices = (0..arms.len()).collect::<Vec<_>>();
+        *random_best(&indices, rng, |i| samples[*i]).unwrap()
+    }
+}
+
+/// With probability `epsilon` sample a uniformly random arm, and otherwise the
+/// arm with the best mean. See `mcts::select::EpsilonGreedy`.
+#[derive(Clone, Copy, Debug)]
+pub struct EpsilonGreedy {
+    pub epsilon: f64,
+}
+
+impl EpsilonGreedy {
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+}
+
+impl Default for EpsilonGreedy {
+    fn default() -> Self {
+        Self { epsilon: 0.1 }
+    }
+}
+
+impl BanditPolicy for EpsilonGreedy {
+    fn select(&mut self, arms: &[Arm], rng: &mut SmallRng) -> usize {
+        if rng.gen::<f64>() < self.epsilon {
+            rng.gen_range(0..arms.len())
+        } else {
+            best_arm(arms, rng, Arm::mean)
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        validate_epsilon(self.epsilon)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A fixed set of arms sampled under a `BanditPolicy`.
+#[derive(Clone, Debug)]
+pub struct Bandit<P: BanditPolicy> {
+    pub arms: Vec<Arm>,
+    pub policy: P,
+}
+
+impl<P: BanditPolicy> Bandit<P> {
+    pub fn new(num_arms: usize, policy: P) -> Self {
+        Self {
+            arms: vec![Arm::default(); num_arms],
+            policy,
+        }
+    }
+
+    /// The arm to sample next.
+    pub fn select(&mut self, rng: &mut SmallRng) -> usize {
+        self.policy.select(&self.arms, rng)
+    }
+
+    pub fn update(&mut self, arm: usize, reward: f64) {
+        self.arms[arm].update(reward);
+    }
+
+    /// Sample `samples` times, scoring the chosen arm with `reward`.
+    pub fn run(
+        &mut self,
+        samples: u32,
+        rng: &mut SmallRng,
+        mut reward: impl FnMut(usize, &mut SmallRng) -> f64,
+    ) {
+        for _ in 0..samples {
+            let arm = self.select(rng);
+            let value = reward(arm, rng);
+            self.update(arm, value);
+        }
+    }
+
+    /// The most sampled arm, breaking ties by the mean reward.
+    pub fn best(&self) -> Option<usize> {
+        (0..self.arms.len()).max_by(|a, b| {
+            let (a, b) = (&self.arms[*a], &self.arms[*b]);
+            a.visits.cmp(&b.visits).then(a.mean().total_cmp(&b.mean()))
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Flat search: a bandit over the root actions, sampling each with a uniformly
+/// random playout and playing the most sampled.
+pub struct FlatBandit<G: Game, P: BanditPolicy = Ucb1> {
+    pub policy: P,
+    pub samples: u32,
+    pub max_playout_depth: u32,
+    pub name: String,
+    rng: SmallRng,
+    root_visits: Vec<(G::A, u32)>,
+    game_type: PhantomData<G>,
+}
+
+impl<G: Game, P: BanditPolicy + Default> Default for FlatBandit<G, P> {
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+impl<G: Game, P: BanditPolicy> FlatBandit<G, P> {
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy,
+            samples: 1000,
+            max_playout_depth: 100,
+            name: "flat_bandit".into(),
+            rng: SmallRng::from_entropy(),
+            root_visits: vec![],
+            game_type: PhantomData,
+        }
+    }
+
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn max_playout_depth(mut self, max_playout_depth: u32) -> Self {
+        self.max_playout_depth = max_playout_depth;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// The bandit over the actions of `state` after sampling, with the actions
+    /// in the order of its arms.
+    pub fn evaluate(&mut self, state: &G::S) -> (Vec<G::A>, Bandit<P>) {
+        let mut actions = vec![];
+        G::generate_actions(state, &mut actions);
+        let mut bandit = Bandit::new(actions.len(), self.policy.clone());
+        if actions.is_empty() {
+            return (actions, bandit);
+        }
+        let max_depth = self.max_playout_depth;
+        let mut buffer = vec![];
+        bandit.run(self.samples, &mut self.rng, |arm, rng| {
+            let mut current = G::apply(state.clone(), &actions[arm]);
+            for _ in 0..max_depth {
+                if G::is_terminal(&current) {
+                    // Utilities lie in [-1, 1]
+                    return (G::get_reward(state, &current) + 1.) / 2.;
+                }
+                buffer.clear();
+                G::generate_actions(&current, &mut buffer);
+                let action = &buffer[rng.gen_range(0..buffer.len())];
+                current = G::apply(current, action);
+            }
+            0.5
+        });
+        (actions, bandit)
+    }
+}
+
+impl<G: Game + Sync + Send, P: BanditPolicy> Search for FlatBandit<G, P> {
+    type G = G;
+
+    fn friendly_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn set_friendly_name(&mut self, name: &str) {
+        self.name = name.into();
+    }
+
+    fn choose_action(&mut self, state: &G::S) -> G::A {
+        let (actions, bandit) = self.evaluate(state);
+        let best = bandit.best().expect("no legal actions");
+        self.root_visits = actions
+            .iter()
+            .cloned()
+            .zip(bandit.arms.iter().map(|arm| arm.visits))
+            .collect();
+        actions[best].clone()
+    }
+
+    fn root_visits(&self) -> Vec<(G::A, u32)> {
+        self.root_visits.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+
+    // Arms with Bernoulli rewards of the given means
+    fn sample<P: BanditPolicy>(policy: P, means: &[f64]) -> Bandit<P> {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut bandit = Bandit::new(means.len(), policy);
+        bandit.run(2000, &mut rng, |arm, rng| {
+            if rng.gen::<f64>() < means[arm] {
+                1.
+            } else {
+                0.
+            }
+        });
+        bandit
+    }
+
+    #[test]
+    fn test_policies() {
+        let means = [0.2, 0.8, 0.5];
+        let round_robin = sample(RoundRobin, &means);
+        assert!(round_robin
+            .arms
+            .iter()
+            .all(|arm| arm.visits == 666 || arm.visits == 667));
+        for bandit in [
+            sample(Ucb1::default(), &means).arms,
+            sample(Ucb1Tuned::default(), &means).arms,
+            sample(Thompson, &means).arms,
+            sample(EpsilonGreedy::default(), &means).arms,
+        ] {
+            // The best arm is found and sampled most
+            assert!(bandit[1].visits > 1000);
+            assert!((bandit[1].mean() - 0.8).abs() < 0.05);
+        }
+        assert!(EpsilonGreedy::default().epsilon(1.5).validate().is_err());
+    }
+
+    #[test]
+    fn test_flat_bandit() {
+        // X to move and win at 2, or lose to O at 5
+        let mut state = HashedPosition::new();
+        for i in [0, 4, 1, 3] {
+            state = TicTacToe::apply(state, &Move(i));
+        }
+        let mut search = FlatBandit::<TicTacToe>::default().samples(2000).seed(0);
+        assert_eq!(search.choose_action(&state), Move(2));
+        let visits = search.root_visits();
+        assert_eq!(visits.len(), 5);
+        assert_eq!(visits.iter().map(|(_, n)| n).sum::<u32>(), 2000);
+    }
+}
@@ -0,0 +1,252 @@
+//! Search for games which decompose into independent regions (see
+//! `Game::decompose`), such as Nim piles or settled areas of a Go board.
+//!
+//! Each component is small compared to the whole, so it can be searched (or
+//! solved) far more cheaply, and the results combined to choose a move in the
+//! sum. States which do not decompose are searched as usual.
+
+use crate::game::{Game, PlayerIndex};
+use crate::strategies::mcts::{SearchConfig, Strategy, TreeSearch};
+use crate::strategies::Search;
+use crate::zobrist::ZobristHashMap;
+
+/// How per-component results are combined into a move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Combine {
+    /// Search each component with the inner search, and play the move with the
+    /// highest expected score for the player to move. Suits games scored by
+    /// adding up regions, like territory.
+    #[default]
+    Utility,
+    /// Solve each component exactly for its Grundy value, and play a move
+    /// which leaves a sum of zero. This is exact for impartial games under
+    /// the normal play convention (the player unable to move loses), such as
+    /// Nim, and requires components small enough to solve. When the sum is
+    /// already zero there is no winning move, and the inner search plays on the
+    /// whole state.
+    Grundy,
+}
+
+#[derive(Clone)]
+pub struct DecomposedSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+{
+    pub inner: TreeSearch<G, S>,
+    pub combine: Combine,
+    // Grundy values of solved components, kept between moves, by Zobrist hash
+    grundy: ZobristHashMap<Vec<(G::S, u64)>>,
+}
+
+impl<G, S> DecomposedSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+{
+    pub fn new(inner: TreeSearch<G, S>) -> Self {
+        Self {
+            inner,
+            combine: Combine::default(),
+            grundy: ZobristHashMap::default(),
+        }
+    }
+
+    pub fn combine(mut self, combine: Combine) -> Self {
+        self.combine = combine;
+        self
+    }
+
+    /// The best move in each component, with its expected score for the
+    /// player to move.
+    fn search_components(&mut self, components: &[G::S]) -> Vec<(G::A, f64)> {
+        components
+            .iter()
+            .map(|component| {
+                let action = self.inner.search(component).action;
+                let player = G::player_to_move(component).to_index();
+                let score = self
+                    .inner
+                    .index
+                    .get(self.inner.root_id)
+                    .edges()
+                    .iter()
+                    .find(|edge| edge.action == action)
+                    .map_or(0., |edge| edge.stats.expected_score(player));
+                (action, score)
+            })
+            .collect()
+    }
+
+    /// The Grundy value of a state, treating it as an impartial game.
+    pub fn grundy_value(&mut self, state: &G::S) -> u64 {
+        if G::is_terminal(state) {
+            return 0;
+        }
+        let hash = G::zobrist_hash(state);
+        if let Some((_, value)) = self
+            .grundy
+            .get(hash)
+            .and_then(|bucket| bucket.iter().find(|(s, _)| s == state))
+        {
+            return *value;
+        }
+
+        let components = G::decompose(state);
+        let value = if components.is_empty() {
+            let mut actions = Vec::new();
+            G::generate_actions(state, &mut actions);
+            let mut options = actions
+                .iter()
+                .map(|action| self.grundy_value(&G::apply(state.clone(), action)))
+                .collect::<Vec<_>>();
+            options.sort_unstable();
+            options.dedup();
+            // The minimum excludant
+            options
+                .iter()
+                .enumerate()
+                .find(|(i, value)| *i as u64 != **value)
+                .map_or(options.len() as u64, |(i, _)| i as u64)
+        } else {
+            components
+                .iter()
+                .fold(0, |acc, component| acc ^ self.grundy_value(component))
+        };
+
+        self.grundy
+            .entry(hash)
+            .or_default()
+            .push((state.clone(), value));
+        value
+    }
+
+    /// A move in one of the components which leaves a Grundy sum of zero.
+    fn winning_move(&mut self, state: &G::S, components: &[G::S]) -> Option<G::A> {
+        let values = components
+            .iter()
+            .map(|component| self.grundy_value(component))
+            .collect::<Vec<_>>();
+        let total = values.iter().fold(0, |acc, value| acc ^ value);
+        if total == 0 {
+            return None;
+        }
+
+        for (i, component) in components.iter().enumerate() {
+            let target = values[i] ^ total;
+            if target >= values[i] {
+                continue;
+            }
+            let mut actions = Vec::new();
+            G::generate_actions(component, &mut actions);
+            for action in actions {
+                if self.grundy_value(&G::apply(component.clone(), &action)) == target {
+                    return Some(G::lift_action(state, i, &action));
+                }
+            }
+        }
+        unreachable!("a non-zero Grundy sum always has a winning move")
+    }
+}
+
+impl<G, S> Search for DecomposedSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+{
+    type G = G;
+
+    fn friendly_name(&self) -> String {
+        format!("decompose[{}]", self.inner.friendly_name())
+    }
+
+    fn choose_action(&mut self, state: &G::S) -> G::A {
+        let components = G::decompose(state);
+        if components.len() < 2 {
+            return self.inner.choose_action(state);
+        }
+
+        match self.combine {
+            Combine::Utility => {
+                let (index, (action, _)) = self
+                    .search_components(&components)
+                    .into_iter()
+                    .enumerate()
+                    .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+                    .unwrap();
+                G::lift_action(state, index, &action)
+            }
+            Combine::Grundy => self
+                .winning_move(state, &components)
+                .unwrap_or_else(|| self.inner.choose_action(state)),
+        }
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init);
+        if self.combine == Combine::Grundy {
+            for component in G::decompose(init) {
+                self.grundy_value(&component);
+            }
+        }
+    }
+
+    fn set_friendly_name(&mut self, name: &str) {
+        self.inner.set_friendly_name(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::nim::{Nim, NimState};
+    use crate::strategies::mcts::strategy;
+    use nimlib::{NimGame, Stack};
+
+    #[test]
+    fn test_decomposed_search() {
+        type TS = TreeSearch<Nim, strategy::Ucb1>;
+        let make = |combine| {
+            DecomposedSearch::new(
+                TS::default().config(SearchConfig::default().max_iterations(200).seed(0)),
+            )
+            .combine(combine)
+        };
+
+        // The winning move leaves a position which nimlib values at zero
+        let init = NimState::new();
+        let mut state = NimState {
+            game: NimGame::new(
+                init.rules.clone(),
+                vec![Stack(2), Stack(0), Stack(3), Stack(6)],
+            ),
+            ..init
+        };
+        assert_eq!(Nim::decompose(&state).len(), 3);
+        let mut search = make(Combine::Grundy);
+        search.prepare(&state);
+        for component in Nim::decompose(&state) {
+            let bucket = search.grundy.get(Nim::zobrist_hash(&component)).unwrap();
+            assert!(bucket.iter().any(|(s, _)| *s == component));
+        }
+        assert_eq!(search.grundy_value(&state), state.game.calculate_nimber().0);
+        assert_ne!(state.game.calculate_nimber().0, 0);
+        let action = search.choose_action(&state);
+        state = Nim::apply(state, &action);
+        assert_eq!(state.game.calculate_nimber().0, 0);
+
+        // The utility combination always finds a legal move
+        let mut search = make(Combine::Utility);
+        let mut state = NimState::new();
+        while !Nim::is_terminal(&state) {
+            let action = search.choose_action(&state);
+            let mut actions = Vec::new();
+            Nim::generate_actions(&state, &mut actions);
+            assert!(actions.contains(&action));
+            state = Nim::apply(state, &action);
+        }
+    }
+}
@@ -8,13 +8,14 @@ use crate::util::random_best;
 
 use std::marker::PhantomData;
 
+/// Flat Monte Carlo with the same number of playouts for every action. See
+/// `bandit::FlatBandit` to spend the playouts unevenly, under a bandit policy.
 pub struct FlatMonteCarloStrategy<G: Game> {
     pub samples_per_move: u32, // TODO: also suppose samples per state
     pub max_rollout_depth: u32,
     pub max_rollouts: u32,
     pub verbose: bool,
     pub game_type: PhantomData<G>,
-    pub ucb1: Option<f64>,
     pub name: String,
 }
 
@@ -26,7 +27,6 @@ impl<G: Game> FlatMonteCarloStrategy<G> {
             max_rollouts: u32::MAX,
             verbose: false,
             game_type: PhantomData,
-            ucb1: None,
             name: "flat_mc".into(),
         }
     }
@@ -121,18 +121,8 @@ impl<G: Game + Sync + Send> Search for FlatMonteCarloStrategy<G> {
             }
         }
 
-        let ucb1 = |w: f64, n: f64, c: f64| w / n + c * (n.ln() / n);
-
-        if let Some(c) = self.ucb1 {
-            random_best(wins.as_slice(), &mut rng, |x| {
-                ucb1(x.0 as f64, self.samples_per_move as f64, c)
-            })
+        random_best(wins.as_slice(), &mut rng, |x| x.0 as f64)
             .map(|x| x.1.clone())
             .unwrap()
-        } else {
-            random_best(wins.as_slice(), &mut rng, |x| x.0 as f64)
-                .map(|x| x.1.clone())
-                .unwrap()
-        }
     }
 }
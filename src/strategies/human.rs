@@ -2,10 +2,14 @@ use std::io;
 use std::io::Write;
 use std::marker::PhantomData;
 
+use crate::display::{DiffStyle, StateDiff};
 use crate::{game::Game, strategies::Search};
 
 pub struct HumanAgent<G: Game> {
     name: String,
+    // The state shown last time, to mark what changed since
+    previous: Option<G::S>,
+    style: DiffStyle,
     marker: PhantomData<G>,
 }
 
@@ -19,9 +23,16 @@ impl<G: Game> HumanAgent<G> {
     pub fn new() -> Self {
         Self {
             name: "human".into(),
+            previous: None,
+            style: DiffStyle::from_env(),
             marker: PhantomData,
         }
     }
+
+    pub fn style(mut self, style: DiffStyle) -> Self {
+        self.style = style;
+        self
+    }
 }
 
 impl<G: Game> Search for HumanAgent<G>
@@ -31,7 +42,10 @@ where
     type G = G;
 
     fn choose_action(&mut self, state: &<Self::G as Game>::S) -> <Self::G as Game>::A {
-        print!("State is:\n{}", state);
+        print!(
+            "State is:\n{}",
+            StateDiff::new(self.previous.as_ref(), state, self.style)
+        );
         let mut input = String::new();
         loop {
             input.clear();
@@ -40,7 +54,10 @@ where
             match io::stdin().read_line(&mut input) {
                 Ok(_) => match G::parse_action(state, input.as_str()) {
                     None => eprintln!("Error parsing input: >{}<", input.trim()),
-                    Some(action) => return action,
+                    Some(action) => {
+                        self.previous = Some(G::apply(state.clone(), &action));
+                        return action;
+                    }
                 },
                 Err(error) => {
                     eprintln!("Error reading input: {}", error);
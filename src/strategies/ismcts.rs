@@ -0,0 +1,329 @@
+//! Multiple-observer information set MCTS (MO-ISMCTS; Cowling, Powley and
+//! Whitehouse, 2012) for games with hidden information.
+//!
+//! Each player has their own tree, whose nodes stand for that player's
+//! information sets: a move the player cannot see (see `Game::observe_action`)
+//! leads to a single node whatever the move was. Every iteration starts from a
+//! determinization of the root as the searching player sees it (see
+//! `Game::determinize_for`), and descends all of the trees together, the
+//! player to move choosing in their own tree. So no player's choices depend on
+//! information they do not have, such as an opponent's hidden bid.
+//!
+//! Since a node's children vary with the determinization, selection uses the
+//! subset-armed UCB of ISMCTS: a child's exploration term counts the
+//! iterations in which it was available rather than the parent's visits.
+
+use super::Search;
+use crate::game::{Game, PlayerId, PlayerIndex};
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use std::marker::PhantomData;
+
+/// A node of one player's tree.
+#[derive(Clone, Debug)]
+pub struct Node<A> {
+    // Children keyed by the incoming action as the tree's owner sees it
+    pub children: Vec<(Option<A>, usize)>,
+    pub visits: u32,
+    // Iterations in which the incoming action was legal
+    pub availability: u32,
+    // The total utility of the player who made the incoming move
+    pub score: f64,
+}
+
+impl<A> Default for Node<A> {
+    fn default() -> Self {
+        Self {
+            children: vec![],
+            visits: 0,
+            availability: 0,
+            score: 0.,
+        }
+    }
+}
+
+/// One player's tree. The root is at index 0.
+#[derive(Clone, Debug)]
+pub struct Tree<A> {
+    pub nodes: Vec<Node<A>>,
+}
+
+impl<A: PartialEq> Tree<A> {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    pub fn child(&self, node: usize, key: &Option<A>) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, child)| *child)
+    }
+
+    fn child_or_insert(&mut self, node: usize, key: Option<A>) -> usize {
+        if let Some(child) = self.child(node, &key) {
+            return child;
+        }
+        let child = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.nodes[node].children.push((key, child));
+        child
+    }
+}
+
+pub struct MoIsmcts<G: Game> {
+    pub max_iterations: usize,
+    pub exploration_constant: f64,
+    pub max_playout_depth: usize,
+    pub name: String,
+    // The trees of the last search, indexed by player, and the player who
+    // searched
+    pub trees: Vec<Tree<G::A>>,
+    pub searcher: PlayerId,
+    rng: SmallRng,
+    game_type: PhantomData<G>,
+}
+
+impl<G: Game> Default for MoIsmcts<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: Game> MoIsmcts<G> {
+    pub fn new() -> Self {
+        Self {
+            max_iterations: 10_000,
+            exploration_constant: 0.7,
+            max_playout_depth: usize::MAX,
+            name: "mo-ismcts".into(),
+            trees: vec![],
+            searcher: PlayerId::default(),
+            rng: SmallRng::from_entropy(),
+            game_type: PhantomData,
+        }
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn exploration_constant(mut self, exploration_constant: f64) -> Self {
+        self.exploration_constant = exploration_constant;
+        self
+    }
+
+    pub fn max_playout_depth(mut self, max_playout_depth: usize) -> Self {
+        self.max_playout_depth = max_playout_depth;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Run the search from `state`, leaving a tree for each player in
+    /// `trees`.
+    pub fn search(&mut self, state: &G::S) {
+        self.searcher = G::player_id(state);
+        self.trees = (0..G::num_players()).map(|_| Tree::new()).collect();
+        for _ in 0..self.max_iterations {
+            let determinized = G::determinize_for(state.clone(), self.searcher, &mut self.rng);
+            self.iterate(determinized);
+        }
+    }
+
+    fn iterate(&mut self, mut state: G::S) {
+        let num_players = G::num_players();
+        // The path through each tree, and the player who moved into each node
+        let mut paths = vec![vec![0]; num_players];
+        let mut movers = vec![];
+        let mut actions = vec![];
+
+        // Select and expand in the mover's tree, following along in the others
+        while !G::is_terminal(&state) {
+            let mover = G::player_id(&state).index();
+            actions.clear();
+            G::generate_actions(&state, &mut actions);
+            let node = *paths[mover].last().unwrap();
+            let keys = actions
+                .iter()
+                .map(|action| G::observe_action(&state, action, PlayerId(mover)))
+                .collect::<Vec<_>>();
+            let untried = (0..actions.len())
+                .filter(|i| self.trees[mover].child(node, &keys[*i]).is_none())
+                .collect::<Vec<_>>();
+            let expand = !untried.is_empty();
+            let choice = if expand {
+                untried[self.rng.gen_range(0..untried.len())]
+            } else {
+                self.select(mover, node, &keys)
+            };
+
+            let tree = &mut self.trees[mover];
+            for key in keys.iter() {
+                if let Some(child) = tree.child(node, key) {
+                    tree.nodes[child].availability += 1;
+                }
+            }
+            let action = &actions[choice];
+            for (player, path) in paths.iter_mut().enumerate() {
+                let key = G::observe_action(&state, action, PlayerId(player));
+                let node = *path.last().unwrap();
+                let tree = &mut self.trees[player];
+                let child = tree.child_or_insert(node, key);
+                if expand && player == mover {
+                    tree.nodes[child].availability += 1;
+                }
+                path.push(child);
+            }
+            movers.push(mover);
+            state = G::apply(state, action);
+            if expand {
+                break;
+            }
+        }
+
+        // Play out uniformly at random
+        let mut depth = 0;
+        while !G::is_terminal(&state) && depth < self.max_playout_depth {
+            actions.clear();
+            G::generate_actions(&state, &mut actions);
+            let action = &actions[self.rng.gen_range(0..actions.len())];
+            state = G::apply(state, action);
+            depth += 1;
+        }
+        let utilities = if G::is_terminal(&state) {
+            G::compute_utilities(&state)
+        } else {
+            G::utilities_nonterminal(&state).unwrap_or_else(|| G::compute_utilities(&state))
+        };
+
+        // Back up in every tree
+        for (player, path) in paths.iter().enumerate() {
+            let tree = &mut self.trees[player];
+            tree.nodes[0].visits += 1;
+            for (node, mover) in path[1..].iter().zip(&movers) {
+                tree.nodes[*node].visits += 1;
+                tree.nodes[*node].score += utilities[*mover];
+            }
+        }
+    }
+
+    // The child with the best subset-armed UCB score among those available.
+    // Every key has a child, since there was nothing to expand.
+    fn select(&mut self, mover: usize, node: usize, keys: &[Option<G::A>]) -> usize {
+        let tree = &self.trees[mover];
+        let score = |i: usize| {
+            let child = &tree.nodes[tree.child(node, &keys[i]).unwrap()];
+            let visits = child.visits.max(1) as f64;
+            let exploit = child.score / visits;
+            let explore = ((child.availability.max(1) as f64).ln() / visits).sqrt();
+            exploit + self.exploration_constant * explore
+        };
+        let mut best = vec![];
+        let mut best_score = f64::NEG_INFINITY;
+        for i in 0..keys.len() {
+            let s = score(i);
+            if s > best_score {
+                best_score = s;
+                best.clear();
+            }
+            if s == best_score {
+                best.push(i);
+            }
+        }
+        best[self.rng.gen_range(0..best.len())]
+    }
+
+    /// The root children of `player`'s tree, with their visits.
+    pub fn root_children(&self, player: impl PlayerIndex) -> Vec<(Option<G::A>, u32)> {
+        let tree = &self.trees[player.to_index()];
+        tree.nodes[0]
+            .children
+            .iter()
+            .map(|(key, child)| (key.clone(), tree.nodes[*child].visits))
+            .collect()
+    }
+}
+
+impl<G: Game + Sync + Send> Search for MoIsmcts<G> {
+    type G = G;
+
+    fn friendly_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn set_friendly_name(&mut self, name: &str) {
+        self.name = name.into();
+    }
+
+    fn choose_action(&mut self, state: &G::S) -> G::A {
+        self.search(state);
+        // The player to move sees their own moves, so every root key is some
+        // action
+        self.root_children(self.searcher)
+            .into_iter()
+            .max_by_key(|(_, visits)| *visits)
+            .and_then(|(key, _)| key)
+            .expect("no legal actions")
+    }
+
+    fn root_visits(&self) -> Vec<(G::A, u32)> {
+        if self.trees.is_empty() {
+            return vec![];
+        }
+        self.root_children(self.searcher)
+            .into_iter()
+            .filter_map(|(key, visits)| key.map(|action| (action, visits)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::bid_ttt::{BiddingTicTacToe, Move, Piece};
+    use crate::games::ttt::{self, TicTacToe};
+
+    #[test]
+    fn test_mo_ismcts() {
+        // With perfect information this is ISMCTS on a single determinization
+        // X to move and win at 2, or lose to O at 5
+        let mut state = ttt::HashedPosition::new();
+        for i in [0, 4, 1, 3] {
+            state = TicTacToe::apply(state, &ttt::Move(i));
+        }
+        let mut search = MoIsmcts::<TicTacToe>::new().max_iterations(2000).seed(0);
+        assert_eq!(search.choose_action(&state), ttt::Move(2));
+
+        // X's bids are all one move in O's tree
+        let state = BiddingTicTacToe::with_chips(3);
+        let mut search = MoIsmcts::<BiddingTicTacToe>::new()
+            .max_iterations(500)
+            .seed(0);
+        search.search(&state);
+        assert_eq!(search.root_children(Piece::X).len(), 4);
+        let o_root = search.root_children(Piece::O);
+        assert_eq!(o_root.len(), 1);
+        assert_eq!(o_root[0], (None, 500));
+
+        // O's search does not depend on X's actual bid
+        let visits = |bid| {
+            let state = <BiddingTicTacToe as Game>::apply(state, &Move::Bid(bid));
+            let mut search = MoIsmcts::<BiddingTicTacToe>::new()
+                .max_iterations(500)
+                .seed(0);
+            search.choose_action(&state);
+            search.root_visits()
+        };
+        assert_eq!(visits(0), visits(3));
+    }
+}
@@ -0,0 +1,278 @@
+//! Analysis mode: search a position without committing to a move.
+//!
+//! `TreeSearch::analyze` runs the usual iterations but skips the play oriented
+//! parts of `search` (easy moves, disagreement resolution, adjudication and
+//! final action selection). It reports every root action with its principal
+//! variation, and how the root value evolved over the search.
+//!
+//! ```ignore
+//! let report = ts.analyze(&state, Budget::Iterations(100_000));
+//! println!("{report}");
+//! ```
+
+use super::index::Id;
+use super::node::{NodeState, Proof};
+use super::{SearchConfig, Strategy, TreeIndex, TreeMetrics, TreeSearch};
+use crate::display::DiffStyle;
+use crate::game::{Game, PlayerIndex};
+use crate::util::{pv_string, replay_string};
+
+use std::fmt::Display;
+use std::time::Duration;
+
+/// How long to analyze for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+/// One root action, as seen by the search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveAnalysis<A> {
+    pub action: A,
+    pub visits: u32,
+    // Fraction of the root's visits
+    pub share: f64,
+    // Expected score for the player to move at the root, and its standard
+    // error
+    pub value: f64,
+    pub standard_error: f64,
+    pub proof: Option<Proof>,
+    // The most visited line starting with this action
+    pub pv: Vec<A>,
+}
+
+/// The root value and best action at some point during the search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueSample<A> {
+    pub iterations: usize,
+    pub elapsed: Duration,
+    // Expected score for the player to move at the root
+    pub value: f64,
+    pub best: Option<A>,
+}
+
+/// The outcome of `TreeSearch::analyze`.
+#[derive(Clone, Debug)]
+pub struct AnalysisReport<G: Game> {
+    pub state: G::S,
+    // Root actions, most visited first
+    pub moves: Vec<MoveAnalysis<G::A>>,
+    // Samples taken after 1, 2, 4, 8, ... iterations and at the end
+    pub value_trend: Vec<ValueSample<G::A>>,
+    pub value: f64,
+    pub proof: Option<Proof>,
+    pub iterations: usize,
+    pub elapsed: Duration,
+    pub metrics: TreeMetrics,
+}
+
+impl<G: Game> AnalysisReport<G> {
+    /// The principal variation of the most visited action.
+    pub fn pv(&self) -> &[G::A] {
+        self.moves.first().map_or(&[], |m| &m.pv)
+    }
+
+    /// The positions along the principal variation, marking what each move
+    /// changed.
+    pub fn replay_pv(&self, style: DiffStyle) -> String {
+        replay_string::<G>(&self.state, self.pv(), style)
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Search `state` for the given budget, which replaces the configured
+    /// iteration and time limits, and report on the tree without choosing an
+    /// action. Work budgets such as `max_expansions` still apply.
+    pub fn analyze(&mut self, state: &G::S, budget: Budget) -> AnalysisReport<G> {
+        let (max_iterations, max_time) = match budget {
+            Budget::Iterations(n) => (n, Duration::default()),
+            Budget::Time(time) => (usize::MAX, time),
+        };
+        // The budget stands in for the configured limits until the end
+        let limits = (self.config.max_iterations, self.config.max_time);
+        self.config.max_iterations = max_iterations;
+        self.config.max_time = max_time;
+        let root_id = self.begin(state);
+        self.timer.start(max_time);
+
+        let player = G::player_to_move(state).to_index();
+        let mut value_trend = vec![];
+        let mut next_sample = 1;
+        let mut iterations = 0;
+        while iterations < max_iterations {
+            if self.timer.done() || self.work_budget_exhausted() || self.stop_requested() {
+                break;
+            }
+            self.iterate(root_id, state);
+            iterations += 1;
+            self.report_progress(state, iterations, false);
+            if iterations == next_sample {
+                value_trend.push(self.value_sample(player, iterations));
+                next_sample *= 2;
+            }
+        }
+        if value_trend.last().map(|s| s.iterations) != Some(iterations) {
+            value_trend.push(self.value_sample(player, iterations));
+        }
+        (self.config.max_iterations, self.config.max_time) = limits;
+        self.stop.reset();
+        self.report_progress(state, iterations, true);
+
+        AnalysisReport {
+            state: state.clone(),
+            moves: self.analyze_moves(player),
+            value_trend,
+            value: self.root_stats.expected_score(player),
+            proof: self.root_proof(),
+            iterations,
+            elapsed: self.timer.elapsed(),
+            metrics: self.tree_metrics(),
+        }
+    }
+
+    fn value_sample(&self, player: usize, iterations: usize) -> ValueSample<G::A> {
+        let root = self.index.get(self.root_id);
+        ValueSample {
+            iterations,
+            elapsed: self.timer.elapsed(),
+            value: self.root_stats.expected_score(player),
+            best: root
+                .is_expanded()
+                .then(|| root.children_by_visits()[0].action.clone()),
+        }
+    }
+
+    pub(crate) fn analyze_moves(&self, player: usize) -> Vec<MoveAnalysis<G::A>> {
+        let root = self.index.get(self.root_id);
+        if !root.is_expanded() {
+            return vec![];
+        }
+        let total = root.edges().iter().map(|e| e.stats.num_visits).sum::<u32>();
+        root.children_by_visits()
+            .into_iter()
+            .map(|edge| {
+                let mut pv = vec![edge.action.clone()];
+                if let Some(child_id) = edge.node_id {
+                    most_visited_line(&self.index, child_id, &mut pv);
+                }
+                MoveAnalysis {
+                    action: edge.action.clone(),
+                    visits: edge.stats.num_visits,
+                    share: edge.stats.num_visits as f64 / total.max(1) as f64,
+                    value: edge.stats.expected_score(player),
+                    standard_error: edge.stats.standard_error(player),
+                    proof: edge.proof(player),
+                    pv,
+                }
+            })
+            .collect()
+    }
+}
+
+// Follow the most visited explored children from `node_id`. The depth is
+// bounded by the number of nodes, since transpositions may form cycles.
+fn most_visited_line<A: crate::game::Action>(
+    index: &TreeIndex<A>,
+    mut node_id: Id,
+    line: &mut Vec<A>,
+) {
+    for _ in 0..index.len() {
+        let NodeState::Expanded(edges) = &index.get(node_id).state else {
+            return;
+        };
+        let Some(edge) = edges
+            .iter()
+            .filter(|edge| edge.is_explored())
+            .max_by_key(|edge| edge.stats.num_visits)
+        else {
+            return;
+        };
+        line.push(edge.action.clone());
+        node_id = edge.node_id.unwrap();
+    }
+}
+
+impl<G: Game> Display for AnalysisReport<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let player = G::player_to_move(&self.state).to_index();
+        let marker =
+            |proof: Option<Proof>| proof.map_or(String::new(), |p| format!(" {}", p.marker()));
+        writeln!(
+            f,
+            "{} iterations in {:.2?}, {} nodes, value {:.3}{}",
+            self.iterations,
+            self.elapsed,
+            self.metrics.num_nodes,
+            self.value,
+            marker(self.proof)
+        )?;
+        for m in &self.moves {
+            writeln!(
+                f,
+                "{:>7} {:>6.1}% {:>7.3} ±{:.3}{} {}",
+                m.visits,
+                100. * m.share,
+                m.value,
+                m.standard_error,
+                marker(m.proof),
+                pv_string::<G>(&m.pv, &self.state)
+            )?;
+        }
+        write!(f, "value trend (player {player}):")?;
+        for sample in &self.value_trend {
+            write!(f, " {}:{:.3}", sample.iterations, sample.value)?;
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, TicTacToe};
+    use crate::strategies::mcts::strategy;
+
+    #[test]
+    fn test_analyze() {
+        let state = HashedPosition::new();
+        let mut ts =
+            TreeSearch::<TicTacToe, strategy::Ucb1>::default().config(SearchConfig::new().seed(3));
+
+        let report = ts.analyze(&state, Budget::Iterations(1000));
+        assert_eq!(report.iterations, 1000);
+        assert_eq!(report.moves.len(), 9);
+        // The first iteration only visits the root
+        assert_eq!(report.moves.iter().map(|m| m.visits).sum::<u32>(), 999);
+        assert!(report.moves.windows(2).all(|w| w[0].visits >= w[1].visits));
+        assert!(report.moves.iter().all(|m| m.pv[0] == m.action));
+        assert!(report.pv().len() > 1);
+        assert!(report.moves[0].standard_error < 0.1);
+
+        let samples = report
+            .value_trend
+            .iter()
+            .map(|s| s.iterations)
+            .collect::<Vec<_>>();
+        assert_eq!(samples, [1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1000]);
+        assert_eq!(report.value_trend.last().unwrap().value, report.value);
+        assert!(report.to_string().contains("value trend"));
+        let replay = report.replay_pv(DiffStyle::default());
+        assert_eq!(
+            replay.lines().filter(|l| l.ends_with(':')).count(),
+            report.pv().len() + 1
+        );
+        assert!(replay.contains("[X]"));
+
+        let report = ts.analyze(&state, Budget::Time(Duration::from_millis(20)));
+        assert!(report.iterations > 0);
+        assert!(report.elapsed >= Duration::from_millis(20));
+    }
+}
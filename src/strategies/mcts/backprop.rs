@@ -1,18 +1,45 @@
-use super::node::NodeStats;
+use super::history::{ngram_context, MAX_NGRAM};
+use super::node::{NodeStats, Proof};
 use super::stack::NodeStack;
 use super::*;
 use crate::game::Game;
 
 use rustc_hash::FxHashMap;
 
-pub trait BackpropStrategy: Clone + Sync + Send + Default {
-    fn update_amaf<G: Game>(
+pub trait BackpropStrategy<G: Game>: Clone + Sync + Send + Default {
+    /// A factor applied to the trial's utilities before they are backed up,
+    /// allowing trials to be weighted by how they ended.
+    #[allow(unused_variables)]
+    fn trial_weight(&self, status: &simulate::Status) -> f64 {
+        1.
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    /// The utilities to back up into the edge leading to `node_id` (or into
+    /// the root's statistics), given the trial's. `None` backs up the trial's
+    /// utilities unchanged. AMAF and history statistics always take the
+    /// trial's.
+    #[allow(unused_variables)]
+    fn backup_value(
+        &self,
+        index: &TreeIndex<G::A>,
+        node_id: index::Id,
+        utilities: &[f64],
+    ) -> Option<Vec<f64>> {
+        None
+    }
+
+    fn update_amaf(
         &self,
         stack: &NodeStack<G::A>,
         trace: &[(G::A, usize)],
         index: &mut TreeIndex<G::A>,
         node_id: index::Id,
         utilities: &[f64],
+        mover: bool,
     ) {
         // NOTE: O(n) here, but amaf could be calculated top down
         let node = index.get(node_id);
@@ -28,10 +55,16 @@ pub trait BackpropStrategy: Clone + Sync + Send + Default {
                 .filter_map(|edge| edge.node_id.map(|node_id| (edge.action.clone(), node_id)))
                 .collect();
 
+            let parent_player = index.get(parent_id).player_idx;
             for (action, p) in trace {
                 if let Some(child_id) = sibling_actions.get(action) {
                     let child = index.get_mut(*child_id);
-                    if child.player_idx == *p {
+                    let matches = if mover {
+                        parent_player == *p
+                    } else {
+                        child.player_idx == *p
+                    };
+                    if matches {
                         (0..G::num_players()).for_each(|i| {
                             let parent = index.get_mut(parent_id);
                             // NOTE: O(n) lookup
@@ -45,32 +78,32 @@ pub trait BackpropStrategy: Clone + Sync + Send + Default {
         }
     }
 
-    fn update_grave<G: Game>(
+    fn update_grave(
         &self,
         trace: &[(G::A, usize)],
         index: &mut TreeIndex<G::A>,
         global: &mut TreeStats<G>,
         node_id: index::Id,
         utilities: &[f64],
+        mover: bool,
     ) {
         let node = index.get_mut(node_id);
         if !node.is_root() {
             for (action, p) in trace {
-                let players = global
+                if mover && *p != node.player_idx {
+                    continue;
+                }
+                global
+                    .history
                     .grave
-                    .entry(node.hash)
-                    .or_insert_with(|| vec![Default::default(); G::num_players()]);
-                let player = players.get_mut(*p).unwrap();
-                let grave_stats = player.entry(action.clone()).or_default();
-                grave_stats.num_visits += 1;
-                grave_stats.score += utilities[*p];
+                    .update(node.hash, *p, action, utilities[*p]);
             }
         }
     }
 
     // TODO: cleanup the arguments to this, or just move it to TreeSearch
     #[allow(clippy::too_many_arguments)]
-    fn update<G>(
+    fn update(
         &self,
         stack: &NodeStack<G::A>,
         global: &mut TreeStats<G>,
@@ -79,63 +112,131 @@ pub trait BackpropStrategy: Clone + Sync + Send + Default {
         trial: simulate::Trial<G>,
         player: usize,
         flags: BackpropFlags,
-    ) where
-        G: Game,
-    {
-        // init_amaf: GRAVE | GLOBAL
-        let mut amaf_actions = if flags.grave() || flags.global() {
+    ) {
+        // init_amaf: AMAF | GRAVE, when playout actions are included
+        let mut amaf_actions = if (flags.amaf() || flags.grave()) && flags.playout() {
             trial.actions.clone()
         } else {
             vec![]
         };
 
-        let utilities = G::compute_utilities(&trial.state);
-        for (parent_id_opt, node_id) in stack.reverse_pairs2() {
+        // Tree path actions, collected bottom up, for GLOBAL, NGRAM and REPLY
+        let mut tree_actions = vec![];
+        let sequences = flags.ngram() || flags.reply();
+
+        let mut utilities = trial.utilities;
+        let weight = self.trial_weight(&trial.status);
+        if weight != 1. {
+            utilities.iter_mut().for_each(|utility| *utility *= weight);
+        }
+        for (parent_id_opt, node_id) in stack.with_parent() {
             debug_assert!(
                 (parent_id_opt.is_some() && !index.get(*node_id).is_root())
                     || (parent_id_opt.is_none() && index.get(*node_id).is_root())
             );
+            let backed_up = self.backup_value(index, *node_id, &utilities);
+            let values = backed_up.as_deref().unwrap_or(&utilities);
             if index.get(*node_id).is_root() {
-                root_stats.update(&utilities);
+                root_stats.update(values);
             } else {
                 let parent_id = parent_id_opt.cloned().unwrap();
                 debug_assert_ne!(parent_id, *node_id);
                 let parent = index.get_mut(parent_id);
-                parent.child_edge_mut(*node_id).stats.update(&utilities);
+                parent.child_edge_mut(*node_id).stats.update(values);
             }
 
             // update: AMAF
             if flags.amaf() {
-                self.update_amaf::<G>(stack, &trial.actions, index, *node_id, &utilities);
+                self.update_amaf(
+                    stack,
+                    &amaf_actions,
+                    index,
+                    *node_id,
+                    &utilities,
+                    flags.mover(),
+                );
             } else if flags.grave() {
-                self.update_grave::<G>(&amaf_actions, index, global, *node_id, &utilities);
+                self.update_grave(
+                    &amaf_actions,
+                    index,
+                    global,
+                    *node_id,
+                    &utilities,
+                    flags.mover(),
+                );
             }
 
-            // push_action: GRAVE | GLOBAL
-            if flags.grave() || flags.global() {
+            // push_action: AMAF | GRAVE (tree actions), GLOBAL, NGRAM, REPLY
+            if flags.amaf() || flags.grave() || flags.global() || sequences {
                 let node = index.get(*node_id);
                 if !node.is_root() {
                     let parent_id = parent_id_opt.cloned().unwrap();
                     let action = stack.edge(index, parent_id, *node_id).action.clone();
-                    amaf_actions.push((action, node.player_idx));
+                    // The action was made by the player to move at the parent
+                    let mover = index.get(parent_id).player_idx;
+                    if (flags.amaf() || flags.grave()) && flags.tree() {
+                        // Counted for the player to move after the action,
+                        // unless MOVER restricts updates to the player who
+                        // made it
+                        let player = if flags.mover() {
+                            mover
+                        } else {
+                            node.player_idx
+                        };
+                        amaf_actions.push((action.clone(), player));
+                    }
+                    if flags.global() || sequences {
+                        tree_actions.push((action, mover));
+                    }
                 };
             }
         }
 
         // update: GLOBAL
         if flags.global() {
-            for (action, _) in &amaf_actions {
+            for (action, _) in trial.actions.iter().chain(tree_actions.iter()) {
                 // let player = G::player_to_move(&ctx.state).to_index();
-                let action_stats = global.actions.entry(action.clone()).or_default();
-                action_stats.num_visits += 1;
-                action_stats.score += utilities[player];
-
-                let player_action_stats = global.player_actions[player]
-                    .entry(action.clone())
-                    .or_default();
-                player_action_stats.num_visits += 1;
-                for u in utilities.iter().take(G::num_players()) {
-                    player_action_stats.score += u;
+                let history = &mut global.history;
+                history.actions.update(action, utilities[player]);
+                history.player_actions[player]
+                    .update(action, utilities.iter().take(G::num_players()).sum());
+            }
+        }
+
+        // update: NGRAM | REPLY, over the whole trial from the root
+        if sequences {
+            let sequence = tree_actions
+                .iter()
+                .rev()
+                .chain(trial.actions.iter())
+                .collect::<Vec<_>>();
+            let actions = sequence
+                .iter()
+                .map(|(action, _)| action.clone())
+                .collect::<Vec<_>>();
+            let history = &mut global.history;
+            for (i, (action, mover)) in sequence.iter().enumerate() {
+                if flags.ngram() {
+                    for n in 1..=MAX_NGRAM.min(i + 1) {
+                        let context = ngram_context(&actions[i + 1 - n..i]);
+                        history
+                            .ngrams
+                            .update(context, *mover, action, utilities[*mover]);
+                    }
+                }
+                if flags.reply() {
+                    let outcome = Proof::of(&utilities, *mover);
+                    // Replies to the previous one and two actions
+                    for n in 2..=3.min(i + 1) {
+                        let context = ngram_context(&actions[i + 1 - n..i]);
+                        match outcome {
+                            Proof::Win => history.replies.insert(context, *mover, action),
+                            Proof::Loss if flags.forget_reply() => {
+                                history.replies.forget(context, *mover, action)
+                            }
+                            _ => {}
+                        }
+                    }
                 }
             }
         }
@@ -145,4 +246,484 @@ pub trait BackpropStrategy: Clone + Sync + Send + Default {
 #[derive(Default, Clone)]
 pub struct Classic;
 
-impl BackpropStrategy for Classic {}
+impl<G: Game> BackpropStrategy<G> for Classic {}
+
+/// Back up the trial's utilities along the path, as `Classic` does, but
+/// scale the utilities of playouts stopped by `max_playout_depth` by
+/// `turn_limit_weight`, shrinking the less reliable estimates of unfinished
+/// games towards a draw.
+#[derive(Clone)]
+pub struct Weighted {
+    pub turn_limit_weight: f64,
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        Self {
+            turn_limit_weight: 1.,
+        }
+    }
+}
+
+impl Weighted {
+    pub fn turn_limit_weight(mut self, turn_limit_weight: f64) -> Self {
+        self.turn_limit_weight = turn_limit_weight;
+        self
+    }
+}
+
+impl<G: Game> BackpropStrategy<G> for Weighted {
+    fn trial_weight(&self, status: &simulate::Status) -> f64 {
+        match status.end_type {
+            Some(simulate::EndType::TurnLimit) => self.turn_limit_weight,
+            _ => 1.,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if (0. ..=1.).contains(&self.turn_limit_weight) {
+            Ok(())
+        } else {
+            Err(ConfigError::TurnLimitWeightOutOfRange(
+                self.turn_limit_weight,
+            ))
+        }
+    }
+}
+
+/// A hybrid of averaging and minimax backups, in the spirit of the
+/// MCTS-minimax hybrids of Baier and Winands and of implicit minimax backups
+/// (Lanctot et al., 2014). Each edge on the path is updated with a mix of the
+/// trial's utilities and the minimax value of the node it leads to,
+///
+/// ```text
+/// (1 - weight) * trial + weight * best child
+/// ```
+///
+/// where the best child is the one the player to move there would pick: a
+/// proven child, or one with at least `min_visits` visits, by mean score for
+/// that player. Since the children's means are themselves mixed, the minimax
+/// values reach down through the well visited part of the tree, and forced
+/// lines are not averaged away as they are with `Classic`. Nodes without such
+/// children back up the trial alone.
+#[derive(Clone)]
+pub struct Minimax {
+    pub weight: f64,
+    pub min_visits: u32,
+    pub inner: Weighted,
+}
+
+impl Default for Minimax {
+    fn default() -> Self {
+        Self {
+            weight: 0.5,
+            min_visits: 5,
+            inner: Weighted::default(),
+        }
+    }
+}
+
+impl Minimax {
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn min_visits(mut self, min_visits: u32) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    pub fn inner(mut self, inner: Weighted) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    /// The utilities of the node's best child for the player to move there,
+    /// if it has a proven or sufficiently visited child.
+    fn minimax_value<A: crate::game::Action>(
+        &self,
+        index: &TreeIndex<A>,
+        node_id: index::Id,
+    ) -> Option<Vec<f64>> {
+        let node = index.get(node_id);
+        let node::NodeState::Expanded(edges) = &node.state else {
+            return None;
+        };
+        let mover = node.player_idx;
+        let value = |edge: &node::Edge<A>| match &edge.proven {
+            Some(proven) => proven[mover],
+            None => edge.stats.expected_score(mover),
+        };
+        let best = edges
+            .iter()
+            .filter(|edge| edge.proven.is_some() || edge.stats.num_visits >= self.min_visits)
+            .max_by(|a, b| value(a).total_cmp(&value(b)))?;
+        Some(match &best.proven {
+            Some(proven) => proven.clone(),
+            None => (0..best.stats.player.len())
+                .map(|i| best.stats.expected_score(i))
+                .collect(),
+        })
+    }
+}
+
+impl<G: Game> BackpropStrategy<G> for Minimax {
+    fn trial_weight(&self, status: &simulate::Status) -> f64 {
+        BackpropStrategy::<G>::trial_weight(&self.inner, status)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(0. ..=1.).contains(&self.weight) {
+            return Err(ConfigError::MinimaxWeightOutOfRange(self.weight));
+        }
+        BackpropStrategy::<G>::validate(&self.inner)
+    }
+
+    fn backup_value(
+        &self,
+        index: &TreeIndex<G::A>,
+        node_id: index::Id,
+        utilities: &[f64],
+    ) -> Option<Vec<f64>> {
+        let minimax = self.minimax_value(index, node_id)?;
+        Some(
+            utilities
+                .iter()
+                .zip(minimax)
+                .map(|(trial, best)| (1. - self.weight) * trial + self.weight * best)
+                .collect(),
+        )
+    }
+}
+
+/// The softmax of `values / temperature`, shifted by the maximum for
+/// stability.
+pub fn softmax(values: &[f64], temperature: f64) -> Vec<f64> {
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let weights = values
+        .iter()
+        .map(|v| ((v - max) / temperature).exp())
+        .collect::<Vec<_>>();
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// `temperature * ln(sum(exp(values / temperature)))`, the soft maximum of
+/// `values`.
+pub fn soft_max(values: &[f64], temperature: f64) -> f64 {
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let total: f64 = values.iter().map(|v| ((v - max) / temperature).exp()).sum();
+    max + temperature * total.ln()
+}
+
+/// Maximum entropy backup for `select::Boltzmann` (MENTS; Xiao et al., 2019).
+/// After the usual update, the soft values (`PlayerStats::soft`) of the edges
+/// on the path are refreshed from the leaf up. An edge into a node with
+/// visited children takes the node's soft value: the soft maximum of its
+/// edges for the player to move there, and the expectation under the
+/// corresponding softmax policy for everyone else. Other edges take their
+/// mean score.
+///
+/// `temperature` should match the selection strategy's.
+#[derive(Clone)]
+pub struct Soft {
+    pub temperature: f64,
+    pub inner: Weighted,
+}
+
+impl Default for Soft {
+    fn default() -> Self {
+        Self {
+            temperature: 0.1,
+            inner: Weighted::default(),
+        }
+    }
+}
+
+impl Soft {
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn inner(mut self, inner: Weighted) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    fn soft_values<A: crate::game::Action>(
+        &self,
+        index: &TreeIndex<A>,
+        node_id: index::Id,
+        fallback: &NodeStats,
+    ) -> Vec<f64> {
+        let node = index.get(node_id);
+        let visited = match &node.state {
+            node::NodeState::Expanded(edges) => edges
+                .iter()
+                .filter(|edge| edge.stats.num_visits > 0)
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        let num_players = fallback.player.len();
+        if visited.is_empty() {
+            return (0..num_players)
+                .map(|i| fallback.expected_score(i))
+                .collect();
+        }
+
+        let mover = node.player_idx;
+        let values = visited
+            .iter()
+            .map(|edge| edge.stats.player[mover].soft)
+            .collect::<Vec<_>>();
+        let policy = softmax(&values, self.temperature);
+        (0..num_players)
+            .map(|i| {
+                if i == mover {
+                    soft_max(&values, self.temperature)
+                } else {
+                    visited
+                        .iter()
+                        .zip(&policy)
+                        .map(|(edge, p)| p * edge.stats.player[i].soft)
+                        .sum()
+                }
+            })
+            .collect()
+    }
+}
+
+impl<G: Game> BackpropStrategy<G> for Soft {
+    fn trial_weight(&self, status: &simulate::Status) -> f64 {
+        BackpropStrategy::<G>::trial_weight(&self.inner, status)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.temperature <= 0. {
+            return Err(ConfigError::NonPositiveTemperature(self.temperature));
+        }
+        BackpropStrategy::<G>::validate(&self.inner)
+    }
+
+    fn update(
+        &self,
+        stack: &NodeStack<G::A>,
+        global: &mut TreeStats<G>,
+        index: &mut TreeIndex<G::A>,
+        root_stats: &mut NodeStats,
+        trial: simulate::Trial<G>,
+        player: usize,
+        flags: BackpropFlags,
+    ) {
+        BackpropStrategy::<G>::update(
+            &self.inner,
+            stack,
+            global,
+            index,
+            root_stats,
+            trial,
+            player,
+            flags,
+        );
+
+        for (parent_id, child_id) in stack.reverse_pairs() {
+            let edge_stats = &stack.edge(index, *parent_id, *child_id).stats;
+            let values = self.soft_values(index, *child_id, edge_stats);
+            let edge = index.get_mut(*parent_id).child_edge_mut(*child_id);
+            for (stats, value) in edge.stats.player.iter_mut().zip(values) {
+                stats.soft = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, TicTacToe};
+    use crate::strategies::mcts::{select, strategy, TreeSearch};
+    use crate::strategies::Search;
+
+    type TS = TreeSearch<TicTacToe, strategy::RaveMastDm>;
+
+    // Expand to the terminal state on the first iteration so the trial has
+    // only tree actions and no playout actions.
+    fn search_one_iteration(amaf_update: usize) -> TS {
+        let mut ts = TS::default().config(
+            SearchConfig::default()
+                .expand_threshold(0)
+                .max_iterations(1)
+                .select(select::Rave::default().amaf_update(BackpropFlags(amaf_update)))
+                .seed(0),
+        );
+        _ = ts.choose_action(&HashedPosition::default());
+        ts
+    }
+
+    fn grave_after_one_iteration(amaf_update: usize) -> TreeStats<TicTacToe> {
+        search_one_iteration(amaf_update).stats
+    }
+
+    fn grave_visits(stats: &TreeStats<TicTacToe>) -> u32 {
+        stats
+            .history
+            .grave
+            .contexts()
+            .flat_map(|(_, players)| players)
+            .flat_map(|actions| actions.iter())
+            .map(|(_, action_stats)| action_stats.num_visits)
+            .sum()
+    }
+
+    #[test]
+    fn test_amaf_update_sources() {
+        let both = grave_visits(&grave_after_one_iteration(TREE | PLAYOUT));
+        let tree = grave_visits(&grave_after_one_iteration(TREE));
+        let playout = grave_visits(&grave_after_one_iteration(PLAYOUT));
+
+        assert!(both > 0);
+        assert_eq!(tree, both);
+        assert_eq!(playout, 0);
+        assert_eq!(grave_visits(&grave_after_one_iteration(0)), both);
+    }
+
+    #[test]
+    fn test_turn_limit_weight() {
+        let weighted = Weighted::default().turn_limit_weight(0.5);
+        let weight = |end_type| {
+            BackpropStrategy::<TicTacToe>::trial_weight(&weighted, &simulate::Status { end_type })
+        };
+        assert_eq!(weight(Some(simulate::EndType::TurnLimit)), 0.5);
+        assert_eq!(weight(Some(simulate::EndType::NaturalEnd)), 1.);
+        assert_eq!(weight(None), 1.);
+    }
+
+    #[test]
+    fn test_softmax() {
+        let policy = softmax(&[0., 0.1, 0.2], 0.1);
+        assert!((policy.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        assert!((policy[1] / policy[0] - 1f64.exp()).abs() < 1e-9);
+        assert_eq!(softmax(&[0., 1000.], 0.1), [0., 1.]);
+        assert!((soft_max(&[0., 0.], 0.1) - 0.1 * 2f64.ln()).abs() < 1e-12);
+        assert!((soft_max(&[-1., 0.5], 0.001) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_soft_backprop() {
+        use crate::games::ttt::Move;
+
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ments>::default()
+            .config(SearchConfig::default().max_iterations(2000).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // The winning edge ends the game, so its soft value is its mean score
+        let root = ts.index.get(ts.root_id);
+        let win = root.edges().iter().find(|e| e.action == Move(2)).unwrap();
+        assert_eq!(win.stats.player[0].soft, 1.);
+        assert_eq!(win.stats.player[1].soft, -1.);
+        for edge in root.edges().iter().filter(|e| e.stats.num_visits > 0) {
+            assert!(edge.stats.player[0].soft < 1. + 1e-9);
+        }
+
+        let config = SearchConfig::<TicTacToe, strategy::Ments>::default()
+            .backprop(Soft::default().temperature(0.));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::NonPositiveTemperature(0.))
+        );
+    }
+
+    #[test]
+    fn test_minimax_backup() {
+        use crate::games::ttt::Move;
+        use crate::strategies::mcts::{select, simulate};
+
+        #[derive(Clone, Default)]
+        struct Ucb1Minimax;
+
+        impl Strategy<TicTacToe> for Ucb1Minimax {
+            type Select = select::Ucb1;
+            type Simulate = simulate::Uniform;
+            type Backprop = Minimax;
+            type FinalAction = select::RobustChild;
+        }
+
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+        let mut ts = TreeSearch::<TicTacToe, Ucb1Minimax>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // The root is mixed with its proven winning child
+        let minimax = Minimax::default();
+        let root = ts.index.get(ts.root_id);
+        let win = root.edges().iter().find(|e| e.action == Move(2)).unwrap();
+        assert_eq!(win.proven, Some(vec![1., -1.]));
+        assert_eq!(
+            BackpropStrategy::<TicTacToe>::backup_value(&minimax, &ts.index, ts.root_id, &[0., 0.]),
+            Some(vec![0.5, -0.5])
+        );
+
+        // Averaging undervalues the forced win
+        let mut classic = TreeSearch::<TicTacToe, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        classic.choose_action(&state);
+        assert!(ts.root_stats.expected_score(0) > classic.root_stats.expected_score(0));
+
+        let config = SearchConfig::<TicTacToe, Ucb1Minimax>::default()
+            .backprop(Minimax::default().weight(1.5));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::MinimaxWeightOutOfRange(1.5))
+        );
+    }
+
+    #[test]
+    fn test_amaf_update_players() {
+        // The players whose tables hold O's reply to the first move, at the
+        // node it was played from
+        let tables = |amaf_update| {
+            let ts = search_one_iteration(amaf_update);
+            let visited = |id| {
+                let edges = ts.index.get(id).edges();
+                edges.iter().find(|edge| edge.stats.num_visits > 0).unwrap()
+            };
+            let node_id = visited(ts.root_id).node_id.unwrap();
+            let reply = &visited(node_id).action;
+            let players = ts.stats.history.grave.context(ts.index.get(node_id).hash);
+            players
+                .unwrap()
+                .iter()
+                .map(|actions| actions.get(reply).is_some())
+                .collect::<Vec<_>>()
+        };
+        // Counted for X, the player to move after it, as before MOVER existed
+        assert_eq!(tables(TREE), [true, false]);
+        assert_eq!(tables(TREE | MOVER), [false, true]);
+    }
+
+    #[test]
+    fn test_amaf_update_mover() {
+        let all = grave_visits(&grave_after_one_iteration(TREE));
+        let stats = grave_after_one_iteration(TREE | MOVER);
+        let mover = grave_visits(&stats);
+        assert!(mover > 0);
+        assert!(mover < all);
+        for (_, players) in stats.history.grave.contexts() {
+            // Alternating play: only one player's table is populated per node
+            assert!(players.iter().filter(|actions| !actions.is_empty()).count() <= 1);
+        }
+    }
+}
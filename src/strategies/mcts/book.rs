@@ -1,17 +1,23 @@
-use super::index;
-use crate::game::Action;
+use super::{index, ConfigError};
+use crate::game::{Action, Game, PlayerId};
+use crate::strategies::Search;
+use crate::zobrist::ZobristHashMap;
 
 use rustc_hash::FxHashMap;
 use serde::Serialize;
+use std::sync::Arc;
 
+/// A book entry. Children are keyed by action and refer to an index id for
+/// the sequence based `OpeningBook`, or a state hash for the
+/// `TranspositionBook`.
 #[derive(Clone, Debug, Serialize)]
-pub struct Entry<A: Action> {
-    pub children: FxHashMap<A, index::Id>,
+pub struct Entry<A: Action, K = index::Id> {
+    pub children: FxHashMap<A, K>,
     pub utilities: Vec<f64>,
     pub num_visits: u64,
 }
 
-impl<A: Action> Entry<A> {
+impl<A: Action, K> Entry<A, K> {
     fn update(&mut self, utilities: &[f64]) {
         assert_eq!(self.utilities.len(), utilities.len());
         self.utilities
@@ -42,6 +48,15 @@ impl<A: Action> Entry<A> {
             num_visits: 0,
         }
     }
+
+    fn merge<L>(&mut self, other: &Entry<A, L>) {
+        assert_eq!(self.utilities.len(), other.utilities.len());
+        self.utilities
+            .iter_mut()
+            .zip(other.utilities.iter())
+            .for_each(|(score, other)| *score += other);
+        self.num_visits += other.num_visits;
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -119,3 +134,545 @@ impl<A: Action> OpeningBook<A> {
         self.get(current_id).score(player)
     }
 }
+
+/// The hash key used for book lookups. Symmetric states share an entry when
+/// the game provides a canonical representation.
+pub fn state_key<G: Game>(state: &G::S) -> u64 {
+    G::zobrist_hash(&G::canonical_representation(state.clone()))
+}
+
+/// An opening book keyed by the zobrist hash of the canonicalized state rather
+/// than by action sequence, so that transposed lines share their statistics.
+/// Actions are stored as `Game::canonical_action`s, and mapped back to the
+/// probed state on lookup.
+///
+/// NOTE: this requires the game to implement `Game::zobrist_hash`.
+#[derive(Clone, Debug)]
+pub struct TranspositionBook<A: Action> {
+    pub table: ZobristHashMap<Entry<A, u64>>,
+    pub num_players: usize,
+}
+
+impl<A: Action> TranspositionBook<A> {
+    pub fn new(num_players: usize) -> Self {
+        Self {
+            table: ZobristHashMap::default(),
+            num_players,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.0.is_empty()
+    }
+
+    pub fn get(&self, hash: u64) -> Option<&Entry<A, u64>> {
+        self.table.get(hash)
+    }
+
+    fn entry(&mut self, hash: u64) -> &mut Entry<A, u64> {
+        let num_players = self.num_players;
+        self.table
+            .entry(hash)
+            .or_insert_with(|| Entry::new(num_players))
+    }
+
+    /// Add a game record played from `init`.
+    pub fn add<G: Game<A = A>>(&mut self, init: &G::S, sequence: &[A], utilities: &[f64]) {
+        let mut state = init.clone();
+        let mut hash = state_key::<G>(&state);
+        self.entry(hash).update(utilities);
+
+        for action in sequence {
+            let canonical = G::canonical_action(&state, action);
+            state = G::apply(state, action);
+            let child_hash = state_key::<G>(&state);
+            self.entry(hash).children.insert(canonical, child_hash);
+            self.entry(child_hash).update(utilities);
+            hash = child_hash;
+        }
+    }
+
+    /// Convert a sequence based book whose root corresponds to `init`.
+    pub fn from_opening_book<G: Game<A = A>>(book: &OpeningBook<A>, init: &G::S) -> Self {
+        let mut result = Self::new(book.num_players);
+        let mut stack = vec![(book.root_id, init.clone())];
+        while let Some((id, state)) = stack.pop() {
+            let hash = state_key::<G>(&state);
+            let entry = book.get(id);
+            result.entry(hash).merge(entry);
+            for (action, child_id) in &entry.children {
+                let child_state = G::apply(state.clone(), action);
+                let child_hash = state_key::<G>(&child_state);
+                result
+                    .entry(hash)
+                    .children
+                    .insert(G::canonical_action(&state, action), child_hash);
+                stack.push((*child_id, child_state));
+            }
+        }
+        result
+    }
+
+    pub fn score<G: Game<A = A>>(&self, state: &G::S, player: usize) -> Option<f64> {
+        self.get(state_key::<G>(state))
+            .and_then(|entry| entry.score(player))
+    }
+
+    /// The book actions for `state`, best first, with their scores for the
+    /// player to move.
+    pub fn recommendations<G: Game<A = A>>(&self, state: &G::S) -> Vec<(A, f64)> {
+        use crate::game::PlayerIndex;
+        let player = G::player_to_move(state).to_index();
+        let mut result = self
+            .get(state_key::<G>(state))
+            .map(|entry| {
+                entry
+                    .children
+                    .iter()
+                    .filter_map(|(action, child_hash)| {
+                        self.get(*child_hash)
+                            .and_then(|child| child.score(player))
+                            .map(|score| (G::action_from_canonical(state, action), score))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        result
+    }
+}
+
+impl<A: Action> TranspositionBook<A> {
+    /// The best scoring book action for `state` among those visited at least
+    /// `min_visits` times. Actions which are not legal here, as when a game's
+    /// canonical actions don't match its canonical states, are skipped.
+    pub fn best_action<G: Game<A = A>>(&self, state: &G::S, min_visits: u64) -> Option<A> {
+        self.best_move::<G>(state, min_visits)
+            .map(|(action, _)| action)
+    }
+
+    /// `best_action` along with its score for the player to move.
+    pub fn best_move<G: Game<A = A>>(&self, state: &G::S, min_visits: u64) -> Option<(A, f64)> {
+        use crate::game::PlayerIndex;
+        let player = G::player_to_move(state).to_index();
+        let entry = self.get(state_key::<G>(state))?;
+        let mut legal = Vec::new();
+        G::generate_actions(state, &mut legal);
+        entry
+            .children
+            .iter()
+            .map(|(action, child_hash)| (G::action_from_canonical(state, action), child_hash))
+            .filter(|(action, _)| legal.contains(action))
+            .filter_map(|(action, child_hash)| {
+                let child = self.get(*child_hash)?;
+                (child.num_visits >= min_visits)
+                    .then(|| child.score(player))
+                    .flatten()
+                    .map(|score| (action, score))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+/// A book consulted by `TreeSearch` before each search. See
+/// `SearchConfig::opening_book`. The best book move for the state is played
+/// without searching when it has been visited at least `min_visits` times and
+/// scores at least `min_score` for the player to move, on the book's scale of
+/// 0 (a certain loss) to 1 (a certain win).
+///
+/// A sequence based `OpeningBook` can be used after conversion with
+/// `TranspositionBook::from_opening_book`. The book is shared between copies
+/// of the configuration.
+#[derive(Clone, Debug)]
+pub struct BookMoves<A: Action> {
+    pub book: Arc<TranspositionBook<A>>,
+    pub min_visits: u64,
+    pub min_score: f64,
+}
+
+impl<A: Action> BookMoves<A> {
+    pub fn new(book: TranspositionBook<A>) -> Self {
+        Self {
+            book: Arc::new(book),
+            min_visits: 1,
+            min_score: 0.,
+        }
+    }
+
+    pub fn min_visits(mut self, min_visits: u64) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// The book move for `state`, if one clears the thresholds.
+    pub fn lookup<G: Game<A = A>>(&self, state: &G::S) -> Option<A> {
+        self.book
+            .best_move::<G>(state, self.min_visits)
+            .filter(|(_, score)| *score >= self.min_score)
+            .map(|(action, _)| action)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        if (0. ..=1.).contains(&self.min_score) {
+            Ok(())
+        } else {
+            Err(ConfigError::BookScoreOutOfRange(self.min_score))
+        }
+    }
+}
+
+/// Plays the best book move while the state is in the book, and the inner
+/// search once it leaves.
+#[derive(Clone)]
+pub struct BookSearch<S: Search> {
+    pub book: TranspositionBook<<S::G as Game>::A>,
+    pub inner: S,
+    // Book moves visited fewer times than this are ignored
+    pub min_visits: u64,
+    // The number of moves played from the book
+    pub book_moves: usize,
+}
+
+impl<S: Search> BookSearch<S> {
+    pub fn new(book: TranspositionBook<<S::G as Game>::A>, inner: S) -> Self {
+        Self {
+            book,
+            inner,
+            min_visits: 1,
+            book_moves: 0,
+        }
+    }
+
+    pub fn min_visits(mut self, min_visits: u64) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+}
+
+impl<S: Search> Search for BookSearch<S> {
+    type G = S::G;
+
+    fn friendly_name(&self) -> String {
+        format!("book[{}]", self.inner.friendly_name())
+    }
+
+    fn choose_action(&mut self, state: &<S::G as Game>::S) -> <S::G as Game>::A {
+        match self.book.best_action::<S::G>(state, self.min_visits) {
+            Some(action) => {
+                self.book_moves += 1;
+                action
+            }
+            None => self.inner.choose_action(state),
+        }
+    }
+
+    fn prepare(&mut self, init: &<S::G as Game>::S) {
+        self.inner.prepare(init);
+    }
+
+    fn set_friendly_name(&mut self, name: &str) {
+        self.inner.set_friendly_name(name);
+    }
+}
+
+/// The results of `evaluate_book`, from the point of view of the player using
+/// the book.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct BookEvaluation {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub book_moves: usize,
+}
+
+impl BookEvaluation {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// The mean score, counting a draw as half a win.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+
+    /// The Elo gain from using the book.
+    pub fn elo(&self) -> f64 {
+        crate::util::elo_difference(self.score())
+    }
+}
+
+// Play out a two player game from `state`, returning the seat in `players` of
+// the winner.
+fn play_from<G: Game>(mut state: G::S, players: [&mut dyn Search<G = G>; 2]) -> Option<PlayerId> {
+    let first = G::player_id(&state);
+    let seat = |player: PlayerId| {
+        if player == first {
+            PlayerId(0)
+        } else {
+            PlayerId(1)
+        }
+    };
+    while !G::is_terminal(&state) {
+        let action = players[seat(G::player_id(&state)).index()].choose_action(&state);
+        state = G::apply(state, &action);
+    }
+    G::winner_id(&state).map(seat)
+}
+
+/// Measure what a book adds to a strategy by playing the strategy with the
+/// book against the strategy alone. Each opening is played `games_per_opening`
+/// times with the book player moving first and as many again moving second.
+/// Games run in parallel, each with fresh copies of the strategy.
+///
+/// A seeded strategy plays the same game every time from the same opening and
+/// side, so use a range of openings (or unseeded strategies) for a
+/// meaningful sample. Only two player games are supported.
+pub fn evaluate_book<S>(
+    book: &TranspositionBook<<S::G as Game>::A>,
+    base: &S,
+    openings: &[<S::G as Game>::S],
+    games_per_opening: usize,
+    min_visits: u64,
+) -> BookEvaluation
+where
+    S: Search + Clone,
+{
+    use rayon::prelude::*;
+    assert_eq!(S::G::num_players(), 2);
+
+    let games = (0..openings.len())
+        .flat_map(|i| (0..2 * games_per_opening).map(move |k| (i, k % 2 == 1)))
+        .collect::<Vec<_>>();
+    games
+        .into_par_iter()
+        .map(|(i, book_second)| {
+            let mut with_book = BookSearch::new(book.clone(), base.clone()).min_visits(min_visits);
+            let mut without = base.clone();
+            let winner = if book_second {
+                play_from::<S::G>(openings[i].clone(), [&mut without, &mut with_book])
+                    .map(PlayerId::opponent)
+            } else {
+                play_from::<S::G>(openings[i].clone(), [&mut with_book, &mut without])
+            };
+            let mut result = BookEvaluation {
+                book_moves: with_book.book_moves,
+                ..Default::default()
+            };
+            match winner {
+                Some(PlayerId(0)) => result.wins += 1,
+                Some(_) => result.losses += 1,
+                None => result.draws += 1,
+            }
+            result
+        })
+        .reduce(BookEvaluation::default, |a, b| BookEvaluation {
+            wins: a.wins + b.wins,
+            losses: a.losses + b.losses,
+            draws: a.draws + b.draws,
+            book_moves: a.book_moves + b.book_moves,
+        })
+}
+
+/// Book lookups as used by `QuasiBestFirst`.
+pub trait BookScore<G: Game>: Clone + Sync + Send {
+    fn empty(num_players: usize) -> Self;
+
+    /// The score for `player` of playing `action` in `state`, where `path` is
+    /// the sequence of actions leading to `state` from the book root.
+    fn score_action(
+        &self,
+        state: &G::S,
+        path: &[G::A],
+        action: &G::A,
+        player: usize,
+    ) -> Option<f64>;
+}
+
+impl<G: Game> BookScore<G> for OpeningBook<G::A> {
+    fn empty(num_players: usize) -> Self {
+        Self::new(num_players)
+    }
+
+    fn score_action(
+        &self,
+        _state: &G::S,
+        path: &[G::A],
+        action: &G::A,
+        player: usize,
+    ) -> Option<f64> {
+        let mut key = path.to_vec();
+        key.push(action.clone());
+        self.score(key.as_slice(), player)
+    }
+}
+
+impl<G: Game> BookScore<G> for TranspositionBook<G::A> {
+    fn empty(num_players: usize) -> Self {
+        Self::new(num_players)
+    }
+
+    fn score_action(
+        &self,
+        state: &G::S,
+        _path: &[G::A],
+        action: &G::A,
+        player: usize,
+    ) -> Option<f64> {
+        let child = G::apply(state.clone(), action);
+        self.score::<G>(&child, player)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, Move, TicTacToe};
+
+    #[test]
+    fn test_transpositions_share_entries() {
+        let init = HashedPosition::new();
+        let a = [Move(0), Move(4), Move(2)];
+        let b = [Move(2), Move(4), Move(0)];
+
+        let mut seq = OpeningBook::new(2);
+        seq.add(&a, &[1., -1.]);
+        seq.add(&b, &[-1., 1.]);
+
+        let mut book = TranspositionBook::new(2);
+        book.add::<TicTacToe>(&init, &a, &[1., -1.]);
+        book.add::<TicTacToe>(&init, &b, &[-1., 1.]);
+
+        let end = a.iter().fold(init, TicTacToe::apply);
+        let entry = book.get(state_key::<TicTacToe>(&end)).unwrap();
+        assert_eq!(entry.num_visits, 2);
+        assert_eq!(book.score::<TicTacToe>(&end, 0), Some(0.5));
+
+        // The sequence book keeps the lines apart
+        assert_eq!(seq.score(&a, 0), Some(1.));
+        assert_eq!(seq.score(&b, 0), Some(0.));
+
+        let converted = TranspositionBook::from_opening_book::<TicTacToe>(&seq, &init);
+        assert_eq!(converted.len(), book.len());
+        assert_eq!(converted.score::<TicTacToe>(&end, 0), Some(0.5));
+        assert_eq!(converted.recommendations::<TicTacToe>(&init).len(), 2);
+
+        // Scoring through the QBF interface
+        let path = &a[..2];
+        let state = path.iter().fold(init, TicTacToe::apply);
+        let seq_score = BookScore::<TicTacToe>::score_action(&seq, &state, path, &a[2], 0);
+        let hash_score = BookScore::<TicTacToe>::score_action(&book, &state, path, &a[2], 0);
+        assert_eq!(seq_score, Some(1.));
+        assert_eq!(hash_score, Some(0.5));
+    }
+
+    #[test]
+    fn test_symmetric_lookup() {
+        let init = HashedPosition::new();
+        let mut book = TranspositionBook::new(2);
+        book.add::<TicTacToe>(&init, &[Move(0), Move(4), Move(1)], &[1., -1.]);
+
+        // X . .    . . X    . . .
+        // . O .    . O .    . O .
+        // . . .    . . .    X . .
+        let recorded = [Move(0), Move(4)].iter().fold(init, TicTacToe::apply);
+        let reflected = [Move(2), Move(4)].iter().fold(init, TicTacToe::apply);
+        let rotated = [Move(6), Move(4)].iter().fold(init, TicTacToe::apply);
+        let played = TicTacToe::apply(recorded, &Move(1));
+
+        // The book move is found from each position, mapped into its frame
+        assert_eq!(book.best_action::<TicTacToe>(&reflected, 1), Some(Move(1)));
+        assert_eq!(
+            book.recommendations::<TicTacToe>(&reflected),
+            vec![(Move(1), 1.)]
+        );
+        let action = book.best_action::<TicTacToe>(&rotated, 1).unwrap();
+        assert!([Move(3), Move(7)].contains(&action));
+        let reached = TicTacToe::apply(rotated, &action);
+        assert_eq!(
+            state_key::<TicTacToe>(&reached),
+            state_key::<TicTacToe>(&played)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_book() {
+        use crate::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+
+        let init = HashedPosition::new();
+        let mut book = TranspositionBook::new(2);
+        book.add::<TicTacToe>(&init, &[Move(4)], &[1., -1.]);
+        book.add::<TicTacToe>(&init, &[Move(0)], &[-1., 1.]);
+        book.add::<TicTacToe>(&init, &[Move(1)], &[1., -1.]);
+        book.add::<TicTacToe>(&init, &[Move(1)], &[-1., 1.]);
+        assert_eq!(book.best_action::<TicTacToe>(&init, 1), Some(Move(4)));
+        assert_eq!(book.best_action::<TicTacToe>(&init, 2), Some(Move(1)));
+        assert_eq!(book.best_action::<TicTacToe>(&init, 3), None);
+
+        let base = TreeSearch::<TicTacToe, strategy::Ucb1>::new()
+            .config(SearchConfig::default().max_iterations(50).seed(0));
+        let evaluation = evaluate_book(&book, &base, &[init], 2, 1);
+        assert_eq!(evaluation.games(), 4);
+        // Only the first move of the games where the book player starts
+        assert_eq!(evaluation.book_moves, 2);
+        assert!((0. ..=1.).contains(&evaluation.score()));
+
+        assert_eq!(crate::util::elo_difference(0.5), 0.);
+        assert!((crate::util::elo_difference(0.75) - 190.85).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_search_book_moves() {
+        use crate::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+
+        let init = HashedPosition::new();
+        let mut book = TranspositionBook::new(2);
+        book.add::<TicTacToe>(&init, &[Move(0)], &[1., -1.]);
+        book.add::<TicTacToe>(&init, &[Move(0)], &[0., 0.]);
+        let search = |book: BookMoves<Move>| {
+            let config = SearchConfig::default()
+                .max_iterations(100)
+                .opening_book(book)
+                .seed(0);
+            TreeSearch::<TicTacToe, strategy::Ucb1>::new()
+                .config(config)
+                .search(&init)
+        };
+
+        // The corner scores 0.75 for the first player over two visits
+        let result = search(BookMoves::new(book.clone()).min_visits(2));
+        assert!(result.book_move);
+        assert_eq!(result.action, Move(0));
+        assert_eq!(result.iterations, 0);
+
+        // Otherwise the position is searched
+        for book in [
+            BookMoves::new(book.clone()).min_visits(3),
+            BookMoves::new(book.clone()).min_score(0.8),
+        ] {
+            let result = search(book);
+            assert!(!result.book_move);
+            assert_eq!(result.iterations, 100);
+        }
+        let after = TicTacToe::apply(init, &Move(4));
+        assert_eq!(
+            BookMoves::new(book.clone()).lookup::<TicTacToe>(&after),
+            None
+        );
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+                .opening_book(BookMoves::new(book).min_score(1.5))
+                .validate(),
+            Err(ConfigError::BookScoreOutOfRange(1.5))
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! A bounded cache of leaf evaluations, for evaluators (see
+//! `SimulateStrategy::evaluate`) which cost far more than the rest of an
+//! iteration, such as neural networks.
+//!
+//! Entries are keyed by zobrist hash and evicted least recently used first.
+//! Clones share the same cache, so one cache can serve every search in a
+//! process.
+
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::{Arc, Mutex};
+
+const NIL: usize = usize::MAX;
+
+// The hash of the state and the player the evaluation was made for
+type Key = (u64, usize);
+
+struct Slot {
+    key: Key,
+    value: Vec<f64>,
+    prev: usize,
+    next: usize,
+}
+
+// A doubly linked list threaded through `slots`, most recently used first
+struct Lru {
+    map: FxHashMap<Key, usize>,
+    slots: Vec<Slot>,
+    head: usize,
+    tail: usize,
+    capacity: usize,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            map: FxHashMap::default(),
+            slots: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            capacity,
+        }
+    }
+
+    fn detach(&mut self, i: usize) {
+        let (prev, next) = (self.slots[i].prev, self.slots[i].next);
+        match prev {
+            NIL => self.head = next,
+            _ => self.slots[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            _ => self.slots[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        self.slots[i].prev = NIL;
+        self.slots[i].next = self.head;
+        match self.head {
+            NIL => self.tail = i,
+            head => self.slots[head].prev = i,
+        }
+        self.head = i;
+    }
+
+    fn get(&mut self, key: Key) -> Option<Vec<f64>> {
+        let i = *self.map.get(&key)?;
+        self.detach(i);
+        self.push_front(i);
+        Some(self.slots[i].value.clone())
+    }
+
+    fn insert(&mut self, key: Key, value: Vec<f64>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let i = if let Some(&i) = self.map.get(&key) {
+            self.slots[i].value = value;
+            self.detach(i);
+            i
+        } else if self.slots.len() < self.capacity {
+            self.slots.push(Slot {
+                key,
+                value,
+                prev: NIL,
+                next: NIL,
+            });
+            self.map.insert(key, self.slots.len() - 1);
+            self.slots.len() - 1
+        } else {
+            let i = self.tail;
+            self.detach(i);
+            self.map.remove(&self.slots[i].key);
+            self.map.insert(key, i);
+            self.slots[i].key = key;
+            self.slots[i].value = value;
+            i
+        };
+        self.push_front(i);
+    }
+}
+
+struct Shared {
+    lru: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        self.hits as f64 / (self.hits + self.misses).max(1) as f64
+    }
+}
+
+#[derive(Clone)]
+pub struct EvalCache(Arc<Shared>);
+
+impl EvalCache {
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(Shared {
+            lru: Mutex::new(Lru::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn get(&self, hash: u64, player: usize) -> Option<Vec<f64>> {
+        let value = self.0.lru.lock().unwrap().get((hash, player));
+        match value {
+            Some(_) => self.0.hits.fetch_add(1, Relaxed),
+            None => self.0.misses.fetch_add(1, Relaxed),
+        };
+        value
+    }
+
+    pub fn insert(&self, hash: u64, player: usize, utilities: Vec<f64>) {
+        self.0.lru.lock().unwrap().insert((hash, player), utilities);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let lru = self.0.lru.lock().unwrap();
+        CacheStats {
+            hits: self.0.hits.load(Relaxed),
+            misses: self.0.misses.load(Relaxed),
+            len: lru.map.len(),
+            capacity: lru.capacity,
+        }
+    }
+
+    /// Drop every entry and reset the counters.
+    pub fn clear(&self) {
+        let mut lru = self.0.lru.lock().unwrap();
+        *lru = Lru::new(lru.capacity);
+        self.0.hits.store(0, Relaxed);
+        self.0.misses.store(0, Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, Piece, TicTacToe};
+    use crate::strategies::mcts::simulate::{Cached, SimulateStrategy};
+    use crate::strategies::mcts::{
+        backprop, select, SearchConfig, Strategy, TreeSearch, TreeStats,
+    };
+
+    #[test]
+    fn test_lru() {
+        let cache = EvalCache::new(2);
+        cache.insert(1, 0, vec![1.]);
+        cache.insert(2, 0, vec![2.]);
+        assert_eq!(cache.get(1, 0), Some(vec![1.]));
+        // 2 is now the least recently used
+        cache.insert(3, 0, vec![3.]);
+        assert_eq!(cache.get(2, 0), None);
+        assert_eq!(cache.get(1, 0), Some(vec![1.]));
+        assert_eq!(cache.get(3, 0), Some(vec![3.]));
+        assert_eq!(cache.get(3, 1), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.len), (3, 2, 2));
+        assert_eq!(stats.hit_rate(), 0.6);
+
+        cache.clear();
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                capacity: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_cached_evaluation() {
+        #[derive(Clone, Default)]
+        struct Center;
+
+        impl SimulateStrategy<TicTacToe> for Center {
+            fn evaluate(
+                &mut self,
+                state: &HashedPosition,
+                _stats: &TreeStats<TicTacToe>,
+                _player: usize,
+            ) -> Option<Vec<f64>> {
+                match state.position.get(4) {
+                    Some(Piece::X) => Some(vec![1., -1.]),
+                    _ => Some(vec![-1., 1.]),
+                }
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct CachedCenter;
+
+        impl Strategy<TicTacToe> for CachedCenter {
+            type Select = select::Ucb1;
+            type Simulate = Cached<TicTacToe, Center>;
+            type Backprop = backprop::Classic;
+            type FinalAction = select::RobustChild;
+        }
+
+        let mut ts = TreeSearch::<TicTacToe, CachedCenter>::default()
+            .config(SearchConfig::default().max_iterations(500).seed(0));
+        let mut copy = ts.clone();
+        ts.search(&HashedPosition::new());
+        let stats = ts.config.simulate.cache.stats();
+        assert!(stats.hits > 0);
+        assert!(stats.len > 0);
+
+        // The copy shares the cache, so the same search is all hits
+        copy.search(&HashedPosition::new());
+        let shared = copy.config.simulate.cache.stats();
+        assert_eq!(shared.misses, stats.misses);
+        assert!(shared.hits > stats.hits);
+    }
+
+    #[test]
+    fn test_cached_without_hashes() {
+        use crate::games::nim::Nim;
+        use crate::strategies::mcts::{simulate, ConfigError};
+
+        #[derive(Clone, Default)]
+        struct CachedNim;
+
+        impl Strategy<Nim> for CachedNim {
+            type Select = select::Ucb1;
+            type Simulate = Cached<Nim, simulate::Uniform>;
+            type Backprop = backprop::Classic;
+            type FinalAction = select::RobustChild;
+        }
+
+        assert_eq!(
+            SearchConfig::<Nim, CachedNim>::default().validate(),
+            Err(ConfigError::UnhashedCache)
+        );
+    }
+}
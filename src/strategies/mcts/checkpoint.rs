@@ -0,0 +1,190 @@
+//! Saving the search tree to disk and resuming from it, for searches which
+//! run over many sessions such as opening book builds.
+//!
+//! A checkpoint holds the tree, the root statistics, the transposition table
+//! and the state of the random number generator. It is taken between calls
+//! to `search`. Each search ends by reseeding the generator from itself, and
+//! the checkpoint records the seed. The next search after `restore` continues
+//! from the restored tree as with `TreeReuse`, whether or not it is
+//! configured: from the same position, or one a few moves below it. Taking a
+//! checkpoint leaves the search untouched, so the original and the restored
+//! search continue alike.
+//! The configuration is not saved, and should be the same when restoring.
+//! Nor are the global statistics of `TreeStats`, such as MAST.
+
+use super::index::Id;
+use super::node::NodeStats;
+use super::table::TableEntry;
+use super::{SearchConfig, Strategy, TreeIndex, TreeSearch};
+use crate::game::{Action, Game};
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "A: Serialize, S: Serialize",
+    deserialize = "A: DeserializeOwned, S: DeserializeOwned"
+))]
+pub struct Checkpoint<A: Action, S: Eq> {
+    // The position at the root of the tree
+    pub state: S,
+    pub index: TreeIndex<A>,
+    pub root_id: Id,
+    pub root_stats: NodeStats,
+    pub table: Vec<TableEntry<S>>,
+    // The seed which recreates the random number generator, unless it was
+    // replaced since the last search. The restored search then keeps its own.
+    pub rng_seed: Option<u64>,
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Reseed the generator from itself, for a checkpoint to recreate it.
+    pub(crate) fn reseed_rng(&mut self) {
+        self.rng_seed = self.config.rng.gen();
+        self.config.rng = SmallRng::seed_from_u64(self.rng_seed);
+    }
+
+    /// Take a checkpoint of the tree from the last search, which was of
+    /// `state`.
+    pub fn checkpoint(&self, state: &G::S) -> Checkpoint<G::A, G::S> {
+        debug_assert_eq!(self.index.get(self.root_id).hash, G::zobrist_hash(state));
+        Checkpoint {
+            state: state.clone(),
+            index: self.index.clone(),
+            root_id: self.root_id,
+            root_stats: self.root_stats.clone(),
+            table: self.table.all_entries().cloned().collect(),
+            rng_seed: (SmallRng::seed_from_u64(self.rng_seed) == self.config.rng)
+                .then_some(self.rng_seed),
+        }
+    }
+
+    /// Replace the tree with a checkpoint's, for the next search to continue
+    /// from.
+    pub fn restore(&mut self, checkpoint: Checkpoint<G::A, G::S>) {
+        self.index = checkpoint.index;
+        self.root_id = checkpoint.root_id;
+        self.root_stats = checkpoint.root_stats;
+        self.table = self.new_table();
+        for entry in checkpoint.table {
+            self.table.restore(entry);
+        }
+        if let Some(seed) = checkpoint.rng_seed {
+            self.rng_seed = seed;
+            self.config.rng = SmallRng::seed_from_u64(seed);
+        }
+        self.previous_root = Some(checkpoint.state);
+        self.resume = true;
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::A: DeserializeOwned,
+    G::S: std::fmt::Display + Serialize + DeserializeOwned,
+{
+    /// Write a checkpoint of the tree from the last search, of `state`, to
+    /// `path` as JSON.
+    pub fn save_checkpoint(&self, state: &G::S, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(writer, &self.checkpoint(state)).map_err(io::Error::other)
+    }
+
+    /// Restore the tree from a checkpoint written by `save_checkpoint`.
+    pub fn load_checkpoint(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let reader = BufReader::new(File::open(path)?);
+        let checkpoint = serde_json::from_reader(reader).map_err(io::Error::from)?;
+        self.restore(checkpoint);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::strategy;
+    use crate::strategies::Search;
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    #[test]
+    fn test_checkpoint() {
+        let config = || {
+            SearchConfig::default()
+                .max_iterations(500)
+                .use_transpositions(true)
+                .reuse_tree(Default::default())
+                .seed(0)
+        };
+        let state = HashedPosition::new();
+        let mut ts = TS::default().config(config());
+        ts.choose_action(&state);
+        let rng = ts.config.rng.clone();
+
+        let path =
+            std::env::temp_dir().join(format!("mcts-checkpoint-{}.json", std::process::id()));
+        ts.save_checkpoint(&state, &path).unwrap();
+        assert_eq!(ts.config.rng, rng);
+        // Without tree reuse, the restored tree is still resumed
+        let mut restored = TS::default().config(config().seed(1));
+        restored.config.reuse_tree = None;
+        restored.load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.index.len(), ts.index.len());
+        assert_eq!(restored.table.len(), ts.table.len());
+        assert_eq!(restored.root_visits(), ts.root_visits());
+        assert_eq!(restored.root_stats.num_visits, 500);
+
+        // Both continue the same way
+        assert_eq!(ts.choose_action(&state), restored.choose_action(&state));
+        assert_eq!(restored.root_stats.num_visits, 1000);
+        assert_eq!(restored.root_visits(), ts.root_visits());
+
+        // The next position keeps its subtree
+        let next = TicTacToe::apply(state, &Move(4));
+        restored.restore(ts.checkpoint(&state));
+        restored.search(&next);
+        assert!(restored.root_stats.num_visits > 500);
+    }
+
+    #[test]
+    fn test_checkpoint_rng() {
+        let state = HashedPosition::new();
+        let mut ts = TS::default().config(SearchConfig::default().max_iterations(100).seed(0));
+        ts.search(&state);
+        let checkpoint = ts.checkpoint(&state);
+        assert!(checkpoint.rng_seed.is_some());
+
+        // A generator replaced since the search is not recorded
+        ts.config.rng = SmallRng::seed_from_u64(1);
+        assert_eq!(ts.checkpoint(&state).rng_seed, None);
+
+        // A malformed seed is an error rather than a panic
+        let path =
+            std::env::temp_dir().join(format!("mcts-checkpoint-rng-{}.json", std::process::id()));
+        let mut json = serde_json::to_value(&checkpoint).unwrap();
+        json["rng_seed"] = serde_json::json!([1, 2, 3]);
+        std::fs::write(&path, json.to_string()).unwrap();
+        let error = ts.load_checkpoint(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
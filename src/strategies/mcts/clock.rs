@@ -0,0 +1,202 @@
+//! Time management from a game clock. See `TimeControl`.
+
+use super::{SearchConfig, Strategy, TimeControl, TreeSearch};
+use crate::game::{Game, PlayerIndex};
+use std::time::Duration;
+
+/// The state of `TimeControl` through the current search.
+#[derive(Clone, Debug)]
+pub(crate) struct TimeManager {
+    control: TimeControl,
+    // The expected score of the most visited root child at the last check
+    previous_score: Option<f64>,
+    pub(crate) panicked: bool,
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Set the time left on the clock for the next search, if `time_control`
+    /// is configured.
+    pub fn set_clock(&mut self, remaining: Duration) {
+        if let Some(control) = &mut self.config.time_control {
+            control.remaining = remaining;
+        }
+    }
+
+    // The allotment capped by `max_time`, if set
+    fn cap_time(&self, allotment: Duration) -> Duration {
+        if self.config.max_time == Duration::default() {
+            allotment
+        } else {
+            allotment.min(self.config.max_time)
+        }
+    }
+
+    /// Start the timer for a search: the allotment of `time_control` if
+    /// configured, or else `max_time`.
+    pub(crate) fn start_clock(&mut self) {
+        self.time_manager = self.config.time_control.map(|control| TimeManager {
+            control,
+            previous_score: None,
+            panicked: false,
+        });
+        let duration = match self.config.time_control {
+            Some(control) => self.cap_time(control.allotment()),
+            None => self.config.max_time,
+        };
+        self.timer.start(duration);
+    }
+
+    /// Check the root every `check_interval` iterations under `time_control`,
+    /// extending the deadline if the best child's score has dropped. Returns
+    /// true if the search should stop because the most visited child can no
+    /// longer be overtaken.
+    pub(crate) fn check_clock(&mut self, state: &G::S, iterations: usize) -> bool {
+        let Some(manager) = &self.time_manager else {
+            return false;
+        };
+        let control = manager.control;
+        let root = self.index.get(self.root_id);
+        if iterations % control.check_interval != 0 || !root.is_expanded() {
+            return false;
+        }
+
+        let player = G::player_to_move(state).to_index();
+        let mut visits = root
+            .edges()
+            .iter()
+            .map(|edge| (edge.stats.num_visits, edge.stats.expected_score(player)))
+            .collect::<Vec<_>>();
+        visits.sort_by_key(|(n, _)| std::cmp::Reverse(*n));
+        let (best_visits, best_score) = visits[0];
+        let second_visits = visits.get(1).map_or(0, |(n, _)| *n);
+
+        let panic = !manager.panicked
+            && manager
+                .previous_score
+                .is_some_and(|previous| previous - best_score > control.panic_drop);
+        if panic {
+            let extended = self.cap_time(control.panic_allotment());
+            self.timer.extend(extended);
+        }
+        let manager = self.time_manager.as_mut().unwrap();
+        manager.panicked |= panic;
+        manager.previous_score = Some(best_score);
+
+        if !control.early_stop {
+            return false;
+        }
+        if visits.len() == 1 {
+            return true;
+        }
+        let elapsed = self.timer.elapsed();
+        let Some(budget) = self.timer.budget() else {
+            return false;
+        };
+        if elapsed.is_zero() {
+            return false;
+        }
+        let remaining = iterations as f64 * budget.saturating_sub(elapsed).as_secs_f64()
+            / elapsed.as_secs_f64();
+        let remaining = remaining.min((self.config.max_iterations - iterations) as f64);
+        (best_visits - second_visits) as f64 > remaining
+    }
+
+    /// Whether the deadline was extended by panic time in the last search.
+    pub fn panicked(&self) -> bool {
+        self.time_manager
+            .as_ref()
+            .is_some_and(|manager| manager.panicked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::{strategy, ConfigError};
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    #[test]
+    fn test_time_control() {
+        let control = TimeControl::new(Duration::from_secs(60), Duration::from_secs(1));
+        assert_eq!(control.allotment(), Duration::from_secs(3));
+        assert_eq!(control.panic_allotment(), Duration::from_secs(6));
+        // Never more than half of the time left
+        let control = control.remaining(Duration::from_secs(1));
+        assert_eq!(control.allotment(), Duration::from_millis(500));
+        assert_eq!(control.panic_allotment(), Duration::from_millis(500));
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+                .time_control(control.panic_factor(0.5))
+                .validate(),
+            Err(ConfigError::InvalidTimeControl(control.panic_factor(0.5)))
+        );
+
+        // X to move and win at 2, or lose to O at 5. The allotment of a minute
+        // is never reached: the lead of the winning move becomes insurmountable
+        // in the iterations left first.
+        let mut state = HashedPosition::new();
+        for i in [0, 4, 1, 3] {
+            state = TicTacToe::apply(state, &Move(i));
+        }
+        let control =
+            TimeControl::new(Duration::from_secs(600), Duration::default()).moves_to_go(10);
+        // `time_control` lifts the iteration limit, so it is set afterwards
+        let config = |control| {
+            SearchConfig::default()
+                .time_control(control)
+                .max_iterations(5000)
+                .seed(0)
+        };
+        let mut ts = TS::default().config(config(control));
+        let result = ts.search(&state);
+        assert_eq!(result.action, Move(2));
+        assert!(result.early_stop);
+        assert!(result.iterations < 5000);
+
+        ts.set_clock(Duration::from_millis(100));
+        assert_eq!(
+            ts.config.time_control.unwrap().remaining,
+            Duration::from_millis(100)
+        );
+
+        // Without early stopping the whole budget is used
+        let mut ts = TS::default().config(config(control.early_stop(false)));
+        let result = ts.search(&state);
+        assert!(!result.early_stop);
+        assert_eq!(result.iterations, 5000);
+    }
+
+    #[test]
+    fn test_panic_time() {
+        // Any drop in the best child's score triggers panic time, doubling the
+        // allotment
+        let control = TimeControl::new(Duration::from_secs(600), Duration::default())
+            .moves_to_go(20)
+            .early_stop(false)
+            .panic_drop(0.);
+        let config = |control| {
+            SearchConfig::default()
+                .time_control(control)
+                .max_iterations(2000)
+                .seed(0)
+        };
+        let mut ts = TS::default().config(config(control));
+        ts.search(&HashedPosition::new());
+        assert!(ts.panicked());
+        assert_eq!(ts.timer.budget(), Some(control.panic_allotment()));
+
+        let mut ts = TS::default().config(config(control.panic_drop(1.)));
+        ts.search(&HashedPosition::new());
+        assert!(!ts.panicked());
+        assert_eq!(ts.timer.budget(), Some(control.allotment()));
+    }
+}
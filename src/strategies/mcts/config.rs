@@ -1,9 +1,16 @@
 use super::*;
 
-use crate::game::Game;
+use crate::game::{ConfigHints, Game};
+use crate::strategies::Verdict;
 use node::QInit;
+use progress::{OnProgress, Progress};
 use rand::rngs::SmallRng;
 use rand_core::SeedableRng;
+use restrict::RootFilter;
+use schedule::Schedule;
+use std::fmt;
+use std::time::Duration;
+use table::{TableBounds, TableVerification, TranspositionLinking};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -11,6 +18,29 @@ pub const GRAVE: usize = 0b001;
 pub const GLOBAL: usize = 0b010;
 pub const AMAF: usize = 0b100;
 
+// AMAF/GRAVE update sources. If neither TREE nor PLAYOUT is set, both are used.
+pub const TREE: usize = 0b1000;
+pub const PLAYOUT: usize = 0b10000;
+
+// Restrict AMAF/GRAVE updates to actions made by the player to move at the
+// node being updated. Tree actions are then counted for the player who made
+// them, rather than the player to move after them.
+pub const MOVER: usize = 0b100000;
+
+// Maintain `TreeStats::guide` for `simulate::TableGuided`.
+pub const GUIDE: usize = 0b1000000;
+
+// Maintain the N-gram statistics in `History::ngrams` for `simulate::Nst`.
+pub const NGRAM: usize = 0b10000000;
+
+// Maintain the last good replies in `History::replies` for
+// `simulate::LastGoodReply`.
+pub const REPLY: usize = 0b100000000;
+
+// With REPLY, also forget replies which lose.
+pub const FORGET_REPLY: usize = 0b1000000000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct BackpropFlags(pub usize);
 
 impl BackpropFlags {
@@ -25,6 +55,36 @@ impl BackpropFlags {
     pub fn amaf(&self) -> bool {
         self.0 & AMAF == AMAF
     }
+
+    /// Include actions from the tree portion of the path in AMAF updates.
+    pub fn tree(&self) -> bool {
+        self.0 & (TREE | PLAYOUT) != PLAYOUT
+    }
+
+    /// Include actions from the playout in AMAF updates.
+    pub fn playout(&self) -> bool {
+        self.0 & (TREE | PLAYOUT) != TREE
+    }
+
+    pub fn mover(&self) -> bool {
+        self.0 & MOVER == MOVER
+    }
+
+    pub fn guide(&self) -> bool {
+        self.0 & GUIDE == GUIDE
+    }
+
+    pub fn ngram(&self) -> bool {
+        self.0 & NGRAM == NGRAM
+    }
+
+    pub fn reply(&self) -> bool {
+        self.0 & REPLY == REPLY
+    }
+
+    pub fn forget_reply(&self) -> bool {
+        self.0 & FORGET_REPLY == FORGET_REPLY
+    }
 }
 
 impl std::ops::BitOr for BackpropFlags {
@@ -36,10 +96,797 @@ impl std::ops::BitOr for BackpropFlags {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    // The root is never expanded, so no action can be chosen
+    ExpandThresholdTooHigh {
+        expand_threshold: u32,
+        max_iterations: usize,
+    },
+    ZeroPlayoutDepth,
+    ZeroCutoffDepth,
+    EpsilonOutOfRange(f64),
+    StatsDecayOutOfRange(f64),
+    InvalidEasyMove(EasyMove),
+    InvalidTimeControl(TimeControl),
+    ZeroProgressInterval,
+    NegativeDisagreementBudget(f64),
+    InvalidAdjudication(Adjudication),
+    InvalidTrialRecording(TrialRecording),
+    TurnLimitWeightOutOfRange(f64),
+    NonPositiveTemperature(f64),
+    MinimaxWeightOutOfRange(f64),
+    NGramLengthOutOfRange(usize),
+    BookScoreOutOfRange(f64),
+    InvalidTreeReuse(TreeReuse),
+    InvalidAutoExpand(AutoExpand),
+    InvalidTreeParallel(TreeParallel),
+    InvalidEvalBatch(EvalBatch),
+    InvalidUcd(Ucd),
+    // Sequential halving needs a number of iterations to divide
+    UnboundedHalving,
+    // The root needs room for a child
+    MaxNodesTooSmall(usize),
+    ZeroTableCapacity,
+    // An evaluation cache on a game without zobrist hashes
+    UnhashedCache,
+    InvalidQInit(QInit),
+    // A trace replayed with a configuration other than the one recorded
+    TraceMismatch {
+        recorded: u64,
+        config: u64,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ExpandThresholdTooHigh {
+                expand_threshold,
+                max_iterations,
+            } => write!(
+                f,
+                "expand_threshold ({expand_threshold}) must be less than max_iterations ({max_iterations})"
+            ),
+            ConfigError::ZeroPlayoutDepth => write!(f, "max_playout_depth must be non-zero"),
+            ConfigError::ZeroCutoffDepth => write!(f, "cutoff depth must be non-zero"),
+            ConfigError::EpsilonOutOfRange(epsilon) => {
+                write!(f, "epsilon ({epsilon}) must be in the range [0, 1]")
+            }
+            ConfigError::StatsDecayOutOfRange(gamma) => {
+                write!(f, "stats_decay ({gamma}) must be in the range [0, 1]")
+            }
+            ConfigError::InvalidEasyMove(easy_move) => write!(
+                f,
+                "invalid easy_move {easy_move:?}: min_fraction must be in [0, 1], visit_ratio at least 1 and check_interval non-zero"
+            ),
+            ConfigError::InvalidTimeControl(control) => write!(
+                f,
+                "invalid time_control {control:?}: moves_to_go and check_interval must be non-zero, max_fraction in (0, 1], panic_drop non-negative and panic_factor at least 1"
+            ),
+            ConfigError::ZeroProgressInterval => {
+                write!(f, "on_progress interval must be non-zero")
+            }
+            ConfigError::NegativeDisagreementBudget(budget) => {
+                write!(f, "disagreement_budget ({budget}) must not be negative")
+            }
+            ConfigError::InvalidAdjudication(adjudication) => write!(
+                f,
+                "invalid adjudication {adjudication:?}: resign_below must be less than claim_above and consecutive non-zero"
+            ),
+            ConfigError::InvalidTrialRecording(recording) => write!(
+                f,
+                "invalid record_trials {recording:?}: every must be non-zero"
+            ),
+            ConfigError::TurnLimitWeightOutOfRange(weight) => {
+                write!(f, "turn_limit_weight ({weight}) must be in the range [0, 1]")
+            }
+            ConfigError::NonPositiveTemperature(temperature) => {
+                write!(f, "temperature ({temperature}) must be positive")
+            }
+            ConfigError::MinimaxWeightOutOfRange(weight) => {
+                write!(f, "minimax weight ({weight}) must be in the range [0, 1]")
+            }
+            ConfigError::NGramLengthOutOfRange(max_n) => write!(
+                f,
+                "N-gram length ({max_n}) must be in the range [1, {}]",
+                history::MAX_NGRAM
+            ),
+            ConfigError::BookScoreOutOfRange(score) => {
+                write!(f, "opening book min_score ({score}) must be in the range [0, 1]")
+            }
+            ConfigError::InvalidTreeReuse(reuse) => {
+                write!(f, "invalid reuse_tree {reuse:?}: top_k must be non-zero")
+            }
+            ConfigError::InvalidAutoExpand(auto) => write!(
+                f,
+                "invalid auto_expand {auto:?}: max_nodes and check_interval must be non-zero, and max_threshold at least expand_threshold, which must be non-zero"
+            ),
+            ConfigError::InvalidTreeParallel(parallel) => write!(
+                f,
+                "invalid tree_parallel {parallel:?}: threads must be non-zero"
+            ),
+            ConfigError::InvalidEvalBatch(batch) => write!(
+                f,
+                "invalid eval_batch {batch:?}: size must be non-zero, and tree_parallel unset"
+            ),
+            ConfigError::InvalidUcd(ucd) => write!(
+                f,
+                "invalid ucd {ucd:?}: depths must be at most {MAX_UCD_DEPTH}"
+            ),
+            ConfigError::UnboundedHalving => write!(
+                f,
+                "sequential_halving needs a budget, or max_iterations to be set"
+            ),
+            ConfigError::MaxNodesTooSmall(max_nodes) => {
+                write!(f, "max_nodes ({max_nodes}) must be at least 2")
+            }
+            ConfigError::ZeroTableCapacity => {
+                write!(f, "transposition table capacity must be non-zero")
+            }
+            ConfigError::UnhashedCache => write!(
+                f,
+                "the evaluation cache needs a game with zobrist hashes (see Game::supports_transpositions)"
+            ),
+            ConfigError::InvalidQInit(q_init) => {
+                write!(f, "invalid q_init {q_init:?}: the value must be finite")
+            }
+            ConfigError::TraceMismatch { recorded, config } => write!(
+                f,
+                "trace recorded with config hash {recorded:#x} cannot be replayed with {config:#x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub(crate) fn validate_epsilon(epsilon: f64) -> Result<(), ConfigError> {
+    if (0. ..=1.).contains(&epsilon) {
+        Ok(())
+    } else {
+        Err(ConfigError::EpsilonOutOfRange(epsilon))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Budgets and enhancements applied on top of a strategy's defaults by
+/// `SearchConfig::preset`.
+///
+/// - `Fast`: 1,000 iterations, returning early on an obvious move.
+/// - `Balanced`: 10,000 iterations, returning early on an obvious move.
+/// - `Strong`: 100,000 iterations, resolving disagreements between the most
+///   visited and best scoring moves, and using transpositions where the game
+///   supports them.
+///
+/// Playout enhancements such as MAST and decisive moves are part of the
+/// strategy's type; choose a strategy like `strategy::RaveMastDm` for those.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Fast,
+    Balanced,
+    Strong,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Thresholds for returning early when one root action clearly dominates. The
+/// root is checked every `check_interval` iterations once `min_fraction` of the
+/// budget (iterations, or time if set) has been spent. The search stops when
+/// the most visited child has at least `visit_ratio` times the visits of the
+/// runner-up, leads it in expected score by `score_margin`, and was also the
+/// most visited child at the previous check.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EasyMove {
+    pub min_fraction: f64,
+    pub check_interval: usize,
+    pub visit_ratio: f64,
+    pub score_margin: f64,
+}
+
+impl Default for EasyMove {
+    fn default() -> Self {
+        Self {
+            min_fraction: 0.1,
+            check_interval: 100,
+            visit_ratio: 4.,
+            score_margin: 0.1,
+        }
+    }
+}
+
+impl EasyMove {
+    pub fn min_fraction(mut self, min_fraction: f64) -> Self {
+        self.min_fraction = min_fraction;
+        self
+    }
+
+    pub fn check_interval(mut self, check_interval: usize) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    pub fn visit_ratio(mut self, visit_ratio: f64) -> Self {
+        self.visit_ratio = visit_ratio;
+        self
+    }
+
+    pub fn score_margin(mut self, score_margin: f64) -> Self {
+        self.score_margin = score_margin;
+        self
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if (0. ..=1.).contains(&self.min_fraction)
+            && self.visit_ratio >= 1.
+            && self.check_interval > 0
+        {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidEasyMove(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Time management from a game clock, in place of a fixed `max_time`. Each
+/// search is allotted `remaining / moves_to_go + increment`, but no more than
+/// `max_fraction` of `remaining`. `max_time`, if also set, caps the allotment.
+///
+/// The root is checked every `check_interval` iterations. With `early_stop`
+/// the search returns once the runner-up could not overtake the most visited
+/// child in the iterations expected to remain. If the expected score of the
+/// most visited child has fallen by more than `panic_drop` since the previous
+/// check, the allotment is extended once to `panic_factor` times its length,
+/// again within `max_fraction` of `remaining`. With `tree_parallel` only the
+/// allotment is used.
+///
+/// The clock is not kept by the search: update `remaining` before each move,
+/// for example with `TreeSearch::set_clock`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub remaining: Duration,
+    pub increment: Duration,
+    pub moves_to_go: u32,
+    pub max_fraction: f64,
+    pub early_stop: bool,
+    pub panic_drop: f64,
+    pub panic_factor: f64,
+    pub check_interval: usize,
+}
+
+impl TimeControl {
+    // So that a search always gets to expand the root
+    const MIN_ALLOTMENT: Duration = Duration::from_millis(1);
+
+    pub fn new(remaining: Duration, increment: Duration) -> Self {
+        Self {
+            remaining,
+            increment,
+            moves_to_go: 30,
+            max_fraction: 0.5,
+            early_stop: true,
+            panic_drop: 0.1,
+            panic_factor: 2.,
+            check_interval: 100,
+        }
+    }
+
+    pub fn remaining(mut self, remaining: Duration) -> Self {
+        self.remaining = remaining;
+        self
+    }
+
+    pub fn moves_to_go(mut self, moves_to_go: u32) -> Self {
+        self.moves_to_go = moves_to_go;
+        self
+    }
+
+    pub fn max_fraction(mut self, max_fraction: f64) -> Self {
+        self.max_fraction = max_fraction;
+        self
+    }
+
+    pub fn early_stop(mut self, early_stop: bool) -> Self {
+        self.early_stop = early_stop;
+        self
+    }
+
+    pub fn panic_drop(mut self, panic_drop: f64) -> Self {
+        self.panic_drop = panic_drop;
+        self
+    }
+
+    pub fn panic_factor(mut self, panic_factor: f64) -> Self {
+        self.panic_factor = panic_factor;
+        self
+    }
+
+    pub fn check_interval(mut self, check_interval: usize) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    fn limit(&self, duration: Duration) -> Duration {
+        duration
+            .min(self.remaining.mul_f64(self.max_fraction))
+            .max(Self::MIN_ALLOTMENT)
+    }
+
+    /// The time allotted to the next search.
+    pub fn allotment(&self) -> Duration {
+        self.limit(self.remaining / self.moves_to_go + self.increment)
+    }
+
+    /// The time allotted to the next search once extended by panic time.
+    pub fn panic_allotment(&self) -> Duration {
+        self.limit((self.remaining / self.moves_to_go + self.increment).mul_f64(self.panic_factor))
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.moves_to_go > 0
+            && self.max_fraction > 0.
+            && self.max_fraction <= 1.
+            && self.panic_drop >= 0.
+            && self.panic_factor >= 1.
+            && self.check_interval > 0
+        {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidTimeControl(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Thresholds for resigning or claiming the win. After each search, the root's
+/// expected score for the player to move is compared against `resign_below`
+/// and `claim_above`. Once it has been past the same threshold for
+/// `consecutive` searches in a row, each of at least `min_iterations`
+/// iterations, the search result carries a `Verdict`.
+///
+/// The count runs across calls to `search` within a game: a search continues
+/// the streak of the last one whose root it follows within a move by each
+/// player. Streaks are kept for a few recent games, so a `TreeSearch` may
+/// play games at the same time, as in a tournament.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjudication {
+    pub resign_below: f64,
+    pub claim_above: f64,
+    pub consecutive: usize,
+    pub min_iterations: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_below: -0.95,
+            claim_above: 0.95,
+            consecutive: 3,
+            min_iterations: 1000,
+        }
+    }
+}
+
+impl Adjudication {
+    pub fn resign_below(mut self, resign_below: f64) -> Self {
+        self.resign_below = resign_below;
+        self
+    }
+
+    /// Use `f64::INFINITY` to never claim the win.
+    pub fn claim_above(mut self, claim_above: f64) -> Self {
+        self.claim_above = claim_above;
+        self
+    }
+
+    pub fn consecutive(mut self, consecutive: usize) -> Self {
+        self.consecutive = consecutive;
+        self
+    }
+
+    pub fn min_iterations(mut self, min_iterations: usize) -> Self {
+        self.min_iterations = min_iterations;
+        self
+    }
+
+    /// The verdict suggested by a single search, before counting.
+    pub(crate) fn verdict(&self, score: f64, iterations: usize) -> Option<Verdict> {
+        if iterations < self.min_iterations {
+            None
+        } else if score <= self.resign_below {
+            Some(Verdict::Resign)
+        } else if score >= self.claim_above {
+            Some(Verdict::ClaimWin)
+        } else {
+            None
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.resign_below < self.claim_above && self.consecutive > 0 {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidAdjudication(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Keep a copy of every `every`th playout of a search, up to the most recent
+/// `capacity` of them, for inspection with `TreeSearch::recorded_trials`. The
+/// buffer is emptied at the start of each search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrialRecording {
+    pub every: usize,
+    pub capacity: usize,
+}
+
+impl Default for TrialRecording {
+    fn default() -> Self {
+        Self {
+            every: 1,
+            capacity: 1000,
+        }
+    }
+}
+
+impl TrialRecording {
+    pub fn every(mut self, every: usize) -> Self {
+        self.every = every;
+        self
+    }
+
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.every > 0 {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidTrialRecording(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Carry the subtree of the new position over from the previous search. The
+/// new root is looked for among the positions up to `Game::num_players` moves
+/// below the previous root; if it is not found the tree starts afresh.
+///
+/// The retained subtree is pruned to bound its memory. Of each node's explored
+/// children, only the `top_k` most visited are kept, those whose edge has
+/// fewer than `min_visits` visits are dropped, and so are nodes more than
+/// `max_depth` moves below the new root. A dropped child's edge keeps its
+/// statistics; only the subtree below it is freed. By default nothing is
+/// pruned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeReuse {
+    pub top_k: Option<usize>,
+    pub min_visits: u32,
+    pub max_depth: Option<usize>,
+}
+
+impl TreeReuse {
+    pub fn top_k(mut self, top_k: usize) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    pub fn min_visits(mut self, min_visits: u32) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.top_k != Some(0) {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidTreeReuse(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Adapt the expand threshold during the search so that the tree ends up with
+/// about `max_nodes` nodes. Every `check_interval` iterations the rate at which
+/// nodes were created is compared with the rate which would use up the rest of
+/// the node budget over the rest of the search, estimated from the iteration or
+/// time limit. The threshold is raised in proportion when nodes are created too
+/// quickly, up to `max_threshold`, and halved back towards the configured
+/// `expand_threshold` when they are created at under half the rate.
+///
+/// With no iteration or time limit, the threshold is only raised, to
+/// `max_threshold`, once the tree reaches `max_nodes`. This is a soft limit;
+/// use `max_expansions` to stop the search at a hard one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoExpand {
+    pub max_nodes: usize,
+    pub check_interval: usize,
+    pub max_threshold: u32,
+}
+
+impl Default for AutoExpand {
+    fn default() -> Self {
+        Self {
+            max_nodes: 1_000_000,
+            check_interval: 100,
+            max_threshold: 1000,
+        }
+    }
+}
+
+impl AutoExpand {
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    pub fn check_interval(mut self, check_interval: usize) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    pub fn max_threshold(mut self, max_threshold: u32) -> Self {
+        self.max_threshold = max_threshold;
+        self
+    }
+
+    /// The threshold to use from now on, given the current one, how many nodes
+    /// were created over the last `check_interval` iterations, the size of the
+    /// tree and the number of iterations expected to remain, if known.
+    pub(crate) fn adjust(
+        &self,
+        threshold: u32,
+        min_threshold: u32,
+        created: usize,
+        num_nodes: usize,
+        remaining_iterations: Option<f64>,
+    ) -> u32 {
+        if num_nodes >= self.max_nodes {
+            return self.max_threshold;
+        }
+        let Some(remaining_iterations) = remaining_iterations else {
+            return threshold;
+        };
+        let rate = created as f64 / self.check_interval as f64;
+        let target = (self.max_nodes - num_nodes) as f64 / remaining_iterations.max(1.);
+        if rate > target {
+            let raised = (threshold as f64 * rate / target.max(f64::MIN_POSITIVE)).ceil();
+            (raised.min(self.max_threshold as f64) as u32).max(threshold + 1)
+        } else if rate < target / 2. {
+            (threshold / 2).max(min_threshold)
+        } else {
+            threshold
+        }
+        .clamp(min_threshold, self.max_threshold)
+    }
+
+    fn validate(&self, expand_threshold: u32) -> Result<(), ConfigError> {
+        if self.max_nodes > 0
+            && self.check_interval > 0
+            && expand_threshold > 0
+            && self.max_threshold >= expand_threshold
+        {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidAutoExpand(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Search with several threads descending the same tree. The tree is locked
+/// while a thread selects a leaf and while it backs up the result, but the
+/// playouts run concurrently. Each edge on a thread's path carries
+/// `virtual_loss` virtual visits scored as losses until its playout is backed
+/// up, which steers the other threads to different leaves.
+///
+/// Each thread plays out with its own clone of the simulate strategy and its
+/// own random number generator, so results are not reproducible from the seed.
+/// The clones are merged back into the search's strategy afterwards (see
+/// `SimulateStrategy::merge`). Playouts read the global statistics, such as
+/// MAST's, from a copy each thread refreshes every few iterations. Easy move
+/// checks are skipped while the threads run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeParallel {
+    pub threads: usize,
+    pub virtual_loss: u32,
+}
+
+impl Default for TreeParallel {
+    fn default() -> Self {
+        Self {
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            virtual_loss: 1,
+        }
+    }
+}
+
+impl TreeParallel {
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn virtual_loss(mut self, virtual_loss: u32) -> Self {
+        self.virtual_loss = virtual_loss;
+        self
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.threads > 0 {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidTreeParallel(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Select `size` leaves before evaluating any of them, and evaluate them
+/// together with `SimulateStrategy::evaluate_batch`, as a neural network
+/// evaluator wants. As with `TreeParallel`, each edge on the path to a pending
+/// leaf carries `virtual_loss` virtual visits scored as losses, so that the
+/// leaves of a batch differ. Leaves which the strategy does not evaluate are
+/// played out as usual.
+///
+/// The search stops at its budgets, for an easy move, or by the clock only
+/// between batches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalBatch {
+    pub size: usize,
+    pub virtual_loss: u32,
+}
+
+impl Default for EvalBatch {
+    fn default() -> Self {
+        Self {
+            size: 8,
+            virtual_loss: 1,
+        }
+    }
+}
+
+impl EvalBatch {
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn virtual_loss(mut self, virtual_loss: u32) -> Self {
+        self.virtual_loss = virtual_loss;
+        self
+    }
+
+    fn validate(&self, tree_parallel: bool) -> Result<(), ConfigError> {
+        if self.size > 0 && !tree_parallel {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidEvalBatch(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// The deepest UCD adaptation accepted. Each level multiplies the cost of
+// scoring a child by the branching factor.
+pub const MAX_UCD_DEPTH: u32 = 3;
+
+/// Upper Confidence bounds for rooted Directed acyclic graphs (UCD; Saffidine,
+/// Cazenave and Méhat, 2012). With transpositions a node may be reached from
+/// several parents, but each incoming edge only counts the visits made through
+/// it. UCD instead scores an edge by the statistics of the edges below it, up
+/// to `d1` levels down for the mean, `d2` for the edge's visits and `d3` for
+/// the parent's visits (the sum over its edges), so that what was learned
+/// about a node through any parent is shared. At depth zero this is UCT.
+///
+/// ```text
+/// mean_d(e) = sum(mean_{d-1}(f) * n_{d-1}(f)) / sum(n_{d-1}(f))
+/// n_d(e)    = sum(n_{d-1}(f))
+/// ```
+///
+/// where `f` ranges over the edges of the child of `e`. Edges into a child
+/// which is not expanded, or whose edges are all unvisited, keep their own
+/// statistics. Used by `select::Ucb1` when `SearchConfig::ucd` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ucd {
+    pub d1: u32,
+    pub d2: u32,
+    pub d3: u32,
+}
+
+impl Default for Ucd {
+    fn default() -> Self {
+        Self {
+            d1: 1,
+            d2: 1,
+            d3: 1,
+        }
+    }
+}
+
+impl Ucd {
+    pub fn new(d1: u32, d2: u32, d3: u32) -> Self {
+        Self { d1, d2, d3 }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if [self.d1, self.d2, self.d3]
+            .iter()
+            .all(|depth| *depth <= MAX_UCD_DEPTH)
+        {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidUcd(*self))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Sequential Halving at the root (Karnin, Koren and Somekh, 2013), as used
+/// for the root of SHOT (Cazenave, 2014). The iteration `budget`, by default
+/// `max_iterations`, is split evenly over `ceil(log2(K))` rounds for `K` root
+/// children. Each round the remaining children are selected in turn, equally
+/// often, after which the better half by mean score is kept. Below the root
+/// the select strategy is used as usual.
+///
+/// This spends less of the budget confirming the best child than UCB does, in
+/// exchange for finding it more reliably. The final action is the best scoring
+/// child still remaining, whatever the `final_action` strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SequentialHalving {
+    pub budget: Option<usize>,
+}
+
+impl SequentialHalving {
+    pub fn budget(mut self, budget: usize) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// What to do when the tree reaches `SearchConfig::max_nodes`. Either way no
+/// node is added beyond the limit: a selection which would create one stops at
+/// its parent instead, so the search carries on refining the estimates of the
+/// nodes it has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NodeRecycling {
+    // Keep the tree as it is
+    Stop,
+    // Free the least visited subtrees, down to about half of `max_nodes`, so
+    // that the tree can keep growing where the search is concentrated. Their
+    // edge statistics are kept. With `tree_parallel` the tree is only pruned
+    // when no iteration is in flight.
+    #[default]
+    Prune,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 pub trait Strategy<G: Game>: Clone + Sync + Send + Default {
     type Select: select::SelectStrategy<G>;
     type Simulate: simulate::SimulateStrategy<G>;
-    type Backprop: backprop::BackpropStrategy;
+    type Backprop: backprop::BackpropStrategy<G>;
     type FinalAction: select::SelectStrategy<G>;
 
     fn friendly_name() -> String {
@@ -67,7 +914,98 @@ where
     pub max_playout_depth: usize,
     pub max_iterations: usize,
     pub max_time: std::time::Duration,
+    // Budgets in work done rather than iterations: nodes added to the tree, and
+    // actions applied in the tree and in playouts. These only take effect once
+    // the root is expanded, so that there is always an action to return.
+    pub max_expansions: usize,
+    pub max_applied_actions: usize,
     pub use_transpositions: bool,
+    // Which existing nodes a transposition may link to. See
+    // `TranspositionLinking`.
+    pub transposition_linking: TranspositionLinking,
+    // The size of the transposition table and which entries are replaced once
+    // it is full. Unbounded by default. See `TableBounds`.
+    pub table_bounds: TableBounds,
+    // Whether transpositions are matched by hash alone, or also by comparing
+    // states to detect collisions. See `TableVerification`.
+    pub table_verification: TableVerification,
+    // Share the statistics of transposed nodes between their parents during
+    // selection. Disabled by default. See `Ucd`.
+    pub ucd: Option<Ucd>,
+    // Decay factor applied to the MAST and GRAVE statistics between calls to
+    // `choose_action`. A value of 1.0 retains all statistics.
+    pub stats_decay: f64,
+    // Solve positions exactly instead of simulating when the game estimates no
+    // more than this many moves remain. Zero disables the solver.
+    pub solve_threshold: usize,
+    // The total number of pseudo-visits distributed over the root's edges by
+    // `TreeSearch::warm_start`.
+    pub warm_start_visits: u32,
+    // Return early when one root action dominates. Disabled by default.
+    pub easy_move: Option<EasyMove>,
+    // Allot the time for each search from a game clock. Disabled by default.
+    // See `TimeControl`.
+    pub time_control: Option<TimeControl>,
+    // Divide the iterations over the root's children in halving rounds, rather
+    // than by the select strategy. Disabled by default. See
+    // `SequentialHalving`.
+    pub sequential_halving: Option<SequentialHalving>,
+    // When the most visited and best scoring root children disagree at the end
+    // of the search, continue for up to this fraction of the iterations spent
+    // until they agree. Zero disables the second stage.
+    pub disagreement_budget: f64,
+    // Regenerate the actions at each expanded node on the way down and repair
+    // its edges. Needed when a node can be reached in states with different
+    // legal actions, as with transpositions in games with history-dependent
+    // rules.
+    pub revalidate_edges: bool,
+    // Rescale the exploitation terms of the UCB family to [-1, 1] using the
+    // range of utilities seen so far in the search, so that one exploration
+    // constant behaves comparably across games with different utility ranges.
+    pub normalize_scores: bool,
+    // Resign or claim the win when the root score stays past a threshold.
+    // Disabled by default.
+    pub adjudication: Option<Adjudication>,
+    // Keep the children of the root and the principal variation ordered by
+    // visits after each search, and refresh the root's order for easy move
+    // checks, so that analysis output need not sort them again.
+    pub sort_children: bool,
+    // Record a sample of the playouts for analysis. Disabled by default.
+    pub record_trials: Option<TrialRecording>,
+    // Keep the relevant subtree between searches. Disabled by default.
+    pub reuse_tree: Option<TreeReuse>,
+    // Declare the game zero-sum. Debug builds then check that the utilities
+    // of every trial sum to zero, since strategies which read a single
+    // player's score rely on it to model the opponents.
+    pub zero_sum: bool,
+    // Tune the expand threshold to a node budget during the search. Disabled
+    // by default.
+    pub auto_expand: Option<AutoExpand>,
+    // Run the iterations on several threads sharing the tree. Disabled by
+    // default.
+    pub tree_parallel: Option<TreeParallel>,
+    // Bound the size of the tree for long searches, and what to do on reaching
+    // it. Unbounded by default.
+    pub max_nodes: usize,
+    pub node_recycling: NodeRecycling,
+    // Play book moves without searching. Disabled by default.
+    pub opening_book: Option<book::BookMoves<G::A>>,
+    // Report the progress of the search to a callback. Disabled by default.
+    // See `OnProgress`.
+    pub on_progress: Option<OnProgress<G::A>>,
+    // Record the seed and iterations of each search for replay. Disabled by
+    // default. See `SearchTrace`.
+    pub record_trace: bool,
+    // Vary the exploration constant of the select strategy, or the epsilon of
+    // the simulate strategy, from move to move. Disabled by default. See
+    // `Schedule`.
+    pub exploration_schedule: Option<Schedule>,
+    pub epsilon_schedule: Option<Schedule>,
+    // Only search the root actions for which this returns true. Disabled by
+    // default. See `restrict`.
+    pub root_filter: Option<RootFilter<G>>,
+    // Evaluate leaves in batches. Disabled by default. See `EvalBatch`.
+    pub eval_batch: Option<EvalBatch>,
     pub rng: SmallRng,
     pub verbose: bool,
     pub name: String,
@@ -89,7 +1027,38 @@ where
             max_playout_depth: usize::MAX,
             max_iterations: usize::MAX,
             max_time: Default::default(),
+            max_expansions: usize::MAX,
+            max_applied_actions: usize::MAX,
             use_transpositions: false,
+            transposition_linking: TranspositionLinking::default(),
+            table_bounds: TableBounds::default(),
+            table_verification: TableVerification::default(),
+            ucd: None,
+            stats_decay: 1.,
+            solve_threshold: 0,
+            warm_start_visits: 100,
+            easy_move: None,
+            time_control: None,
+            sequential_halving: None,
+            disagreement_budget: 0.,
+            revalidate_edges: false,
+            normalize_scores: false,
+            adjudication: None,
+            sort_children: false,
+            record_trials: None,
+            reuse_tree: None,
+            zero_sum: false,
+            auto_expand: None,
+            tree_parallel: None,
+            max_nodes: usize::MAX,
+            node_recycling: NodeRecycling::default(),
+            opening_book: None,
+            on_progress: None,
+            record_trace: false,
+            exploration_schedule: None,
+            epsilon_schedule: None,
+            root_filter: None,
+            eval_batch: None,
             rng: SmallRng::from_entropy(),
             verbose: false,
             name: format!("mcts[{}]", S::friendly_name()),
@@ -156,11 +1125,178 @@ where
         }
     }
 
+    pub fn max_expansions(mut self, max_expansions: usize) -> Self {
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    pub fn max_applied_actions(mut self, max_applied_actions: usize) -> Self {
+        self.max_applied_actions = max_applied_actions;
+        self
+    }
+
     pub fn use_transpositions(mut self, use_transpositions: bool) -> Self {
         self.use_transpositions = use_transpositions;
         self
     }
 
+    pub fn transposition_linking(mut self, transposition_linking: TranspositionLinking) -> Self {
+        self.transposition_linking = transposition_linking;
+        self
+    }
+
+    pub fn table_bounds(mut self, table_bounds: TableBounds) -> Self {
+        self.table_bounds = table_bounds;
+        self
+    }
+
+    pub fn table_verification(mut self, table_verification: TableVerification) -> Self {
+        self.table_verification = table_verification;
+        self
+    }
+
+    pub fn stats_decay(mut self, stats_decay: f64) -> Self {
+        self.stats_decay = stats_decay;
+        self
+    }
+
+    pub fn solve_threshold(mut self, solve_threshold: usize) -> Self {
+        self.solve_threshold = solve_threshold;
+        self
+    }
+
+    pub fn warm_start_visits(mut self, warm_start_visits: u32) -> Self {
+        self.warm_start_visits = warm_start_visits;
+        self
+    }
+
+    pub fn easy_move(mut self, easy_move: EasyMove) -> Self {
+        self.easy_move = Some(easy_move);
+        self
+    }
+
+    pub fn time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_control = Some(time_control);
+        self.max_iterations(usize::MAX)
+    }
+
+    pub fn sequential_halving(mut self, halving: SequentialHalving) -> Self {
+        self.sequential_halving = Some(halving);
+        self
+    }
+
+    pub fn ucd(mut self, ucd: Ucd) -> Self {
+        self.ucd = Some(ucd);
+        self
+    }
+
+    /// Call `callback` with a snapshot of the search every `interval`
+    /// iterations. See `OnProgress`.
+    pub fn on_progress(
+        mut self,
+        interval: usize,
+        callback: impl Fn(&Progress<G::A>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(OnProgress {
+            interval,
+            callback: std::sync::Arc::new(callback),
+        });
+        self
+    }
+
+    pub fn record_trace(mut self, record_trace: bool) -> Self {
+        self.record_trace = record_trace;
+        self
+    }
+
+    pub fn exploration_schedule(mut self, schedule: Schedule) -> Self {
+        self.exploration_schedule = Some(schedule);
+        self
+    }
+
+    pub fn epsilon_schedule(mut self, schedule: Schedule) -> Self {
+        self.epsilon_schedule = Some(schedule);
+        self
+    }
+
+    pub fn root_filter(
+        mut self,
+        filter: impl Fn(&G::S, &G::A) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.root_filter = Some(std::sync::Arc::new(filter));
+        self
+    }
+
+    pub fn opening_book(mut self, book: book::BookMoves<G::A>) -> Self {
+        self.opening_book = Some(book);
+        self
+    }
+
+    pub fn disagreement_budget(mut self, disagreement_budget: f64) -> Self {
+        self.disagreement_budget = disagreement_budget;
+        self
+    }
+
+    pub fn sort_children(mut self, sort_children: bool) -> Self {
+        self.sort_children = sort_children;
+        self
+    }
+
+    pub fn revalidate_edges(mut self, revalidate_edges: bool) -> Self {
+        self.revalidate_edges = revalidate_edges;
+        self
+    }
+
+    pub fn normalize_scores(mut self, normalize_scores: bool) -> Self {
+        self.normalize_scores = normalize_scores;
+        self
+    }
+
+    pub fn adjudication(mut self, adjudication: Adjudication) -> Self {
+        self.adjudication = Some(adjudication);
+        self
+    }
+
+    pub fn record_trials(mut self, record_trials: TrialRecording) -> Self {
+        self.record_trials = Some(record_trials);
+        self
+    }
+
+    pub fn reuse_tree(mut self, reuse_tree: TreeReuse) -> Self {
+        self.reuse_tree = Some(reuse_tree);
+        self
+    }
+
+    pub fn zero_sum(mut self, zero_sum: bool) -> Self {
+        self.zero_sum = zero_sum;
+        self
+    }
+
+    pub fn auto_expand(mut self, auto_expand: AutoExpand) -> Self {
+        self.auto_expand = Some(auto_expand);
+        self
+    }
+
+    pub fn tree_parallel(mut self, tree_parallel: TreeParallel) -> Self {
+        self.tree_parallel = Some(tree_parallel);
+        self
+    }
+
+    pub fn eval_batch(mut self, eval_batch: EvalBatch) -> Self {
+        self.eval_batch = Some(eval_batch);
+        self
+    }
+
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    pub fn node_recycling(mut self, node_recycling: NodeRecycling) -> Self {
+        self.node_recycling = node_recycling;
+        self
+    }
+
     pub fn name(mut self, name: &str) -> Self {
         self.name = name.to_string();
         self
@@ -180,4 +1316,263 @@ where
         self.verbose = verbose;
         self
     }
+
+    /// Apply the hints suggested by a game on top of the current settings.
+    pub fn hints(mut self, hints: ConfigHints) -> Self {
+        use select::SelectStrategy;
+
+        if let Some(scale) = hints.exploration_scale {
+            self.select.scale_exploration(scale);
+        }
+        if let Some(max_playout_depth) = hints.max_playout_depth {
+            self.max_playout_depth = max_playout_depth;
+        }
+        if let Some(expand_threshold) = hints.expand_threshold {
+            self.expand_threshold = expand_threshold;
+        }
+        self
+    }
+
+    /// The strategy's default configuration adjusted by the game's
+    /// `recommended_config_hints`.
+    pub fn default_for() -> Self {
+        S::config().hints(G::recommended_config_hints())
+    }
+
+    /// Apply a named budget and set of enhancements. See `Preset`.
+    pub fn preset(self, preset: Preset) -> Self {
+        match preset {
+            Preset::Fast => self.max_iterations(1_000).easy_move(EasyMove::default()),
+            Preset::Balanced => self.max_iterations(10_000).easy_move(EasyMove::default()),
+            Preset::Strong => self
+                .max_iterations(100_000)
+                .disagreement_budget(0.25)
+                .use_transpositions(G::supports_transpositions()),
+        }
+    }
+
+    /// Check the configuration for inconsistent settings.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        use backprop::BackpropStrategy;
+        use select::SelectStrategy;
+        use simulate::SimulateStrategy;
+
+        // With a time limit the iteration count is not a meaningful bound.
+        if self.max_time == std::time::Duration::default()
+            && self.time_control.is_none()
+            && self.max_iterations <= self.expand_threshold as usize
+        {
+            return Err(ConfigError::ExpandThresholdTooHigh {
+                expand_threshold: self.expand_threshold,
+                max_iterations: self.max_iterations,
+            });
+        }
+        if self.max_playout_depth == 0 {
+            return Err(ConfigError::ZeroPlayoutDepth);
+        }
+        if !(0. ..=1.).contains(&self.stats_decay) {
+            return Err(ConfigError::StatsDecayOutOfRange(self.stats_decay));
+        }
+        if let Some(easy_move) = &self.easy_move {
+            easy_move.validate()?;
+        }
+        if let Some(time_control) = &self.time_control {
+            time_control.validate()?;
+        }
+        if let Some(adjudication) = &self.adjudication {
+            adjudication.validate()?;
+        }
+        if let Some(recording) = &self.record_trials {
+            recording.validate()?;
+        }
+        if let Some(reuse) = &self.reuse_tree {
+            reuse.validate()?;
+        }
+        if let Some(auto) = &self.auto_expand {
+            auto.validate(self.expand_threshold)?;
+        }
+        if let Some(parallel) = &self.tree_parallel {
+            parallel.validate()?;
+        }
+        if let Some(batch) = &self.eval_batch {
+            batch.validate(self.tree_parallel.is_some())?;
+        }
+        if let Some(ucd) = &self.ucd {
+            ucd.validate()?;
+        }
+        if let Some(halving) = &self.sequential_halving {
+            if halving.budget.unwrap_or(self.max_iterations) == usize::MAX {
+                return Err(ConfigError::UnboundedHalving);
+            }
+        }
+        if let Some(book) = &self.opening_book {
+            book.validate()?;
+        }
+        if self.on_progress.as_ref().is_some_and(|o| o.interval == 0) {
+            return Err(ConfigError::ZeroProgressInterval);
+        }
+        if self.table_bounds.capacity == 0 {
+            return Err(ConfigError::ZeroTableCapacity);
+        }
+        self.q_init.validate()?;
+        if self.max_nodes < 2 {
+            return Err(ConfigError::MaxNodesTooSmall(self.max_nodes));
+        }
+        if self.disagreement_budget < 0. {
+            return Err(ConfigError::NegativeDisagreementBudget(
+                self.disagreement_budget,
+            ));
+        }
+        self.select.validate()?;
+        self.simulate.validate()?;
+        self.backprop.validate()?;
+        self.final_action.validate()
+    }
+
+    /// Finish the builder, validating the result.
+    pub fn build(self) -> Result<Self, ConfigError> {
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::TicTacToe;
+
+    type Config = SearchConfig<TicTacToe, strategy::Ucb1Mast>;
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::new().build().is_ok());
+        assert!(Config::new().max_iterations(2).validate().is_ok());
+        assert_eq!(
+            Config::new()
+                .expand_threshold(2)
+                .max_iterations(2)
+                .validate(),
+            Err(ConfigError::ExpandThresholdTooHigh {
+                expand_threshold: 2,
+                max_iterations: 2
+            })
+        );
+        assert!(Config::new()
+            .expand_threshold(2)
+            .max_iterations(2)
+            .max_time(std::time::Duration::from_millis(1))
+            .validate()
+            .is_ok());
+        assert_eq!(
+            Config::new().max_playout_depth(0).validate(),
+            Err(ConfigError::ZeroPlayoutDepth)
+        );
+        assert_eq!(
+            Config::new().q_init(QInit::Fixed(f64::INFINITY)).validate(),
+            Err(ConfigError::InvalidQInit(QInit::Fixed(f64::INFINITY)))
+        );
+        assert_eq!(
+            Config::new().max_nodes(1).validate(),
+            Err(ConfigError::MaxNodesTooSmall(1))
+        );
+        assert_eq!(
+            Config::new().stats_decay(1.5).validate(),
+            Err(ConfigError::StatsDecayOutOfRange(1.5))
+        );
+        assert_eq!(
+            Config::new()
+                .simulate(simulate::EpsilonGreedy::with_epsilon(-0.1))
+                .validate(),
+            Err(ConfigError::EpsilonOutOfRange(-0.1))
+        );
+        assert_eq!(
+            SearchConfig::<TicTacToe, dynamic::Dynamic>::new()
+                .backprop(dynamic::DynBackprop::new(
+                    backprop::Weighted::default().turn_limit_weight(2.)
+                ))
+                .validate(),
+            Err(ConfigError::TurnLimitWeightOutOfRange(2.))
+        );
+        let easy_move = EasyMove::default().visit_ratio(0.5);
+        assert_eq!(
+            Config::new().easy_move(easy_move).validate(),
+            Err(ConfigError::InvalidEasyMove(easy_move))
+        );
+        let auto = AutoExpand::default().max_threshold(4);
+        assert!(Config::new().auto_expand(auto).validate().is_ok());
+        assert_eq!(
+            Config::new()
+                .expand_threshold(8)
+                .auto_expand(auto)
+                .validate(),
+            Err(ConfigError::InvalidAutoExpand(auto))
+        );
+    }
+
+    #[test]
+    fn test_auto_expand_adjust() {
+        let auto = AutoExpand::default()
+            .max_nodes(1000)
+            .check_interval(100)
+            .max_threshold(64);
+        // 100 nodes per 100 iterations, with room for 500 over 1000 iterations
+        assert_eq!(auto.adjust(1, 1, 100, 500, Some(1000.)), 2);
+        assert_eq!(auto.adjust(4, 1, 100, 500, Some(1000.)), 8);
+        assert_eq!(auto.adjust(40, 1, 100, 500, Some(1000.)), 64);
+        // On target, or under it with nothing to lower
+        assert_eq!(auto.adjust(4, 1, 50, 500, Some(1000.)), 4);
+        assert_eq!(auto.adjust(1, 1, 0, 500, Some(1000.)), 1);
+        // Well under target
+        assert_eq!(auto.adjust(8, 2, 10, 500, Some(1000.)), 4);
+        assert_eq!(auto.adjust(3, 2, 10, 500, Some(1000.)), 2);
+        // Unknown budget, then the node limit reached
+        assert_eq!(auto.adjust(3, 1, 100, 500, None), 3);
+        assert_eq!(auto.adjust(3, 1, 0, 1000, None), 64);
+    }
+
+    #[test]
+    fn test_preset() {
+        use crate::games::nim::Nim;
+
+        let config = Config::new().preset(Preset::Fast);
+        assert_eq!(config.max_iterations, 1_000);
+        assert!(config.easy_move.is_some());
+        assert!(config.validate().is_ok());
+
+        let config = Config::new().preset(Preset::Strong);
+        assert_eq!(config.max_iterations, 100_000);
+        assert!(config.use_transpositions);
+
+        // Nim has no zobrist hash to share nodes with
+        let config = SearchConfig::<Nim, strategy::Ucb1>::default().preset(Preset::Strong);
+        assert!(!config.use_transpositions);
+
+        // Presets are applied on top of the strategy and game settings
+        let config = SearchConfig::<TicTacToe, strategy::Ucb1>::default_for()
+            .seed(0)
+            .preset(Preset::Balanced);
+        assert_eq!(config.expand_threshold, 1);
+
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default()
+            .config(SearchConfig::default().preset(Preset::Fast).seed(0));
+        let result = ts.search(&Default::default());
+        assert!(result.iterations <= 1_000);
+    }
+
+    #[test]
+    fn test_default_for() {
+        use crate::games::gonnect::Gonnect;
+        use crate::games::knightthrough::Knightthrough;
+
+        // Games without hints keep the strategy defaults
+        let config = SearchConfig::<TicTacToe, strategy::Ucb1>::default_for();
+        assert_eq!(config.select.exploration_constant, 2f64.sqrt());
+        assert_eq!(config.max_playout_depth, usize::MAX);
+
+        let config = SearchConfig::<Knightthrough<8, 8>, strategy::Ucb1>::default_for();
+        assert_eq!(config.select.exploration_constant, 2f64.sqrt() * 0.5);
+
+        let config = SearchConfig::<Gonnect<5>, strategy::RaveMastDm>::default_for();
+        assert_eq!(config.max_playout_depth, 100);
+    }
 }
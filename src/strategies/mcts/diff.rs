@@ -0,0 +1,246 @@
+//! Side by side comparison of two searches from the same state, for questions
+//! like "why did adding MAST make it worse on this game?"
+//!
+//! ```ignore
+//! let diff = diff::run(&state, uct_config, mast_config, 0);
+//! println!("{diff}");
+//! ```
+
+use super::index::Id;
+use super::node::NodeState;
+use super::{SearchConfig, Strategy, TreeIndex, TreeSearch};
+use crate::game::{Game, PlayerIndex};
+use crate::strategies::Search;
+use crate::util::pv_string;
+
+use std::fmt::Display;
+
+/// The statistics of one root action in each search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootEdgeDiff<A> {
+    pub action: A,
+    pub visits: [u32; 2],
+    // Fraction of the root's visits
+    pub share: [f64; 2],
+    // Expected score for the player to move at the root
+    pub score: [f64; 2],
+}
+
+#[derive(Clone, Debug)]
+pub struct TreeDiff<G: Game> {
+    pub state: G::S,
+    pub actions: [G::A; 2],
+    pub root: Vec<RootEdgeDiff<G::A>>,
+    // Total variation distance between the root visit distributions
+    pub visit_distance: f64,
+    // The first depth at which the most visited lines disagree, if they do
+    // before either runs out of explored children
+    pub divergence_depth: Option<usize>,
+    pub pv: [Vec<G::A>; 2],
+    // The length of the common prefix of the two principal variations
+    pub pv_common: usize,
+    pub num_nodes: [usize; 2],
+}
+
+/// Run both configurations from `state` with the same seed and compare the
+/// resulting trees.
+pub fn run<G, S1, S2>(
+    state: &G::S,
+    a: SearchConfig<G, S1>,
+    b: SearchConfig<G, S2>,
+    seed: u64,
+) -> TreeDiff<G>
+where
+    G: Game,
+    S1: Strategy<G>,
+    S2: Strategy<G>,
+    SearchConfig<G, S1>: Default,
+    SearchConfig<G, S2>: Default,
+{
+    let mut ts_a = TreeSearch::<G, S1>::default().config(a.seed(seed));
+    let mut ts_b = TreeSearch::<G, S2>::default().config(b.seed(seed));
+    let action_a = ts_a.choose_action(state);
+    let action_b = ts_b.choose_action(state);
+    compare(state, [action_a, action_b], &ts_a, &ts_b)
+}
+
+/// Compare two searches which have already been run from `state`.
+pub fn compare<G, S1, S2>(
+    state: &G::S,
+    actions: [G::A; 2],
+    a: &TreeSearch<G, S1>,
+    b: &TreeSearch<G, S2>,
+) -> TreeDiff<G>
+where
+    G: Game,
+    S1: Strategy<G>,
+    S2: Strategy<G>,
+    SearchConfig<G, S1>: Default,
+    SearchConfig<G, S2>: Default,
+{
+    let player = G::player_to_move(state).to_index();
+    let root = root_diff::<G>(player, [(&a.index, a.root_id), (&b.index, b.root_id)]);
+    let visit_distance = root
+        .iter()
+        .map(|edge| (edge.share[0] - edge.share[1]).abs())
+        .sum::<f64>()
+        / 2.;
+
+    let pv = [a.principle_variation(), b.principle_variation()];
+    let pv_common = pv[0]
+        .iter()
+        .zip(pv[1].iter())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    TreeDiff {
+        state: state.clone(),
+        actions,
+        root,
+        visit_distance,
+        divergence_depth: divergence_depth::<G>(&a.index, a.root_id, &b.index, b.root_id),
+        pv,
+        pv_common,
+        num_nodes: [a.index.len(), b.index.len()],
+    }
+}
+
+fn root_diff<G: Game>(
+    player: usize,
+    trees: [(&TreeIndex<G::A>, Id); 2],
+) -> Vec<RootEdgeDiff<G::A>> {
+    let mut root: Vec<RootEdgeDiff<G::A>> = vec![];
+    for (i, (index, root_id)) in trees.into_iter().enumerate() {
+        let NodeState::Expanded(edges) = &index.get(root_id).state else {
+            continue;
+        };
+        let total = edges
+            .iter()
+            .map(|edge| edge.stats.num_visits)
+            .sum::<u32>()
+            .max(1);
+        for edge in edges {
+            let entry = match root.iter_mut().position(|e| e.action == edge.action) {
+                Some(pos) => &mut root[pos],
+                None => {
+                    root.push(RootEdgeDiff {
+                        action: edge.action.clone(),
+                        visits: [0; 2],
+                        share: [0.; 2],
+                        score: [0.; 2],
+                    });
+                    root.last_mut().unwrap()
+                }
+            };
+            entry.visits[i] = edge.stats.num_visits;
+            entry.share[i] = edge.stats.num_visits as f64 / total as f64;
+            entry.score[i] = edge.stats.expected_score(player);
+        }
+    }
+    root.sort_by_key(|edge| std::cmp::Reverse(edge.visits[0].max(edge.visits[1])));
+    root
+}
+
+fn most_visited<A: crate::game::Action>(index: &TreeIndex<A>, node_id: Id) -> Option<(A, Id)> {
+    let NodeState::Expanded(edges) = &index.get(node_id).state else {
+        return None;
+    };
+    edges
+        .iter()
+        .filter(|edge| edge.is_explored())
+        .max_by_key(|edge| edge.stats.num_visits)
+        .map(|edge| (edge.action.clone(), edge.node_id.unwrap()))
+}
+
+fn divergence_depth<G: Game>(
+    index_a: &TreeIndex<G::A>,
+    mut id_a: Id,
+    index_b: &TreeIndex<G::A>,
+    mut id_b: Id,
+) -> Option<usize> {
+    let mut depth = 0;
+    loop {
+        let (action_a, child_a) = most_visited(index_a, id_a)?;
+        let (action_b, child_b) = most_visited(index_b, id_b)?;
+        if action_a != action_b {
+            return Some(depth);
+        }
+        id_a = child_a;
+        id_b = child_b;
+        depth += 1;
+    }
+}
+
+impl<G: Game> Display for TreeDiff<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let notation = |action: &G::A| G::notation(&self.state, action);
+        writeln!(
+            f,
+            "chosen: {} vs {}",
+            notation(&self.actions[0]),
+            notation(&self.actions[1])
+        )?;
+        writeln!(
+            f,
+            "nodes: {} vs {}, root visit distance: {:.3}",
+            self.num_nodes[0], self.num_nodes[1], self.visit_distance
+        )?;
+        match self.divergence_depth {
+            Some(depth) => writeln!(f, "most visited lines diverge at depth {depth}")?,
+            None => writeln!(f, "most visited lines agree")?,
+        }
+        writeln!(f, "PV a: {}", pv_string::<G>(&self.pv[0], &self.state))?;
+        writeln!(f, "PV b: {}", pv_string::<G>(&self.pv[1], &self.state))?;
+        writeln!(f, "PVs share {} moves", self.pv_common)?;
+        for edge in &self.root {
+            writeln!(
+                f,
+                "{:>12} {:>7} {:>6.1}% {:>7.3} | {:>7} {:>6.1}% {:>7.3}",
+                notation(&edge.action),
+                edge.visits[0],
+                100. * edge.share[0],
+                edge.score[0],
+                edge.visits[1],
+                100. * edge.share[1],
+                edge.score[1]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, TicTacToe};
+    use crate::strategies::mcts::{select, strategy};
+
+    #[test]
+    fn test_diff() {
+        let state = HashedPosition::new();
+        let config = || SearchConfig::<TicTacToe, strategy::Ucb1>::default().max_iterations(500);
+
+        // The same configuration and seed produce identical trees
+        let diff = run(&state, config(), config(), 7);
+        assert_eq!(diff.actions[0], diff.actions[1]);
+        assert_eq!(diff.visit_distance, 0.);
+        assert_eq!(diff.divergence_depth, None);
+        assert_eq!(diff.pv_common, diff.pv[0].len());
+        assert_eq!(diff.root.len(), 9);
+        assert!(diff
+            .root
+            .iter()
+            .all(|edge| edge.visits[0] == edge.visits[1]));
+
+        let diff = run(
+            &state,
+            config(),
+            SearchConfig::<TicTacToe, strategy::Ucb1Mast>::default()
+                .max_iterations(500)
+                .select(select::Ucb1::with_c(0.1)),
+            7,
+        );
+        assert!(diff.visit_distance > 0.);
+        assert!(diff.to_string().contains("root visit distance"));
+    }
+}
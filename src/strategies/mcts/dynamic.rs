@@ -0,0 +1,571 @@
+//! Runtime composition of search strategies.
+//!
+//! `Strategy` fixes the select, simulate, backprop and final action
+//! components at compile time, which is the right choice for performance but
+//! makes every combination a new type. The `Dynamic` strategy instead boxes
+//! each component, so that a configuration can be assembled at runtime (e.g.,
+//! from command line arguments) at the cost of a virtual call per component
+//! invocation.
+//!
+//! ```ignore
+//! let config = SearchConfig::<G, Dynamic>::new()
+//!     .select(DynSelect::new(select::Ucb1::with_c(0.5)))
+//!     .simulate(DynSimulate::new(simulate::DecisiveMove::<G>::new()));
+//! let ts = TreeSearch::<G, Dynamic>::new().config(config);
+//! ```
+//!
+//! A static configuration can be erased with `SearchConfig::into_dynamic`, and
+//! the presets in `strategy` are available by name through `preset`.
+
+use super::backprop::BackpropStrategy;
+use super::index::Id;
+use super::node::{Edge, NodeStats};
+use super::select::{SelectContext, SelectStrategy};
+use super::simulate::{SimulateStrategy, Trial};
+use super::stack::NodeStack;
+use super::strategy::match_preset;
+use super::*;
+use crate::game::Game;
+
+use rand::rngs::SmallRng;
+use std::any::Any;
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Object safe shim over `SelectStrategy`. The associated `Score` and `Aux`
+// types are not object safe, so only whole selections are forwarded.
+trait ObjectSelect<G: Game>: Sync + Send {
+    fn best_child(&mut self, ctx: &SelectContext<'_, G>, rng: &mut SmallRng) -> usize;
+    fn backprop_flags(&self) -> BackpropFlags;
+    fn validate(&self) -> Result<(), ConfigError>;
+    fn grave_threshold(&self) -> Option<u32>;
+    fn scale_exploration(&mut self, scale: f64);
+    fn set_exploration(&mut self, value: f64);
+    fn box_clone(&self) -> Box<dyn ObjectSelect<G>>;
+}
+
+impl<G: Game, S: SelectStrategy<G> + 'static> ObjectSelect<G> for S {
+    fn best_child(&mut self, ctx: &SelectContext<'_, G>, rng: &mut SmallRng) -> usize {
+        SelectStrategy::best_child(self, ctx, rng)
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        SelectStrategy::backprop_flags(self)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        SelectStrategy::validate(self)
+    }
+
+    fn grave_threshold(&self) -> Option<u32> {
+        SelectStrategy::grave_threshold(self)
+    }
+
+    fn scale_exploration(&mut self, scale: f64) {
+        SelectStrategy::scale_exploration(self, scale)
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        SelectStrategy::set_exploration(self, value)
+    }
+
+    fn box_clone(&self) -> Box<dyn ObjectSelect<G>> {
+        Box::new(self.clone())
+    }
+}
+
+/// A boxed `SelectStrategy`, used for both selection and final action
+/// selection by `Dynamic`. Only `best_child` is forwarded to the inner
+/// strategy, so wrappers such as `select::EpsilonGreedy` which score children
+/// through their inner strategy must be applied before erasing, not around a
+/// `DynSelect`.
+pub struct DynSelect<G: Game>(Box<dyn ObjectSelect<G>>);
+
+impl<G: Game> DynSelect<G> {
+    pub fn new<S: SelectStrategy<G> + 'static>(select: S) -> Self {
+        Self(Box::new(select))
+    }
+}
+
+impl<G: Game> Clone for DynSelect<G> {
+    fn clone(&self) -> Self {
+        Self(self.0.box_clone())
+    }
+}
+
+impl<G: Game> Default for DynSelect<G> {
+    fn default() -> Self {
+        Self::new(select::Ucb1::default())
+    }
+}
+
+impl<G: Game> SelectStrategy<G> for DynSelect<G> {
+    type Score = f64;
+    type Aux = ();
+
+    fn setup(&mut self, _: &SelectContext<'_, G>) -> Self::Aux {}
+
+    fn best_child(&mut self, ctx: &SelectContext<'_, G>, rng: &mut SmallRng) -> usize {
+        self.0.best_child(ctx, rng)
+    }
+
+    fn score_child(&self, _: &SelectContext<'_, G>, _: Id, _: &Edge<G::A>, _: ()) -> f64 {
+        unreachable!("DynSelect only forwards best_child")
+    }
+
+    fn unvisited_value(&self, _: &SelectContext<'_, G>, _: ()) -> f64 {
+        unreachable!("DynSelect only forwards best_child")
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.0.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.0.validate()
+    }
+
+    fn grave_threshold(&self) -> Option<u32> {
+        self.0.grave_threshold()
+    }
+
+    fn scale_exploration(&mut self, scale: f64) {
+        self.0.scale_exploration(scale)
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.0.set_exploration(value)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+trait ObjectSimulate<G: Game>: Sync + Send {
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>>;
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>>;
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>>;
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A;
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G>;
+    fn backprop_flags(&self) -> BackpropFlags;
+    fn validate(&self) -> Result<(), ConfigError>;
+    fn prepare(&mut self, init: &G::S);
+    fn set_epsilon(&mut self, epsilon: f64);
+    fn merge(&mut self, other: Box<dyn ObjectSimulate<G>>);
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn box_clone(&self) -> Box<dyn ObjectSimulate<G>>;
+}
+
+impl<G: Game, S: SimulateStrategy<G> + 'static> ObjectSimulate<G> for S {
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        SimulateStrategy::evaluate(self, state, stats, player)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        SimulateStrategy::evaluate_batch(self, states, stats, player)
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        SimulateStrategy::priors(self, state, actions)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        SimulateStrategy::select_move(self, state, available, stats, player, rng)
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        SimulateStrategy::playout(self, state, max_playout_depth, stats, player, rng)
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        SimulateStrategy::backprop_flags(self)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        SimulateStrategy::validate(self)
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        SimulateStrategy::prepare(self, init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        SimulateStrategy::set_epsilon(self, epsilon)
+    }
+
+    fn merge(&mut self, other: Box<dyn ObjectSimulate<G>>) {
+        // Clones have the same type, so this only fails for mismatched boxes
+        if let Ok(other) = other.into_any().downcast::<S>() {
+            SimulateStrategy::merge(self, *other)
+        }
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn box_clone(&self) -> Box<dyn ObjectSimulate<G>> {
+        Box::new(self.clone())
+    }
+}
+
+/// A boxed `SimulateStrategy`. All methods are forwarded, so a `DynSimulate`
+/// may also be wrapped, e.g. by `simulate::DecisiveMove`.
+pub struct DynSimulate<G: Game>(Box<dyn ObjectSimulate<G>>);
+
+impl<G: Game> DynSimulate<G> {
+    pub fn new<S: SimulateStrategy<G> + 'static>(simulate: S) -> Self {
+        Self(Box::new(simulate))
+    }
+}
+
+impl<G: Game> Clone for DynSimulate<G> {
+    fn clone(&self) -> Self {
+        Self(self.0.box_clone())
+    }
+}
+
+impl<G: Game> Default for DynSimulate<G> {
+    fn default() -> Self {
+        Self::new(simulate::Uniform)
+    }
+}
+
+impl<G: Game> SimulateStrategy<G> for DynSimulate<G> {
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        self.0.evaluate(state, stats, player)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        self.0.evaluate_batch(states, stats, player)
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        self.0.priors(state, actions)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        self.0.select_move(state, available, stats, player, rng)
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        self.0.playout(state, max_playout_depth, stats, player, rng)
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.0.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.0.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.0.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.0.set_epsilon(epsilon)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.0.merge(other.0)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+trait ObjectBackprop<G: Game>: Sync + Send {
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &self,
+        stack: &NodeStack<G::A>,
+        global: &mut TreeStats<G>,
+        index: &mut TreeIndex<G::A>,
+        root_stats: &mut NodeStats,
+        trial: Trial<G>,
+        player: usize,
+        flags: BackpropFlags,
+    );
+    fn validate(&self) -> Result<(), ConfigError>;
+    fn box_clone(&self) -> Box<dyn ObjectBackprop<G>>;
+}
+
+impl<G: Game, B: BackpropStrategy<G> + 'static> ObjectBackprop<G> for B {
+    fn update(
+        &self,
+        stack: &NodeStack<G::A>,
+        global: &mut TreeStats<G>,
+        index: &mut TreeIndex<G::A>,
+        root_stats: &mut NodeStats,
+        trial: Trial<G>,
+        player: usize,
+        flags: BackpropFlags,
+    ) {
+        BackpropStrategy::update(self, stack, global, index, root_stats, trial, player, flags)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        BackpropStrategy::validate(self)
+    }
+
+    fn box_clone(&self) -> Box<dyn ObjectBackprop<G>> {
+        Box::new(self.clone())
+    }
+}
+
+/// A boxed `BackpropStrategy`.
+pub struct DynBackprop<G: Game>(Box<dyn ObjectBackprop<G>>);
+
+impl<G: Game> DynBackprop<G> {
+    pub fn new<B: BackpropStrategy<G> + 'static>(backprop: B) -> Self {
+        Self(Box::new(backprop))
+    }
+}
+
+impl<G: Game> Clone for DynBackprop<G> {
+    fn clone(&self) -> Self {
+        Self(self.0.box_clone())
+    }
+}
+
+impl<G: Game> Default for DynBackprop<G> {
+    fn default() -> Self {
+        Self::new(backprop::Classic)
+    }
+}
+
+impl<G: Game> BackpropStrategy<G> for DynBackprop<G> {
+    fn update(
+        &self,
+        stack: &NodeStack<G::A>,
+        global: &mut TreeStats<G>,
+        index: &mut TreeIndex<G::A>,
+        root_stats: &mut NodeStats,
+        trial: Trial<G>,
+        player: usize,
+        flags: BackpropFlags,
+    ) {
+        self.0
+            .update(stack, global, index, root_stats, trial, player, flags)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.0.validate()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A strategy whose components are chosen at runtime. Defaults to UCB1 with
+/// uniform playouts and robust child final action selection, as
+/// `strategy::Ucb1`.
+#[derive(Clone, Default)]
+pub struct Dynamic;
+
+impl<G: Game> Strategy<G> for Dynamic {
+    type Select = DynSelect<G>;
+    type Simulate = DynSimulate<G>;
+    type Backprop = DynBackprop<G>;
+    type FinalAction = DynSelect<G>;
+
+    fn friendly_name() -> String {
+        "dynamic".into()
+    }
+
+    fn config() -> SearchConfig<G, Self> {
+        SearchConfig::new().final_action(DynSelect::new(select::RobustChild))
+    }
+}
+
+impl<G, S> SearchConfig<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    S::Select: 'static,
+    S::Simulate: 'static,
+    S::Backprop: 'static,
+    S::FinalAction: 'static,
+{
+    /// Box the strategy components, keeping all other settings.
+    pub fn into_dynamic(self) -> SearchConfig<G, Dynamic> {
+        SearchConfig {
+            select: DynSelect::new(self.select),
+            simulate: DynSimulate::new(self.simulate),
+            backprop: DynBackprop::new(self.backprop),
+            final_action: DynSelect::new(self.final_action),
+            q_init: self.q_init,
+            expand_threshold: self.expand_threshold,
+            max_playout_depth: self.max_playout_depth,
+            max_iterations: self.max_iterations,
+            max_time: self.max_time,
+            max_expansions: self.max_expansions,
+            max_applied_actions: self.max_applied_actions,
+            use_transpositions: self.use_transpositions,
+            transposition_linking: self.transposition_linking,
+            table_bounds: self.table_bounds,
+            table_verification: self.table_verification,
+            ucd: self.ucd,
+            stats_decay: self.stats_decay,
+            solve_threshold: self.solve_threshold,
+            warm_start_visits: self.warm_start_visits,
+            easy_move: self.easy_move,
+            time_control: self.time_control,
+            sequential_halving: self.sequential_halving,
+            disagreement_budget: self.disagreement_budget,
+            revalidate_edges: self.revalidate_edges,
+            normalize_scores: self.normalize_scores,
+            adjudication: self.adjudication,
+            sort_children: self.sort_children,
+            record_trials: self.record_trials,
+            reuse_tree: self.reuse_tree,
+            zero_sum: self.zero_sum,
+            auto_expand: self.auto_expand,
+            tree_parallel: self.tree_parallel,
+            max_nodes: self.max_nodes,
+            node_recycling: self.node_recycling,
+            opening_book: self.opening_book,
+            on_progress: self.on_progress,
+            record_trace: self.record_trace,
+            exploration_schedule: self.exploration_schedule,
+            epsilon_schedule: self.epsilon_schedule,
+            root_filter: self.root_filter,
+            eval_batch: self.eval_batch,
+            rng: self.rng,
+            verbose: self.verbose,
+            name: self.name,
+        }
+    }
+}
+
+pub use super::strategy::PRESETS;
+
+/// The default configuration of one of the strategies in `strategy`, looked up
+/// by the same names as tournament manifests use (see `PRESETS`).
+pub fn preset<G: Game + 'static>(name: &str) -> Option<SearchConfig<G, Dynamic>> {
+    fn erase<G: Game + 'static, S: Strategy<G>>() -> SearchConfig<G, Dynamic>
+    where
+        S::Select: 'static,
+        S::Simulate: 'static,
+        S::Backprop: 'static,
+        S::FinalAction: 'static,
+    {
+        S::config().into_dynamic()
+    }
+
+    match_preset!(name, S => Some(erase::<G, S>()), _other => None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, TicTacToe};
+    use crate::strategies::Search;
+
+    fn search<S>(config: SearchConfig<TicTacToe, S>) -> (u8, TreeStats<TicTacToe>)
+    where
+        S: Strategy<TicTacToe>,
+    {
+        let mut ts = TreeSearch::<TicTacToe, S>::new().config(config);
+        let action = ts.choose_action(&HashedPosition::default());
+        (action.0, ts.stats)
+    }
+
+    #[test]
+    fn test_into_dynamic_matches_static() {
+        let config = strategy::Ucb1Mast::config().max_iterations(500).seed(7);
+        let (expected, expected_stats) = search(config.clone());
+        let (actual, actual_stats) = search(config.into_dynamic());
+        assert_eq!(actual, expected);
+        assert_eq!(actual_stats.iter_count, expected_stats.iter_count);
+        assert_eq!(actual_stats.accum_depth, expected_stats.accum_depth);
+    }
+
+    #[test]
+    fn test_runtime_composition() {
+        let config = SearchConfig::<TicTacToe, Dynamic>::new()
+            .select(DynSelect::new(select::Ucb1Tuned::default()))
+            .simulate(DynSimulate::new(simulate::DecisiveMove::<
+                TicTacToe,
+                DynSimulate<TicTacToe>,
+            >::new()))
+            .final_action(DynSelect::new(select::MaxAvgScore))
+            .max_iterations(200)
+            .seed(0);
+        assert!(config.validate().is_ok());
+        let (_, stats) = search(config);
+        assert_eq!(stats.iter_count, 200);
+    }
+
+    #[test]
+    fn test_presets() {
+        for name in ["ucb1", "ucb1_tuned_dm_mast", "rave_mast_dm"] {
+            let config = preset::<TicTacToe>(name)
+                .unwrap()
+                .max_iterations(50)
+                .seed(0);
+            assert!(config.validate().is_ok(), "{name}");
+            search(config);
+        }
+        assert!(PRESETS
+            .iter()
+            .all(|name| preset::<TicTacToe>(name).is_some()));
+        assert!(preset::<TicTacToe>("unknown").is_none());
+    }
+}
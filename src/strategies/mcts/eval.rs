@@ -0,0 +1,486 @@
+//! Searching with a learned evaluator, such as a neural network, in the style
+//! of AlphaZero. An `Evaluator` scores a state and weighs its actions in one
+//! call. The `Network` simulate strategy backs up the score of each leaf in
+//! place of a playout, and hands the weights to the edges as priors when the
+//! leaf is expanded, for `select::Puct` to search with. `strategy::AlphaZero`
+//! puts the two together:
+//!
+//! ```ignore
+//! let config = SearchConfig::<G, strategy::AlphaZero>::new()
+//!     .simulate(eval::Network::new(model))
+//!     .eval_batch(EvalBatch::default().size(32));
+//! ```
+//!
+//! With `SearchConfig::eval_batch`, leaves are collected across iterations
+//! and passed to `Evaluator::evaluate_batch` together. No network backend is
+//! bundled: implement `Evaluator` for a model to plug it in.
+
+use super::index::Id;
+use super::search::{action_priors, evaluated_trial, solvable};
+use super::simulate::{estimated_utilities, SimulateStrategy, Trial};
+use super::{EvalBatch, SearchConfig, SearchContext, Strategy, TreeSearch, TreeStats};
+use crate::game::{Game, PlayerIndex};
+
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+// Leaves whose priors are kept until they are expanded, before starting over
+const PENDING_CAPACITY: usize = 1 << 16;
+
+/// The utilities of each player at a state, and weights for its actions.
+pub type Evaluation<A> = (Vec<f64>, Priors<A>);
+
+pub type Priors<A> = Vec<(A, f32)>;
+
+// Evaluated states and their priors, by zobrist hash
+type Pending<S, A> = FxHashMap<u64, Vec<(S, Priors<A>)>>;
+
+pub trait Evaluator<G: Game>: Send + Sync {
+    /// The utilities of every player at the non-terminal `state`, as from
+    /// `Game::compute_utilities`, and weights for its legal actions. Actions
+    /// left out get no weight, and the weights need not be normalized.
+    fn evaluate(&self, state: &G::S) -> Evaluation<G::A>;
+
+    /// Evaluate several states at once, in order. Evaluators which run faster
+    /// on batches override this.
+    fn evaluate_batch(&self, states: &[G::S]) -> Vec<Evaluation<G::A>> {
+        states.iter().map(|state| self.evaluate(state)).collect()
+    }
+}
+
+impl<G, F> Evaluator<G> for F
+where
+    G: Game,
+    F: Fn(&G::S) -> Evaluation<G::A> + Send + Sync,
+{
+    fn evaluate(&self, state: &G::S) -> Evaluation<G::A> {
+        self(state)
+    }
+}
+
+/// The game's own estimates, `Game::utilities_nonterminal` and
+/// `Game::action_priors`. The default evaluator of `Network`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GameEstimates;
+
+impl<G: Game> Evaluator<G> for GameEstimates {
+    fn evaluate(&self, state: &G::S) -> Evaluation<G::A> {
+        let mut actions = Vec::new();
+        G::generate_actions(state, &mut actions);
+        let priors = action_priors::<G>(state, &actions);
+        (
+            estimated_utilities::<G>(state, G::player_to_move(state).to_index()),
+            actions.into_iter().zip(priors).collect(),
+        )
+    }
+}
+
+/// Scores each leaf with an `Evaluator` instead of playing it out, and keeps
+/// the weights it gave the leaf's actions as their priors for when the leaf
+/// is expanded. A leaf expanded without having been evaluated, such as the
+/// root of a new search, is evaluated again for its priors.
+///
+/// Clones share the evaluator.
+pub struct Network<G: Game> {
+    pub evaluator: Arc<dyn Evaluator<G>>,
+    // The priors of evaluated leaves, until they are expanded
+    pending: Pending<G::S, G::A>,
+    num_pending: usize,
+}
+
+impl<G: Game> Network<G> {
+    pub fn new(evaluator: impl Evaluator<G> + 'static) -> Self {
+        Self {
+            evaluator: Arc::new(evaluator),
+            pending: FxHashMap::default(),
+            num_pending: 0,
+        }
+    }
+
+    fn remember(&mut self, state: &G::S, priors: Priors<G::A>) {
+        if self.num_pending >= PENDING_CAPACITY {
+            self.pending.clear();
+            self.num_pending = 0;
+        }
+        self.pending
+            .entry(G::zobrist_hash(state))
+            .or_default()
+            .push((state.clone(), priors));
+        self.num_pending += 1;
+    }
+
+    fn recall(&mut self, state: &G::S) -> Option<Priors<G::A>> {
+        let pending = self.pending.get_mut(&G::zobrist_hash(state))?;
+        let i = pending.iter().position(|(pending, _)| pending == state)?;
+        self.num_pending -= 1;
+        Some(pending.swap_remove(i).1)
+    }
+}
+
+impl<G: Game> Clone for Network<G> {
+    fn clone(&self) -> Self {
+        Self {
+            evaluator: self.evaluator.clone(),
+            pending: self.pending.clone(),
+            num_pending: self.num_pending,
+        }
+    }
+}
+
+impl<G: Game> Default for Network<G> {
+    fn default() -> Self {
+        Self::new(GameEstimates)
+    }
+}
+
+impl<G: Game> SimulateStrategy<G> for Network<G> {
+    fn evaluate(&mut self, state: &G::S, _: &TreeStats<G>, _: usize) -> Option<Vec<f64>> {
+        let (utilities, priors) = self.evaluator.evaluate(state);
+        self.remember(state, priors);
+        Some(utilities)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        _: &TreeStats<G>,
+        _: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        let evaluations = self.evaluator.evaluate_batch(states);
+        debug_assert_eq!(evaluations.len(), states.len());
+        states
+            .iter()
+            .zip(evaluations)
+            .map(|(state, (utilities, priors))| {
+                self.remember(state, priors);
+                Some(utilities)
+            })
+            .collect()
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (_, pending) in other.pending {
+            for (state, priors) in pending {
+                self.remember(&state, priors);
+            }
+        }
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        let priors = self
+            .recall(state)
+            .unwrap_or_else(|| self.evaluator.evaluate(state).1);
+        let priors = priors.into_iter().collect::<FxHashMap<_, _>>();
+        Some(
+            actions
+                .iter()
+                .map(|action| priors.get(action).map_or(0., |p| *p as f64))
+                .collect(),
+        )
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Run iterations from `root_id` until `max_iterations` have been run,
+    /// counting `iterations` run before, selecting a batch of leaves before
+    /// evaluating them together. Returns the number of iterations run in all,
+    /// and whether the search stopped early for an easy move or by the clock.
+    /// Both are checked once a batch is backed up.
+    pub(crate) fn iterate_batched(
+        &mut self,
+        root_id: Id,
+        state: &G::S,
+        mut iterations: usize,
+        max_iterations: usize,
+        batch: EvalBatch,
+    ) -> (usize, bool, bool) {
+        let player = G::player_to_move(state).to_index();
+        let mut easy_move = false;
+        let mut early_stop = false;
+        let mut previous_best = None;
+        while iterations < max_iterations && !easy_move && !early_stop {
+            if self.timer.done() || self.work_budget_exhausted() || self.stop_requested() {
+                break;
+            }
+            self.recycle_nodes(state);
+
+            // Select the leaves and mark each path with virtual loss. Until the
+            // root is expanded every selection would end there.
+            let size = if self.index.get(root_id).is_leaf() {
+                1
+            } else {
+                batch.size.min(max_iterations - iterations)
+            };
+            let mut leaves = Vec::with_capacity(size);
+            let mut paths = Vec::with_capacity(size);
+            for _ in 0..size {
+                self.reset_iter();
+                let mut ctx = SearchContext::new(root_id, state.clone());
+                self.select(&mut ctx);
+                self.apply_virtual_loss(batch.virtual_loss, true);
+                leaves.push(ctx.state);
+                paths.push((
+                    std::mem::take(&mut self.stack),
+                    std::mem::take(&mut self.path),
+                ));
+            }
+
+            // Back up each result in place of its virtual loss
+            let trials = self.simulate_batch(&leaves, player);
+            for ((stack, path), trial) in paths.into_iter().zip(trials) {
+                self.reset_iter();
+                self.stack = stack;
+                self.path = path;
+                self.apply_virtual_loss(batch.virtual_loss, false);
+                self.trial = Some(trial);
+                self.finish_iteration(player);
+                iterations += 1;
+                self.report_progress(state, iterations, false);
+                early_stop |= self.check_clock(state, iterations);
+                easy_move |=
+                    !early_stop && self.check_easy_move(state, iterations, &mut previous_best);
+            }
+        }
+        (iterations, easy_move, early_stop)
+    }
+
+    /// Evaluate the leaves which the simulate strategy can evaluate in one
+    /// batch, and solve or play out the rest.
+    fn simulate_batch(&mut self, leaves: &[G::S], player: usize) -> Vec<Trial<G>> {
+        let (indices, states): (Vec<usize>, Vec<G::S>) = leaves
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf)| {
+                !G::is_terminal(leaf) && !solvable::<G>(leaf, self.config.solve_threshold)
+            })
+            .map(|(i, leaf)| (i, leaf.clone()))
+            .unzip();
+        let mut evaluated = vec![None; leaves.len()];
+        if !states.is_empty() {
+            let results = self
+                .config
+                .simulate
+                .evaluate_batch(&states, &self.stats, player);
+            for (i, utilities) in indices.into_iter().zip(results) {
+                evaluated[i] = utilities;
+            }
+        }
+        leaves
+            .iter()
+            .zip(evaluated)
+            .map(|(leaf, utilities)| match utilities {
+                Some(utilities) => evaluated_trial(leaf, utilities),
+                None => self.simulate(leaf, player),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::{strategy, ConfigError, EasyMove, TreeParallel};
+    use crate::strategies::Search;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::sync::Mutex;
+
+    // Prefers the center, and counts its calls
+    fn center(calls: Arc<AtomicUsize>) -> impl Evaluator<TicTacToe> {
+        move |state: &HashedPosition| {
+            calls.fetch_add(1, Relaxed);
+            let mut actions = Vec::new();
+            TicTacToe::generate_actions(state, &mut actions);
+            let priors = actions
+                .into_iter()
+                .map(|action| (action, if action == Move(4) { 8. } else { 1. }))
+                .collect();
+            (vec![0., 0.], priors)
+        }
+    }
+
+    #[test]
+    fn test_network() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut ts = TreeSearch::<TicTacToe, strategy::AlphaZero>::default().config(
+            SearchConfig::default()
+                .simulate(Network::new(center(calls.clone())))
+                .max_iterations(200)
+                .seed(0),
+        );
+        let result = ts.search(&HashedPosition::new());
+
+        // The priors came from the evaluator, which ran once per leaf
+        let root = ts.index.get(ts.root_id);
+        let prior = |i: usize| root.edges()[i].prior;
+        assert_eq!(prior(4), 0.5);
+        assert_eq!(prior(0), 0.0625);
+        assert!(calls.load(Relaxed) <= result.iterations);
+
+        // The game's estimates are enough to find a win
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let mut ts = TreeSearch::<TicTacToe, strategy::AlphaZero>::default()
+            .config(SearchConfig::default().max_iterations(500).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+    }
+
+    // Records the size of each batch
+    // Records the size of each batch, and 0 for a state evaluated alone
+    struct Batches(Mutex<Vec<usize>>);
+
+    impl Evaluator<TicTacToe> for Batches {
+        fn evaluate(&self, state: &HashedPosition) -> Evaluation<Move> {
+            self.0.lock().unwrap().push(0);
+            Evaluator::<TicTacToe>::evaluate(&GameEstimates, state)
+        }
+
+        fn evaluate_batch(&self, states: &[HashedPosition]) -> Vec<Evaluation<Move>> {
+            self.0.lock().unwrap().push(states.len());
+            states
+                .iter()
+                .map(|state| Evaluator::<TicTacToe>::evaluate(&GameEstimates, state))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_eval_batch() {
+        let batches = Arc::new(Batches(Mutex::new(vec![])));
+        let mut ts = TreeSearch::<TicTacToe, strategy::AlphaZero>::default().config(
+            SearchConfig::default()
+                .simulate(Network {
+                    evaluator: batches.clone(),
+                    ..Default::default()
+                })
+                .eval_batch(EvalBatch::default().size(8).virtual_loss(2))
+                .max_iterations(100)
+                .seed(0),
+        );
+        let result = ts.search(&HashedPosition::new());
+        assert_eq!(result.iterations, 100);
+        let sizes = batches.0.lock().unwrap().clone();
+        assert!(sizes.iter().all(|size| (1..=8).contains(size)));
+        assert!(sizes.iter().sum::<usize>() <= 100);
+        assert_eq!(sizes[0], 1);
+        assert!(sizes.contains(&8));
+
+        // Every virtual visit was removed again
+        let root = ts.index.get(ts.root_id);
+        assert!(root
+            .edges()
+            .iter()
+            .all(|edge| edge.stats.num_visits_virtual.load(Relaxed) == 0));
+        let visits = root.edges().iter().map(|e| e.stats.num_visits).sum::<u32>();
+        assert_eq!(visits + 1, 100);
+
+        let config = SearchConfig::<TicTacToe, strategy::AlphaZero>::default;
+        let batch = EvalBatch::default().size(0);
+        assert_eq!(
+            config().eval_batch(batch).validate(),
+            Err(ConfigError::InvalidEvalBatch(batch))
+        );
+        assert_eq!(
+            config()
+                .eval_batch(EvalBatch::default())
+                .tree_parallel(TreeParallel::default())
+                .validate(),
+            Err(ConfigError::InvalidEvalBatch(EvalBatch::default()))
+        );
+    }
+
+    // Values each state by its hash, so that the root children disagree
+    struct Noisy(Batches);
+
+    impl Noisy {
+        fn perturb(state: &HashedPosition, (_, priors): Evaluation<Move>) -> Evaluation<Move> {
+            let value = (TicTacToe::zobrist_hash(state) % 201) as f64 / 100. - 1.;
+            (vec![value, -value], priors)
+        }
+    }
+
+    impl Evaluator<TicTacToe> for Noisy {
+        fn evaluate(&self, state: &HashedPosition) -> Evaluation<Move> {
+            Self::perturb(state, self.0.evaluate(state))
+        }
+
+        fn evaluate_batch(&self, states: &[HashedPosition]) -> Vec<Evaluation<Move>> {
+            let evaluations = self.0.evaluate_batch(states);
+            states
+                .iter()
+                .zip(evaluations)
+                .map(|(state, evaluation)| Self::perturb(state, evaluation))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_eval_batch_disagreement() {
+        let mut extended = 0;
+        for seed in 0..20 {
+            let noisy = Arc::new(Noisy(Batches(Mutex::new(vec![]))));
+            let mut ts = TreeSearch::<TicTacToe, strategy::AlphaZero>::default().config(
+                SearchConfig::default()
+                    .simulate(Network {
+                        evaluator: noisy.clone(),
+                        ..Default::default()
+                    })
+                    .eval_batch(EvalBatch::default().size(8))
+                    .max_iterations(20)
+                    .disagreement_budget(2.)
+                    .seed(seed),
+            );
+            let state = [0, 4, 8]
+                .into_iter()
+                .fold(HashedPosition::new(), |state, i| {
+                    TicTacToe::apply(state, &Move(i))
+                });
+            let result = ts.search(&state);
+            assert!(result.extra_iterations <= 40);
+            if result.extra_iterations > 0 {
+                // The second stage evaluates in batches too
+                assert_eq!(result.extra_iterations % 8, 0);
+                let sizes = noisy.0 .0.lock().unwrap().clone();
+                assert!(sizes.iter().all(|size| (1..=8).contains(size)));
+                extended += 1;
+            }
+        }
+        assert!(extended > 0);
+    }
+
+    #[test]
+    fn test_eval_batch_easy_move() {
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let config = || {
+            SearchConfig::<TicTacToe, strategy::AlphaZero>::default()
+                .eval_batch(EvalBatch::default().size(8))
+                .max_iterations(5000)
+                .seed(0)
+        };
+
+        let mut ts = TreeSearch::default().config(config());
+        let result = ts.search(&state);
+        assert_eq!(result.iterations, 5000);
+        assert!(!result.easy_move);
+
+        // Stops at the end of the batch that reached the check
+        let mut ts = TreeSearch::default().config(config().easy_move(EasyMove::default()));
+        let result = ts.search(&state);
+        assert_eq!(result.action, Move(2));
+        assert!(result.easy_move);
+        assert!(result.iterations < 5000);
+    }
+}
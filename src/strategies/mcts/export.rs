@@ -0,0 +1,186 @@
+//! A stable export of the search tree for external analysis, such as loading
+//! into a notebook:
+//!
+//! ```ignore
+//! let export = ts.export(&state, 3);
+//! std::fs::write("tree.json", serde_json::to_string(&export)?)?;
+//! ```
+//!
+//! The format is a nested tree of `ExportNode`, rooted at the position of the
+//! last search. Actions are written with `Game::notation`, scores are the
+//! expected utility of each player, and nodes on the principal variation are
+//! marked. Only explored edges are included, and a transposed node appears
+//! under every parent which reaches it. `EXPORT_VERSION` is raised whenever a
+//! field changes meaning or is removed.
+
+use super::index::Id;
+use super::node::{NodeState, NodeStats, Proof};
+use super::{SearchConfig, Strategy, TreeSearch};
+use crate::game::Game;
+
+use serde::{Deserialize, Serialize};
+
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TreeExport {
+    pub version: u32,
+    pub num_players: usize,
+    // Iterations of the searches which built the tree
+    pub iterations: usize,
+    // The nodes below this depth are left out
+    pub depth_limit: usize,
+    pub root: ExportNode,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportNode {
+    // The move into the node, in the notation of its parent. None at the root.
+    pub action: Option<String>,
+    // The player to move
+    pub player: usize,
+    pub visits: u32,
+    // The expected utility of each player
+    pub scores: Vec<f64>,
+    // The proven result for the player who moved into the node, if any
+    pub proof: Option<Proof>,
+    pub terminal: bool,
+    // Whether the node is on the principal variation
+    pub pv: bool,
+    // Children, most visited first
+    pub children: Vec<ExportNode>,
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Export the tree from the last search, which was of `state`, down to
+    /// `depth_limit` moves below the root.
+    pub fn export(&self, state: &G::S, depth_limit: usize) -> TreeExport {
+        let root = self.export_node(
+            self.root_id,
+            state,
+            &self.root_stats,
+            None,
+            None,
+            depth_limit,
+            Some(&self.pv),
+        );
+        TreeExport {
+            version: EXPORT_VERSION,
+            num_players: G::num_players(),
+            iterations: self.stats.iter_count,
+            depth_limit,
+            root,
+        }
+    }
+
+    // The rest of the principal variation is passed down along it
+    #[allow(clippy::too_many_arguments)]
+    fn export_node(
+        &self,
+        node_id: Id,
+        state: &G::S,
+        stats: &NodeStats,
+        action: Option<String>,
+        proof: Option<Proof>,
+        depth: usize,
+        pv: Option<&[G::A]>,
+    ) -> ExportNode {
+        let node = self.index.get(node_id);
+        let mut children = vec![];
+        if let (NodeState::Expanded(_), true) = (&node.state, depth > 0) {
+            for edge in node.children_by_visits() {
+                let Some(child_id) = edge.node_id else {
+                    continue;
+                };
+                let child_pv = pv
+                    .and_then(|pv| pv.split_first())
+                    .filter(|(first, _)| **first == edge.action)
+                    .map(|(_, rest)| rest);
+                children.push(self.export_node(
+                    child_id,
+                    &G::apply(state.clone(), &edge.action),
+                    &edge.stats,
+                    Some(G::notation(state, &edge.action)),
+                    edge.proof(node.player_idx),
+                    depth - 1,
+                    child_pv,
+                ));
+            }
+        }
+        ExportNode {
+            action,
+            player: node.player_idx,
+            visits: stats.num_visits,
+            scores: (0..G::num_players())
+                .map(|player| stats.expected_score(player))
+                .collect(),
+            proof,
+            terminal: node.is_terminal(),
+            pv: pv.is_some(),
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::strategy;
+    use crate::strategies::Search;
+
+    #[test]
+    fn test_export() {
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        let state = HashedPosition::new();
+        ts.choose_action(&state);
+
+        let export = ts.export(&state, 2);
+        assert_eq!(export.version, EXPORT_VERSION);
+        assert_eq!(export.iterations, 1000);
+        let root = &export.root;
+        assert_eq!(root.action, None);
+        assert!(root.pv);
+        assert_eq!(root.children.len(), 9);
+        assert!(root.children.iter().all(|child| child.player == 1));
+        assert_eq!(
+            root.children.iter().map(|child| child.visits).sum::<u32>(),
+            root.visits - 1
+        );
+        assert!(root
+            .children
+            .iter()
+            .flat_map(|child| &child.children)
+            .all(|grandchild| grandchild.children.is_empty()));
+
+        // One node per depth is on the principal variation
+        let pv = std::iter::successors(Some(root), |node| {
+            node.children.iter().find(|child| child.pv)
+        })
+        .skip(1)
+        .map(|node| node.action.clone().unwrap())
+        .collect::<Vec<_>>();
+        let notation = ts.pv[..2]
+            .iter()
+            .scan(state, |state, action| {
+                let notation = TicTacToe::notation(state, action);
+                *state = TicTacToe::apply(*state, action);
+                Some(notation)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pv, notation);
+
+        let json = serde_json::to_string(&export).unwrap();
+        let parsed = serde_json::from_str::<TreeExport>(&json).unwrap();
+        assert_eq!(parsed.root.visits, root.visits);
+        assert_eq!(parsed.root.children.len(), 9);
+        assert_eq!(ts.export(&state, 0).root.children, []);
+    }
+}
@@ -0,0 +1,149 @@
+//! Sequential Halving at the root. See `SequentialHalving`.
+
+use super::node::{Edge, NodeState};
+use super::{SearchConfig, Strategy, TreeSearch};
+use crate::game::{Action, Game, PlayerIndex};
+
+/// The progress of sequential halving through the current search.
+#[derive(Clone, Debug)]
+pub(crate) struct Halving {
+    // The root edges still in contention, and how often each has been
+    // selected this round
+    arms: Vec<usize>,
+    round_visits: Vec<u32>,
+    // The selections of each arm per round
+    per_arm: u32,
+    rounds_left: u32,
+    budget_left: usize,
+}
+
+impl Halving {
+    fn new(num_arms: usize, budget: usize) -> Self {
+        let mut halving = Self {
+            arms: (0..num_arms).collect(),
+            round_visits: vec![],
+            per_arm: 0,
+            rounds_left: num_arms.next_power_of_two().trailing_zeros().max(1),
+            budget_left: budget,
+        };
+        halving.start_round();
+        halving
+    }
+
+    fn start_round(&mut self) {
+        let round_budget = self.budget_left / self.rounds_left as usize;
+        self.per_arm = (round_budget / self.arms.len()).max(1) as u32;
+        self.round_visits = vec![0; self.arms.len()];
+    }
+
+    fn round_done(&self) -> bool {
+        self.arms.len() > 1 && self.round_visits.iter().all(|n| *n >= self.per_arm)
+    }
+
+    // Keep the better half of the arms by mean score
+    fn halve<A: Action>(&mut self, edges: &[Edge<A>], player: usize) {
+        let score = |i: &usize| edges[*i].stats.exploitation_score(player);
+        self.arms.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        self.arms.truncate(self.arms.len().div_ceil(2));
+        self.rounds_left = (self.rounds_left - 1).max(1);
+        self.start_round();
+    }
+
+    fn next(&mut self) -> usize {
+        let i = (0..self.arms.len())
+            .min_by_key(|i| self.round_visits[*i])
+            .unwrap();
+        self.round_visits[i] += 1;
+        self.budget_left = self.budget_left.saturating_sub(1);
+        self.arms[i]
+    }
+
+    fn best<A: Action>(&self, edges: &[Edge<A>], player: usize) -> usize {
+        let score = |i: &&usize| edges[**i].stats.exploitation_score(player);
+        *self
+            .arms
+            .iter()
+            .max_by(|a, b| score(a).total_cmp(&score(b)))
+            .unwrap()
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// The root edge to select next under `SequentialHalving`, or `None` if
+    /// it is not configured or the root is not expanded.
+    pub(crate) fn halving_child(&mut self, state: &G::S) -> Option<usize> {
+        let config = self.config.sequential_halving?;
+        let NodeState::Expanded(edges) = &self.index.get(self.root_id).state else {
+            return None;
+        };
+        let budget = config.budget.unwrap_or(self.config.max_iterations);
+        let halving = self
+            .halving
+            .get_or_insert_with(|| Halving::new(edges.len(), budget));
+        if halving.round_done() {
+            halving.halve(edges, G::player_to_move(state).to_index());
+        }
+        Some(halving.next())
+    }
+
+    /// The best scoring root edge still in contention under
+    /// `SequentialHalving`, if it ran in this search.
+    pub(crate) fn halving_final_child(&self, state: &G::S) -> Option<usize> {
+        self.config.sequential_halving?;
+        let halving = self.halving.as_ref()?;
+        let NodeState::Expanded(edges) = &self.index.get(self.root_id).state else {
+            return None;
+        };
+        Some(halving.best(edges, G::player_to_move(state).to_index()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::{strategy, ConfigError, SequentialHalving};
+    use crate::strategies::Search;
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    #[test]
+    fn test_sequential_halving() {
+        // X to move and win at 2, or lose to O at 5
+        let mut state = HashedPosition::new();
+        for i in [0, 4, 1, 3] {
+            state = TicTacToe::apply(state, &Move(i));
+        }
+        let mut ts = TS::default().config(
+            SearchConfig::default()
+                .max_iterations(1000)
+                .sequential_halving(SequentialHalving::default())
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // Five children over three rounds, each with a third of the remaining
+        // budget: 66 selections each, then 111 each for the best three, then
+        // 168 each for the best two.
+        let mut visits = ts.root_visits().iter().map(|(_, n)| *n).collect::<Vec<_>>();
+        visits.sort();
+        assert_eq!(visits, vec![66, 66, 177, 345, 345]);
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+                .sequential_halving(SequentialHalving::default())
+                .validate(),
+            Err(ConfigError::UnboundedHalving)
+        );
+        assert!(SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+            .sequential_halving(SequentialHalving::default().budget(100))
+            .validate()
+            .is_ok());
+    }
+}
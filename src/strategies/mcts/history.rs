@@ -0,0 +1,271 @@
+use super::node::ActionStats;
+use crate::game::{Action, Game};
+
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
+
+// The longest action sequences kept in `History::ngrams`
+pub const MAX_NGRAM: usize = 3;
+
+/// The context under which `History::ngrams` keeps the statistics of an
+/// action played right after `prefix`, the preceding actions, oldest first.
+pub fn ngram_context<A: Action>(prefix: &[A]) -> u64 {
+    let mut hasher = FxHasher::default();
+    prefix.len().hash(&mut hasher);
+    prefix.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Statistics for actions independent of where they were played.
+#[derive(Clone, Debug)]
+pub struct ActionTable<A: Action>(FxHashMap<A, ActionStats>);
+
+impl<A: Action> Default for ActionTable<A> {
+    fn default() -> Self {
+        Self(FxHashMap::default())
+    }
+}
+
+impl<A: Action> ActionTable<A> {
+    #[inline]
+    pub fn get(&self, action: &A) -> Option<&ActionStats> {
+        self.0.get(action)
+    }
+
+    /// The average score of the action, or `None` if it has not been visited.
+    #[inline]
+    pub fn mean(&self, action: &A) -> Option<f64> {
+        self.get(action)
+            .filter(|stats| stats.num_visits > 0)
+            .map(|stats| stats.score / stats.num_visits as f64)
+    }
+
+    #[inline]
+    pub fn update(&mut self, action: &A, score: f64) {
+        let stats = self.0.entry(action.clone()).or_default();
+        stats.num_visits += 1;
+        stats.score += score;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&A, &ActionStats)> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// See `ActionStats::decay`. Entries with no remaining visits are removed.
+    pub fn decay(&mut self, gamma: f64) {
+        self.0.retain(|_, stats| stats.decay(gamma));
+    }
+}
+
+/// Per-player action statistics keyed by a context, such as the hash of the
+/// node at which the action was played.
+#[derive(Clone, Debug)]
+pub struct ContextTable<A: Action> {
+    num_players: usize,
+    contexts: FxHashMap<u64, Vec<ActionTable<A>>>,
+}
+
+impl<A: Action> ContextTable<A> {
+    pub fn new(num_players: usize) -> Self {
+        Self {
+            num_players,
+            contexts: FxHashMap::default(),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, context: u64, player: usize, action: &A) -> Option<&ActionStats> {
+        self.contexts
+            .get(&context)
+            .and_then(|players| players[player].get(action))
+    }
+
+    /// The tables for each player in the given context.
+    #[inline]
+    pub fn context(&self, context: u64) -> Option<&[ActionTable<A>]> {
+        self.contexts.get(&context).map(Vec::as_slice)
+    }
+
+    #[inline]
+    pub fn update(&mut self, context: u64, player: usize, action: &A, score: f64) {
+        self.contexts
+            .entry(context)
+            .or_insert_with(|| vec![ActionTable::default(); self.num_players])[player]
+            .update(action, score);
+    }
+
+    pub fn contexts(&self) -> impl Iterator<Item = (&u64, &[ActionTable<A>])> {
+        self.contexts
+            .iter()
+            .map(|(context, players)| (context, players.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+
+    /// Decay every table, dropping contexts in which nothing remains.
+    pub fn decay(&mut self, gamma: f64) {
+        self.contexts.retain(|_, players| {
+            players.iter_mut().for_each(|actions| actions.decay(gamma));
+            players.iter().any(|actions| !actions.is_empty())
+        });
+    }
+}
+
+/// The last good reply of each player to a context of preceding actions, as
+/// given by `ngram_context`.
+#[derive(Clone, Debug)]
+pub struct ReplyTable<A: Action>(FxHashMap<(u64, usize), A>);
+
+impl<A: Action> Default for ReplyTable<A> {
+    fn default() -> Self {
+        Self(FxHashMap::default())
+    }
+}
+
+impl<A: Action> ReplyTable<A> {
+    #[inline]
+    pub fn get(&self, context: u64, player: usize) -> Option<&A> {
+        self.0.get(&(context, player))
+    }
+
+    #[inline]
+    pub fn insert(&mut self, context: u64, player: usize, action: &A) {
+        self.0.insert((context, player), action.clone());
+    }
+
+    /// Remove the reply to `context` if it is `action`.
+    #[inline]
+    pub fn forget(&mut self, context: u64, player: usize, action: &A) {
+        if self.get(context, player) == Some(action) {
+            self.0.remove(&(context, player));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+/// The action history shared by the select, simulate and backprop strategies.
+/// It persists between searches, subject to `SearchConfig::stats_decay`.
+///
+/// Strategies read from the tables directly and the backprop strategy writes
+/// to them according to the `BackpropFlags` requested:
+///
+/// - `actions` and `player_actions` (GLOBAL) hold MAST statistics for every
+///   action in the trial. The former is scored from the searching player's
+///   perspective and the latter is indexed by the searching player.
+/// - `grave` (GRAVE) holds AMAF statistics keyed by node hash and by the
+///   player who made the action.
+/// - `ngrams` (NGRAM) holds statistics for sequences of up to `MAX_NGRAM`
+///   consecutive actions in the trial, keyed by the `ngram_context` of the
+///   actions before the last, and scored for the player who made the last.
+/// - `replies` (REPLY) holds the last winning reply to each one and two action
+///   context. With FORGET_REPLY, a reply is also dropped when it loses.
+///
+/// New heuristics should add a table here rather than keeping their own maps,
+/// so that decay and reuse between searches apply uniformly.
+#[derive(Clone, Debug)]
+pub struct History<G: Game> {
+    pub actions: ActionTable<G::A>,
+    pub player_actions: Vec<ActionTable<G::A>>,
+    pub grave: ContextTable<G::A>,
+    pub ngrams: ContextTable<G::A>,
+    pub replies: ReplyTable<G::A>,
+}
+
+impl<G: Game> Default for History<G> {
+    fn default() -> Self {
+        Self {
+            actions: ActionTable::default(),
+            player_actions: vec![ActionTable::default(); G::num_players()],
+            grave: ContextTable::new(G::num_players()),
+            ngrams: ContextTable::new(G::num_players()),
+            replies: ReplyTable::default(),
+        }
+    }
+}
+
+impl<G: Game> History<G> {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+            && self.player_actions.iter().all(ActionTable::is_empty)
+            && self.grave.is_empty()
+            && self.ngrams.is_empty()
+            && self.replies.is_empty()
+    }
+
+    /// Decay all tables. Entries which fall to zero visits are removed. Replies
+    /// have no visits to decay, so they are kept unless everything is.
+    pub fn decay(&mut self, gamma: f64) {
+        debug_assert!((0. ..=1.).contains(&gamma));
+        self.actions.decay(gamma);
+        self.player_actions
+            .iter_mut()
+            .for_each(|actions| actions.decay(gamma));
+        self.grave.decay(gamma);
+        self.ngrams.decay(gamma);
+        if gamma == 0. {
+            self.replies.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{Move, TicTacToe};
+
+    #[test]
+    fn test_history() {
+        let mut history = History::<TicTacToe>::default();
+        assert!(history.is_empty());
+
+        history.actions.update(&Move(4), 1.);
+        history.actions.update(&Move(4), 0.);
+        assert_eq!(history.actions.mean(&Move(4)), Some(0.5));
+        assert_eq!(history.actions.mean(&Move(0)), None);
+
+        history.grave.update(7, 1, &Move(2), -1.);
+        assert_eq!(history.grave.get(7, 1, &Move(2)).unwrap().num_visits, 1);
+        assert!(history.grave.get(7, 0, &Move(2)).is_none());
+        assert!(history.grave.get(8, 1, &Move(2)).is_none());
+
+        let context = ngram_context(&[Move(4), Move(0)]);
+        assert_ne!(context, ngram_context(&[Move(0), Move(4)]));
+        assert_ne!(ngram_context::<Move>(&[]), ngram_context(&[Move(4)]));
+        history.ngrams.update(context, 0, &Move(8), 1.);
+
+        history.replies.insert(context, 1, &Move(8));
+        history.replies.forget(context, 1, &Move(7));
+        assert_eq!(history.replies.get(context, 1), Some(&Move(8)));
+        assert_eq!(history.replies.get(context, 0), None);
+        history.decay(0.5);
+        assert_eq!(history.replies.len(), 1);
+        history.replies.forget(context, 1, &Move(8));
+        assert!(history.replies.is_empty());
+        history.replies.insert(context, 1, &Move(8));
+        assert!(!history.is_empty());
+
+        history.decay(0.);
+        assert!(history.is_empty());
+    }
+}
@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub struct Id(usize);
 
 impl Id {
@@ -14,12 +14,12 @@ impl Id {
 }
 
 // TODO: benchmark keeping child/sibling relationships here vs. on Node (space vs. time)
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct Entry<T: Serialize> {
     value: T,
 }
 
-#[derive(Clone, Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct Arena<T: Serialize>(Vec<Entry<T>>);
 
 impl<T: Serialize> Arena<T> {
@@ -44,10 +44,23 @@ impl<T: Serialize> Arena<T> {
         &mut self.0[id.0].value
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter().map(|entry| &entry.value)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.0.iter_mut().map(|entry| &mut entry.value)
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    /// The memory reserved for entries, not counting what they own.
+    pub fn capacity_bytes(&self) -> usize {
+        self.0.capacity() * std::mem::size_of::<Entry<T>>()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
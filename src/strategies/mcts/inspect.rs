@@ -0,0 +1,220 @@
+//! Structured queries on the search tree, for tests and dashboards. Where
+//! `verbose_summary` prints, these return the numbers.
+
+use super::index::Id;
+use super::node::{Edge, NodeState, NodeStats, Proof};
+use super::{SearchConfig, Strategy, TreeSearch};
+use crate::game::{Action, Game};
+
+use rustc_hash::FxHashSet;
+
+/// An edge of the tree, as seen by the player to move at its parent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChildStats<A> {
+    pub action: A,
+    pub visits: u32,
+    // Expected score, and its standard error
+    pub value: f64,
+    pub standard_error: f64,
+    pub prior: f32,
+    pub proof: Option<Proof>,
+    // Whether the edge leads to a node in the tree
+    pub explored: bool,
+}
+
+/// The nodes at one depth of the tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepthCount {
+    pub nodes: usize,
+    pub expanded: usize,
+    pub terminal: usize,
+    // Edges out of the expanded nodes, and those leading to nodes
+    pub edges: usize,
+    pub explored_edges: usize,
+}
+
+/// The node reached by following some actions from the root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtree<A> {
+    pub player: usize,
+    pub visits: u32,
+    // Expected score for the player to move at the node
+    pub value: f64,
+    // Children, most visited first. Empty unless the node is expanded.
+    pub children: Vec<ChildStats<A>>,
+    // The nodes below this one, counting it at depth 0. See
+    // `TreeSearch::tree_shape_histogram`.
+    pub shape: Vec<DepthCount>,
+}
+
+impl<A> Subtree<A> {
+    pub fn node_count(&self) -> usize {
+        self.shape.iter().map(|depth| depth.nodes).sum()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.shape.len() - 1
+    }
+}
+
+fn child_stats<A: Action>(edge: &Edge<A>, player: usize) -> ChildStats<A> {
+    ChildStats {
+        action: edge.action.clone(),
+        visits: edge.stats.num_visits,
+        value: edge.stats.expected_score(player),
+        standard_error: edge.stats.standard_error(player),
+        prior: edge.prior,
+        proof: edge.proof(player),
+        explored: edge.is_explored(),
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// The root's children, most visited first.
+    pub fn root_children_stats(&self) -> Vec<ChildStats<G::A>> {
+        self.children_stats(self.root_id)
+    }
+
+    /// The number of nodes held by the tree.
+    pub fn node_count(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The depth of the deepest node below the root.
+    pub fn max_depth_reached(&self) -> usize {
+        self.tree_shape_histogram().len() - 1
+    }
+
+    /// The nodes at each depth below the root, starting with the root itself.
+    /// A node reached by several paths through transpositions is counted once,
+    /// at its shallowest depth.
+    pub fn tree_shape_histogram(&self) -> Vec<DepthCount> {
+        self.shape(self.root_id)
+    }
+
+    /// The node reached by playing `path` from the root, or `None` if the
+    /// path leaves the tree.
+    pub fn subtree(&self, path: &[G::A]) -> Option<Subtree<G::A>> {
+        let mut node_id = self.root_id;
+        let mut stats: &NodeStats = &self.root_stats;
+        for action in path {
+            let NodeState::Expanded(edges) = &self.index.get(node_id).state else {
+                return None;
+            };
+            let edge = edges.iter().find(|edge| edge.action == *action)?;
+            node_id = edge.node_id?;
+            stats = &edge.stats;
+        }
+        let player = self.index.get(node_id).player_idx;
+        Some(Subtree {
+            player,
+            visits: stats.num_visits,
+            value: stats.expected_score(player),
+            children: self.children_stats(node_id),
+            shape: self.shape(node_id),
+        })
+    }
+
+    fn children_stats(&self, node_id: Id) -> Vec<ChildStats<G::A>> {
+        let node = self.index.get(node_id);
+        if !node.is_expanded() {
+            return vec![];
+        }
+        node.children_by_visits()
+            .into_iter()
+            .map(|edge| child_stats(edge, node.player_idx))
+            .collect()
+    }
+
+    // Breadth first, so that each node is counted at its shallowest depth
+    fn shape(&self, node_id: Id) -> Vec<DepthCount> {
+        let mut shape = vec![];
+        let mut seen = FxHashSet::default();
+        seen.insert(node_id);
+        let mut level = vec![node_id];
+        while !level.is_empty() {
+            let mut count = DepthCount::default();
+            let mut next = vec![];
+            for id in level {
+                let node = self.index.get(id);
+                count.nodes += 1;
+                count.terminal += node.is_terminal() as usize;
+                let NodeState::Expanded(edges) = &node.state else {
+                    continue;
+                };
+                count.expanded += 1;
+                count.edges += edges.len();
+                for child in edges.iter().filter_map(|edge| edge.node_id) {
+                    count.explored_edges += 1;
+                    if seen.insert(child) {
+                        next.push(child);
+                    }
+                }
+            }
+            shape.push(count);
+            level = next;
+        }
+        shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::strategy;
+
+    #[test]
+    fn test_inspect() {
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(2000).seed(0));
+        let state = HashedPosition::new();
+        ts.search(&state);
+
+        let children = ts.root_children_stats();
+        assert_eq!(children.len(), 9);
+        assert!(children.windows(2).all(|w| w[0].visits >= w[1].visits));
+        assert!(children.iter().all(|child| child.explored));
+
+        // Without transpositions every node is reached once
+        let shape = ts.tree_shape_histogram();
+        assert_eq!(shape[0].nodes, 1);
+        assert_eq!(shape[1].nodes, 9);
+        assert_eq!(
+            shape.iter().map(|d| d.nodes).sum::<usize>(),
+            ts.node_count()
+        );
+        assert_eq!(ts.max_depth_reached(), shape.len() - 1);
+        assert!(ts.max_depth_reached() <= 9);
+        for (depth, next) in shape.iter().zip(&shape[1..]) {
+            assert_eq!(depth.explored_edges, next.nodes);
+        }
+
+        let root = ts.subtree(&[]).unwrap();
+        assert_eq!(root.children, children);
+        assert_eq!(root.shape, shape);
+        assert_eq!(root.node_count(), ts.node_count());
+
+        let best = &children[0];
+        let subtree = ts.subtree(&[best.action]).unwrap();
+        assert_eq!(subtree.player, 1);
+        assert_eq!(subtree.visits, best.visits);
+        assert_eq!(subtree.value, -best.value);
+        assert!(subtree.max_depth() < ts.max_depth_reached());
+        assert_eq!(
+            subtree.shape[0].explored_edges,
+            subtree
+                .children
+                .iter()
+                .filter(|child| child.explored)
+                .count()
+        );
+        assert_eq!(ts.subtree(&[best.action, best.action]), None);
+    }
+}
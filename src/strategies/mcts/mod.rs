@@ -1,15 +1,36 @@
+pub mod analysis;
 pub mod backprop;
 pub mod book;
+pub mod cache;
+pub mod checkpoint;
+pub mod clock;
 pub mod config;
+pub mod diff;
+pub mod dynamic;
+pub mod eval;
+pub mod export;
+pub mod halving;
+pub mod history;
 pub mod index;
+pub mod inspect;
 pub mod node;
+pub mod parallel;
+pub mod policy;
+pub mod profile;
+pub mod progress;
 pub mod render;
+pub mod restrict;
+pub mod reuse;
+pub mod schedule;
 pub mod search;
 pub mod select;
 pub mod simulate;
+pub mod solver;
 pub mod stack;
 pub mod strategy;
 pub mod table;
+pub mod testbed;
+pub mod trace;
 
 pub use config::*;
 pub use search::*;
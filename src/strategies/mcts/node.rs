@@ -1,30 +1,55 @@
 use super::*;
-use crate::game::Action;
+use crate::game::{Action, PlayerIndex};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering::*;
+use std::sync::{OnceLock, RwLock};
 
-#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct ActionStats {
     pub num_visits: u32,
     pub score: f64,
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl ActionStats {
+    /// Scale the visit count by `gamma`, preserving the average score. Returns
+    /// `false` if no visits remain. The count is rounded down, so that with
+    /// any `gamma` below one repeated decay drops every visit in the end.
+    pub fn decay(&mut self, gamma: f64) -> bool {
+        let num_visits = (self.num_visits as f64 * gamma).floor() as u32;
+        if num_visits == 0 {
+            *self = Self::default();
+            return false;
+        }
+        self.score *= num_visits as f64 / self.num_visits as f64;
+        self.num_visits = num_visits;
+        true
+    }
+}
+
+/// Per player statistics of an edge. The sum of squared deviations from the
+/// mean (`m2`) is maintained with Welford's online algorithm, which stays
+/// accurate where a running sum of squares would lose precision over millions
+/// of visits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerStats {
     pub score: f64,
-    pub sum_squared_score: f64,
+    pub m2: f64,
     pub amaf: ActionStats,
+    // The entropy regularized value maintained by `backprop::Soft`
+    pub soft: f64,
 }
 
 impl Default for PlayerStats {
     fn default() -> Self {
         Self {
             score: 0.,
-            sum_squared_score: 0.,
+            m2: 0.,
             amaf: ActionStats::default(),
+            soft: 0.,
         }
     }
 }
@@ -41,7 +66,19 @@ impl FromStr for QInit {
             "Loss" => Ok(QInit::Loss),
             "Parent" => Ok(QInit::Parent),
             "Win" => Ok(QInit::Win),
-            _ => Err(ParseQInitError),
+            _ => {
+                // The parameterized variants, as formatted by `Debug`
+                let (name, value) = s
+                    .strip_suffix(')')
+                    .and_then(|s| s.split_once('('))
+                    .ok_or(ParseQInitError)?;
+                let value = value.trim().parse().map_err(|_| ParseQInitError)?;
+                match name {
+                    "Fixed" => Ok(QInit::Fixed(value)),
+                    "ParentMinus" => Ok(QInit::ParentMinus(value)),
+                    _ => Err(ParseQInitError),
+                }
+            }
         }
     }
 }
@@ -53,6 +90,12 @@ impl FromStr for QInit {
 /// value is a common approach and the default used here. Using infinity will
 /// encourage exploration of unvisited child nodes.
 ///
+/// First play urgency (FPU) makes the value a tunable real number: either a
+/// fixed value (`Fixed`), or the parent's value less a reduction
+/// (`ParentMinus`), which keeps the search on its best children for longer.
+/// Values are on the scale of the exploitation term, i.e. after
+/// `SearchConfig::normalize_scores` if it is set.
+///
 /// TODO: there are other strategies we could employ:
 ///
 ///   - Average: the average value from historical outcomes in simulation in this
@@ -62,7 +105,7 @@ impl FromStr for QInit {
 ///   - Custom: the client could provide an implementation rather than coupling
 ///     this to the implementation of `SelectStratey`.
 #[allow(unused)]
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum QInit {
     #[default]
     Parent,
@@ -70,16 +113,72 @@ pub enum QInit {
     Loss,
     Draw,
     Infinity,
+    Fixed(f64),
+    ParentMinus(f64),
 }
 
-#[derive(Clone, Serialize, Debug)]
+impl QInit {
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            QInit::Fixed(value) | QInit::ParentMinus(value) if !value.is_finite() => {
+                Err(ConfigError::InvalidQInit(*self))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The game theoretic result of an edge for a given player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Proof {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Proof {
+    /// Classify exact utilities from the perspective of `player`: a win if
+    /// they are strictly best, a draw if they share the best, else a loss.
+    pub fn of(utilities: &[f64], player: usize) -> Proof {
+        let own = utilities[player];
+        let best_other = utilities
+            .iter()
+            .enumerate()
+            .filter(|(p, _)| *p != player)
+            .map(|(_, u)| *u)
+            .fold(f64::NEG_INFINITY, f64::max);
+        if own > best_other {
+            Proof::Win
+        } else if own == best_other {
+            Proof::Draw
+        } else {
+            Proof::Loss
+        }
+    }
+
+    /// A short marker for analysis output.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Proof::Win => "W",
+            Proof::Loss => "L",
+            Proof::Draw => "D",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Edge<A: Action> {
     pub node_id: Option<index::Id>,
     pub action: A,
-    pub stats: NodeStats,
+    pub stats: EdgeStats,
+    // The exact utilities of the child under best play, once proven
+    pub proven: Option<Vec<f64>>,
+    // The probability of the action under the prior policy, from
+    // `SimulateStrategy::priors` or `Game::action_priors`
+    pub prior: f32,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct NodeStats {
     pub num_visits: u32,
 
@@ -108,12 +207,126 @@ impl<A: Action> Edge<A> {
         Self {
             action,
             node_id: None,
-            stats: NodeStats::new(num_players),
+            stats: EdgeStats::new(num_players),
+            proven: None,
+            prior: 0.,
+        }
+    }
+
+    pub fn proof(&self, player: usize) -> Option<Proof> {
+        self.proven
+            .as_ref()
+            .map(|utilities| Proof::of(utilities, player))
+    }
+}
+
+/// The statistics of an edge, which are only allocated once they are first
+/// written. Most edges of a high-branching node are never visited, and until
+/// then they share a single empty `NodeStats` per player count.
+#[derive(Debug)]
+pub enum EdgeStats {
+    Unvisited(&'static NodeStats),
+    Visited(Box<NodeStats>),
+}
+
+impl EdgeStats {
+    pub fn new(num_players: usize) -> Self {
+        EdgeStats::Unvisited(NodeStats::empty(num_players))
+    }
+
+    pub fn is_allocated(&self) -> bool {
+        matches!(self, EdgeStats::Visited(_))
+    }
+
+    /// Heap memory owned by these statistics.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            EdgeStats::Unvisited(_) => 0,
+            EdgeStats::Visited(stats) => {
+                std::mem::size_of::<NodeStats>()
+                    + stats.player.capacity() * std::mem::size_of::<PlayerStats>()
+            }
+        }
+    }
+}
+
+impl From<NodeStats> for EdgeStats {
+    fn from(stats: NodeStats) -> Self {
+        EdgeStats::Visited(Box::new(stats))
+    }
+}
+
+impl Clone for EdgeStats {
+    fn clone(&self) -> Self {
+        match self {
+            EdgeStats::Unvisited(empty) => EdgeStats::Unvisited(empty),
+            EdgeStats::Visited(stats) => EdgeStats::Visited(stats.clone()),
+        }
+    }
+}
+
+impl Deref for EdgeStats {
+    type Target = NodeStats;
+
+    fn deref(&self) -> &NodeStats {
+        match self {
+            EdgeStats::Unvisited(empty) => empty,
+            EdgeStats::Visited(stats) => stats,
+        }
+    }
+}
+
+impl DerefMut for EdgeStats {
+    fn deref_mut(&mut self) -> &mut NodeStats {
+        if let EdgeStats::Unvisited(empty) = self {
+            *self = EdgeStats::Visited(Box::new((*empty).clone()));
+        }
+        match self {
+            EdgeStats::Visited(stats) => stats,
+            EdgeStats::Unvisited(_) => unreachable!(),
         }
     }
 }
 
+impl Serialize for EdgeStats {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.deref().serialize(serializer)
+    }
+}
+
+// Statistics which were never written share the empty `NodeStats` again
+impl<'de> Deserialize<'de> for EdgeStats {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stats = NodeStats::deserialize(deserializer)?;
+        let unwritten = stats.num_visits == 0
+            && stats
+                .player
+                .iter()
+                .all(|p| p.score == 0. && p.m2 == 0. && p.soft == 0. && p.amaf.num_visits == 0);
+        Ok(if unwritten {
+            EdgeStats::new(stats.player.len())
+        } else {
+            stats.into()
+        })
+    }
+}
+
 impl NodeStats {
+    /// A shared, never modified, empty `NodeStats` for `num_players` players.
+    pub fn empty(num_players: usize) -> &'static NodeStats {
+        static EMPTY: OnceLock<RwLock<Vec<&'static NodeStats>>> = OnceLock::new();
+        let empty = EMPTY.get_or_init(Default::default);
+        if let Some(stats) = empty.read().unwrap().get(num_players) {
+            return stats;
+        }
+        let mut empty = empty.write().unwrap();
+        while empty.len() <= num_players {
+            let stats = NodeStats::new(empty.len());
+            empty.push(Box::leak(Box::new(stats)));
+        }
+        empty[num_players]
+    }
+
     pub fn new(num_players: usize) -> Self {
         Self {
             num_visits: 0,
@@ -127,15 +340,39 @@ impl NodeStats {
     }
 
     pub fn update(&mut self, utilities: &[f64]) {
+        let n = self.num_visits as f64;
         self.num_visits += 1;
         utilities.iter().enumerate().for_each(|(p, reward)| {
-            self.player[p].score += reward;
-            self.player[p].sum_squared_score += utilities[p] * utilities[p];
+            let stats = &mut self.player[p];
+            let mean = if n > 0. { stats.score / n } else { 0. };
+            stats.score += reward;
+            stats.m2 += (reward - mean) * (reward - stats.score / (n + 1.));
         });
     }
 
+    /// The (population) variance of the player's scores.
+    pub fn variance(&self, player: impl PlayerIndex) -> f64 {
+        if self.num_visits == 0 {
+            0.
+        } else {
+            self.player[player.to_index()].m2 / self.num_visits as f64
+        }
+    }
+
+    /// The standard error of the player's mean score, from the sample
+    /// variance. Infinite with fewer than two visits.
+    pub fn standard_error(&self, player: impl PlayerIndex) -> f64 {
+        let n = self.num_visits as f64;
+        if self.num_visits < 2 {
+            f64::INFINITY
+        } else {
+            (self.player[player.to_index()].m2 / (n - 1.) / n).sqrt()
+        }
+    }
+
     // NOTE: needs to be overridden for score bounded search
-    pub fn expected_score(&self, player_index: usize) -> f64 {
+    pub fn expected_score(&self, player: impl PlayerIndex) -> f64 {
+        let player_index = player.to_index();
         if self.num_visits == 0 {
             0.
         } else {
@@ -146,12 +383,12 @@ impl NodeStats {
     }
 
     // NOTE: needs to be overridden for score bounded search
-    pub fn exploitation_score(&self, player_index: usize) -> f64 {
-        self.expected_score(player_index)
+    pub fn exploitation_score(&self, player: impl PlayerIndex) -> f64 {
+        self.expected_score(player)
     }
 
     // These numbers come from Ludii
-    pub fn value_estimate_unvisited(&self, player_index: usize, q_init: QInit) -> f64 {
+    pub fn value_estimate_unvisited(&self, player: impl PlayerIndex, q_init: QInit) -> f64 {
         use QInit::*;
         match q_init {
             Draw => 0.,
@@ -161,15 +398,23 @@ impl NodeStats {
                 if self.num_visits == 0 {
                     10000.
                 } else {
-                    self.expected_score(player_index)
+                    self.expected_score(player)
+                }
+            }
+            ParentMinus(reduction) => {
+                if self.num_visits == 0 {
+                    10000.
+                } else {
+                    self.expected_score(player) - reduction
                 }
             }
+            Fixed(value) => value,
             Win => 1.,
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum NodeState<A: Action> {
     Terminal,
     Leaf,
@@ -177,12 +422,16 @@ pub enum NodeState<A: Action> {
     Expanded(Vec<Edge<A>>),
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Node<A: Action> {
     pub player_idx: usize,
     pub state: NodeState<A>,
     pub hash: u64,
     pub is_root: bool,
+    // Edge indices by visits, most visited first, as of the last call to
+    // `sort_children`. Empty until then.
+    #[serde(skip)]
+    order: Vec<u32>,
 }
 
 impl<A: Action> Node<A>
@@ -195,6 +444,7 @@ where
             state: NodeState::Leaf,
             hash,
             is_root: false,
+            order: vec![],
         }
     }
 
@@ -248,6 +498,45 @@ where
         edges
     }
 
+    /// Refresh the cached order of the edges by visits. Starting from the
+    /// previous order, which is usually close to the current one, an insertion
+    /// sort does little more than a linear scan.
+    pub fn sort_children(&mut self) {
+        let NodeState::Expanded(edges) = &self.state else {
+            return;
+        };
+        if self.order.len() != edges.len() {
+            self.order = (0..edges.len() as u32).collect();
+        }
+        let visits = |i: u32| edges[i as usize].stats.num_visits;
+        for i in 1..self.order.len() {
+            let mut j = i;
+            while j > 0 && visits(self.order[j - 1]) < visits(self.order[j]) {
+                self.order.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    /// The edges by visits, most visited first. The order cached by
+    /// `sort_children` is used if it is still sorted; otherwise the edges are
+    /// sorted afresh.
+    pub fn children_by_visits(&self) -> Vec<&Edge<A>> {
+        let edges = self.edges();
+        let visits = |i: &u32| edges[*i as usize].stats.num_visits;
+        if self.order.len() == edges.len()
+            && self
+                .order
+                .windows(2)
+                .all(|w| visits(&w[0]) >= visits(&w[1]))
+        {
+            return self.order.iter().map(|i| &edges[*i as usize]).collect();
+        }
+        let mut sorted = edges.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|edge| std::cmp::Reverse(edge.stats.num_visits));
+        sorted
+    }
+
     pub fn new_root(player: usize, num_players: usize, hash: u64) -> Self {
         debug_assert!((num_players == 0 && player == 0) || player < num_players);
         Self {
@@ -263,4 +552,111 @@ where
     pub fn is_root(&self) -> bool {
         self.is_root
     }
+
+    /// The exact utilities of an expanded node, if they follow from its
+    /// edges: either some edge is a proven win for the player to move, or
+    /// every edge is proven and the best of them is taken.
+    pub fn proven_utilities(&self) -> Option<Vec<f64>> {
+        let NodeState::Expanded(edges) = &self.state else {
+            return None;
+        };
+        let player = self.player_idx;
+        if let Some(edge) = edges
+            .iter()
+            .find(|edge| edge.proof(player) == Some(Proof::Win))
+        {
+            return edge.proven.clone();
+        }
+        edges
+            .iter()
+            .map(|edge| edge.proven.as_ref())
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max_by(|a, b| a[player].total_cmp(&b[player]))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_welford_precision() {
+        // Alternating scores close together, where the naive sum of squares
+        // cancels catastrophically
+        let (a, b) = (0.999_999, 1.);
+        let mut stats = NodeStats::new(2);
+        for i in 0..2_000_000 {
+            let u = if i % 2 == 0 { a } else { b };
+            stats.update(&[u, -u]);
+        }
+        let expected = ((b - a) / 2.) * ((b - a) / 2.);
+        for player in 0..2 {
+            assert!((stats.variance(player) - expected).abs() < expected * 1e-6);
+        }
+        assert!((stats.expected_score(0) - (a + b) / 2.).abs() < 1e-9);
+        assert!((stats.player[1].score + 1_999_999.).abs() < 1e-3);
+
+        let n = stats.num_visits as f64;
+        let error = (expected * n / (n - 1.) / n).sqrt();
+        assert!((stats.standard_error(0) - error).abs() < error * 1e-6);
+    }
+
+    #[test]
+    fn test_welford_small() {
+        let mut stats = NodeStats::new(1);
+        assert_eq!(stats.variance(0), 0.);
+        stats.update(&[1.]);
+        assert_eq!(stats.standard_error(0), f64::INFINITY);
+        for u in [-1., 0., 0.] {
+            stats.update(&[u]);
+        }
+        // Scores 1, -1, 0, 0
+        assert_eq!(stats.expected_score(0), 0.);
+        assert_eq!(stats.variance(0), 0.5);
+        assert!((stats.standard_error(0) - (2f64 / 3. / 4.).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lazy_edge_stats() {
+        let mut a = EdgeStats::new(2);
+        let b = EdgeStats::new(2);
+        assert!(!a.is_allocated());
+        assert!(std::ptr::eq(&*a, &*b));
+        assert_eq!(a.player.len(), 2);
+        assert_eq!(a.heap_size(), 0);
+        assert_eq!(EdgeStats::new(3).player.len(), 3);
+
+        // The first write allocates, leaving the shared empty stats untouched
+        a.update(&[1., -1.]);
+        assert!(a.is_allocated());
+        assert!(a.heap_size() > 0);
+        assert_eq!(a.num_visits, 1);
+        assert_eq!(b.num_visits, 0);
+        assert_eq!(NodeStats::empty(2).player[0].score, 0.);
+    }
+
+    #[test]
+    fn test_q_init() {
+        for q_init in [QInit::Parent, QInit::Fixed(-0.25), QInit::ParentMinus(0.1)] {
+            assert_eq!(QInit::from_str(&format!("{q_init:?}")), Ok(q_init));
+        }
+        assert_eq!(QInit::from_str("Fixed(x)"), Err(ParseQInitError));
+        assert_eq!(QInit::from_str("Other(1)"), Err(ParseQInitError));
+
+        let mut stats = NodeStats::new(2);
+        assert_eq!(stats.value_estimate_unvisited(0, QInit::Fixed(0.3)), 0.3);
+        assert_eq!(
+            stats.value_estimate_unvisited(0, QInit::ParentMinus(0.2)),
+            10000.
+        );
+        stats.update(&[1., -1.]);
+        stats.update(&[0., 0.]);
+        let parent = stats.value_estimate_unvisited(0, QInit::Parent);
+        assert_eq!(
+            stats.value_estimate_unvisited(0, QInit::ParentMinus(0.2)),
+            parent - 0.2
+        );
+    }
 }
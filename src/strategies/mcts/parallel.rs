@@ -0,0 +1,294 @@
+//! Tree parallel iterations with virtual loss. See `TreeParallel`.
+
+use super::index::Id;
+use super::search::simulate_with;
+use super::simulate::SimulateStrategy;
+use super::{SearchConfig, SearchContext, Strategy, TreeParallel, TreeSearch, TreeStats};
+use crate::game::{Game, PlayerIndex};
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use std::sync::Mutex;
+
+// Iterations of a thread between refreshing its copy of the global statistics
+const STATS_REFRESH_INTERVAL: usize = 32;
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Run up to `max_iterations` iterations from `root_id` on the configured
+    /// threads, stopping early at the time or work budget. Returns the number
+    /// of iterations completed.
+    pub(crate) fn iterate_parallel(
+        &mut self,
+        root_id: Id,
+        state: &G::S,
+        max_iterations: usize,
+        parallel: TreeParallel,
+    ) -> usize {
+        let workers = (0..parallel.threads)
+            .map(|_| Worker {
+                simulate: self.config.simulate.clone(),
+                stats: self.stats.clone(),
+                rng: SmallRng::seed_from_u64(self.config.rng.gen()),
+                virtual_loss: parallel.virtual_loss,
+                solve_threshold: self.config.solve_threshold,
+                max_playout_depth: self.config.max_playout_depth,
+            })
+            .collect::<Vec<_>>();
+        let shared = Mutex::new(Shared {
+            search: self,
+            started: 0,
+            completed: 0,
+        });
+        let simulates = std::thread::scope(|scope| {
+            let handles = workers
+                .into_iter()
+                .map(|mut worker| {
+                    let shared = &shared;
+                    scope.spawn(move || {
+                        worker.run(shared, root_id, state, max_iterations);
+                        worker.simulate
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        let completed = shared.into_inner().unwrap().completed;
+        for simulate in simulates {
+            self.config.simulate.merge(simulate);
+        }
+        completed
+    }
+
+    /// Add `amount` virtual visits to each edge along the current stack, or
+    /// remove them again.
+    pub(crate) fn apply_virtual_loss(&mut self, amount: u32, add: bool) {
+        for pair in self.stack.windows(2) {
+            let stats = &mut self.index.get_mut(pair[0]).child_edge_mut(pair[1]).stats;
+            let virtual_visits = stats.num_visits_virtual.get_mut();
+            if add {
+                *virtual_visits += amount;
+            } else {
+                *virtual_visits -= amount;
+            }
+        }
+    }
+}
+
+struct Shared<'a, G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    G::S: std::fmt::Display,
+{
+    search: &'a mut TreeSearch<G, S>,
+    started: usize,
+    completed: usize,
+}
+
+struct Worker<G: Game, S: Strategy<G>> {
+    simulate: S::Simulate,
+    // A copy of the search's statistics for the playouts, refreshed every
+    // `STATS_REFRESH_INTERVAL` iterations, so that they run without the lock
+    stats: TreeStats<G>,
+    rng: SmallRng,
+    virtual_loss: u32,
+    solve_threshold: usize,
+    max_playout_depth: usize,
+}
+
+impl<G, S> Worker<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    fn run(
+        &mut self,
+        shared: &Mutex<Shared<'_, G, S>>,
+        root_id: Id,
+        state: &G::S,
+        max_iterations: usize,
+    ) {
+        let player = G::player_to_move(state).to_index();
+        let mut iterations = 0;
+        loop {
+            // Select a leaf and mark the path with virtual loss
+            let (leaf, stack, path) = {
+                let mut shared = shared.lock().unwrap();
+                let done = shared.started >= max_iterations
+                    || shared.search.timer.done()
+                    || shared.search.work_budget_exhausted()
+                    || shared.search.stop_requested();
+                if done {
+                    return;
+                }
+                let idle = shared.started == shared.completed;
+                shared.started += 1;
+                let ts = &mut shared.search;
+                ts.reset_iter();
+                if idle {
+                    ts.recycle_nodes(state);
+                }
+                if iterations % STATS_REFRESH_INTERVAL == 0 {
+                    self.stats.clone_from(&ts.stats);
+                }
+                // Expand with this thread's strategy, which has seen the
+                // leaves it evaluated
+                std::mem::swap(&mut ts.config.simulate, &mut self.simulate);
+                let mut ctx = SearchContext::new(root_id, state.clone());
+                ts.select(&mut ctx);
+                std::mem::swap(&mut ts.config.simulate, &mut self.simulate);
+                ts.apply_virtual_loss(self.virtual_loss, true);
+                (
+                    ctx.state,
+                    std::mem::take(&mut ts.stack),
+                    std::mem::take(&mut ts.path),
+                )
+            };
+
+            // Play out concurrently with the other threads, without the lock
+            let trial = simulate_with(
+                &mut self.simulate,
+                &self.stats,
+                &leaf,
+                player,
+                self.solve_threshold,
+                self.max_playout_depth,
+                &mut self.rng,
+            );
+            iterations += 1;
+
+            // Back up the result in place of the virtual loss
+            let mut shared = shared.lock().unwrap();
+            let ts = &mut shared.search;
+            ts.stack = stack;
+            ts.path = path;
+            ts.apply_virtual_loss(self.virtual_loss, false);
+            ts.trial = Some(trial);
+            ts.finish_iteration(player);
+            shared.completed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::breakthrough::Breakthrough;
+    use crate::games::ttt::{HashedPosition, Move, TicTacToe};
+    use crate::strategies::mcts::{backprop, select, strategy, ConfigError, TreeIndex};
+    use crate::strategies::Search;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::time::Duration;
+
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    static MOST_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+    // Slow evaluations which count how many run at once
+    #[derive(Clone, Default)]
+    struct Slow;
+
+    impl SimulateStrategy<TicTacToe> for Slow {
+        fn evaluate(
+            &mut self,
+            _: &HashedPosition,
+            _: &TreeStats<TicTacToe>,
+            _: usize,
+        ) -> Option<Vec<f64>> {
+            let in_flight = IN_FLIGHT.fetch_add(1, Relaxed) + 1;
+            MOST_IN_FLIGHT.fetch_max(in_flight, Relaxed);
+            std::thread::sleep(Duration::from_millis(1));
+            IN_FLIGHT.fetch_sub(1, Relaxed);
+            Some(vec![0., 0.])
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SlowStrategy;
+
+    impl Strategy<TicTacToe> for SlowStrategy {
+        type Select = select::Ucb1;
+        type Simulate = Slow;
+        type Backprop = backprop::Classic;
+        type FinalAction = select::RobustChild;
+    }
+
+    fn virtual_visits<A: crate::game::Action>(index: &TreeIndex<A>) -> u32 {
+        index
+            .iter()
+            .filter(|node| node.is_expanded())
+            .flat_map(|node| node.edges())
+            .map(|edge| edge.stats.num_visits_virtual.load(Relaxed))
+            .sum()
+    }
+
+    #[test]
+    fn test_tree_parallel() {
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default().config(
+            SearchConfig::default()
+                .max_iterations(2000)
+                .tree_parallel(TreeParallel::default().threads(4))
+                .seed(0),
+        );
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let result = ts.search(&state);
+        assert_eq!(result.iterations, 2000);
+        assert_eq!(result.action, Move(2));
+        assert_eq!(ts.tree_metrics().iterations, 2000);
+
+        // Every virtual visit was removed again
+        assert_eq!(virtual_visits(&ts.index), 0);
+        let root = ts.index.get(ts.root_id);
+        let visits = root.edges().iter().map(|e| e.stats.num_visits).sum::<u32>();
+        assert_eq!(visits + 1, 2000);
+
+        // A larger game, with a time budget
+        let mut ts = TreeSearch::<Breakthrough<6, 6>, strategy::Ucb1Mast>::default().config(
+            SearchConfig::default()
+                .max_time(std::time::Duration::from_millis(50))
+                .tree_parallel(TreeParallel::default().threads(3).virtual_loss(3))
+                .max_nodes(500),
+        );
+        ts.choose_action(&Default::default());
+        assert!(ts.tree_metrics().iterations > 0);
+        assert!(ts.index.len() <= 500);
+        assert_eq!(virtual_visits(&ts.index), 0);
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+                .tree_parallel(TreeParallel::default().threads(0))
+                .validate(),
+            Err(ConfigError::InvalidTreeParallel(
+                TreeParallel::default().threads(0)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_tree_parallel_overlap() {
+        // The threads evaluate at the same time rather than taking turns
+        let mut ts = TreeSearch::<TicTacToe, SlowStrategy>::default().config(
+            SearchConfig::default()
+                .max_iterations(200)
+                .tree_parallel(TreeParallel::default().threads(4))
+                .seed(0),
+        );
+        ts.search(&HashedPosition::new());
+        assert!(MOST_IN_FLIGHT.load(Relaxed) > 1);
+    }
+}
@@ -0,0 +1,131 @@
+//! Training targets from a search, as in AlphaZero: the root visit counts as
+//! a policy, and the root's value.
+//!
+//! ```ignore
+//! let action = ts.choose_action(&state);
+//! let target = ts.policy_target(1.);
+//! examples.push((state.clone(), target.policy, target.value));
+//! ```
+
+use super::{SearchConfig, Strategy, TreeSearch};
+use crate::game::Game;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PolicyTarget<A> {
+    // The expected score of the root for the player to move
+    pub value: f64,
+    // Each root action with its probability under the policy
+    pub policy: Vec<(A, f64)>,
+    // The expected score of each root action for the player to move, in the
+    // order of `policy`. Zero for unvisited actions.
+    pub child_values: Vec<f64>,
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// The root actions of the last search with probabilities proportional to
+    /// `visits^(1/temperature)`, in the order of `Game::generate_actions`. A
+    /// temperature of zero puts all of the probability on the most visited
+    /// action. Empty if the root was not expanded.
+    pub fn policy_distribution(&self, temperature: f64) -> Vec<(G::A, f64)> {
+        let root = self.index.get(self.root_id);
+        if !root.is_expanded() {
+            return vec![];
+        }
+        let edges = root.edges();
+        let most = edges
+            .iter()
+            .map(|edge| edge.stats.num_visits)
+            .max()
+            .unwrap_or(0);
+        let weights = if most == 0 {
+            vec![1.; edges.len()]
+        } else if temperature <= 0. {
+            let best = edges
+                .iter()
+                .position(|edge| edge.stats.num_visits == most)
+                .unwrap();
+            (0..edges.len())
+                .map(|i| if i == best { 1. } else { 0. })
+                .collect()
+        } else {
+            // Relative to the most visited, so that large counts can't overflow
+            edges
+                .iter()
+                .map(|edge| (edge.stats.num_visits as f64 / most as f64).powf(1. / temperature))
+                .collect::<Vec<_>>()
+        };
+        let total: f64 = weights.iter().sum();
+        edges
+            .iter()
+            .zip(weights)
+            .map(|(edge, weight)| (edge.action.clone(), weight / total))
+            .collect()
+    }
+
+    /// The policy at `temperature` along with the values of the root and its
+    /// children. See `policy_distribution`.
+    pub fn policy_target(&self, temperature: f64) -> PolicyTarget<G::A> {
+        let root = self.index.get(self.root_id);
+        let player = root.player_idx;
+        let child_values = if root.is_expanded() {
+            root.edges()
+                .iter()
+                .map(|edge| edge.stats.expected_score(player))
+                .collect()
+        } else {
+            vec![]
+        };
+        PolicyTarget {
+            value: self.root_stats.expected_score(player),
+            policy: self.policy_distribution(temperature),
+            child_values,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::strategy;
+    use crate::strategies::Search;
+
+    #[test]
+    fn test_policy_distribution() {
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        assert!(ts.policy_distribution(1.).is_empty());
+
+        let state = HashedPosition::new();
+        let action = ts.choose_action(&state);
+        let visits = ts.root_visits();
+
+        let policy = ts.policy_distribution(1.);
+        assert_eq!(policy.len(), 9);
+        assert!((policy.iter().map(|(_, p)| p).sum::<f64>() - 1.).abs() < 1e-9);
+        let total = visits.iter().map(|(_, n)| *n as f64).sum::<f64>();
+        for ((action, p), (_, n)) in policy.iter().zip(&visits) {
+            assert!((p - *n as f64 / total).abs() < 1e-9, "{action:?}");
+        }
+
+        // Sharper at low temperatures, and one-hot at zero
+        let max = |policy: &[(Move, f64)]| policy.iter().map(|(_, p)| *p).fold(0., f64::max);
+        assert!(max(&ts.policy_distribution(0.5)) > max(&policy));
+        let greedy = ts.policy_distribution(0.);
+        assert_eq!(greedy.iter().filter(|(_, p)| *p == 1.).count(), 1);
+        assert!(greedy.iter().any(|(a, p)| *a == action && *p == 1.));
+
+        let target = ts.policy_target(1.);
+        assert_eq!(target.policy, policy);
+        assert_eq!(target.child_values.len(), 9);
+        assert_eq!(target.value, ts.root_stats.expected_score(0));
+    }
+}
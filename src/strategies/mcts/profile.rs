@@ -0,0 +1,245 @@
+//! Instrumentation of playout hot spots. With the `profile-playouts` feature
+//! enabled, the default `SimulateStrategy::playout` times each call to the
+//! game's `generate_actions`, `apply`, `is_terminal` and `winner` (via
+//! `compute_utilities`), and accumulates the results per game type. Without
+//! the feature the recorder compiles away and nothing is collected.
+//!
+//! ```ignore
+//! // cargo run --release --features profile-playouts --bin druid
+//! println!("{}", mcts::strategies::mcts::profile::report());
+//! ```
+
+use crate::game::Game;
+
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(feature = "profile-playouts")]
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Section {
+    pub calls: u64,
+    pub elapsed: Duration,
+}
+
+impl Section {
+    fn merge(&mut self, other: &Section) {
+        self.calls += other.calls;
+        self.elapsed += other.elapsed;
+    }
+}
+
+/// Time spent in the game implementation during playouts. Any time not
+/// accounted for by the sections is spent in the simulate strategy itself,
+/// e.g. choosing moves.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlayoutProfile {
+    pub playouts: u64,
+    pub elapsed: Duration,
+    pub generate_actions: Section,
+    pub apply: Section,
+    pub is_terminal: Section,
+    pub winner: Section,
+}
+
+impl PlayoutProfile {
+    fn sections(&self) -> [(&'static str, &Section); 4] {
+        [
+            ("generate_actions", &self.generate_actions),
+            ("apply", &self.apply),
+            ("is_terminal", &self.is_terminal),
+            ("winner", &self.winner),
+        ]
+    }
+
+    fn merge(&mut self, other: &PlayoutProfile) {
+        self.playouts += other.playouts;
+        self.elapsed += other.elapsed;
+        self.generate_actions.merge(&other.generate_actions);
+        self.apply.merge(&other.apply);
+        self.is_terminal.merge(&other.is_terminal);
+        self.winner.merge(&other.winner);
+    }
+
+    /// Playout time spent outside of the game's methods.
+    pub fn other(&self) -> Duration {
+        self.sections()
+            .iter()
+            .fold(self.elapsed, |acc, (_, section)| {
+                acc.saturating_sub(section.elapsed)
+            })
+    }
+}
+
+impl Display for PlayoutProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(
+            f,
+            "{} playouts in {:.3}s",
+            self.playouts,
+            self.elapsed.as_secs_f64()
+        )?;
+        for (name, section) in self.sections() {
+            let per_call = if section.calls > 0 {
+                section.elapsed.as_nanos() / section.calls as u128
+            } else {
+                0
+            };
+            writeln!(
+                f,
+                "  {:<18} {:>6.2}% {:>12} calls {:>8} ns/call",
+                name,
+                100. * section.elapsed.as_secs_f64() / total,
+                section.calls,
+                per_call
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:<18} {:>6.2}%",
+            "other",
+            100. * self.other().as_secs_f64() / total
+        )
+    }
+}
+
+static PROFILES: Mutex<Vec<(&'static str, PlayoutProfile)>> = Mutex::new(Vec::new());
+
+/// The accumulated profile for a game type, if any playouts were recorded.
+pub fn get<G: Game>() -> Option<PlayoutProfile> {
+    let name = std::any::type_name::<G>();
+    PROFILES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, profile)| *profile)
+}
+
+/// Discard all accumulated profiles.
+pub fn reset() {
+    PROFILES.lock().unwrap().clear();
+}
+
+/// A breakdown of the accumulated profiles for every game type.
+pub fn report() -> String {
+    PROFILES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, profile)| format!("{name}: {profile}"))
+        .collect()
+}
+
+#[cfg_attr(not(feature = "profile-playouts"), allow(dead_code))]
+fn record(name: &'static str, profile: &PlayoutProfile) {
+    let mut profiles = PROFILES.lock().unwrap();
+    match profiles.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, existing)) => existing.merge(profile),
+        None => profiles.push((name, *profile)),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Op {
+    GenerateActions,
+    Apply,
+    IsTerminal,
+    Winner,
+}
+
+/// Times the game calls made by a single playout. The profile is merged into
+/// the global table when the recorder is dropped, so the lock is taken once
+/// per playout rather than once per call.
+pub(crate) struct Recorder<G: Game> {
+    #[cfg(feature = "profile-playouts")]
+    start: Instant,
+    #[cfg(feature = "profile-playouts")]
+    profile: PlayoutProfile,
+    game_type: PhantomData<G>,
+}
+
+impl<G: Game> Recorder<G> {
+    #[inline(always)]
+    pub(crate) fn new() -> Self {
+        Self {
+            #[cfg(feature = "profile-playouts")]
+            start: Instant::now(),
+            #[cfg(feature = "profile-playouts")]
+            profile: PlayoutProfile::default(),
+            game_type: PhantomData,
+        }
+    }
+
+    #[cfg(not(feature = "profile-playouts"))]
+    #[inline(always)]
+    pub(crate) fn time<R>(&mut self, _op: Op, f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    #[cfg(feature = "profile-playouts")]
+    #[inline(always)]
+    pub(crate) fn time<R>(&mut self, op: Op, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        let section = match op {
+            Op::GenerateActions => &mut self.profile.generate_actions,
+            Op::Apply => &mut self.profile.apply,
+            Op::IsTerminal => &mut self.profile.is_terminal,
+            Op::Winner => &mut self.profile.winner,
+        };
+        section.calls += 1;
+        section.elapsed += start.elapsed();
+        result
+    }
+}
+
+#[cfg(feature = "profile-playouts")]
+impl<G: Game> Drop for Recorder<G> {
+    fn drop(&mut self) {
+        self.profile.playouts = 1;
+        self.profile.elapsed = self.start.elapsed();
+        record(std::any::type_name::<G>(), &self.profile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::nim::Nim;
+
+    #[test]
+    fn test_profile() {
+        let mut profile = PlayoutProfile::default();
+        profile.merge(&PlayoutProfile {
+            playouts: 2,
+            elapsed: Duration::from_millis(10),
+            apply: Section {
+                calls: 4,
+                elapsed: Duration::from_millis(4),
+            },
+            ..Default::default()
+        });
+        assert_eq!(profile.playouts, 2);
+        assert_eq!(profile.apply.calls, 4);
+        assert_eq!(profile.other(), Duration::from_millis(6));
+        assert!(profile.to_string().contains("apply"));
+
+        record("test_profile", &profile);
+        record("test_profile", &profile);
+        assert!(report().contains("test_profile: 4 playouts"));
+
+        {
+            let mut recorder = Recorder::<Nim>::new();
+            assert_eq!(recorder.time(Op::Apply, || 1 + 1), 2);
+        }
+        if cfg!(feature = "profile-playouts") {
+            assert!(get::<Nim>().unwrap().apply.calls >= 1);
+        } else {
+            assert!(get::<Nim>().is_none());
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Live reports from a running search, for example to display in a GUI. See
+//! `SearchConfig::on_progress`.
+
+use super::analysis::MoveAnalysis;
+use super::{SearchConfig, Strategy, TreeSearch};
+use crate::game::{Game, PlayerIndex};
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A snapshot of the search, passed to the `on_progress` callback.
+#[derive(Clone, Debug)]
+pub struct Progress<A> {
+    pub iterations: usize,
+    pub elapsed: Duration,
+    // Expected score for the player to move at the root
+    pub value: f64,
+    // Root actions, most visited first
+    pub moves: Vec<MoveAnalysis<A>>,
+}
+
+impl<A> Progress<A> {
+    /// The principal variation of the most visited action.
+    pub fn pv(&self) -> &[A] {
+        self.moves.first().map_or(&[], |m| &m.pv)
+    }
+}
+
+pub type ProgressCallback<A> = Arc<dyn Fn(&Progress<A>) + Send + Sync>;
+
+/// Call `callback` with a `Progress` snapshot every `interval` iterations, and
+/// once more when the search ends. The callback runs on the searching thread,
+/// so it should hand the snapshot off rather than block. Not called with
+/// `tree_parallel`.
+#[derive(Clone)]
+pub struct OnProgress<A> {
+    pub interval: usize,
+    pub callback: ProgressCallback<A>,
+}
+
+impl<A> fmt::Debug for OnProgress<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnProgress")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// A snapshot of the search from `state` after `iterations` iterations.
+    pub fn progress(&self, state: &G::S, iterations: usize) -> Progress<G::A> {
+        let player = G::player_to_move(state).to_index();
+        Progress {
+            iterations,
+            elapsed: self.timer.elapsed(),
+            value: self.root_stats.expected_score(player),
+            moves: self.analyze_moves(player),
+        }
+    }
+
+    /// Call the `on_progress` callback if it is due after `iterations`, or
+    /// unconditionally if `last`.
+    pub(crate) fn report_progress(&self, state: &G::S, iterations: usize, last: bool) {
+        let Some(observer) = &self.config.on_progress else {
+            return;
+        };
+        if last || iterations % observer.interval == 0 {
+            (observer.callback)(&self.progress(state, iterations));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::{strategy, ConfigError};
+    use crate::strategies::Search;
+
+    use std::sync::Mutex;
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    #[test]
+    fn test_on_progress() {
+        // X to move and win at 2, or lose to O at 5
+        let mut state = HashedPosition::new();
+        for i in [0, 4, 1, 3] {
+            state = TicTacToe::apply(state, &Move(i));
+        }
+        let reports = Arc::new(Mutex::new(vec![]));
+        let sink = reports.clone();
+        let mut ts = TS::default().config(
+            SearchConfig::default()
+                .max_iterations(1050)
+                .on_progress(100, move |progress| {
+                    sink.lock().unwrap().push(progress.clone())
+                })
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        let reports = reports.lock().unwrap();
+        let iterations = reports.iter().map(|p| p.iterations).collect::<Vec<_>>();
+        assert_eq!(
+            iterations,
+            vec![100, 200, 300, 400, 500, 600, 700, 800, 900, 1000, 1050]
+        );
+        let last = reports.last().unwrap();
+        assert_eq!(last.pv().first(), Some(&Move(2)));
+        assert_eq!(last.moves.len(), 5);
+        assert_eq!(
+            last.moves.iter().map(|m| m.visits).sum::<u32>() as usize,
+            ts.root_visits()
+                .iter()
+                .map(|(_, n)| *n as usize)
+                .sum::<usize>()
+        );
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+                .on_progress(0, |_| {})
+                .validate(),
+            Err(ConfigError::ZeroProgressInterval)
+        );
+    }
+}
@@ -1,19 +1,38 @@
-use crate::game::Game;
+//! Rendering the search tree in the Graphviz DOT language. Whole trees are
+//! only legible up to a few hundred nodes, so `RenderConfig` can prune by
+//! depth, visits and rank among siblings.
+//!
+//! ```ignore
+//! let dot = RenderConfig::new()
+//!     .max_depth(4)
+//!     .top_k(3)
+//!     .color_by_value(true)
+//!     .render(&ts, &state);
+//! ```
 
-use super::{index, table::TranspositionTable, Strategy, TreeIndex, TreeSearch};
+use crate::game::{Action, Game};
 
+use super::node::Proof;
+use super::{index, table::TranspositionTable, ScoreBounds, Strategy, TreeIndex, TreeSearch};
+
+use std::fmt::Write;
+
+/// Render the whole tree from the default state to stdout.
 pub fn render<G: Game, S: Strategy<G>>(search: &TreeSearch<G, S>)
 where
     G::S: NodeRender,
 {
-    print::<G>(&search.index, search.root_id);
+    RenderConfig::new().print(search, &G::S::default());
 }
 
+/// Render the whole tree to stdout, merging transposed nodes.
 pub fn render_trans<G: Game, S: Strategy<G>>(search: &TreeSearch<G, S>, state: &G::S)
 where
     G::S: NodeRender,
 {
-    print_trans::<G>(&search.index, &search.table, search.root_id, state.clone());
+    RenderConfig::new()
+        .transpositions(true)
+        .print(search, state);
 }
 
 pub trait NodeRender {
@@ -26,85 +45,253 @@ pub trait NodeRender {
     }
 }
 
-//////////////////////////////////////////////////////////////////////////////////////////////////
+/// Which part of the tree to render, and how.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderConfig {
+    // Nodes deeper than this below the root are left out
+    pub max_depth: usize,
+    // Nodes with fewer visits are left out, along with their subtrees
+    pub min_visits: u32,
+    // Only the most visited children of each node are kept
+    pub top_k: usize,
+    // Color each node from red to green by its expected score for the player
+    // who moved into it, normalized to the range of utilities seen
+    pub color_by_value: bool,
+    // Draw transposed nodes once, as found in the transposition table
+    pub transpositions: bool,
+}
 
-fn canonical_id<S: Eq + Clone>(
-    k: u64,
-    table: &TranspositionTable<S>,
-    state: S,
-) -> Option<index::Id> {
-    table.get_const(k, state).map(|ts| ts.node_id)
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            min_visits: 0,
+            top_k: usize::MAX,
+            color_by_value: false,
+            transpositions: false,
+        }
+    }
 }
 
-fn print_trans<G>(
-    index: &TreeIndex<G::A>,
-    table: &TranspositionTable<G::S>,
-    root_id: index::Id,
-    init_state: G::S,
-) where
-    G: Game,
-    G::S: NodeRender,
-{
-    println!("graph {{");
-    println!("  graph [ranksep=3, ratio=auto, concentrate=true, bgcolor=black];");
-    println!("  edge [color=white];");
-    println!("{}", G::S::preamble());
-    let mut stack = vec![(root_id, root_id, root_id, init_state.clone())];
-    while let Some((parent_id, parent_print_id, node_id, state)) = stack.pop() {
-        let hash = G::zobrist_hash(&state);
-        let print_id = canonical_id(hash, table, state.clone()).unwrap_or(root_id);
-        println!("  \"{}\" {};", print_id.get_raw(), state.render());
-        if parent_id != node_id {
-            println!(
-                "  \"{}\" -- \"{}\";",
-                parent_print_id.get_raw(),
-                print_id.get_raw()
-            );
+impl RenderConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn min_visits(mut self, min_visits: u32) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    pub fn top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    pub fn color_by_value(mut self, color_by_value: bool) -> Self {
+        self.color_by_value = color_by_value;
+        self
+    }
+
+    pub fn transpositions(mut self, transpositions: bool) -> Self {
+        self.transpositions = transpositions;
+        self
+    }
+
+    /// The tree below `state`, the root of the last search, as a DOT graph.
+    pub fn render<G: Game, S: Strategy<G>>(&self, search: &TreeSearch<G, S>, state: &G::S) -> String
+    where
+        G::S: NodeRender,
+    {
+        let table = self.transpositions.then_some(&search.table);
+        let mut out = String::new();
+        self.write_graph::<G>(
+            &mut out,
+            &search.index,
+            table,
+            &search.stats.score_bounds,
+            search.root_id,
+            state,
+        )
+        .unwrap();
+        out
+    }
+
+    pub fn print<G: Game, S: Strategy<G>>(&self, search: &TreeSearch<G, S>, state: &G::S)
+    where
+        G::S: NodeRender,
+    {
+        print!("{}", self.render(search, state));
+    }
+
+    fn write_graph<G>(
+        &self,
+        out: &mut String,
+        index: &TreeIndex<G::A>,
+        table: Option<&TranspositionTable<G::S>>,
+        bounds: &ScoreBounds,
+        root_id: index::Id,
+        init_state: &G::S,
+    ) -> std::fmt::Result
+    where
+        G: Game,
+        G::S: NodeRender,
+    {
+        writeln!(out, "graph {{")?;
+        if table.is_some() {
+            writeln!(
+                out,
+                "  graph [ranksep=3, ratio=auto, concentrate=true, bgcolor=black];"
+            )?;
+        } else {
+            writeln!(
+                out,
+                "  graph [layout=twopi, ranksep=3, ratio=auto, bgcolor=black];"
+            )?;
         }
-        let node = index.get(node_id);
-        if node.is_expanded() {
-            for edge in node.edges().iter().filter(|edge| edge.is_explored()) {
+        writeln!(out, "  edge [color=white];")?;
+        writeln!(out, "{}", G::S::preamble())?;
+
+        // (parent, parent's printed id, node, state, depth, value)
+        let mut stack = vec![(root_id, root_id, root_id, init_state.clone(), 0, None)];
+        while let Some((parent_id, parent_print_id, node_id, state, depth, value)) = stack.pop() {
+            let print_id = match table {
+                Some(table) => {
+                    canonical_id(G::zobrist_hash(&state), table, state.clone()).unwrap_or(root_id)
+                }
+                None => node_id,
+            };
+            writeln!(out, "  \"{}\" {};", print_id.get_raw(), state.render())?;
+            if let Some(value) = value.filter(|_| self.color_by_value) {
+                writeln!(
+                    out,
+                    "  \"{}\" [color=\"{}\"];",
+                    print_id.get_raw(),
+                    value_color(bounds.normalize(value))
+                )?;
+            }
+            if parent_id != node_id {
+                writeln!(
+                    out,
+                    "  \"{}\" -- \"{}\"{};",
+                    parent_print_id.get_raw(),
+                    print_id.get_raw(),
+                    edge_attrs(index, parent_id, node_id)
+                )?;
+            }
+            let node = index.get(node_id);
+            if !node.is_expanded() || depth >= self.max_depth {
+                continue;
+            }
+            let children = node
+                .children_by_visits()
+                .into_iter()
+                .filter(|edge| edge.is_explored() && edge.stats.num_visits >= self.min_visits)
+                .take(self.top_k)
+                .collect::<Vec<_>>();
+            // Pushed in reverse so that the most visited child is printed first
+            for edge in children.into_iter().rev() {
                 stack.push((
                     node_id,
                     print_id,
                     edge.node_id.unwrap(),
                     G::apply(state.clone(), &edge.action),
+                    depth + 1,
+                    Some(edge.stats.expected_score(node.player_idx)),
                 ));
             }
         }
+        writeln!(out, "}}")
     }
-    println!("}}");
 }
 
-fn print<G>(index: &TreeIndex<G::A>, root_id: index::Id)
-where
-    G: Game,
-    G::S: NodeRender,
-{
-    println!("graph {{");
-    println!("  graph [layout=twopi, ranksep=3, ratio=auto, bgcolor=black];");
-    println!("  edge [color=white];");
-    println!("{}", G::S::preamble());
-    let mut stack = vec![(root_id, root_id, G::S::default())];
-    while let Some((parent_id, node_id, state)) = stack.pop() {
-        println!("  \"{}\" {};", node_id.get_raw(), state.render());
-        if parent_id != node_id {
-            println!(
-                "  \"{}\" -- \"{}\";",
-                parent_id.get_raw(),
-                node_id.get_raw()
-            );
-        }
-        let node = index.get(node_id);
-        if node.is_expanded() {
-            for edge in node.edges().iter().filter(|x| x.is_explored()) {
-                stack.push((
-                    node_id,
-                    edge.node_id.unwrap(),
-                    G::apply(state.clone(), &edge.action),
-                ));
-            }
-        }
+//////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn canonical_id<S: Eq + Clone>(
+    k: u64,
+    table: &TranspositionTable<S>,
+    state: S,
+) -> Option<index::Id> {
+    table.get_const(k, state).map(|ts| ts.node_id)
+}
+
+// An HSV color from red at -1 to green at 1
+fn value_color(value: f64) -> String {
+    let hue = (value.clamp(-1., 1.) + 1.) / 6.;
+    format!("{hue:.3} 0.900 0.900")
+}
+
+// Proven edges are labeled with their result for the player making the move.
+fn edge_attrs<A: Action>(
+    index: &TreeIndex<A>,
+    parent_id: index::Id,
+    child_id: index::Id,
+) -> String {
+    let parent = index.get(parent_id);
+    parent
+        .edges()
+        .iter()
+        .find(|edge| edge.node_id == Some(child_id))
+        .and_then(|edge| edge.proof(parent.player_idx))
+        .map(|proof| {
+            let color = match proof {
+                Proof::Win => "green",
+                Proof::Loss => "red",
+                Proof::Draw => "yellow",
+            };
+            format!(
+                " [label=\"{}\", color={color}, fontcolor={color}]",
+                proof.marker()
+            )
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::{strategy, SearchConfig};
+    use crate::strategies::Search;
+
+    #[test]
+    fn test_render_config() {
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        let state = HashedPosition::new();
+        ts.choose_action(&state);
+
+        let nodes = |dot: &str| dot.lines().filter(|line| line.ends_with(" ;")).count();
+        let edges = |dot: &str| dot.lines().filter(|line| line.contains(" -- ")).count();
+
+        let dot = RenderConfig::new().render(&ts, &state);
+        assert_eq!(nodes(&dot), ts.node_count());
+        assert_eq!(edges(&dot), ts.node_count() - 1);
+        assert!(!dot.contains("color=\""));
+
+        let dot = RenderConfig::new().max_depth(1).render(&ts, &state);
+        assert_eq!(nodes(&dot), 10);
+        let dot = RenderConfig::new()
+            .max_depth(2)
+            .top_k(2)
+            .render(&ts, &state);
+        assert_eq!(nodes(&dot), 1 + 2 + 4);
+        assert_eq!(edges(&dot), 6);
+        let dot = RenderConfig::new().min_visits(u32::MAX).render(&ts, &state);
+        assert_eq!((nodes(&dot), edges(&dot)), (1, 0));
+
+        // Every node but the root is colored
+        let dot = RenderConfig::new()
+            .max_depth(1)
+            .color_by_value(true)
+            .render(&ts, &state);
+        assert_eq!(dot.matches("color=\"").count(), 9);
+        assert_eq!(value_color(-1.), "0.000 0.900 0.900");
+        assert_eq!(value_color(1.), "0.333 0.900 0.900");
     }
-    println!("}}");
 }
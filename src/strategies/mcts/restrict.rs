@@ -0,0 +1,224 @@
+//! Restricting the search to some of the root's actions, to analyze chosen
+//! candidate moves or to keep an engine from playing certain moves, such as
+//! repetitions. Either pass the actions for one search to
+//! `TreeSearch::search_restricted`, or filter every search with
+//! `SearchConfig::root_filter`:
+//!
+//! ```ignore
+//! let config = SearchConfig::new().root_filter(|state, action| !repeats(state, action));
+//! ```
+//!
+//! The other edges of the root are set aside for the search and put back
+//! afterwards, with their statistics unchanged. If none of the legal actions
+//! are allowed, the restriction is ignored rather than leaving the search
+//! without a move.
+
+use super::node::{Edge, NodeState};
+use super::{SearchConfig, SearchResult, Strategy, TreeSearch};
+use crate::game::Game;
+
+use std::sync::Arc;
+
+pub type RootFilter<G> = Arc<dyn Fn(&<G as Game>::S, &<G as Game>::A) -> bool + Send + Sync>;
+
+/// The root edges set aside by a restricted search, with their indices.
+pub(crate) type SetAside<A> = Vec<(usize, Edge<A>)>;
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Search `state` considering only the root actions in `allowed`.
+    pub fn search_restricted(&mut self, state: &G::S, allowed: &[G::A]) -> SearchResult<G::A> {
+        self.allowed = Some(allowed.to_vec());
+        let result = self.search(state);
+        self.allowed = None;
+        result
+    }
+
+    /// Choose among the root actions in `allowed`.
+    pub fn choose_action_restricted(&mut self, state: &G::S, allowed: &[G::A]) -> G::A {
+        self.search_restricted(state, allowed).action
+    }
+
+    /// Whether `action` may be chosen at the root `state`.
+    pub(crate) fn allows(&self, state: &G::S, action: &G::A) -> bool {
+        self.allowed
+            .as_ref()
+            .map_or(true, |allowed| allowed.contains(action))
+            && self
+                .config
+                .root_filter
+                .as_ref()
+                .map_or(true, |filter| filter(state, action))
+    }
+
+    /// Set aside the root edges which are not allowed, expanding the root if
+    /// needed.
+    pub(crate) fn restrict_root(&mut self, state: &G::S) {
+        if (self.allowed.is_none() && self.config.root_filter.is_none()) || G::is_terminal(state) {
+            return;
+        }
+        if self.index.get(self.root_id).is_leaf() {
+            self.expand(self.root_id, state);
+        } else if self.config.revalidate_edges {
+            self.revalidate_edges(self.root_id, state);
+        }
+        let NodeState::Expanded(edges) = &self.index.get(self.root_id).state else {
+            return;
+        };
+        let disallowed = edges
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| !self.allows(state, &edge.action))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if disallowed.len() == edges.len() {
+            return;
+        }
+        self.set_aside_root_edges(&disallowed);
+    }
+
+    /// Set aside the root edges at `indices`, which are in increasing order.
+    pub(crate) fn set_aside_root_edges(&mut self, indices: &[usize]) {
+        let edges = self.index.get_mut(self.root_id).edges_mut();
+        let mut set_aside = vec![];
+        let mut kept = vec![];
+        for (i, edge) in std::mem::take(edges).into_iter().enumerate() {
+            if indices.contains(&i) {
+                set_aside.push((i, edge));
+            } else {
+                kept.push(edge);
+            }
+        }
+        *edges = kept;
+        self.set_aside = Some(set_aside);
+    }
+
+    /// Put back the root edges set aside by `restrict_root`, in their places,
+    /// returning their indices.
+    pub(crate) fn unrestrict_root(&mut self) -> Option<Vec<usize>> {
+        let set_aside = self.set_aside.take()?;
+        let edges = self.index.get_mut(self.root_id).edges_mut();
+        let mut indices = vec![];
+        for (i, edge) in set_aside {
+            edges.insert(i, edge);
+            indices.push(i);
+        }
+        Some(indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::index::Id;
+    use crate::strategies::mcts::{strategy, NodeRecycling};
+    use crate::strategies::Search;
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    #[test]
+    fn test_restricted() {
+        let mut ts = TS::default().config(
+            SearchConfig::default()
+                .max_iterations(500)
+                .reuse_tree(Default::default())
+                .seed(0),
+        );
+        let state = HashedPosition::new();
+        let allowed = [Move(0), Move(8)];
+        let result = ts.search_restricted(&state, &allowed);
+        assert!(allowed.contains(&result.action));
+
+        // The other edges are back, but were not searched
+        let children = ts.root_children_stats();
+        assert_eq!(children.len(), 9);
+        let visited = children
+            .iter()
+            .filter(|child| child.visits > 0)
+            .map(|child| child.action)
+            .collect::<Vec<_>>();
+        assert_eq!(visited.len(), 2);
+        assert!(visited.iter().all(|action| allowed.contains(action)));
+        let edges = ts.index.get(ts.root_id).edges();
+        assert!(edges
+            .iter()
+            .enumerate()
+            .all(|(i, edge)| edge.action == Move(i as u8)));
+
+        // Reusing the tree, the next search may pick any move
+        ts.choose_action(&state);
+        assert!(ts
+            .root_children_stats()
+            .iter()
+            .all(|child| child.visits > 0));
+
+        // Nothing legal is allowed, so nothing is restricted
+        let result = ts.search_restricted(&state, &[]);
+        assert_eq!(ts.root_children_stats().len(), 9);
+        assert!(result.action.0 < 9);
+
+        let mut ts = TS::default().config(
+            SearchConfig::default()
+                .max_iterations(200)
+                .root_filter(|_, action| *action != Move(4))
+                .seed(0),
+        );
+        for _ in 0..5 {
+            assert_ne!(ts.choose_action(&state), Move(4));
+        }
+    }
+
+    // The number of nodes reachable from `id`, in `state`, checking that each
+    // edge leads to the node of the position after its action
+    fn reachable(ts: &TS, id: Id, state: &HashedPosition) -> usize {
+        let node = ts.index.get(id);
+        assert_eq!(node.hash, TicTacToe::zobrist_hash(state));
+        if !node.is_expanded() {
+            return 1;
+        }
+        let mut count = 1;
+        for edge in node.edges() {
+            if let Some(child_id) = edge.node_id {
+                let child = TicTacToe::apply(*state, &edge.action);
+                count += reachable(ts, child_id, &child);
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_restricted_recycling() {
+        let mut ts = TS::default().config(
+            SearchConfig::default()
+                .max_iterations(2000)
+                .max_nodes(30)
+                .node_recycling(NodeRecycling::Prune)
+                .reuse_tree(Default::default())
+                .seed(0),
+        );
+        let state = HashedPosition::new();
+        ts.search(&state);
+        let allowed = [Move(0), Move(8)];
+        let visits = |ts: &TS| {
+            ts.root_children_stats()
+                .iter()
+                .filter(|child| !allowed.contains(&child.action))
+                .map(|child| (child.action, child.visits))
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+        let before = visits(&ts);
+
+        // The tree is recycled while the other edges are set aside, and they
+        // still lead to their subtrees afterwards
+        ts.search_restricted(&state, &allowed);
+        assert!(ts.tree_metrics().recycled_nodes > 0);
+        assert_eq!(reachable(&ts, ts.root_id, &state), ts.index.len());
+        assert_eq!(visits(&ts), before);
+    }
+}
@@ -0,0 +1,325 @@
+//! Tree reuse between searches. See `TreeReuse`.
+
+use super::index::{self, Id};
+use super::node::{NodeState, NodeStats};
+use super::table::TranspositionTable;
+use super::{NodeRecycling, SearchConfig, Strategy, TreeIndex, TreeReuse, TreeSearch};
+use crate::game::Game;
+
+use rustc_hash::FxHashMap;
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Make the node for `state` the root, keeping its pruned subtree and
+    /// freeing the rest of the tree. Returns `None`, leaving the tree as it
+    /// was, if there was no previous search or `state` is not close enough
+    /// below its root.
+    pub(crate) fn reroot(&mut self, state: &G::S, reuse: TreeReuse) -> Option<Id> {
+        let previous = self.previous_root.take()?;
+        if self.index.is_empty() {
+            return None;
+        }
+        let (old_root, root_stats) = self.find_descendant(&previous, state)?;
+        let (index, table, root_id) = self.copy_subtree(old_root, state, &reuse);
+
+        self.stats.discarded_nodes = self.index.len() - index.len();
+        self.stats.retained_nodes = index.len();
+        self.index = index;
+        self.table = table;
+        self.root_id = root_id;
+        self.root_stats = root_stats;
+        self.reset_stats();
+        Some(root_id)
+    }
+
+    /// Free the least visited subtrees once the tree reaches
+    /// `SearchConfig::max_nodes`, keeping the root, which is in `state`, and
+    /// about half of the nodes. See `NodeRecycling`.
+    pub(crate) fn recycle_nodes(&mut self, state: &G::S) {
+        if self.config.node_recycling != NodeRecycling::Prune
+            || self.index.len() < self.config.max_nodes
+        {
+            return;
+        }
+        // The edges set aside by a restricted search point into the old arena,
+        // so they are copied along with the rest of the root's and set aside
+        // again afterwards
+        let set_aside = self.unrestrict_root();
+        let visits = self
+            .index
+            .iter()
+            .filter(|node| node.is_expanded())
+            .flat_map(|node| node.edges())
+            .filter(|edge| edge.node_id.is_some())
+            .map(|edge| edge.stats.num_visits)
+            .collect::<Vec<_>>();
+        let min_visits = recycle_threshold(visits, self.config.max_nodes / 2);
+        let reuse = TreeReuse::default().min_visits(min_visits);
+        let (index, table, root_id) = self.copy_subtree(self.root_id, state, &reuse);
+        // The root is always the first node, so the search loop's id for it
+        // stays valid
+        debug_assert_eq!(root_id, self.root_id);
+
+        self.stats.recycled_nodes += self.index.len() - index.len();
+        self.stats.tuned_nodes = index.len();
+        self.index = index;
+        self.table = table;
+        if let Some(indices) = set_aside {
+            self.set_aside_root_edges(&indices);
+        }
+    }
+
+    /// Copy the pruned subtree below `old_root`, which is in `state`, into a
+    /// new arena along with a transposition table for it. Returns them with
+    /// the new root's id.
+    fn copy_subtree(
+        &self,
+        old_root: Id,
+        state: &G::S,
+        reuse: &TreeReuse,
+    ) -> (TreeIndex<G::A>, TranspositionTable<G::S>, Id) {
+        let mut index = index::Arena::new();
+        let mut table = self.new_table();
+        let mut new_ids = FxHashMap::default();
+        let root_id = index.insert(self.index.get(old_root).clone());
+        index.get_mut(root_id).is_root = true;
+        new_ids.insert(old_root, root_id);
+
+        let track_states = self.config.use_transpositions;
+        let mut pending = vec![(root_id, 0, track_states.then(|| state.clone()))];
+        while let Some((node_id, depth, node_state)) = pending.pop() {
+            if let Some(node_state) = &node_state {
+                let hash = index.get(node_id).hash;
+                table.insert(hash, node_id, node_state.clone(), depth);
+            }
+            let NodeState::Expanded(edges) = &mut index.get_mut(node_id).state else {
+                continue;
+            };
+
+            let keep = kept_children(
+                edges.iter().map(|edge| edge.node_id.map(|_| &*edge.stats)),
+                depth,
+                reuse,
+            );
+            let mut children = vec![];
+            for (i, (edge, keep)) in edges.iter_mut().zip(keep).enumerate() {
+                let Some(old_id) = edge.node_id else {
+                    continue;
+                };
+                if !keep {
+                    edge.node_id = None;
+                    continue;
+                }
+                match new_ids.get(&old_id) {
+                    Some(new_id) => edge.node_id = Some(*new_id),
+                    None => children.push((i, old_id, edge.action.clone())),
+                }
+            }
+
+            for (i, old_id, action) in children {
+                if let Some(new_id) = new_ids.get(&old_id) {
+                    index.get_mut(node_id).edges_mut()[i].node_id = Some(*new_id);
+                    continue;
+                }
+                let mut child = self.index.get(old_id).clone();
+                child.is_root = false;
+                let new_id = index.insert(child);
+                new_ids.insert(old_id, new_id);
+                index.get_mut(node_id).edges_mut()[i].node_id = Some(new_id);
+                let child_state = node_state
+                    .as_ref()
+                    .map(|node_state| G::apply(node_state.clone(), &action));
+                pending.push((new_id, depth + 1, child_state));
+            }
+        }
+
+        (index, table, root_id)
+    }
+
+    /// The node for `state` among the positions up to `Game::num_players`
+    /// moves below the root, which is in `root_state`, along with its
+    /// statistics.
+    fn find_descendant(&self, root_state: &G::S, state: &G::S) -> Option<(Id, NodeStats)> {
+        let mut frontier = vec![(self.root_id, root_state.clone(), self.root_stats.clone())];
+        for depth in 0..=G::num_players() {
+            let mut next = vec![];
+            for (node_id, node_state, stats) in frontier {
+                if node_state == *state {
+                    return Some((node_id, stats));
+                }
+                if depth == G::num_players() {
+                    continue;
+                }
+                let NodeState::Expanded(edges) = &self.index.get(node_id).state else {
+                    continue;
+                };
+                next.extend(edges.iter().filter_map(|edge| {
+                    edge.node_id.map(|child_id| {
+                        let child_state = G::apply(node_state.clone(), &edge.action);
+                        (child_id, child_state, NodeStats::clone(&edge.stats))
+                    })
+                }));
+            }
+            frontier = next;
+        }
+        None
+    }
+}
+
+// The fewest visits an edge needs for its child to survive recycling, so that
+// at most `target` nodes, counting the root, are kept
+fn recycle_threshold(mut visits: Vec<u32>, target: usize) -> u32 {
+    let kept = target.saturating_sub(1);
+    if visits.len() <= kept {
+        return 0;
+    }
+    visits.sort_unstable_by_key(|visits| std::cmp::Reverse(*visits));
+    visits[kept] + 1
+}
+
+// Which of a node's explored children, given by their edge statistics, to keep
+fn kept_children<'a>(
+    children: impl Iterator<Item = Option<&'a NodeStats>>,
+    depth: usize,
+    reuse: &TreeReuse,
+) -> Vec<bool> {
+    let children = children.collect::<Vec<_>>();
+    if reuse.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+        return vec![false; children.len()];
+    }
+    let mut keep = children
+        .iter()
+        .map(|stats| stats.is_some_and(|stats| stats.num_visits >= reuse.min_visits))
+        .collect::<Vec<_>>();
+    if let Some(top_k) = reuse.top_k {
+        let mut ranked = (0..children.len()).filter(|i| keep[*i]).collect::<Vec<_>>();
+        ranked.sort_by_key(|i| std::cmp::Reverse(children[*i].unwrap().num_visits));
+        for i in ranked.into_iter().skip(top_k) {
+            keep[i] = false;
+        }
+    }
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, Move, TicTacToe};
+    use crate::strategies::mcts::{strategy, ConfigError};
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    fn play(moves: &[u8]) -> HashedPosition {
+        moves.iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        })
+    }
+
+    fn config(reuse: TreeReuse) -> SearchConfig<TicTacToe, strategy::Ucb1> {
+        SearchConfig::default()
+            .max_iterations(2000)
+            .reuse_tree(reuse)
+            .seed(0)
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut ts = TS::default().config(config(TreeReuse::default()));
+        ts.search(&play(&[]));
+        let nodes = ts.index.len();
+        let edge_visits = ts
+            .index
+            .get(ts.root_id)
+            .edges()
+            .iter()
+            .find(|edge| edge.action == Move(4))
+            .unwrap()
+            .stats
+            .num_visits;
+
+        // Two moves later, the subtree below the center opening is kept
+        ts.search(&play(&[4, 0]));
+        let metrics = ts.tree_metrics();
+        assert!(metrics.retained_nodes > 1);
+        assert!(metrics.discarded_nodes > 0);
+        assert_eq!(metrics.retained_nodes + metrics.discarded_nodes, nodes);
+        assert!(ts.root_stats.num_visits as usize >= 2000);
+        assert!(ts.root_stats.num_visits < edge_visits + 2000);
+        assert!(ts.index.get(ts.root_id).is_root());
+
+        // The same position again keeps the whole tree
+        let nodes = ts.index.len();
+        let result = ts.search(&play(&[4, 0]));
+        assert_eq!(ts.tree_metrics().retained_nodes, nodes);
+        assert_eq!(result.expansions, ts.index.len() - nodes);
+
+        // An unrelated position starts afresh
+        ts.search(&play(&[0, 1, 2]));
+        assert_eq!(ts.tree_metrics().retained_nodes, 0);
+    }
+
+    #[test]
+    fn test_pruning() {
+        let retained = |reuse: TreeReuse| {
+            let mut ts = TS::default().config(config(reuse).use_transpositions(true));
+            ts.search(&play(&[]));
+            ts.search(&play(&[4]));
+            ts.tree_metrics().retained_nodes
+        };
+
+        let all = retained(TreeReuse::default());
+        assert!(all > 100);
+        assert_eq!(retained(TreeReuse::default().max_depth(0)), 1);
+        assert_eq!(retained(TreeReuse::default().top_k(1).max_depth(3)), 4);
+        let pruned = retained(TreeReuse::default().min_visits(50));
+        assert!(1 < pruned && pruned < all);
+
+        assert_eq!(
+            config(TreeReuse::default().top_k(0)).validate(),
+            Err(ConfigError::InvalidTreeReuse(TreeReuse::default().top_k(0)))
+        );
+    }
+
+    #[test]
+    fn test_recycle_threshold() {
+        assert_eq!(recycle_threshold(vec![5, 1, 3], 10), 0);
+        assert_eq!(recycle_threshold(vec![5, 1, 3, 2], 3), 3);
+        assert_eq!(recycle_threshold(vec![4, 4, 4], 2), 5);
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let search = |recycling: NodeRecycling| {
+            let mut ts = TS::default().config(
+                SearchConfig::default()
+                    .max_iterations(5000)
+                    .max_nodes(30)
+                    .node_recycling(recycling)
+                    .use_transpositions(true)
+                    .seed(0),
+            );
+            let result = ts.search(&play(&[0, 3, 1, 4]));
+            assert_eq!(result.iterations, 5000);
+            assert_eq!(result.action, Move(2));
+            assert!(ts.index.len() <= 30);
+            assert!(ts.index.get(ts.root_id).is_root());
+            (result, ts.tree_metrics())
+        };
+
+        let (result, metrics) = search(NodeRecycling::Stop);
+        assert_eq!(metrics.recycled_nodes, 0);
+        assert_eq!(result.expansions, 29);
+
+        let (result, metrics) = search(NodeRecycling::Prune);
+        assert!(metrics.recycled_nodes > 0);
+        assert_eq!(
+            result.expansions,
+            metrics.num_nodes + metrics.recycled_nodes - 1
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Search parameters which vary from move to move. See
+//! `SearchConfig::exploration_schedule` and `SearchConfig::epsilon_schedule`.
+//!
+//! ```ignore
+//! let config = SearchConfig::new()
+//!     .exploration_schedule(Schedule::Linear { start: 1.4, end: 0.5, moves: 40 })
+//!     .epsilon_schedule(Schedule::custom(|ctx| match ctx.remaining {
+//!         Some(remaining) if remaining < Duration::from_secs(10) => 0.,
+//!         _ => 0.1,
+//!     }));
+//! ```
+
+use super::select::SelectStrategy;
+use super::simulate::SimulateStrategy;
+use super::{SearchConfig, Strategy, TreeSearch};
+use crate::game::Game;
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What a schedule is evaluated on before each search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScheduleContext {
+    // The searches run since `Search::prepare`, so 0 for the first move
+    pub move_number: usize,
+    // The time left on the clock, with `time_control`
+    pub remaining: Option<Duration>,
+}
+
+pub type ScheduleFn = Arc<dyn Fn(&ScheduleContext) -> f64 + Send + Sync>;
+
+/// A parameter as a function of the move number or the clock.
+#[derive(Clone)]
+pub enum Schedule {
+    Constant(f64),
+    // From `start` at the first move to `end` after `moves` moves
+    Linear {
+        start: f64,
+        end: f64,
+        moves: usize,
+    },
+    // From `start` towards `end`, halving the difference every `half_life`
+    // moves
+    Exponential {
+        start: f64,
+        end: f64,
+        half_life: f64,
+    },
+    Custom(ScheduleFn),
+}
+
+impl Schedule {
+    pub fn custom(f: impl Fn(&ScheduleContext) -> f64 + Send + Sync + 'static) -> Self {
+        Schedule::Custom(Arc::new(f))
+    }
+
+    pub fn value(&self, ctx: &ScheduleContext) -> f64 {
+        match self {
+            Schedule::Constant(value) => *value,
+            Schedule::Linear { start, end, moves } => {
+                let t = if *moves == 0 {
+                    1.
+                } else {
+                    (ctx.move_number as f64 / *moves as f64).min(1.)
+                };
+                start + (end - start) * t
+            }
+            Schedule::Exponential {
+                start,
+                end,
+                half_life,
+            } => end + (start - end) * 0.5f64.powf(ctx.move_number as f64 / half_life),
+            Schedule::Custom(f) => f(ctx),
+        }
+    }
+}
+
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Constant(value) => f.debug_tuple("Constant").field(value).finish(),
+            Schedule::Linear { start, end, moves } => f
+                .debug_struct("Linear")
+                .field("start", start)
+                .field("end", end)
+                .field("moves", moves)
+                .finish(),
+            Schedule::Exponential {
+                start,
+                end,
+                half_life,
+            } => f
+                .debug_struct("Exponential")
+                .field("start", start)
+                .field("end", end)
+                .field("half_life", half_life)
+                .finish(),
+            Schedule::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// Set the scheduled parameters for the next search, and count it.
+    pub(crate) fn apply_schedules(&mut self) {
+        let ctx = ScheduleContext {
+            move_number: self.move_number,
+            remaining: self.config.time_control.map(|control| control.remaining),
+        };
+        if let Some(schedule) = &self.config.exploration_schedule {
+            let value = schedule.value(&ctx);
+            self.config.select.set_exploration(value);
+        }
+        if let Some(schedule) = &self.config.epsilon_schedule {
+            let value = schedule.value(&ctx);
+            self.config.simulate.set_epsilon(value);
+        }
+        self.move_number += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::{strategy, TimeControl};
+    use crate::strategies::Search;
+
+    #[test]
+    fn test_schedule() {
+        let at = |move_number| ScheduleContext {
+            move_number,
+            remaining: None,
+        };
+        let linear = Schedule::Linear {
+            start: 2.,
+            end: 1.,
+            moves: 4,
+        };
+        assert_eq!(linear.value(&at(0)), 2.);
+        assert_eq!(linear.value(&at(2)), 1.5);
+        assert_eq!(linear.value(&at(10)), 1.);
+        let exponential = Schedule::Exponential {
+            start: 1.,
+            end: 0.,
+            half_life: 2.,
+        };
+        assert_eq!(exponential.value(&at(0)), 1.);
+        assert_eq!(exponential.value(&at(4)), 0.25);
+        assert_eq!(Schedule::Constant(0.3).value(&at(7)), 0.3);
+        assert_eq!(format!("{:?}", Schedule::custom(|_| 0.)), "Custom(..)");
+
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1Mast>::default().config(
+            SearchConfig::default()
+                .exploration_schedule(linear)
+                .epsilon_schedule(Schedule::custom(|ctx| match ctx.remaining {
+                    Some(remaining) if remaining < Duration::from_secs(1) => 0.,
+                    _ => 0.5,
+                }))
+                .time_control(TimeControl::new(
+                    Duration::from_secs(60),
+                    Duration::default(),
+                ))
+                .max_iterations(100)
+                .seed(0),
+        );
+        let mut state = HashedPosition::new();
+        for expected in [2., 1.75, 1.5] {
+            let action = ts.choose_action(&state);
+            assert_eq!(ts.config.select.exploration_constant, expected);
+            assert_eq!(ts.config.simulate.epsilon, 0.5);
+            state = TicTacToe::apply(state, &action);
+        }
+        ts.set_clock(Duration::from_millis(500));
+        ts.choose_action(&state);
+        assert_eq!(ts.config.simulate.epsilon, 0.);
+
+        // A new game starts the schedule over
+        ts.prepare(&HashedPosition::new());
+        ts.choose_action(&HashedPosition::new());
+        assert_eq!(ts.config.select.exploration_constant, 2.);
+    }
+}
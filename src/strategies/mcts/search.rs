@@ -1,26 +1,37 @@
 use super::backprop::BackpropStrategy;
+use super::clock;
+use super::config::EasyMove;
 use super::config::SearchConfig;
 use super::config::Strategy;
+use super::halving;
+use super::history::History;
 use super::index;
 use super::index::Id;
-use super::node;
 use super::node::Node;
 use super::node::NodeState;
 use super::node::NodeStats;
+use super::node::Proof;
+use super::node::{EdgeStats, PlayerStats};
+use super::restrict;
 use super::select::SelectContext;
 use super::select::SelectStrategy;
 use super::simulate::SimulateStrategy;
-use super::simulate::Trial;
-use super::stack::NodeStack;
-use super::table::TranspositionTable;
+use super::simulate::{EndType, Status, Trial};
+use super::solver;
+use super::stack::{NodeStack, ReversePairs};
+use super::table::{TableStats, TranspositionTable};
+use super::trace;
 use crate::game::Game;
 use crate::game::PlayerIndex;
 use crate::strategies::mcts::node::Edge;
-use crate::strategies::Search;
+use crate::strategies::{Search, SearchMetrics, Verdict};
 use crate::timer;
 use crate::util::pv_string;
 
+use rand::rngs::SmallRng;
 use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
 
 pub struct SearchContext<G: Game> {
     pub current_id: Id,
@@ -44,29 +55,285 @@ impl<G: Game> SearchContext<G> {
     }
 }
 
+/// The range of utilities backpropagated during the current search. See
+/// `SearchConfig::normalize_scores`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoreBounds {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for ScoreBounds {
+    fn default() -> Self {
+        Self {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl ScoreBounds {
+    pub fn update(&mut self, utilities: &[f64]) {
+        for u in utilities {
+            self.min = self.min.min(*u);
+            self.max = self.max.max(*u);
+        }
+    }
+
+    /// Map a score in [min, max] to [-1, 1]. Scores are returned unchanged
+    /// until the range is known.
+    #[inline]
+    pub fn normalize(&self, score: f64) -> f64 {
+        if self.max > self.min {
+            2. * (score - self.min) / (self.max - self.min) - 1.
+        } else {
+            score
+        }
+    }
+
+    /// The factor by which `normalize` scales differences between scores.
+    #[inline]
+    pub fn scale(&self) -> f64 {
+        if self.max > self.min {
+            2. / (self.max - self.min)
+        } else {
+            1.
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TreeStats<G: Game> {
-    pub actions: FxHashMap<G::A, node::ActionStats>,
-    pub grave: FxHashMap<u64, Vec<FxHashMap<G::A, node::ActionStats>>>,
-    pub player_actions: Vec<FxHashMap<G::A, node::ActionStats>>,
+    pub history: History<G>,
     pub accum_depth: usize,
     pub iter_count: usize,
+    pub score_bounds: ScoreBounds,
+    // The verdict suggested by the most recent searches, and for how many
+    // searches in a row. See `Adjudication`.
+    pub verdict_streak: Option<(Verdict, usize)>,
+    // Nodes carried over from the previous search, including the root, and
+    // nodes freed in doing so. See `TreeReuse`.
+    pub retained_nodes: usize,
+    pub discarded_nodes: usize,
+    // The most visited action and its visits at each node on a path backed up
+    // in this search, keyed by the node's hash. Only maintained when the
+    // `GUIDE` backprop flag is set.
+    pub guide: FxHashMap<u64, (G::A, u32)>,
+    // The expand threshold in effect, and the tree size when it was last
+    // adjusted. See `AutoExpand`.
+    pub expand_threshold: u32,
+    pub tuned_nodes: usize,
+    // Nodes freed in this search to stay within `SearchConfig::max_nodes`
+    pub recycled_nodes: usize,
 }
 
 impl<G: Game> Default for TreeStats<G> {
     fn default() -> Self {
         Self {
-            actions: FxHashMap::default(),
-            grave: FxHashMap::default(),
-            player_actions: vec![Default::default(); G::num_players()],
+            history: History::default(),
             accum_depth: 0,
             iter_count: 0,
+            score_bounds: ScoreBounds::default(),
+            verdict_streak: None,
+            retained_nodes: 0,
+            discarded_nodes: 0,
+            guide: FxHashMap::default(),
+            expand_threshold: 1,
+            tuned_nodes: 0,
+            recycled_nodes: 0,
         }
     }
 }
 
+impl<G: Game> TreeStats<G> {
+    /// Decay the MAST and GRAVE tables which persist between searches. Entries
+    /// which fall to zero visits are removed.
+    pub fn decay(&mut self, gamma: f64) {
+        self.history.decay(gamma);
+    }
+}
+
 pub type TreeIndex<A> = index::Arena<Node<A>>;
 
+fn proof_suffix(proof: Option<Proof>) -> String {
+    match proof {
+        Some(Proof::Win) => " (proven win)".into(),
+        Some(Proof::Loss) => " (proven loss)".into(),
+        Some(Proof::Draw) => " (proven draw)".into(),
+        None => "".into(),
+    }
+}
+
+/// True if the utilities sum to zero, up to rounding.
+pub fn is_zero_sum(utilities: &[f64]) -> bool {
+    let sum: f64 = utilities.iter().sum();
+    let scale = utilities.iter().map(|u| u.abs()).fold(1., f64::max);
+    sum.abs() <= 1e-9 * scale
+}
+
+/// The normalized `Game::action_priors` for `actions`, or a uniform prior if the
+/// game gives none or they do not sum to a positive weight.
+pub fn action_priors<G: Game>(state: &G::S, actions: &[G::A]) -> Vec<f32> {
+    normalize_priors(G::action_priors(state, actions), actions.len())
+}
+
+/// Prior weights for `n` actions normalized to sum to one, or a uniform prior
+/// if there are none or they do not sum to a positive weight.
+pub(crate) fn normalize_priors(weights: Option<Vec<f64>>, n: usize) -> Vec<f32> {
+    let uniform = || vec![1. / n.max(1) as f32; n];
+    let Some(weights) = weights else {
+        return uniform();
+    };
+    debug_assert_eq!(weights.len(), n);
+    let total: f64 = weights.iter().filter(|w| **w > 0.).sum();
+    if weights.len() != n || total <= 0. || !total.is_finite() {
+        return uniform();
+    }
+    weights.iter().map(|w| (w.max(0.) / total) as f32).collect()
+}
+
+/// Evaluate a leaf by solving, evaluating or playing it out, in that order of
+/// preference. See `TreeSearch::simulate`.
+pub(crate) fn simulate_with<G: Game, Sim: SimulateStrategy<G>>(
+    simulate: &mut Sim,
+    stats: &TreeStats<G>,
+    state: &G::S,
+    player: usize,
+    solve_threshold: usize,
+    max_playout_depth: usize,
+    rng: &mut SmallRng,
+) -> Trial<G> {
+    if solvable::<G>(state, solve_threshold) {
+        let solution = solver::solve::<G>(state);
+        return Trial {
+            depth: solution.actions.len(),
+            actions: solution.actions,
+            state: solution.state,
+            status: Status {
+                end_type: Some(EndType::Solved),
+            },
+            utilities: solution.utilities,
+        };
+    }
+
+    if !G::is_terminal(state) {
+        if let Some(utilities) = simulate.evaluate(state, stats, player) {
+            return evaluated_trial(state, utilities);
+        }
+    }
+
+    simulate.playout(
+        G::determinize(state.clone(), rng),
+        max_playout_depth,
+        stats,
+        player,
+        rng,
+    )
+}
+
+/// True if the leaf is close enough to the end to be solved rather than
+/// simulated. See `SearchConfig::solve_threshold`.
+pub(crate) fn solvable<G: Game>(state: &G::S, solve_threshold: usize) -> bool {
+    solve_threshold > 0 && G::estimated_remaining_moves(state).is_some_and(|n| n <= solve_threshold)
+}
+
+/// The trial for a leaf scored by `SimulateStrategy::evaluate`.
+pub(crate) fn evaluated_trial<G: Game>(state: &G::S, utilities: Vec<f64>) -> Trial<G> {
+    debug_assert_eq!(utilities.len(), G::num_players());
+    Trial {
+        actions: vec![],
+        state: state.clone(),
+        status: Status {
+            end_type: Some(EndType::Evaluated),
+        },
+        depth: 0,
+        utilities,
+    }
+}
+
+// Games whose verdict streaks are kept apart, for a search shared between
+// games played at the same time
+const MAX_ADJUDICATED_GAMES: usize = 8;
+
+// A verdict and the searches in a row which suggested it
+type VerdictStreak = Option<(Verdict, usize)>;
+
+/// True if `state` is `from` or follows it within `plies` moves.
+fn follows<G: Game>(from: &G::S, state: &G::S, plies: usize) -> bool {
+    if from == state {
+        return true;
+    }
+    if plies == 0 || G::is_terminal(from) {
+        return false;
+    }
+    let mut actions = Vec::new();
+    G::generate_actions(from, &mut actions);
+    actions
+        .iter()
+        .any(|action| follows::<G>(&G::apply(from.clone(), action), state, plies - 1))
+}
+
+/// Counters describing the most recent search.
+#[derive(Clone, Debug, Default)]
+pub struct TreeMetrics {
+    pub num_nodes: usize,
+    pub iterations: usize,
+    pub average_depth: f64,
+    // How often the clock was read to check the time budget, and the number
+    // of iterations between reads at the end of the search.
+    pub clock_reads: u64,
+    pub clock_interval: u32,
+    // Nodes carried over from the previous search and freed in doing so
+    pub retained_nodes: usize,
+    pub discarded_nodes: usize,
+    // The expand threshold in effect at the end of the search
+    pub expand_threshold: u32,
+    // Nodes freed to stay within the node limit. See `NodeRecycling`.
+    pub recycled_nodes: usize,
+    // Transposition table lookups and replacements. See `TableStats`.
+    pub table: TableStats,
+}
+
+/// An estimate of the memory held by the tree. See `TreeSearch::memory_metrics`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryMetrics {
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    // Edges whose statistics have been allocated. See `EdgeStats`.
+    pub allocated_edge_stats: usize,
+    // The arena, edge lists and allocated edge statistics
+    pub bytes: usize,
+    // The same, were every edge's statistics allocated at expansion
+    pub eager_bytes: usize,
+}
+
+/// The outcome of `TreeSearch::search`.
+#[derive(Clone, Debug)]
+pub struct SearchResult<A> {
+    pub action: A,
+    // Includes any `extra_iterations`
+    pub iterations: usize,
+    // Iterations spent resolving a disagreement between the most visited and
+    // best scoring root children. See `SearchConfig::disagreement_budget`.
+    pub extra_iterations: usize,
+    // Nodes added to the tree, and actions applied in the tree and playouts
+    pub expansions: usize,
+    pub applied_actions: usize,
+    pub elapsed: Duration,
+    // The search returned early because one root action dominated. See
+    // `EasyMove`.
+    pub easy_move: bool,
+    // The search returned early because the runner-up could not overtake the
+    // most visited root child in the time left. See `TimeControl`.
+    pub early_stop: bool,
+    // The action was played from `SearchConfig::opening_book` without
+    // searching.
+    pub book_move: bool,
+    // The player to move should resign or may claim the win. See
+    // `Adjudication`.
+    pub verdict: Option<Verdict>,
+}
+
 #[derive(Clone)]
 pub struct TreeSearch<G, S>
 where
@@ -77,15 +344,45 @@ where
 {
     pub(crate) index: TreeIndex<G::A>,
     pub(crate) timer: timer::Timer,
+    pub(crate) stop: timer::StopHandle,
     pub(crate) root_id: Id,
     pub(crate) root_stats: NodeStats,
     pub(crate) pv: Vec<G::A>,
     pub(crate) table: TranspositionTable<G::S>,
+    pub(crate) prior: Option<Vec<(G::A, f64)>>,
 
     pub config: SearchConfig<G, S>,
     pub stats: TreeStats<G>,
     pub stack: Vec<Id>,
+    pub(crate) path: Vec<(G::A, usize)>,
     pub trial: Option<Trial<G>>,
+    // Playouts kept for analysis (see `SearchConfig::record_trials`), and the
+    // number of playouts so far in this search
+    pub(crate) recorded_trials: VecDeque<Trial<G>>,
+    pub(crate) trial_count: usize,
+    // The root state of the previous search, kept for `TreeReuse`
+    pub(crate) previous_root: Option<G::S>,
+    // The last root of each game being adjudicated, with its verdict streak,
+    // most recent first
+    pub(crate) verdict_games: VecDeque<(G::S, VerdictStreak)>,
+    // Continue from the tree restored from a checkpoint in the next search
+    pub(crate) resume: bool,
+    // The rounds of `SequentialHalving` in this search, once the root is
+    // expanded
+    pub(crate) halving: Option<halving::Halving>,
+    // The progress of `TimeControl` through this search
+    pub(crate) time_manager: Option<clock::TimeManager>,
+    // The searches recorded for or replayed from a `SearchTrace`
+    pub(crate) tracer: trace::Tracer,
+    // The seed the generator was reseeded with at the end of the last search,
+    // for checkpoints
+    pub(crate) rng_seed: u64,
+    // The searches since `prepare`, for `Schedule`
+    pub(crate) move_number: usize,
+    // The root actions allowed in this search, and the root edges set aside
+    // for it. See `restrict`.
+    pub(crate) allowed: Option<Vec<G::A>>,
+    pub(crate) set_aside: Option<restrict::SetAside<G::A>>,
 }
 
 impl<G, S> TreeSearch<G, S>
@@ -127,11 +424,26 @@ where
             root_stats: NodeStats::new(G::num_players()),
             pv: vec![],
             stack: vec![],
+            path: vec![],
             table: TranspositionTable::default(),
+            prior: None,
             trial: None,
+            recorded_trials: VecDeque::new(),
+            trial_count: 0,
+            previous_root: None,
+            verdict_games: VecDeque::new(),
+            resume: false,
+            halving: None,
+            time_manager: None,
+            tracer: Default::default(),
+            rng_seed: 0,
+            move_number: 0,
+            allowed: None,
+            set_aside: None,
             index,
             config: S::config(),
             timer: timer::Timer::new(),
+            stop: timer::StopHandle::new(),
             stats: Default::default(),
         }
     }
@@ -143,28 +455,136 @@ where
         self.root_id
     }
 
+    /// A handle to interrupt the search from another thread. A stopped search
+    /// returns its best action so far, once the root has been expanded. Each
+    /// search clears the request as it returns, so a stop made between
+    /// searches ends the next one early.
+    pub fn stop_handle(&self) -> timer::StopHandle {
+        self.stop.clone()
+    }
+
+    /// Seed the next search with a distribution over the root's actions, such
+    /// as one taken from a book or a previous search. Each edge is credited
+    /// with its share of `warm_start_visits` pseudo-visits, each valued at the
+    /// root's mean utilities so far, or zero for a fresh root, so that the
+    /// prior shifts visits without biasing the scores. Actions which are not
+    /// legal at the root are ignored and the remainder renormalized.
+    pub fn warm_start(&mut self, policy: &[(G::A, f64)]) {
+        self.prior = Some(policy.to_vec());
+    }
+
+    fn apply_warm_start(&mut self, state: &G::S) {
+        let Some(policy) = self.prior.take() else {
+            return;
+        };
+        if G::is_terminal(state) {
+            return;
+        }
+
+        let utilities = (0..G::num_players())
+            .map(|player| self.root_stats.expected_score(player))
+            .collect::<Vec<_>>();
+
+        if self.index.get(self.root_id).is_leaf() {
+            self.expand(self.root_id, state);
+        }
+        let edges = self.index.get_mut(self.root_id).edges_mut();
+        let priors = policy
+            .iter()
+            .filter_map(|(action, p)| {
+                let idx = edges.iter().position(|edge| edge.action == *action)?;
+                Some((idx, p.max(0.)))
+            })
+            .collect::<Vec<_>>();
+        let total: f64 = priors.iter().map(|(_, p)| p).sum();
+        if total <= 0. {
+            return;
+        }
+
+        for (idx, p) in priors {
+            let visits = (self.config.warm_start_visits as f64 * p / total).round();
+            for _ in 0..visits as u32 {
+                edges[idx].stats.update(&utilities);
+                self.root_stats.update(&utilities);
+            }
+        }
+    }
+
+    /// The normalized priors of `actions`, from the simulate strategy or else
+    /// from `Game::action_priors`.
+    fn edge_priors(&mut self, state: &G::S, actions: &[G::A]) -> Vec<f32> {
+        let weights = self
+            .config
+            .simulate
+            .priors(state, actions)
+            .or_else(|| G::action_priors(state, actions));
+        normalize_priors(weights, actions.len())
+    }
+
     #[inline]
     pub fn expand(&mut self, node_id: Id, state: &G::S) -> &NodeState<G::A> {
-        let node = self.index.get_mut(node_id);
         if G::is_terminal(state) {
-            node.state = NodeState::Terminal;
+            self.index.get_mut(node_id).state = NodeState::Terminal;
         } else {
             let mut actions = Vec::new();
             G::generate_actions(state, &mut actions);
             debug_assert!(!actions.is_empty());
-            node.state = NodeState::Expanded(
+            let priors = self.edge_priors(state, &actions);
+            self.index.get_mut(node_id).state = NodeState::Expanded(
                 actions
                     .into_iter()
-                    .map(|action| Edge::unexplored(action, G::num_players()))
+                    .zip(priors)
+                    .map(|(action, prior)| Edge {
+                        prior,
+                        ..Edge::unexplored(action, G::num_players())
+                    })
                     .collect(),
             );
         }
-        &node.state // .clone()
+        &self.index.get(node_id).state
+    }
+
+    /// Bring an expanded node's edges in line with the actions legal in
+    /// `state`. Edges for actions which are still legal keep their statistics
+    /// and children, stale edges are dropped, and new actions are added
+    /// unexplored. Edges follow the order of `Game::generate_actions`.
+    pub fn revalidate_edges(&mut self, node_id: Id, state: &G::S) {
+        if !self.index.get(node_id).is_expanded() {
+            return;
+        }
+        let mut actions = Vec::new();
+        G::generate_actions(state, &mut actions);
+        let priors = self.edge_priors(state, &actions);
+        let edges = self.index.get_mut(node_id).edges_mut();
+
+        let mut stale = std::mem::take(edges);
+        *edges = actions
+            .into_iter()
+            .zip(priors)
+            .map(|(action, prior)| {
+                match stale
+                    .iter()
+                    .position(|edge| G::actions_equal_modulo_state(state, &edge.action, &action))
+                {
+                    Some(i) => Edge {
+                        action,
+                        prior,
+                        ..stale.swap_remove(i)
+                    },
+                    None => Edge {
+                        prior,
+                        ..Edge::unexplored(action, G::num_players())
+                    },
+                }
+            })
+            .collect();
     }
 
     #[inline]
     pub fn select(&mut self, ctx: &mut SearchContext<G>) {
         let player = G::player_to_move(&ctx.state).to_index();
+        let grave_threshold = self.config.select.grave_threshold();
+        let mut grave_ref = ctx.current_id;
         debug_assert!(self.stack.is_empty());
         loop {
             self.stack.push(ctx.current_id);
@@ -174,7 +594,7 @@ where
                 .current_stats(&self.index, &self.root_stats)
                 .num_visits;
             let node = self.index.get(ctx.current_id);
-            if node.is_terminal() || num_visits < self.config.expand_threshold {
+            if node.is_terminal() || num_visits < self.expand_threshold() {
                 return;
             }
 
@@ -184,19 +604,37 @@ where
                 if matches!(node_state, NodeState::Terminal) {
                     return;
                 }
+            } else if self.config.revalidate_edges
+                && !(self.set_aside.is_some() && ctx.current_id == self.root_id)
+            {
+                self.revalidate_edges(ctx.current_id, &ctx.state);
             }
 
-            let best_idx = {
+            let halving_idx = if self.stack.len() == 1 {
+                self.halving_child(&ctx.state)
+            } else {
+                None
+            };
+            let best_idx = if let Some(idx) = halving_idx {
+                idx
+            } else {
                 let select_ctx = SelectContext {
                     q_init: self.config.q_init,
                     stack: &stack,
+                    path: &self.path,
+                    grave_ref,
                     root_stats: &self.root_stats,
                     player,
                     state: &ctx.state,
                     index: &self.index,
                     table: &self.table,
-                    grave: &self.stats.grave,
+                    history: &self.stats.history,
                     use_transpositions: self.config.use_transpositions,
+                    ucd: self.config.ucd,
+                    score_bounds: self
+                        .config
+                        .normalize_scores
+                        .then_some(&self.stats.score_bounds),
                 };
 
                 self.config
@@ -207,14 +645,29 @@ where
             let NodeState::Expanded(ref edges) = &(self.index.get(ctx.current_id).state) else {
                 unreachable!()
             };
+            if edges[best_idx].node_id.is_none() && self.index.len() >= self.config.max_nodes {
+                // No room for the child, so play out from here
+                return;
+            }
+            self.path.push((
+                edges[best_idx].action.clone(),
+                G::player_to_move(&ctx.state).to_index(),
+            ));
+            // The deepest node reached through an edge with enough visits
+            // is the GRAVE reference for everything below it.
+            let advance_ref =
+                grave_threshold.is_some_and(|t| edges[best_idx].stats.total_visits() >= t);
 
             if let Some(child_id) = edges[best_idx].node_id {
                 ctx.traverse_apply(child_id, &edges[best_idx].action);
+                if advance_ref {
+                    grave_ref = child_id;
+                }
             } else {
                 {
                     let mut actions = vec![];
                     G::generate_actions(&ctx.state, &mut actions);
-                    debug_assert_eq!(actions[best_idx], edges[best_idx].action);
+                    debug_assert!(actions.contains(&edges[best_idx].action));
                 }
 
                 let action = &edges[best_idx].action;
@@ -224,6 +677,9 @@ where
 
                 ctx.traverse(child_id);
                 ctx.state = state;
+                if advance_ref {
+                    grave_ref = child_id;
+                }
 
                 if self.config.expand_threshold > 0 {
                     self.stack.push(ctx.current_id);
@@ -238,12 +694,14 @@ where
         let child_id = {
             if self.config.use_transpositions {
                 // TODO: the following won't work with symmetries
-                if let Some(entry) = self.table.get(hash, state.clone()) {
+                let depth = self.stack.len();
+                let linking = self.config.transposition_linking;
+                if let Some(entry) = self.table.get_linkable(hash, state, depth, linking) {
                     entry.node_id
                 } else {
                     let child = Node::new(G::player_to_move(state).to_index(), hash);
                     let node_id = self.index.insert(child);
-                    self.table.insert(hash, node_id, state.clone());
+                    self.table.insert(hash, node_id, state.clone(), depth);
                     node_id
                 }
             } else {
@@ -264,18 +722,31 @@ where
 
     #[inline]
     fn select_final_action(&mut self, state: &G::S) -> G::A {
+        if let Some(idx) = self.halving_final_child(state) {
+            match &(self.index.get(self.root_id).state) {
+                NodeState::Expanded(edges) => return edges[idx].action.clone(),
+                _ => unreachable!(),
+            }
+        }
         let stack = NodeStack::new(vec![self.root_id]);
         let idx = self.config.final_action.best_child(
             &SelectContext {
                 q_init: self.config.q_init,
                 stack: &stack,
+                path: &[],
+                grave_ref: self.root_id,
                 root_stats: &self.root_stats,
                 player: G::player_to_move(state).to_index(),
                 state,
                 index: &self.index,
                 table: &self.table,
-                grave: &self.stats.grave,
+                history: &self.stats.history,
                 use_transpositions: self.config.use_transpositions,
+                ucd: self.config.ucd,
+                score_bounds: self
+                    .config
+                    .normalize_scores
+                    .then_some(&self.stats.score_bounds),
             },
             &mut self.config.rng,
         );
@@ -288,19 +759,29 @@ where
 
     #[inline]
     pub(crate) fn simulate(&mut self, state: &G::S, player: usize) -> Trial<G> {
-        self.config.simulate.playout(
-            G::determinize(state.clone(), &mut self.config.rng),
-            self.config.max_playout_depth,
+        simulate_with(
+            &mut self.config.simulate,
             &self.stats,
+            state,
             player,
+            self.config.solve_threshold,
+            self.config.max_playout_depth,
             &mut self.config.rng,
         )
     }
 
     #[inline]
     pub(crate) fn backprop(&mut self, player: usize) {
+        debug_assert!(
+            !self.config.zero_sum || is_zero_sum(&self.trial.as_ref().unwrap().utilities),
+            "utilities {:?} do not sum to zero",
+            self.trial.as_ref().unwrap().utilities
+        );
         self.stats.iter_count += 1;
         self.stats.accum_depth += self.trial.as_ref().unwrap().depth + self.stack.len() - 1;
+        self.stats
+            .score_bounds
+            .update(&self.trial.as_ref().unwrap().utilities);
         let flags = self.config.select.backprop_flags() | self.config.simulate.backprop_flags();
         let stack = NodeStack::new(self.stack.clone());
         self.config
@@ -316,6 +797,24 @@ where
                 player,
                 flags,
             );
+        if flags.guide() {
+            self.update_guide();
+        }
+    }
+
+    /// Record the most visited action of each expanded node on the path.
+    fn update_guide(&mut self) {
+        for node_id in &self.stack {
+            let node = self.index.get(*node_id);
+            if !node.is_expanded() {
+                continue;
+            }
+            if let Some(edge) = node.edges().iter().max_by_key(|e| e.stats.num_visits) {
+                self.stats
+                    .guide
+                    .insert(node.hash, (edge.action.clone(), edge.stats.num_visits));
+            }
+        }
     }
 
     #[allow(dead_code)]
@@ -341,7 +840,7 @@ where
             return;
         }
 
-        let num_threads = 1;
+        let num_threads = self.config.tree_parallel.map_or(1, |p| p.threads);
         let root = self.index.get(self.root_id);
         let total_visits = self.root_stats.num_visits;
         let rate = total_visits as f64 / num_threads as f64 / self.timer.elapsed().as_secs_f64();
@@ -352,52 +851,555 @@ where
 
         let player = G::player_to_move(state);
 
-        // Sort moves by visit count, largest first.
-        let mut children = match &(root.state) {
-            NodeState::Expanded(edges) => edges
-                .iter()
-                .filter(|edge| edge.is_explored())
-                .map(|edge| {
-                    (
-                        edge.stats.num_visits,
-                        edge.stats.player[player.to_index()].score,
-                        edge.action.clone(),
-                    )
-                })
-                .collect::<Vec<_>>(),
-            _ => unreachable!(),
-        };
-
-        children.sort_by_key(|t| !t.0);
+        // Moves by visit count, largest first.
+        let children = root
+            .children_by_visits()
+            .into_iter()
+            .filter(|edge| edge.is_explored())
+            .map(|edge| {
+                (
+                    edge.stats.num_visits,
+                    edge.stats.player[player.to_index()].score,
+                    edge.action.clone(),
+                    edge.proof(player.to_index()),
+                )
+            });
 
         // Dump stats about the top 10 nodes.
-        for (visits, score, m) in children.into_iter().take(10) {
+        for (visits, score, m, proof) in children.take(10) {
             // Normalized so all wins is 100%, all draws is 50%, and all losses is 0%.
             let win_rate = (score + visits as f64) / (visits as f64 * 2.0);
             eprintln!(
-                "{:>6} visits, {:.02}% wins: {}",
+                "{:>6} visits, {:.02}% wins: {}{}",
                 visits,
                 win_rate * 100.0,
                 G::notation(state, &m),
+                proof_suffix(proof),
             );
         }
 
-        eprintln!("PV: {}", pv_string::<G>(self.pv.as_slice(), state))
+        eprintln!(
+            "PV: {}{}",
+            pv_string::<G>(self.pv.as_slice(), state),
+            proof_suffix(self.root_proof())
+        )
     }
 
     #[inline]
     pub(crate) fn reset_iter(&mut self) {
         self.stack.clear();
+        self.path.clear();
         self.trial = None;
     }
 
+    /// An empty transposition table as configured.
+    pub(crate) fn new_table(&self) -> TranspositionTable<G::S> {
+        TranspositionTable::new(self.config.table_bounds)
+            .verification(self.config.table_verification)
+    }
+
     #[inline]
     pub(crate) fn reset(&mut self, player_idx: usize, hash: u64) -> Id {
         self.index.clear();
-        self.table.clear();
+        self.table = self.new_table();
+        self.root_stats = NodeStats::new(G::num_players());
+        self.stats.retained_nodes = 0;
+        self.stats.discarded_nodes = 0;
+        self.reset_stats();
+        self.new_root(player_idx, hash)
+    }
+
+    /// Reset the per search counters, and decay the persistent statistics.
+    pub(crate) fn reset_stats(&mut self) {
         self.stats.accum_depth = 0;
+        self.stats.guide.clear();
+        self.stats.expand_threshold = self.config.expand_threshold;
+        self.stats.tuned_nodes = self.index.len();
+        self.stats.recycled_nodes = 0;
         self.stats.iter_count = 0;
-        self.new_root(player_idx, hash)
+        self.stats.score_bounds = ScoreBounds::default();
+        self.recorded_trials.clear();
+        self.trial_count = 0;
+        if self.config.stats_decay < 1. {
+            self.stats.decay(self.config.stats_decay);
+        }
+    }
+
+    /// Validate the config and set up a fresh tree rooted at `state`.
+    pub(crate) fn begin(&mut self, state: &G::S) -> Id {
+        if let Err(err) = self.config.validate() {
+            panic!("invalid search config: {err}");
+        }
+
+        let reuse = if std::mem::take(&mut self.resume) {
+            Some(self.config.reuse_tree.unwrap_or_default())
+        } else {
+            self.config.reuse_tree
+        };
+        let reused = reuse.and_then(|reuse| self.reroot(state, reuse));
+        let root_id = match reused {
+            Some(root_id) => root_id,
+            None => {
+                let hash = G::zobrist_hash(state);
+                let root_id = self.reset(G::player_to_move(state).to_index(), hash);
+                if self.config.use_transpositions {
+                    self.table.insert(hash, root_id, state.clone(), 0);
+                }
+                root_id
+            }
+        };
+        if self.config.reuse_tree.is_some() {
+            self.previous_root = Some(state.clone());
+        }
+        self.halving = None;
+        self.apply_warm_start(state);
+        root_id
+    }
+
+    /// Run a search from `state`, returning the chosen action along with a
+    /// summary of how the search went.
+    pub fn search(&mut self, state: &G::S) -> SearchResult<G::A> {
+        self.apply_schedules();
+        if let Some(action) = self
+            .config
+            .opening_book
+            .as_ref()
+            .and_then(|book| book.lookup::<G>(state))
+            .filter(|action| self.allows(state, action))
+        {
+            return SearchResult {
+                action,
+                iterations: 0,
+                extra_iterations: 0,
+                expansions: 0,
+                applied_actions: 0,
+                elapsed: Duration::default(),
+                easy_move: false,
+                early_stop: false,
+                book_move: true,
+                verdict: None,
+            };
+        }
+
+        let replayed = self.start_trace();
+        let root_id = self.begin(state);
+        self.restrict_root(state);
+        self.start_clock();
+
+        let mut iterations = 0;
+        let mut easy_move = false;
+        let mut early_stop = false;
+        let mut previous_best = None;
+        if let Some(replayed) = replayed {
+            // Exactly the recorded iterations, whatever the clock says
+            while iterations < replayed.iterations {
+                self.iterate(root_id, state);
+                iterations += 1;
+                self.report_progress(state, iterations, false);
+            }
+        } else if let Some(parallel) = self.config.tree_parallel {
+            iterations =
+                self.iterate_parallel(root_id, state, self.config.max_iterations, parallel);
+        } else if let Some(batch) = self.config.eval_batch {
+            (iterations, easy_move, early_stop) =
+                self.iterate_batched(root_id, state, 0, self.config.max_iterations, batch);
+        } else {
+            while iterations < self.config.max_iterations {
+                if self.timer.done() || self.work_budget_exhausted() || self.stop_requested() {
+                    break;
+                }
+                self.iterate(root_id, state);
+                iterations += 1;
+
+                self.report_progress(state, iterations, false);
+                if self.check_clock(state, iterations) {
+                    early_stop = true;
+                    break;
+                }
+                if self.check_easy_move(state, iterations, &mut previous_best) {
+                    easy_move = true;
+                    break;
+                }
+            }
+        }
+
+        // Second stage: if the most visited and best scoring children disagree,
+        // keep searching (up to a fraction of the iterations already spent)
+        // until they agree, within the same deadline and work budget.
+        let mut extra_iterations = 0;
+        if let Some(replayed) = replayed {
+            while extra_iterations < replayed.extra_iterations {
+                self.iterate(root_id, state);
+                extra_iterations += 1;
+            }
+        } else {
+            let extra_budget =
+                (iterations as f64 * self.config.disagreement_budget).ceil() as usize;
+            while extra_iterations < extra_budget
+                && !self.timer.done()
+                && !self.work_budget_exhausted()
+                && !self.stop_requested()
+                && self.root_disagreement(state)
+            {
+                extra_iterations += self.iterate_round(
+                    root_id,
+                    state,
+                    iterations + extra_iterations,
+                    extra_budget - extra_iterations,
+                );
+            }
+        }
+        self.end_trace(state, iterations, extra_iterations);
+        self.stop.reset();
+
+        self.compute_pv(state);
+        if self.config.sort_children {
+            self.sort_pv_children();
+        }
+        self.verbose_summary(state);
+        if self.config.tree_parallel.is_none() {
+            self.report_progress(state, iterations + extra_iterations, true);
+        }
+        let verdict = self.adjudicate(state, iterations + extra_iterations);
+        let action = self.select_final_action(state);
+        self.unrestrict_root();
+        self.reseed_rng();
+
+        // NOTE: this can fail when root is a leaf. This happens if:
+        //
+        //     max_iterations < expand_threshold
+        //
+        // TODO: We might check for this and unconditionally expand root. I think
+        // a lot of implementations fully expand root on the first iteration.
+        SearchResult {
+            action,
+            iterations: iterations + extra_iterations,
+            extra_iterations,
+            expansions: self.expansions(),
+            applied_actions: self.stats.accum_depth,
+            elapsed: self.timer.elapsed(),
+            easy_move,
+            early_stop,
+            book_move: false,
+            verdict,
+        }
+    }
+
+    /// Update the verdict streak of the game `state` is from with this
+    /// search's root score, returning the verdict once it has held for long
+    /// enough.
+    fn adjudicate(&mut self, state: &G::S, iterations: usize) -> Option<Verdict> {
+        let adjudication = self.config.adjudication?;
+        let player = G::player_to_move(state).to_index();
+        let score = self.root_stats.expected_score(player);
+        let game = self
+            .verdict_games
+            .iter()
+            .position(|(last, _)| follows::<G>(last, state, G::num_players()));
+        let streak = game.and_then(|i| self.verdict_games.remove(i).unwrap().1);
+        self.stats.verdict_streak = match adjudication.verdict(score, iterations) {
+            None => None,
+            Some(verdict) => match streak {
+                Some((previous, count)) if previous == verdict => Some((verdict, count + 1)),
+                _ => Some((verdict, 1)),
+            },
+        };
+        self.verdict_games
+            .push_front((state.clone(), self.stats.verdict_streak));
+        self.verdict_games.truncate(MAX_ADJUDICATED_GAMES);
+        self.stats
+            .verdict_streak
+            .filter(|(_, count)| *count >= adjudication.consecutive)
+            .map(|(verdict, _)| verdict)
+    }
+
+    #[inline]
+    pub(crate) fn iterate(&mut self, root_id: Id, state: &G::S) {
+        self.reset_iter();
+        self.recycle_nodes(state);
+        let mut ctx = SearchContext::new(root_id, state.clone());
+
+        self.select(&mut ctx);
+        self.trial = Some(self.simulate(&ctx.state, G::player_to_move(state).to_index()));
+        self.finish_iteration(G::player_to_move(state).to_index());
+    }
+
+    /// Back up the trial along the stack.
+    pub(crate) fn finish_iteration(&mut self, player: usize) {
+        self.record_trial();
+        self.backprop(player);
+        self.propagate_proofs();
+        self.tune_expand_threshold();
+    }
+
+    /// The expand threshold in effect, which `AutoExpand` may have raised above
+    /// the configured one.
+    pub fn expand_threshold(&self) -> u32 {
+        if self.config.auto_expand.is_some() {
+            self.stats.expand_threshold
+        } else {
+            self.config.expand_threshold
+        }
+    }
+
+    fn tune_expand_threshold(&mut self) {
+        let Some(auto) = self.config.auto_expand else {
+            return;
+        };
+        if self.stats.iter_count % auto.check_interval != 0 {
+            return;
+        }
+        let num_nodes = self.index.len();
+        let created = num_nodes.saturating_sub(self.stats.tuned_nodes);
+        let bounded = self.timer.budget().is_some() || self.config.max_iterations != usize::MAX;
+        let used = self.budget_used(self.stats.iter_count);
+        let remaining_iterations = (bounded && used > 0.)
+            .then(|| self.stats.iter_count as f64 * (1. - used).max(0.) / used);
+        self.stats.expand_threshold = auto.adjust(
+            self.stats.expand_threshold,
+            self.config.expand_threshold,
+            created,
+            num_nodes,
+            remaining_iterations,
+        );
+        self.stats.tuned_nodes = num_nodes;
+    }
+
+    fn record_trial(&mut self) {
+        let Some(recording) = self.config.record_trials else {
+            return;
+        };
+        if self.trial_count % recording.every == 0 && recording.capacity > 0 {
+            if self.recorded_trials.len() == recording.capacity {
+                self.recorded_trials.pop_front();
+            }
+            self.recorded_trials
+                .push_back(self.trial.as_ref().unwrap().clone());
+        }
+        self.trial_count += 1;
+    }
+
+    /// The playouts recorded during the last search, oldest first. Empty
+    /// unless `SearchConfig::record_trials` is set.
+    pub fn recorded_trials(&self) -> &VecDeque<Trial<G>> {
+        &self.recorded_trials
+    }
+
+    /// Take the recorded playouts, leaving the buffer empty.
+    pub fn take_recorded_trials(&mut self) -> Vec<Trial<G>> {
+        self.recorded_trials.drain(..).collect()
+    }
+
+    /// If the trial's result is exact (the leaf was terminal or solved), mark
+    /// the edge into the leaf as proven and continue up the path for as long
+    /// as each parent's result follows from its edges.
+    fn propagate_proofs(&mut self) {
+        let trial = self.trial.as_ref().unwrap();
+        let exact = match trial.status.end_type {
+            Some(EndType::Solved) => true,
+            Some(EndType::NaturalEnd) => trial.actions.is_empty(),
+            _ => false,
+        };
+        if !exact || G::has_simultaneous_moves() {
+            return;
+        }
+
+        let mut utilities = trial.utilities.clone();
+        for (parent_id, child_id) in ReversePairs::new(&self.stack) {
+            let parent = self.index.get_mut(*parent_id);
+            parent.child_edge_mut(*child_id).proven = Some(utilities);
+            match parent.proven_utilities() {
+                Some(proven) => utilities = proven,
+                None => break,
+            }
+        }
+    }
+
+    /// The proven result of the root for the player to move, if known.
+    pub fn root_proof(&self) -> Option<Proof> {
+        let root = self.index.get(self.root_id);
+        root.proven_utilities()
+            .map(|utilities| Proof::of(&utilities, root.player_idx))
+    }
+
+    /// True if the most visited root child is not also the best scoring one.
+    pub(crate) fn root_disagreement(&self, state: &G::S) -> bool {
+        let Some(most_visited) = self.most_visited_child() else {
+            return false;
+        };
+        let player = G::player_to_move(state).to_index();
+        let edges = self.index.get(self.root_id).edges();
+        let score = edges[most_visited].stats.expected_score(player);
+        edges
+            .iter()
+            .filter(|edge| edge.stats.num_visits > 0)
+            .any(|edge| edge.stats.expected_score(player) > score)
+    }
+
+    /// Run one round of the second stage in the configured iteration mode: a
+    /// single iteration, a batch, or an iteration per thread, but no more than
+    /// `limit`. `iterations` were run before. Returns the number run.
+    fn iterate_round(
+        &mut self,
+        root_id: Id,
+        state: &G::S,
+        iterations: usize,
+        limit: usize,
+    ) -> usize {
+        if let Some(parallel) = self.config.tree_parallel {
+            self.iterate_parallel(root_id, state, parallel.threads.min(limit), parallel)
+        } else if let Some(batch) = self.config.eval_batch {
+            let end = iterations + batch.size.min(limit);
+            let (end, _, _) = self.iterate_batched(root_id, state, iterations, end, batch);
+            end - iterations
+        } else {
+            self.iterate(root_id, state);
+            1
+        }
+    }
+
+    pub fn tree_metrics(&self) -> TreeMetrics {
+        TreeMetrics {
+            num_nodes: self.index.len(),
+            iterations: self.stats.iter_count,
+            average_depth: self.stats.accum_depth as f64 / self.stats.iter_count.max(1) as f64,
+            clock_reads: self.timer.clock_reads(),
+            clock_interval: self.timer.interval(),
+            retained_nodes: self.stats.retained_nodes,
+            discarded_nodes: self.stats.discarded_nodes,
+            expand_threshold: self.expand_threshold(),
+            recycled_nodes: self.stats.recycled_nodes,
+            table: self.table.stats(),
+        }
+    }
+
+    /// Walk the tree to estimate its memory use. Unlike `tree_metrics`, this
+    /// takes time in proportion to the size of the tree.
+    pub fn memory_metrics(&self) -> MemoryMetrics {
+        let mut metrics = MemoryMetrics {
+            num_nodes: self.index.len(),
+            bytes: self.index.capacity_bytes(),
+            ..Default::default()
+        };
+        // Inline statistics in place of the `EdgeStats`, and their players
+        let stats_size = std::mem::size_of::<NodeStats>() - std::mem::size_of::<EdgeStats>()
+            + G::num_players() * std::mem::size_of::<PlayerStats>();
+        let mut lazy_bytes = 0;
+        for node in self.index.iter() {
+            let NodeState::Expanded(edges) = &node.state else {
+                continue;
+            };
+            metrics.num_edges += edges.len();
+            metrics.bytes += edges.capacity() * std::mem::size_of::<Edge<G::A>>();
+            for edge in edges {
+                metrics.allocated_edge_stats += edge.stats.is_allocated() as usize;
+                lazy_bytes += edge.stats.heap_size();
+            }
+        }
+        metrics.eager_bytes = metrics.bytes + metrics.num_edges * stats_size;
+        metrics.bytes += lazy_bytes;
+        metrics
+    }
+
+    pub(crate) fn work_budget_exhausted(&self) -> bool {
+        (self.expansions() >= self.config.max_expansions
+            || self.stats.accum_depth >= self.config.max_applied_actions)
+            && self.index.get(self.root_id).is_expanded()
+    }
+
+    /// Whether the search was asked to stop through a `StopHandle`. As with
+    /// the work budgets, this only takes effect once the root is expanded.
+    pub(crate) fn stop_requested(&self) -> bool {
+        self.stop.is_stopped() && self.index.get(self.root_id).is_expanded()
+    }
+
+    /// Nodes added to the tree in this search.
+    fn expansions(&self) -> usize {
+        self.index.len() + self.stats.recycled_nodes - self.stats.retained_nodes.max(1)
+    }
+
+    /// True if the search may stop at `iterations` with an easy move. The most
+    /// visited child is kept in `previous_best` between checks.
+    pub(crate) fn check_easy_move(
+        &mut self,
+        state: &G::S,
+        iterations: usize,
+        previous_best: &mut Option<usize>,
+    ) -> bool {
+        let Some(config) = self.config.easy_move else {
+            return false;
+        };
+        if iterations % config.check_interval == 0
+            && self.budget_used(iterations) >= config.min_fraction
+        {
+            let best = self.most_visited_child();
+            if best.is_some() && best == *previous_best && self.is_easy_move(state, &config) {
+                return true;
+            }
+            *previous_best = best;
+        }
+        false
+    }
+
+    /// The fraction of the search budget spent so far.
+    fn budget_used(&self, iterations: usize) -> f64 {
+        if let Some(budget) = self.timer.budget() {
+            self.timer.elapsed().as_secs_f64() / budget.as_secs_f64()
+        } else {
+            iterations as f64 / self.config.max_iterations as f64
+        }
+    }
+
+    fn most_visited_child(&self) -> Option<usize> {
+        let root = self.index.get(self.root_id);
+        if !root.is_expanded() {
+            return None;
+        }
+        root.edges()
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, edge)| edge.stats.num_visits)
+            .map(|(idx, _)| idx)
+    }
+
+    fn is_easy_move(&mut self, state: &G::S, config: &EasyMove) -> bool {
+        let player = G::player_to_move(state).to_index();
+        if self.config.sort_children {
+            self.index.get_mut(self.root_id).sort_children();
+        }
+        let edges = self.index.get(self.root_id).children_by_visits();
+        match edges[..] {
+            [_] => true,
+            [best, second, ..] => {
+                best.stats.num_visits as f64 >= config.visit_ratio * second.stats.num_visits as f64
+                    && best.stats.expected_score(player)
+                        >= second.stats.expected_score(player) + config.score_margin
+            }
+            [] => false,
+        }
+    }
+
+    /// Refresh the cached child order of the root and the nodes along the
+    /// principal variation.
+    fn sort_pv_children(&mut self) {
+        let mut node_id = Some(self.root_id);
+        let mut actions = self.pv.iter();
+        while let Some(id) = node_id {
+            let node = self.index.get_mut(id);
+            node.sort_children();
+            node_id = actions.next().and_then(|action| {
+                node.edges()
+                    .iter()
+                    .find(|edge| &edge.action == action)
+                    .and_then(|edge| edge.node_id)
+            });
+        }
+    }
+
+    /// Refresh the cached child order of every node in the tree, e.g. before
+    /// rendering it.
+    pub fn sort_all_children(&mut self) {
+        self.index.iter_mut().for_each(Node::sort_children);
     }
 
     fn compute_pv(&mut self, init_state: &G::S) {
@@ -406,18 +1408,28 @@ where
         let mut node = self.index.get(node_id);
         let mut state = init_state.clone();
         let mut stack = NodeStack::new(vec![node_id]);
+        let mut path = vec![];
+        let mut grave_ref = node_id;
+        let grave_threshold = self.config.final_action.grave_threshold();
         let init_player = G::player_to_move(init_state).to_index();
         while node.is_expanded() {
             let select_ctx = SelectContext {
                 q_init: self.config.q_init,
                 player: init_player, // TODO: opponent perspective?
                 stack: &stack,
+                path: &path,
+                grave_ref,
                 root_stats: &self.root_stats,
                 state: &state,
                 index: &self.index,
                 table: &self.table,
-                grave: &self.stats.grave,
+                history: &self.stats.history,
                 use_transpositions: self.config.use_transpositions,
+                ucd: self.config.ucd,
+                score_bounds: self
+                    .config
+                    .normalize_scores
+                    .then_some(&self.stats.score_bounds),
             };
 
             let best_idx = self
@@ -427,8 +1439,12 @@ where
 
             let edge = &node.edges()[best_idx];
             if let Some(child_id) = edge.node_id {
+                if grave_threshold.is_some_and(|t| edge.stats.total_visits() >= t) {
+                    grave_ref = child_id;
+                }
                 node_id = child_id;
                 node = self.index.get(node_id);
+                path.push((edge.action.clone(), G::player_to_move(&state).to_index()));
                 state = G::apply(state, &edge.action);
                 self.pv.push(edge.action.clone());
                 stack.push(node_id);
@@ -453,36 +1469,17 @@ where
     }
 
     fn choose_action(&mut self, state: &G::S) -> G::A {
-        let hash = G::zobrist_hash(state);
-        let root_id = self.reset(G::player_to_move(state).to_index(), hash);
-        if self.config.use_transpositions {
-            self.table.insert(hash, root_id, state.clone());
-        }
-
-        self.timer.start(self.config.max_time);
-
-        for _ in 0..self.config.max_iterations {
-            if self.timer.done() {
-                break;
-            }
-            self.reset_iter();
-            let mut ctx = SearchContext::new(root_id, state.clone());
-
-            self.select(&mut ctx);
-            self.trial = Some(self.simulate(&ctx.state, G::player_to_move(state).to_index()));
-            self.backprop(G::player_to_move(state).to_index());
-        }
+        self.search(state).action
+    }
 
-        self.compute_pv(state);
-        self.verbose_summary(state);
+    fn choose_action_with_verdict(&mut self, state: &G::S) -> (G::A, Option<Verdict>) {
+        let result = self.search(state);
+        (result.action, result.verdict)
+    }
 
-        // NOTE: this can fail when root is a leaf. This happens if:
-        //
-        //     max_iterations < expand_threshold
-        //
-        // TODO: We might check for this and unconditionally expand root. I think
-        // a lot of implementations fully expand root on the first iteration.
-        self.select_final_action(state)
+    fn prepare(&mut self, init: &G::S) {
+        self.move_number = 0;
+        self.config.simulate.prepare(init);
     }
 
     fn make_book_entry(
@@ -511,7 +1508,7 @@ where
             );
         }
 
-        let utilities = G::compute_utilities(&self.trial.as_ref().unwrap().state);
+        let utilities = self.trial.as_ref().unwrap().utilities.clone();
 
         (actions, utilities)
     }
@@ -524,6 +1521,28 @@ where
         self.pv.clone()
     }
 
+    fn search_metrics(&self) -> Option<SearchMetrics> {
+        let metrics = self.tree_metrics();
+        let player = self.index.get(self.root_id).player_idx;
+        Some(SearchMetrics {
+            nodes: metrics.num_nodes,
+            average_depth: metrics.average_depth,
+            elapsed: self.timer.elapsed(),
+            value: self.root_stats.expected_score(player),
+        })
+    }
+
+    fn root_visits(&self) -> Vec<(G::A, u32)> {
+        let root = self.index.get(self.root_id);
+        if !root.is_expanded() {
+            return vec![];
+        }
+        root.edges()
+            .iter()
+            .map(|edge| (edge.action.clone(), edge.stats.num_visits))
+            .collect()
+    }
+
     fn set_friendly_name(&mut self, name: &str) {
         self.config.name = name.to_string();
     }
@@ -1,3 +1,4 @@
+use super::history::History;
 use super::index::Id;
 use super::node::{self, Edge, NodeStats};
 use super::stack::NodeStack;
@@ -10,24 +11,110 @@ use crate::util::random_best;
 
 use rand::rngs::SmallRng;
 use rand::Rng;
-use rustc_hash::FxHashMap;
 
 pub struct SelectContext<'a, G: Game> {
     pub q_init: node::QInit,
     pub stack: &'a NodeStack<G::A>,
+    // The actions leading from the root to the current node, each with the
+    // index of the player who made it. This parallels `stack`, which holds one
+    // more entry (the root), and is maintained during descent so history
+    // aware strategies need not recover it from the index.
+    pub path: &'a [(G::A, usize)],
+    // The deepest node on the stack, including the current node, whose
+    // incoming edge has at least `SelectStrategy::grave_threshold` visits, or
+    // the root. Only maintained for strategies with a threshold.
+    pub grave_ref: Id,
     pub root_stats: &'a NodeStats,
     pub state: &'a G::S,
     pub player: usize,
     pub index: &'a TreeIndex<G::A>,
     pub table: &'a TranspositionTable<G::S>,
-    pub grave: &'a FxHashMap<u64, Vec<FxHashMap<G::A, node::ActionStats>>>,
+    pub history: &'a History<G>,
     pub use_transpositions: bool,
+    pub ucd: Option<Ucd>,
+    // Present when `SearchConfig::normalize_scores` is set
+    pub score_bounds: Option<&'a ScoreBounds>,
 }
 
 impl<'a, G: Game> SelectContext<'a, G> {
     fn current_stats(&self) -> &NodeStats {
         self.stack.current_stats(self.index, self.root_stats)
     }
+
+    /// Normalize a score if `SearchConfig::normalize_scores` is set.
+    #[inline]
+    pub fn normalize(&self, score: f64) -> f64 {
+        self.score_bounds
+            .map_or(score, |bounds| bounds.normalize(score))
+    }
+
+    /// The (normalized) exploitation score of an edge for the searching player.
+    #[inline]
+    pub fn exploitation(&self, stats: &NodeStats) -> f64 {
+        self.normalize(stats.exploitation_score(self.player))
+    }
+
+    /// The (normalized) sample variance of an edge's scores.
+    #[inline]
+    pub fn sample_variance(&self, stats: &NodeStats) -> f64 {
+        let variance = stats.variance(self.player);
+        let scale = self.score_bounds.map_or(1., ScoreBounds::scale);
+        variance * scale * scale
+    }
+
+    /// The visits and total score for the searching player of an edge, adapted
+    /// to `depth` as described in `Ucd`.
+    pub fn adapted_stats(&self, edge: &Edge<G::A>, depth: u32) -> (f64, f64) {
+        let own = || {
+            let visits = edge.stats.total_visits() as f64;
+            (visits, visits * edge.stats.exploitation_score(self.player))
+        };
+        let Some(child_id) = edge.node_id.filter(|_| depth > 0) else {
+            return own();
+        };
+        let child = self.index.get(child_id);
+        if !child.is_expanded() {
+            return own();
+        }
+        let (visits, score) = child
+            .edges()
+            .iter()
+            .map(|edge| self.adapted_stats(edge, depth - 1))
+            .fold((0., 0.), |(n, s), (visits, score)| (n + visits, s + score));
+        if visits == 0. {
+            own()
+        } else {
+            (visits, score)
+        }
+    }
+
+    /// The parent's visits for the UCD exploration term: the sum of the
+    /// adapted visits of the current node's edges.
+    pub fn adapted_parent_visits(&self, depth: u32) -> f64 {
+        let current = self.index.get(self.stack.current_id());
+        current
+            .edges()
+            .iter()
+            .map(|edge| self.adapted_stats(edge, depth).0)
+            .sum()
+    }
+
+    /// The value of an unvisited child. Only the parent's score is normalized,
+    /// the fixed estimates and FPU reduction being on the normalized scale
+    /// already.
+    #[inline]
+    pub fn unvisited_value(&self) -> f64 {
+        let stats = self.current_stats();
+        match self.q_init {
+            node::QInit::Parent if stats.num_visits > 0 => {
+                self.normalize(stats.expected_score(self.player))
+            }
+            node::QInit::ParentMinus(reduction) if stats.num_visits > 0 => {
+                self.normalize(stats.expected_score(self.player)) - reduction
+            }
+            q_init => stats.value_estimate_unvisited(self.player, q_init),
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -58,9 +145,44 @@ pub trait SelectStrategy<G: Game>: Sized + Clone + Sync + Send + Default {
     /// Provide a score for any value that is not yet visited.
     fn unvisited_value(&self, ctx: &SelectContext<'_, G>, aux: Self::Aux) -> Self::Score;
 
+    /// Score a particular unvisited edge, given `unvisited_value`. Strategies
+    /// which tell unvisited edges apart, e.g. by their priors, override this.
+    #[allow(unused_variables)]
+    #[inline(always)]
+    fn unvisited_edge_value(
+        &self,
+        ctx: &SelectContext<'_, G>,
+        edge: &Edge<G::A>,
+        aux: Self::Aux,
+        unvisited_value: Self::Score,
+    ) -> Self::Score {
+        unvisited_value
+    }
+
     fn backprop_flags(&self) -> BackpropFlags {
         BackpropFlags(0)
     }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    /// The visit threshold for GRAVE reference nodes, if the strategy uses
+    /// them. The search tracks the reference during descent and provides it as
+    /// `SelectContext::grave_ref`.
+    fn grave_threshold(&self) -> Option<u32> {
+        None
+    }
+
+    /// Multiply the exploration constant, if the strategy has one. This is how
+    /// `Game::recommended_config_hints` adapts a strategy to a game.
+    #[allow(unused_variables)]
+    fn scale_exploration(&mut self, scale: f64) {}
+
+    /// Set the exploration constant, if the strategy has one. This is how
+    /// `SearchConfig::exploration_schedule` varies it from move to move.
+    #[allow(unused_variables)]
+    fn set_exploration(&mut self, value: f64) {}
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -145,6 +267,23 @@ where
     fn backprop_flags(&self) -> BackpropFlags {
         self.inner.backprop_flags()
     }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        validate_epsilon(self.epsilon)?;
+        self.inner.validate()
+    }
+
+    fn grave_threshold(&self) -> Option<u32> {
+        self.inner.grave_threshold()
+    }
+
+    fn scale_exploration(&mut self, scale: f64) {
+        self.inner.scale_exploration(scale)
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.inner.set_exploration(value)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -183,7 +322,7 @@ where
         if let Some(child_id) = &set[i].node_id {
             strategy.score_child(ctx, *child_id, &set[i], aux)
         } else {
-            unvisited_value
+            strategy.unvisited_edge_value(ctx, &set[i], aux, unvisited_value)
         }
     };
 
@@ -317,6 +456,49 @@ impl<G: Game> SelectStrategy<G> for SecureChild {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Choose the child with the best lower confidence bound on its mean score,
+/// `q - z * standard_error`. Children with a single visit are bounded by
+/// `q - z`. Intended as a final action strategy.
+#[derive(Clone)]
+pub struct Lcb {
+    pub z: f64,
+}
+
+impl Default for Lcb {
+    fn default() -> Self {
+        Self { z: 1.96 }
+    }
+}
+
+impl<G: Game> SelectStrategy<G> for Lcb {
+    type Score = f64;
+    type Aux = ();
+
+    #[inline(always)]
+    fn setup(&mut self, _: &SelectContext<'_, G>) -> Self::Aux {}
+
+    #[inline(always)]
+    fn score_child(
+        &self,
+        ctx: &SelectContext<'_, G>,
+        _child_id: Id,
+        edge: &Edge<G::A>,
+        _: Self::Aux,
+    ) -> f64 {
+        let q = ctx.exploitation(&edge.stats);
+        let scale = ctx.score_bounds.map_or(1., ScoreBounds::scale);
+        let error = edge.stats.standard_error(ctx.player).min(1.);
+        q - self.z * scale * error
+    }
+
+    #[inline(always)]
+    fn unvisited_value(&self, _: &SelectContext<'_, G>, _: Self::Aux) -> f64 {
+        f64::NEG_INFINITY
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 #[derive(Clone, Copy, Default)]
 pub struct ThompsonSampling;
 
@@ -370,6 +552,131 @@ impl<G: Game> SelectStrategy<G> for ThompsonSampling {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Boltzmann exploration over soft values (MENTS; Xiao et al., 2019). A child
+/// is sampled from the softmax of the edges' soft values at `temperature`,
+/// mixed with the uniform distribution in proportion
+/// `epsilon * num_children / ln(visits + 1)`, which anneals exploration as the
+/// node is visited. Unvisited children have a soft value of zero.
+///
+/// The soft values are maintained by `backprop::Soft`, which should use the
+/// same temperature. Useful where rewards are noisy, since the soft values
+/// average over the likely lines rather than chasing a single best one.
+#[derive(Clone)]
+pub struct Boltzmann {
+    pub temperature: f64,
+    pub epsilon: f64,
+}
+
+impl Default for Boltzmann {
+    fn default() -> Self {
+        Self {
+            temperature: 0.1,
+            epsilon: 0.1,
+        }
+    }
+}
+
+impl Boltzmann {
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// The distribution sampled by `best_child` at the current node.
+    pub fn policy<G: Game>(&self, ctx: &SelectContext<'_, G>) -> Vec<f64> {
+        let current = ctx.index.get(ctx.stack.current_id());
+        let edges = current.edges();
+        let values = edges
+            .iter()
+            .map(|edge| {
+                if edge.stats.num_visits > 0 {
+                    ctx.normalize(edge.stats.player[ctx.player].soft)
+                } else {
+                    0.
+                }
+            })
+            .collect::<Vec<_>>();
+        let n = edges.len() as f64;
+        let visits = ctx.current_stats().num_visits as f64;
+        let lambda = (self.epsilon * n / (visits + 1.).ln()).min(1.);
+        backprop::softmax(&values, self.temperature)
+            .into_iter()
+            .map(|p| (1. - lambda) * p + lambda / n)
+            .collect()
+    }
+}
+
+impl<G: Game> SelectStrategy<G> for Boltzmann {
+    type Score = f64;
+    type Aux = ();
+
+    #[inline(always)]
+    fn setup(&mut self, _: &SelectContext<'_, G>) -> Self::Aux {}
+
+    fn best_child(&mut self, ctx: &SelectContext<'_, G>, rng: &mut SmallRng) -> usize {
+        let policy = self.policy(ctx);
+        let mut target = rng.gen::<f64>();
+        for (i, p) in policy.iter().enumerate() {
+            if target < *p {
+                return i;
+            }
+            target -= p;
+        }
+        policy.len() - 1
+    }
+
+    #[inline(always)]
+    fn score_child(
+        &self,
+        ctx: &SelectContext<'_, G>,
+        _child_id: Id,
+        edge: &Edge<G::A>,
+        _: Self::Aux,
+    ) -> f64 {
+        ctx.normalize(edge.stats.player[ctx.player].soft)
+    }
+
+    #[inline(always)]
+    fn unvisited_value(&self, _: &SelectContext<'_, G>, _: Self::Aux) -> f64 {
+        0.
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.temperature <= 0. {
+            return Err(ConfigError::NonPositiveTemperature(self.temperature));
+        }
+        validate_epsilon(self.epsilon)
+    }
+
+    fn scale_exploration(&mut self, scale: f64) {
+        self.epsilon = (self.epsilon * scale).min(1.);
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.epsilon = value.min(1.);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The UCB1 score of an arm with mean score `exploit` and `num_visits`, where
+/// `parent_log` is the log of the total visits. Shared with the flat bandits in
+/// `strategies::bandit`.
+#[inline(always)]
+pub(crate) fn ucb1(
+    exploration_constant: f64,
+    exploit: f64,
+    parent_log: f64,
+    num_visits: f64,
+) -> f64 {
+    exploit + exploration_constant * (parent_log / num_visits).sqrt()
+}
+
 /// Upper Confidence Bounds (UCB1)
 #[derive(Clone)]
 pub struct Ucb1 {
@@ -396,10 +703,21 @@ impl<G: Game> SelectStrategy<G> for Ucb1 {
     type Score = f64;
     type Aux = f64;
 
+    fn scale_exploration(&mut self, scale: f64) {
+        self.exploration_constant *= scale;
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.exploration_constant = value;
+    }
+
     #[inline(always)]
     fn setup(&mut self, ctx: &SelectContext<'_, G>) -> f64 {
-        let stats = ctx.current_stats();
-        ((stats.num_visits as f64).max(1.)).ln()
+        let num_visits = match ctx.ucd {
+            Some(ucd) => ctx.adapted_parent_visits(ucd.d3),
+            None => ctx.current_stats().num_visits as f64,
+        };
+        num_visits.max(1.).ln()
     }
 
     #[inline(always)]
@@ -410,19 +728,27 @@ impl<G: Game> SelectStrategy<G> for Ucb1 {
         edge: &Edge<G::A>,
         parent_log: f64,
     ) -> f64 {
-        let exploit = edge.stats.exploitation_score(ctx.player);
-        let num_visits = edge.stats.total_visits();
-        let explore = (parent_log / num_visits as f64).sqrt();
-        exploit + self.exploration_constant * explore
+        let (exploit, num_visits) = match ctx.ucd {
+            Some(ucd) => {
+                let (visits, score) = ctx.adapted_stats(edge, ucd.d1);
+                let num_visits = if ucd.d2 == ucd.d1 {
+                    visits
+                } else {
+                    ctx.adapted_stats(edge, ucd.d2).0
+                };
+                (ctx.normalize(score / visits), num_visits)
+            }
+            None => (
+                ctx.exploitation(&edge.stats),
+                edge.stats.total_visits() as f64,
+            ),
+        };
+        ucb1(self.exploration_constant, exploit, parent_log, num_visits)
     }
 
     #[inline(always)]
     fn unvisited_value(&self, ctx: &SelectContext<'_, G>, parent_log: f64) -> f64 {
-        let unvisited_value = ctx
-            .current_stats()
-            .value_estimate_unvisited(ctx.player, ctx.q_init);
-
-        unvisited_value + self.exploration_constant * parent_log.sqrt()
+        ctx.unvisited_value() + self.exploration_constant * parent_log.sqrt()
     }
 }
 
@@ -449,10 +775,10 @@ impl Ucb1Tuned {
     }
 }
 
-const VARIANCE_UPPER_BOUND: f64 = 1.;
+pub(crate) const VARIANCE_UPPER_BOUND: f64 = 1.;
 
 #[inline(always)]
-fn ucb1_tuned(
+pub(crate) fn ucb1_tuned(
     exploration_constant: f64,
     exploit: f64,
     sample_variance: f64,
@@ -467,6 +793,14 @@ impl<G: Game> SelectStrategy<G> for Ucb1Tuned {
     type Score = f64;
     type Aux = f64;
 
+    fn scale_exploration(&mut self, scale: f64) {
+        self.exploration_constant *= scale;
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.exploration_constant = value;
+    }
+
     #[inline(always)]
     fn setup(&mut self, ctx: &SelectContext<'_, G>) -> f64 {
         ((ctx.current_stats().num_visits as f64).max(1.)).ln()
@@ -480,11 +814,9 @@ impl<G: Game> SelectStrategy<G> for Ucb1Tuned {
         edge: &Edge<G::A>,
         parent_log: f64,
     ) -> f64 {
-        let exploit = edge.stats.exploitation_score(ctx.player);
+        let exploit = ctx.exploitation(&edge.stats);
         let num_visits = edge.stats.total_visits();
-        let sample_variance = 0f64.max(
-            edge.stats.player[ctx.player].sum_squared_score / num_visits as f64 - exploit * exploit,
-        );
+        let sample_variance = ctx.sample_variance(&edge.stats);
         let visits_fraction = parent_log / num_visits as f64;
 
         ucb1_tuned(
@@ -497,12 +829,9 @@ impl<G: Game> SelectStrategy<G> for Ucb1Tuned {
 
     #[inline(always)]
     fn unvisited_value(&self, ctx: &SelectContext<'_, G>, parent_log: f64) -> Self::Score {
-        let unvisited_value = ctx
-            .current_stats()
-            .value_estimate_unvisited(ctx.player, ctx.q_init);
         ucb1_tuned(
             self.exploration_constant,
-            unvisited_value,
+            ctx.unvisited_value(),
             VARIANCE_UPPER_BOUND,
             parent_log,
         )
@@ -511,6 +840,234 @@ impl<G: Game> SelectStrategy<G> for Ucb1Tuned {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Decoupled UCT (DUCT; Lanctot et al., 2013) for simultaneous move games. At a
+/// simultaneous state (see `Game::is_simultaneous`) each player chooses their
+/// own move by UCB1 over the statistics of the joint actions containing it,
+/// ignoring the others' choices, and the joint action made of those moves is
+/// selected. Elsewhere this is `Ucb1`.
+///
+/// With `robust` set each player chooses their most visited move instead, and
+/// elsewhere the most visited child is chosen, as suits a final action.
+#[derive(Clone)]
+pub struct Duct {
+    pub exploration_constant: f64,
+    pub robust: bool,
+}
+
+impl Default for Duct {
+    fn default() -> Self {
+        Self {
+            exploration_constant: 2f64.sqrt(),
+            robust: false,
+        }
+    }
+}
+
+impl Duct {
+    pub fn with_c(exploration_constant: f64) -> Self {
+        Self {
+            exploration_constant,
+            ..Default::default()
+        }
+    }
+
+    pub fn robust() -> Self {
+        Self {
+            robust: true,
+            ..Default::default()
+        }
+    }
+
+    // Choose each player's move separately, and find the joint action
+    fn decoupled_child<G: Game>(&self, ctx: &SelectContext<'_, G>, rng: &mut SmallRng) -> usize {
+        let edges = ctx.index.get(ctx.stack.current_id()).edges();
+        let moves = edges
+            .iter()
+            .map(|edge| {
+                (0..G::num_players())
+                    .map(|player| G::player_move(ctx.state, &edge.action, player))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let chosen = (0..G::num_players())
+            .map(|player| {
+                // The visits and total score of each of the player's moves
+                let mut marginal = vec![];
+                for (edge, joint) in edges.iter().zip(&moves) {
+                    let i = joint[player];
+                    if marginal.len() <= i {
+                        marginal.resize(i + 1, None);
+                    }
+                    let (visits, score) = marginal[i].get_or_insert((0, 0.));
+                    let n = edge.stats.total_visits();
+                    *visits += n;
+                    *score += n as f64 * ctx.normalize(edge.stats.exploitation_score(player));
+                }
+                let total = marginal.iter().flatten().map(|(n, _)| *n).sum::<u32>();
+                let parent_log = (total as f64).max(1.).ln();
+                let candidates = (0..marginal.len())
+                    .filter(|i| marginal[*i].is_some())
+                    .collect::<Vec<_>>();
+                *random_best(&candidates, rng, |i| {
+                    let (visits, score) = marginal[*i].unwrap();
+                    if self.robust {
+                        visits as f64
+                    } else if visits == 0 {
+                        f64::INFINITY
+                    } else {
+                        let visits = visits as f64;
+                        ucb1(
+                            self.exploration_constant,
+                            score / visits,
+                            parent_log,
+                            visits,
+                        )
+                    }
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        moves
+            .iter()
+            .position(|joint| *joint == chosen)
+            .expect("the actions should include every combination of moves")
+    }
+}
+
+impl<G: Game> SelectStrategy<G> for Duct {
+    type Score = f64;
+    type Aux = f64;
+
+    fn best_child(&mut self, ctx: &SelectContext<'_, G>, rng: &mut SmallRng) -> usize {
+        if G::is_simultaneous(ctx.state) {
+            self.decoupled_child(ctx, rng)
+        } else if self.robust {
+            SelectStrategy::<G>::best_child(&mut RobustChild, ctx, rng)
+        } else {
+            let current = ctx.index.get(ctx.stack.current_id());
+            random_best_index(current.edges(), self, ctx, rng)
+        }
+    }
+
+    fn scale_exploration(&mut self, scale: f64) {
+        self.exploration_constant *= scale;
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.exploration_constant = value;
+    }
+
+    #[inline(always)]
+    fn setup(&mut self, ctx: &SelectContext<'_, G>) -> f64 {
+        (ctx.current_stats().num_visits as f64).max(1.).ln()
+    }
+
+    #[inline(always)]
+    fn score_child(
+        &self,
+        ctx: &SelectContext<'_, G>,
+        _child_id: Id,
+        edge: &Edge<G::A>,
+        parent_log: f64,
+    ) -> f64 {
+        ucb1(
+            self.exploration_constant,
+            ctx.exploitation(&edge.stats),
+            parent_log,
+            edge.stats.total_visits() as f64,
+        )
+    }
+
+    #[inline(always)]
+    fn unvisited_value(&self, ctx: &SelectContext<'_, G>, parent_log: f64) -> f64 {
+        ctx.unvisited_value() + self.exploration_constant * parent_log.sqrt()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Predictor UCB (PUCT) as in AlphaZero. Each child's exploration term is
+/// weighted by its prior probability (see `Game::action_priors`) and decays
+/// with the child's own visits:
+///
+/// ```text
+/// Q(a) + c * P(a) * sqrt(N) / (1 + N(a))
+/// ```
+///
+/// Unvisited children score their `QInit` value plus the exploration term, so
+/// they are tried in order of their priors rather than all first. With the
+/// default uniform prior this is a plain, if less exploratory, UCB.
+#[derive(Clone)]
+pub struct Puct {
+    pub exploration_constant: f64,
+}
+
+impl Puct {
+    pub fn with_c(exploration_constant: f64) -> Self {
+        Self {
+            exploration_constant,
+        }
+    }
+}
+
+impl Default for Puct {
+    fn default() -> Self {
+        Self {
+            exploration_constant: 1.5,
+        }
+    }
+}
+
+impl<G: Game> SelectStrategy<G> for Puct {
+    type Score = f64;
+    type Aux = f64;
+
+    fn scale_exploration(&mut self, scale: f64) {
+        self.exploration_constant *= scale;
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.exploration_constant = value;
+    }
+
+    #[inline(always)]
+    fn setup(&mut self, ctx: &SelectContext<'_, G>) -> f64 {
+        (ctx.current_stats().num_visits as f64).max(1.).sqrt()
+    }
+
+    #[inline(always)]
+    fn score_child(
+        &self,
+        ctx: &SelectContext<'_, G>,
+        _child_id: Id,
+        edge: &Edge<G::A>,
+        parent_sqrt: f64,
+    ) -> f64 {
+        let exploit = ctx.exploitation(&edge.stats);
+        let num_visits = edge.stats.total_visits();
+        let explore = edge.prior as f64 * parent_sqrt / (1 + num_visits) as f64;
+        exploit + self.exploration_constant * explore
+    }
+
+    #[inline(always)]
+    fn unvisited_value(&self, ctx: &SelectContext<'_, G>, _parent_sqrt: f64) -> f64 {
+        ctx.unvisited_value()
+    }
+
+    #[inline(always)]
+    fn unvisited_edge_value(
+        &self,
+        _ctx: &SelectContext<'_, G>,
+        edge: &Edge<G::A>,
+        parent_sqrt: f64,
+        unvisited_value: f64,
+    ) -> f64 {
+        unvisited_value + self.exploration_constant * edge.prior as f64 * parent_sqrt
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 // Ameneyro, F.V., Galvan, E., Morales, A.F.K., 2020. Playing Carcassonne with
 // Monte Carlo Tree Search.
 //
@@ -590,7 +1147,7 @@ impl Default for RaveUcb {
 }
 
 impl RaveUcb {
-    fn score(&self, parent_log: f64, n: u32, sum_squared_score: f64, exploit: f64) -> f64 {
+    fn score(&self, parent_log: f64, n: u32, sample_variance: f64) -> f64 {
         match self {
             RaveUcb::None => 0.,
             RaveUcb::Ucb1 {
@@ -599,7 +1156,6 @@ impl RaveUcb {
             RaveUcb::Ucb1Tuned {
                 exploration_constant,
             } => {
-                let sample_variance = 0f64.max(sum_squared_score / n as f64 - exploit * exploit);
                 let visits_fraction = parent_log / n as f64;
                 ucb1_tuned(
                     *exploration_constant,
@@ -617,6 +1173,9 @@ pub struct Rave {
     pub threshold: u32, // 0 == RAVE, inf = HRAVE, else GRAVE
     pub schedule: RaveSchedule,
     pub ucb: RaveUcb,
+    // Which actions count for GRAVE updates: some combination of TREE,
+    // PLAYOUT and MOVER.
+    pub amaf_update: BackpropFlags,
 }
 
 impl Default for Rave {
@@ -625,6 +1184,7 @@ impl Default for Rave {
             threshold: 700,
             schedule: RaveSchedule::default(),
             ucb: RaveUcb::default(),
+            amaf_update: BackpropFlags(TREE | PLAYOUT),
         }
     }
 }
@@ -635,9 +1195,15 @@ impl Rave {
             threshold,
             schedule,
             ucb,
+            ..Default::default()
         }
     }
 
+    pub fn amaf_update(mut self, amaf_update: BackpropFlags) -> Self {
+        self.amaf_update = amaf_update;
+        self
+    }
+
     pub fn threshold(mut self, threshold: u32) -> Self {
         self.threshold = threshold;
         self
@@ -655,26 +1221,16 @@ impl Rave {
 }
 
 impl Rave {
-    fn get_ref<G: Game>(&self, ctx: &SelectContext<'_, G>, node_id: Id) -> Id {
-        let mut stack = ctx.stack.clone();
-        stack.push(node_id);
-        let rev_pairs = stack.reverse_pairs();
-
-        if ctx.index.get(node_id).is_root() {
-            return node_id;
-        }
-
-        // TODO: we can push this down during select descent rather than walking back up.
-        for (parent_id, child_id) in rev_pairs {
-            if stack
-                .get_stats(ctx.index, ctx.root_stats, *parent_id, *child_id)
-                .total_visits()
-                >= self.threshold
-            {
-                return *child_id;
-            }
+    /// The reference node for a child's GRAVE statistics: the child itself if
+    /// its edge has enough visits, otherwise the reference found on the way
+    /// down.
+    #[inline(always)]
+    fn get_ref<G: Game>(&self, ctx: &SelectContext<'_, G>, child_id: Id, edge: &Edge<G::A>) -> Id {
+        if edge.stats.total_visits() >= self.threshold || ctx.index.get(child_id).is_root() {
+            child_id
+        } else {
+            ctx.grave_ref
         }
-        stack.root()
     }
 
     #[inline(always)]
@@ -691,6 +1247,30 @@ impl<G: Game> SelectStrategy<G> for Rave {
     type Score = f64;
     type Aux = f64;
 
+    fn scale_exploration(&mut self, scale: f64) {
+        match &mut self.ucb {
+            RaveUcb::None => {}
+            RaveUcb::Ucb1 {
+                exploration_constant,
+            }
+            | RaveUcb::Ucb1Tuned {
+                exploration_constant,
+            } => *exploration_constant *= scale,
+        }
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        match &mut self.ucb {
+            RaveUcb::None => {}
+            RaveUcb::Ucb1 {
+                exploration_constant,
+            }
+            | RaveUcb::Ucb1Tuned {
+                exploration_constant,
+            } => *exploration_constant = value,
+        }
+    }
+
     #[inline(always)]
     fn setup(&mut self, ctx: &SelectContext<'_, G>) -> f64 {
         ((ctx.current_stats().num_visits as f64).max(1.)).ln()
@@ -704,41 +1284,41 @@ impl<G: Game> SelectStrategy<G> for Rave {
         edge: &Edge<G::A>,
         parent_log: f64,
     ) -> f64 {
-        let ref_id = self.get_ref(ctx, child_id);
+        let ref_id = self.get_ref(ctx, child_id, edge);
         let hash = ctx.index.get(ref_id).hash;
         let grave_stats = ctx
+            .history
             .grave
-            .get(&hash)
-            .and_then(|player| player[ctx.player].get(&edge.action).cloned())
+            .get(hash, ctx.player, &edge.action)
+            .cloned()
             .unwrap_or_default();
 
         let amaf_n = grave_stats.num_visits;
         let amaf_q = grave_stats.score;
 
         let n = edge.stats.total_visits();
-        let exploit = edge.stats.exploitation_score(ctx.player);
-        let explore = self.ucb.score(
-            parent_log,
-            n,
-            edge.stats.player[ctx.player].sum_squared_score,
-            exploit,
-        );
+        let explore = self
+            .ucb
+            .score(parent_log, n, ctx.sample_variance(&edge.stats));
 
         let b = self.schedule.beta(n, amaf_n);
-        let mean_score = edge.stats.expected_score(ctx.player);
-        let amaf = Self::amaf_score(amaf_n, amaf_q);
+        let mean_score = ctx.normalize(edge.stats.expected_score(ctx.player));
+        let amaf = ctx.normalize(Self::amaf_score(amaf_n, amaf_q));
 
         (1. - b) * mean_score + b * amaf + explore
     }
 
     #[inline(always)]
     fn unvisited_value(&self, ctx: &SelectContext<'_, G>, _: f64) -> f64 {
-        ctx.current_stats()
-            .value_estimate_unvisited(ctx.player, ctx.q_init)
+        ctx.unvisited_value()
     }
 
     fn backprop_flags(&self) -> BackpropFlags {
-        BackpropFlags(GRAVE)
+        BackpropFlags(GRAVE) | self.amaf_update
+    }
+
+    fn grave_threshold(&self) -> Option<u32> {
+        Some(self.threshold)
     }
 }
 
@@ -748,6 +1328,9 @@ impl<G: Game> SelectStrategy<G> for Rave {
 pub struct Amaf {
     pub alpha: f64,
     pub exploration_constant: f64,
+    // Which actions count for AMAF updates: some combination of TREE,
+    // PLAYOUT and MOVER.
+    pub amaf_update: BackpropFlags,
 }
 
 impl Amaf {
@@ -771,6 +1354,11 @@ impl Amaf {
         self.exploration_constant = exploration_constant;
         self
     }
+
+    pub fn amaf_update(mut self, amaf_update: BackpropFlags) -> Self {
+        self.amaf_update = amaf_update;
+        self
+    }
 }
 
 impl Default for Amaf {
@@ -778,6 +1366,7 @@ impl Default for Amaf {
         Self {
             alpha: 1.0,
             exploration_constant: 2f64.sqrt(),
+            amaf_update: BackpropFlags(PLAYOUT),
         }
     }
 }
@@ -786,6 +1375,14 @@ impl<G: Game> SelectStrategy<G> for Amaf {
     type Score = f64;
     type Aux = f64;
 
+    fn scale_exploration(&mut self, scale: f64) {
+        self.exploration_constant *= scale;
+    }
+
+    fn set_exploration(&mut self, value: f64) {
+        self.exploration_constant = value;
+    }
+
     #[inline(always)]
     fn setup(&mut self, ctx: &SelectContext<'_, G>) -> f64 {
         ((ctx.current_stats().num_visits as f64).max(1.)).ln()
@@ -801,9 +1398,9 @@ impl<G: Game> SelectStrategy<G> for Amaf {
     ) -> f64 {
         let amaf_n = 1.max(edge.stats.player[ctx.player].amaf.num_visits) as f64;
         let amaf_q = edge.stats.player[ctx.player].amaf.score;
-        let amaf = amaf_q / amaf_n;
+        let amaf = ctx.normalize(amaf_q / amaf_n);
 
-        let exploit = edge.stats.exploitation_score(ctx.player);
+        let exploit = ctx.exploitation(&edge.stats);
         let num_visits = edge.stats.total_visits();
         let explore = (parent_log / num_visits as f64).sqrt();
 
@@ -815,12 +1412,11 @@ impl<G: Game> SelectStrategy<G> for Amaf {
 
     #[inline(always)]
     fn unvisited_value(&self, ctx: &SelectContext<'_, G>, _: f64) -> f64 {
-        ctx.current_stats()
-            .value_estimate_unvisited(ctx.player, ctx.q_init)
+        ctx.unvisited_value()
     }
 
     fn backprop_flags(&self) -> BackpropFlags {
-        BackpropFlags(AMAF)
+        BackpropFlags(AMAF) | self.amaf_update
     }
 }
 
@@ -870,25 +1466,30 @@ impl<G: Game> SelectStrategy<G> for Amaf {
 /// end while
 /// ```
 #[derive(Clone)]
-pub struct QuasiBestFirst<G: Game, S: Strategy<G>> {
-    pub book: book::OpeningBook<G::A>,
+pub struct QuasiBestFirst<
+    G: Game,
+    S: Strategy<G>,
+    B: book::BookScore<G> = book::OpeningBook<<G as Game>::A>,
+> {
+    pub book: B,
     pub search: TreeSearch<G, S>,
     pub epsilon: f64,
     pub k: Vec<f64>,
     pub key_init: Vec<G::A>,
 }
 
-impl<G, S> QuasiBestFirst<G, S>
+impl<G, S, B> QuasiBestFirst<G, S, B>
 where
     G: Game,
     S: Strategy<G>,
+    B: book::BookScore<G>,
     TreeSearch<G, S>: Default,
 {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn book(mut self, book: book::OpeningBook<G::A>) -> Self {
+    pub fn book(mut self, book: B) -> Self {
         self.book = book;
         self
     }
@@ -914,10 +1515,11 @@ where
     }
 }
 
-impl<G, S> Default for QuasiBestFirst<G, S>
+impl<G, S, B> Default for QuasiBestFirst<G, S, B>
 where
     G: Game,
     S: Strategy<G>,
+    B: book::BookScore<G>,
     TreeSearch<G, S>: Default,
 {
     fn default() -> Self {
@@ -931,7 +1533,7 @@ where
         }
 
         Self {
-            book: book::OpeningBook::new(G::num_players()),
+            book: B::empty(G::num_players()),
             search: TreeSearch::default(),
             epsilon: 0.3,
             k,
@@ -940,10 +1542,11 @@ where
     }
 }
 
-impl<G, S> SelectStrategy<G> for QuasiBestFirst<G, S>
+impl<G, S, B> SelectStrategy<G> for QuasiBestFirst<G, S, B>
 where
     G: Game,
     S: Strategy<G>,
+    B: book::BookScore<G>,
     SearchConfig<G, S>: Default,
 {
     type Score = f64;
@@ -953,17 +1556,11 @@ where
         let current = ctx.index.get(ctx.stack.current_id());
         let available = current.edges();
 
-        // The stack now contains the action path to the terminal state.
-        // TODO: factor this pair iteration out of here
-        let mut key_init = vec![];
-        for (parent_id, child_id) in ctx.stack.pairs() {
-            key_init.push(
-                ctx.stack
-                    .edge(ctx.index, *parent_id, *child_id)
-                    .action
-                    .clone(),
-            );
-        }
+        let key_init = ctx
+            .path
+            .iter()
+            .map(|(action, _)| action.clone())
+            .collect::<Vec<_>>();
         let player_to_move = G::player_to_move(ctx.state).to_index();
         let k_score = self.k[player_to_move];
 
@@ -972,12 +1569,9 @@ where
             enumerated.as_slice(),
             rng,
             |(_, edge): &(usize, Edge<G::A>)| {
-                let mut key = key_init.clone();
-                key.push(edge.action.clone());
-
                 let score = self
                     .book
-                    .score(key.as_slice(), player_to_move)
+                    .score_action(ctx.state, key_init.as_slice(), &edge.action, player_to_move)
                     .unwrap_or(f64::NEG_INFINITY);
                 if score > k_score {
                     score
@@ -1012,4 +1606,9 @@ where
     fn unvisited_value(&self, _: &SelectContext<'_, G>, _: Self::Aux) -> f64 {
         0.
     }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        validate_epsilon(self.epsilon)?;
+        self.search.config.validate()
+    }
 }
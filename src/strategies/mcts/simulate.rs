@@ -1,6 +1,8 @@
+use super::cache::EvalCache;
+use super::profile::{self, Op};
 use super::*;
 use crate::game::Game;
-use crate::game::PlayerIndex;
+use crate::game::{PlayerId, PlayerIndex};
 use crate::strategies::Search;
 use crate::util::random_best;
 
@@ -8,11 +10,17 @@ use rand::rngs::SmallRng;
 use rand::Rng;
 use std::marker::PhantomData;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EndType {
     NaturalEnd,
     // MoveLimit,
     TurnLimit,
+    // The leaf was scored by `SimulateStrategy::evaluate` without a playout
+    Evaluated,
+    // The leaf was solved exactly (see `SearchConfig::solve_threshold`)
+    Solved,
+    // The playout was stopped by `Game::playout_cutoff` (see `Mercy`)
+    Mercy,
 }
 
 #[derive(Debug, Clone)]
@@ -26,12 +34,56 @@ pub struct Trial<G: Game> {
     pub state: G::S,
     pub status: Status,
     pub depth: usize,
+    pub utilities: Vec<f64>,
+}
+
+/// The utilities at the end of a playout. A playout stopped by the depth limit
+/// is scored by `Game::utilities_nonterminal` when the game provides it.
+pub fn utilities<G: Game>(state: &G::S, end_type: Option<EndType>) -> Vec<f64> {
+    match end_type {
+        Some(EndType::TurnLimit) => {
+            G::utilities_nonterminal(state).unwrap_or_else(|| G::compute_utilities(state))
+        }
+        _ => G::compute_utilities(state),
+    }
 }
 
 pub trait SimulateStrategy<G>: Clone + Sync + Send + Default
 where
     G: Game,
 {
+    // Estimate the utilities of a non-terminal leaf directly. A strategy which
+    // returns a value here makes the search simulation-free: the playout is
+    // skipped and the estimate is backpropagated in place of the terminal
+    // utilities.
+    #[allow(unused_variables)]
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        None
+    }
+
+    // `evaluate` for several leaves at once, as collected by
+    // `SearchConfig::eval_batch`. Evaluators which run faster on batches, such
+    // as neural networks, override this.
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        states
+            .iter()
+            .map(|state| self.evaluate(state, stats, player))
+            .collect()
+    }
+
+    // Weights for the actions of a node being expanded, in place of
+    // `Game::action_priors`, for strategies which learn them along with the
+    // value of a state. See `eval::Network`.
+    #[allow(unused_variables)]
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        None
+    }
+
     // The default implementation is a uniform selection
     #[allow(unused_variables)]
     fn select_move<'a>(
@@ -47,18 +99,210 @@ where
 
     fn playout(
         &mut self,
-        mut state: G::S,
+        state: G::S,
         max_playout_depth: usize,
         stats: &TreeStats<G>,
         player: usize,
         rng: &mut SmallRng,
     ) -> Trial<G> {
+        play_out(
+            self,
+            state,
+            max_playout_depth,
+            stats,
+            player,
+            rng,
+            utilities::<G>,
+        )
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        BackpropFlags(0)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    /// One-time setup before playing from `init`. See `Search::prepare`.
+    #[allow(unused_variables)]
+    fn prepare(&mut self, init: &G::S) {}
+
+    /// Set the chance of a random move, if the strategy has one. This is how
+    /// `SearchConfig::epsilon_schedule` varies it from move to move.
+    #[allow(unused_variables)]
+    fn set_epsilon(&mut self, epsilon: f64) {}
+
+    /// Take in what a clone learned while playing out on another thread. The
+    /// clones made for `SearchConfig::tree_parallel` are merged back after
+    /// each search. Most strategies learn through `TreeStats` instead, which
+    /// the threads share.
+    #[allow(unused_variables)]
+    fn merge(&mut self, other: Self) {}
+}
+
+/// Play out from `state` choosing moves with the strategy's `select_move`, for
+/// up to `max_playout_depth` moves, and score the final state with `score`.
+pub fn play_out<G, S>(
+    strategy: &mut S,
+    state: G::S,
+    max_playout_depth: usize,
+    stats: &TreeStats<G>,
+    player: usize,
+    rng: &mut SmallRng,
+    score: impl FnOnce(&G::S, Option<EndType>) -> Vec<f64>,
+) -> Trial<G>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    play_out_until(
+        strategy,
+        state,
+        max_playout_depth,
+        stats,
+        player,
+        rng,
+        |_| None,
+        score,
+    )
+}
+
+// `play_out`, also stopping as soon as `stop` gives a reason to
+#[allow(clippy::too_many_arguments)]
+fn play_out_until<G, S>(
+    strategy: &mut S,
+    mut state: G::S,
+    max_playout_depth: usize,
+    stats: &TreeStats<G>,
+    player: usize,
+    rng: &mut SmallRng,
+    mut stop: impl FnMut(&G::S) -> Option<EndType>,
+    score: impl FnOnce(&G::S, Option<EndType>) -> Vec<f64>,
+) -> Trial<G>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    let mut profile = profile::Recorder::<G>::new();
+    let mut actions = Vec::new();
+    let mut available = Vec::new();
+    let mut depth = 0;
+    let end_type;
+    loop {
+        if profile.time(Op::IsTerminal, || G::is_terminal(&state)) {
+            end_type = Some(EndType::NaturalEnd);
+            break;
+        }
+        if depth >= max_playout_depth {
+            end_type = Some(EndType::TurnLimit);
+            break;
+        }
+        if let Some(reason) = stop(&state) {
+            end_type = Some(reason);
+            break;
+        }
+        available.clear();
+        profile.time(Op::GenerateActions, || {
+            G::generate_actions(&state, &mut available)
+        });
+        if available.is_empty() {
+            end_type = Some(EndType::NaturalEnd);
+            break;
+        }
+        let action: &G::A = strategy.select_move(&state, &available, stats, player, rng);
+        actions.push((action.clone(), G::player_to_move(&state).to_index()));
+        state = profile.time(Op::Apply, || G::apply(state, action));
+        depth += 1;
+    }
+
+    Trial {
+        utilities: profile.time(Op::Winner, || score(&state, end_type)),
+        actions,
+        state,
+        status: Status { end_type },
+        depth,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default, Clone)]
+pub struct Uniform;
+
+impl<G: Game> SimulateStrategy<G> for Uniform {}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Random words drawn from the RNG at a time by `Batched`
+const BATCH_SIZE: usize = 64;
+
+/// Uniform playouts which draw random numbers from the RNG in batches, and map
+/// them to action indices with a multiply and shift rather than a division.
+/// The mapping is biased by at most `n / 2^32` for `n` actions, which is
+/// negligible for playouts. Games which support `Game::sample_action`, such as
+/// through `Game::is_legal_index`, skip generating the legal actions at most
+/// steps. Playouts differ from `Uniform` for the same seed.
+#[derive(Clone)]
+pub struct Batched {
+    words: [u32; BATCH_SIZE],
+    next: usize,
+}
+
+impl Default for Batched {
+    fn default() -> Self {
+        Self {
+            words: [0; BATCH_SIZE],
+            next: BATCH_SIZE,
+        }
+    }
+}
+
+impl Batched {
+    #[inline(always)]
+    fn word(&mut self, rng: &mut SmallRng) -> u32 {
+        if self.next == BATCH_SIZE {
+            rng.fill(&mut self.words[..]);
+            self.next = 0;
+        }
+        let word = self.words[self.next];
+        self.next += 1;
+        word
+    }
+
+    #[inline(always)]
+    fn index(&mut self, len: usize, rng: &mut SmallRng) -> usize {
+        ((self.word(rng) as u64 * len as u64) >> 32) as usize
+    }
+}
+
+impl<G: Game> SimulateStrategy<G> for Batched {
+    fn select_move<'a>(
+        &mut self,
+        _state: &G::S,
+        available: &'a [G::A],
+        _stats: &TreeStats<G>,
+        _player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        &available[self.index(available.len(), rng)]
+    }
+
+    fn playout(
+        &mut self,
+        mut state: G::S,
+        max_playout_depth: usize,
+        _stats: &TreeStats<G>,
+        _player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        let mut profile = profile::Recorder::<G>::new();
         let mut actions = Vec::new();
         let mut available = Vec::new();
         let mut depth = 0;
         let end_type;
         loop {
-            if G::is_terminal(&state) {
+            if profile.time(Op::IsTerminal, || G::is_terminal(&state)) {
                 end_type = Some(EndType::NaturalEnd);
                 break;
             }
@@ -66,37 +310,521 @@ where
                 end_type = Some(EndType::TurnLimit);
                 break;
             }
-            available.clear();
-            G::generate_actions(&state, &mut available);
-            if available.is_empty() {
-                end_type = Some(EndType::NaturalEnd);
-                break;
-            }
-            let action: &G::A = self.select_move(&state, &available, stats, player, rng);
-            actions.push((action.clone(), G::player_to_move(&state).to_index()));
-            state = G::apply(state, action);
+            let word = self.word(rng);
+            let action = match G::sample_action(&state, word) {
+                Some(action) => action,
+                None => {
+                    available.clear();
+                    profile.time(Op::GenerateActions, || {
+                        G::generate_actions(&state, &mut available)
+                    });
+                    if available.is_empty() {
+                        end_type = Some(EndType::NaturalEnd);
+                        break;
+                    }
+                    available[((word as u64 * available.len() as u64) >> 32) as usize].clone()
+                }
+            };
+            let mover = G::player_to_move(&state).to_index();
+            state = profile.time(Op::Apply, || G::apply(state, &action));
+            actions.push((action, mover));
             depth += 1;
         }
 
         Trial {
+            utilities: profile.time(Op::Winner, || utilities::<G>(&state, end_type)),
             actions,
             state,
             status: Status { end_type },
             depth,
         }
     }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Entries in the cache created by `Cached::default`
+const DEFAULT_CACHE_CAPACITY: usize = 1 << 16;
+
+/// Caches the inner strategy's `evaluate` results by zobrist hash, for
+/// expensive evaluators. Clones share the cache, so it carries over between
+/// searches and is shared by copies of a search running in parallel; pass
+/// the same `EvalCache` to separately built searches to share it between
+/// them. Requires `Game::zobrist_hash`: the configuration is rejected unless
+/// `Game::supports_transpositions`.
+#[derive(Clone)]
+pub struct Cached<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub cache: EvalCache,
+    inner: S,
+    marker: PhantomData<G>,
+}
+
+impl<G, S> Cached<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub fn new(inner: S, cache: EvalCache) -> Self {
+        Self {
+            cache,
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn inner(mut self, inner: S) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    pub fn cache(mut self, cache: EvalCache) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+impl<G, S> Default for Cached<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn default() -> Self {
+        Self::new(S::default(), EvalCache::new(DEFAULT_CACHE_CAPACITY))
+    }
+}
+
+impl<G, S> SimulateStrategy<G> for Cached<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        let hash = G::zobrist_hash(state);
+        if let Some(utilities) = self.cache.get(hash, player) {
+            return Some(utilities);
+        }
+        let utilities = self.inner.evaluate(state, stats, player)?;
+        self.cache.insert(hash, player, utilities.clone());
+        Some(utilities)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        let hashes = states.iter().map(G::zobrist_hash).collect::<Vec<_>>();
+        let mut results = hashes
+            .iter()
+            .map(|hash| self.cache.get(*hash, player))
+            .collect::<Vec<_>>();
+        let (misses, missed): (Vec<usize>, Vec<G::S>) = results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| result.is_none())
+            .map(|(i, _)| (i, states[i].clone()))
+            .unzip();
+        if missed.is_empty() {
+            return results;
+        }
+        for (i, utilities) in misses
+            .into_iter()
+            .zip(self.inner.evaluate_batch(&missed, stats, player))
+        {
+            if let Some(utilities) = &utilities {
+                self.cache.insert(hashes[i], player, utilities.clone());
+            }
+            results[i] = utilities;
+        }
+        results
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        self.inner.priors(state, actions)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        self.inner.select_move(state, available, stats, player, rng)
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        self.inner
+            .playout(state, max_playout_depth, stats, player, rng)
+    }
 
     fn backprop_flags(&self) -> BackpropFlags {
-        BackpropFlags(0)
+        self.inner.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        // Without hashes every state would share one entry
+        if !G::supports_transpositions() {
+            return Err(ConfigError::UnhashedCache);
+        }
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.inner.set_epsilon(epsilon)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.inner.merge(other.inner)
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Default, Clone)]
-pub struct Uniform;
+/// The game's `utilities_nonterminal` estimate for a non-terminal state, or a
+/// draw if it has none. The default evaluation of `Evaluator` and `Cutoff`.
+pub fn estimated_utilities<G: Game>(state: &G::S, _player: usize) -> Vec<f64> {
+    G::utilities_nonterminal(state).unwrap_or_else(|| vec![0.; G::num_players()])
+}
+
+/// Scores every non-terminal leaf with a static evaluation function instead of
+/// playing it out, for games where playouts are expensive or uninformative.
+/// The function is given the leaf's state and the searching player and returns
+/// the utilities of all players, which are backpropagated as if they were the
+/// result of a playout.
+///
+/// The default evaluator is `estimated_utilities`.
+///
+/// ```ignore
+/// let config = SearchConfig::new().simulate(simulate::Evaluator::new(|state, _| eval(state)));
+/// ```
+pub struct Evaluator<G: Game> {
+    pub eval: fn(&G::S, usize) -> Vec<f64>,
+}
+
+impl<G: Game> Evaluator<G> {
+    pub fn new(eval: fn(&G::S, usize) -> Vec<f64>) -> Self {
+        Self { eval }
+    }
+}
+
+impl<G: Game> Clone for Evaluator<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G: Game> Copy for Evaluator<G> {}
+
+impl<G: Game> Default for Evaluator<G> {
+    fn default() -> Self {
+        Self::new(estimated_utilities::<G>)
+    }
+}
+
+impl<G: Game> SimulateStrategy<G> for Evaluator<G> {
+    fn evaluate(&mut self, state: &G::S, _: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        Some((self.eval)(state, player))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Moves played out by `Cutoff::default` before evaluating
+const DEFAULT_CUTOFF_DEPTH: usize = 10;
+
+/// Plays out up to `depth` moves, chosen by the inner strategy's `select_move`,
+/// then scores the state reached with a heuristic evaluation rather than by
+/// `Game::utilities_nonterminal`. Playouts which
+/// end naturally before the cutoff keep their exact utilities. The cutoff
+/// applies in addition to `SearchConfig::max_playout_depth`, whichever is
+/// shorter.
+pub struct Cutoff<G: Game, S = Uniform>
+where
+    S: SimulateStrategy<G>,
+{
+    pub depth: usize,
+    pub eval: fn(&G::S, usize) -> Vec<f64>,
+    inner: S,
+}
+
+impl<G, S> Cutoff<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub fn new(depth: usize, eval: fn(&G::S, usize) -> Vec<f64>) -> Self {
+        Self {
+            depth,
+            eval,
+            inner: S::default(),
+        }
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn eval(mut self, eval: fn(&G::S, usize) -> Vec<f64>) -> Self {
+        self.eval = eval;
+        self
+    }
+
+    pub fn inner(mut self, inner: S) -> Self {
+        self.inner = inner;
+        self
+    }
+}
+
+impl<G, S> Clone for Cutoff<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            depth: self.depth,
+            eval: self.eval,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<G, S> Default for Cutoff<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_CUTOFF_DEPTH, estimated_utilities::<G>)
+    }
+}
+
+impl<G, S> SimulateStrategy<G> for Cutoff<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        self.inner.evaluate(state, stats, player)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        self.inner.evaluate_batch(states, stats, player)
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        self.inner.priors(state, actions)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        self.inner.select_move(state, available, stats, player, rng)
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        let depth = max_playout_depth.min(self.depth);
+        let eval = self.eval;
+        play_out(
+            self,
+            state,
+            depth,
+            stats,
+            player,
+            rng,
+            |state, end_type| match end_type {
+                Some(EndType::TurnLimit) => eval(state, player),
+                _ => G::compute_utilities(state),
+            },
+        )
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.inner.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.depth == 0 {
+            return Err(ConfigError::ZeroCutoffDepth);
+        }
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.inner.set_epsilon(epsilon)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.inner.merge(other.inner)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Ends playouts early by the mercy rule: as soon as `Game::playout_cutoff`
+/// finds a player with a decisive advantage, the playout stops and is scored
+/// as a win for that player. Moves are chosen by the inner strategy's
+/// `select_move`. Games which do not implement `playout_cutoff` play out as
+/// they would under the inner strategy.
+#[derive(Clone)]
+pub struct Mercy<G, S = Uniform>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    inner: S,
+    marker: PhantomData<G>,
+}
+
+impl<G, S> Mercy<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub fn inner(mut self, inner: S) -> Self {
+        self.inner = inner;
+        self
+    }
+}
+
+impl<G, S> Default for Mercy<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn default() -> Self {
+        Self {
+            inner: S::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+// A win for `winner` and a loss for everyone else
+fn win_for<G: Game>(winner: PlayerId) -> Vec<f64> {
+    G::players()
+        .map(|player| if player == winner { 1. } else { -1. })
+        .collect()
+}
+
+impl<G, S> SimulateStrategy<G> for Mercy<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        self.inner.evaluate(state, stats, player)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        self.inner.evaluate_batch(states, stats, player)
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        self.inner.priors(state, actions)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        self.inner.select_move(state, available, stats, player, rng)
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        play_out_until(
+            self,
+            state,
+            max_playout_depth,
+            stats,
+            player,
+            rng,
+            |state| G::playout_cutoff(state).map(|_| EndType::Mercy),
+            |state, end_type| match end_type {
+                Some(EndType::Mercy) => {
+                    win_for::<G>(PlayerId::of(&G::playout_cutoff(state).unwrap()))
+                }
+                _ => utilities::<G>(state, end_type),
+            },
+        )
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.inner.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.inner.set_epsilon(epsilon)
+    }
 
-impl<G: Game> SimulateStrategy<G> for Uniform {}
+    fn merge(&mut self, other: Self) {
+        self.inner.merge(other.inner)
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -174,6 +902,23 @@ where
             self.inner.select_move(state, available, stats, player, rng)
         }
     }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.inner.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        validate_epsilon(self.epsilon)?;
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.epsilon = epsilon;
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -300,6 +1045,26 @@ where
         self.choose(state, available, player)
             .unwrap_or_else(|| self.inner.select_move(state, available, stats, player, rng))
     }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.inner.backprop_flags()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.inner.set_epsilon(epsilon)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.inner.merge(other.inner)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -327,15 +1092,9 @@ where
             .iter()
             .map(|action| {
                 // TODO: which player perspective?
-                let score = stats.player_actions[player]
-                    .get(action)
-                    .map_or(1., |stats| {
-                        if stats.num_visits > 0 {
-                            stats.score / stats.num_visits as f64
-                        } else {
-                            1.
-                        }
-                    });
+                let score = stats.history.player_actions[player]
+                    .mean(action)
+                    .unwrap_or(1.);
 
                 (score, action)
             })
@@ -349,6 +1108,417 @@ where
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// N-gram selection technique (NST; Tak, Winands and Björnsson, 2012). MAST
+/// generalized to sequences of consecutive actions: each candidate move is
+/// scored by the mean of its statistics following the last zero, one, ... up
+/// to `max_n - 1` moves of the playout, counting the longer sequences only
+/// once they have `min_visits` visits, and the best scoring move is played.
+/// Statistics are kept per player for the player making the move.
+///
+/// The statistics are gathered over whole trials, tree moves included, but
+/// the playout's own moves are the only context when choosing. Since that
+/// context must include every move played, exploration is built in: with
+/// probability `epsilon` a uniformly random move is played instead, so this
+/// should not be wrapped in `EpsilonGreedy`.
+#[derive(Clone)]
+pub struct Nst<G: Game> {
+    pub max_n: usize,
+    pub min_visits: u32,
+    pub epsilon: f64,
+    // The moves played so far in the current playout
+    recent: Vec<G::A>,
+}
+
+impl<G: Game> Default for Nst<G> {
+    fn default() -> Self {
+        Self {
+            max_n: history::MAX_NGRAM,
+            min_visits: 7,
+            epsilon: 0.1,
+            recent: vec![],
+        }
+    }
+}
+
+impl<G: Game> Nst<G> {
+    pub fn max_n(mut self, max_n: usize) -> Self {
+        self.max_n = max_n;
+        self
+    }
+
+    pub fn min_visits(mut self, min_visits: u32) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    fn score(&self, action: &G::A, stats: &TreeStats<G>, mover: usize) -> f64 {
+        let (mut total, mut count) = (0., 0);
+        for n in 1..=self.max_n.min(self.recent.len() + 1) {
+            let context = history::ngram_context(&self.recent[self.recent.len() + 1 - n..]);
+            let Some(ngram) = stats.history.ngrams.get(context, mover, action) else {
+                continue;
+            };
+            if ngram.num_visits > 0 && (n == 1 || ngram.num_visits >= self.min_visits) {
+                total += ngram.score / ngram.num_visits as f64;
+                count += 1;
+            }
+        }
+        // Optimistic for unseen moves, as with MAST
+        if count == 0 {
+            1.
+        } else {
+            total / count as f64
+        }
+    }
+}
+
+impl<G: Game> SimulateStrategy<G> for Nst<G> {
+    fn backprop_flags(&self) -> BackpropFlags {
+        BackpropFlags(NGRAM)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.epsilon = epsilon;
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(1..=history::MAX_NGRAM).contains(&self.max_n) {
+            return Err(ConfigError::NGramLengthOutOfRange(self.max_n));
+        }
+        validate_epsilon(self.epsilon)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        _player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        let action = if rng.gen::<f64>() < self.epsilon {
+            &available[rng.gen_range(0..available.len())]
+        } else {
+            let mover = G::player_to_move(state).to_index();
+            let action_scores = available
+                .iter()
+                .map(|action| (self.score(action, stats, mover), action))
+                .collect::<Vec<_>>();
+            random_best(&action_scores, rng, |(score, _)| *score)
+                .unwrap()
+                .1
+        };
+        self.recent.push(action.clone());
+        action
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        self.recent.clear();
+        play_out(
+            self,
+            state,
+            max_playout_depth,
+            stats,
+            player,
+            rng,
+            utilities::<G>,
+        )
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Last-Good-Reply (Drake, 2009; Baier and Drake, 2010). The last reply each
+/// player made to the previous move in a trial they won is stored, and played
+/// again in playouts whenever it is legal. Other moves are chosen by the inner
+/// strategy's `select_move`.
+///
+/// - With `lgr2`, replies to the previous two moves are also stored and tried
+///   first (LGR-2), falling back to the one move reply (LGR-1).
+/// - With `forgetting`, a stored reply is dropped again when it is played in
+///   a trial its player loses (LGRF).
+///
+/// Replies are stored for every move of the trial, tree moves included, but
+/// only the playout's own moves are the context when choosing.
+pub struct LastGoodReply<G: Game, S = Uniform>
+where
+    S: SimulateStrategy<G>,
+{
+    pub lgr2: bool,
+    pub forgetting: bool,
+    inner: S,
+    // The moves played so far in the current playout
+    recent: Vec<G::A>,
+}
+
+impl<G, S> LastGoodReply<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub fn lgr2(mut self, lgr2: bool) -> Self {
+        self.lgr2 = lgr2;
+        self
+    }
+
+    pub fn forgetting(mut self, forgetting: bool) -> Self {
+        self.forgetting = forgetting;
+        self
+    }
+
+    pub fn inner(mut self, inner: S) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    // The stored reply to the latest moves of the playout, if any
+    fn reply<'a>(&self, stats: &'a TreeStats<G>, mover: usize) -> Option<&'a G::A> {
+        let longest = if self.lgr2 { 2 } else { 1 };
+        (1..=longest.min(self.recent.len())).rev().find_map(|n| {
+            let context = history::ngram_context(&self.recent[self.recent.len() - n..]);
+            stats.history.replies.get(context, mover)
+        })
+    }
+}
+
+impl<G, S> Clone for LastGoodReply<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            lgr2: self.lgr2,
+            forgetting: self.forgetting,
+            inner: self.inner.clone(),
+            recent: self.recent.clone(),
+        }
+    }
+}
+
+impl<G, S> Default for LastGoodReply<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn default() -> Self {
+        Self {
+            lgr2: true,
+            forgetting: true,
+            inner: S::default(),
+            recent: vec![],
+        }
+    }
+}
+
+impl<G, S> SimulateStrategy<G> for LastGoodReply<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn evaluate(&mut self, state: &G::S, stats: &TreeStats<G>, player: usize) -> Option<Vec<f64>> {
+        self.inner.evaluate(state, stats, player)
+    }
+
+    fn evaluate_batch(
+        &mut self,
+        states: &[G::S],
+        stats: &TreeStats<G>,
+        player: usize,
+    ) -> Vec<Option<Vec<f64>>> {
+        self.inner.evaluate_batch(states, stats, player)
+    }
+
+    fn priors(&mut self, state: &G::S, actions: &[G::A]) -> Option<Vec<f64>> {
+        self.inner.priors(state, actions)
+    }
+
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        let mover = G::player_to_move(state).to_index();
+        let reply = self
+            .reply(stats, mover)
+            .and_then(|reply| available.iter().find(|action| *action == reply));
+        let action = match reply {
+            Some(action) => action,
+            None => self.inner.select_move(state, available, stats, player, rng),
+        };
+        self.recent.push(action.clone());
+        action
+    }
+
+    fn playout(
+        &mut self,
+        state: G::S,
+        max_playout_depth: usize,
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> Trial<G> {
+        self.recent.clear();
+        play_out(
+            self,
+            state,
+            max_playout_depth,
+            stats,
+            player,
+            rng,
+            utilities::<G>,
+        )
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        let forget = if self.forgetting { FORGET_REPLY } else { 0 };
+        self.inner.backprop_flags() | BackpropFlags(REPLY | forget)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.inner.set_epsilon(epsilon)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.inner.merge(other.inner)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Tree-informed playouts. With probability `probability`, a playout move is
+/// the most visited action of the tree node with the same Zobrist hash as the
+/// current state, if the tree has such a node with at least `min_visits`
+/// visits on that action. Otherwise the inner strategy chooses. The nodes are
+/// looked up in `TreeStats::guide`, which the search maintains for this
+/// strategy; it is densest in games with many transpositions. Requires
+/// `Game::zobrist_hash`.
+#[derive(Clone)]
+pub struct TableGuided<G, S = Uniform>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub probability: f64,
+    pub min_visits: u32,
+    inner: S,
+    marker: PhantomData<G>,
+}
+
+impl<G, S> TableGuided<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    pub fn min_visits(mut self, min_visits: u32) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    pub fn inner(mut self, inner: S) -> Self {
+        self.inner = inner;
+        self
+    }
+}
+
+impl<G, S> Default for TableGuided<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn default() -> Self {
+        Self {
+            probability: 0.5,
+            min_visits: 10,
+            inner: S::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<G, S> SimulateStrategy<G> for TableGuided<G, S>
+where
+    G: Game,
+    S: SimulateStrategy<G>,
+{
+    fn select_move<'a>(
+        &mut self,
+        state: &G::S,
+        available: &'a [G::A],
+        stats: &TreeStats<G>,
+        player: usize,
+        rng: &mut SmallRng,
+    ) -> &'a G::A {
+        if rng.gen::<f64>() < self.probability {
+            let guided = stats
+                .guide
+                .get(&G::zobrist_hash(state))
+                .filter(|(_, visits)| *visits >= self.min_visits)
+                // Hashes may collide, so the action must also be legal here
+                .and_then(|(action, _)| available.iter().find(|a| *a == action));
+            if let Some(action) = guided {
+                return action;
+            }
+        }
+        self.inner.select_move(state, available, stats, player, rng)
+    }
+
+    fn backprop_flags(&self) -> BackpropFlags {
+        self.inner.backprop_flags() | BackpropFlags(GUIDE)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        validate_epsilon(self.probability)?;
+        self.inner.validate()
+    }
+
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.inner.set_epsilon(epsilon)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.inner.merge(other.inner)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 #[derive(Clone)]
 pub struct MetaMcts<G: Game, S: Strategy<G>> {
     pub inner: TreeSearch<G, S>,
@@ -383,4 +1553,7 @@ where
         let index = available.iter().position(|p| *p == action).unwrap();
         &available[index]
     }
+    fn prepare(&mut self, init: &G::S) {
+        self.inner.prepare(init)
+    }
 }
@@ -0,0 +1,104 @@
+//! A small exhaustive solver, used to evaluate positions near the end of the
+//! game exactly rather than by playout. This performs a max^n search over the
+//! full remaining game tree, so it should only be applied where the remaining
+//! game is trivially small.
+
+use crate::game::{Game, PlayerIndex};
+
+pub struct Solution<G: Game> {
+    pub utilities: Vec<f64>,
+    // The principal variation with the player index for each action
+    pub actions: Vec<(G::A, usize)>,
+    // The terminal state at the end of the principal variation
+    pub state: G::S,
+}
+
+pub fn solve<G: Game>(state: &G::S) -> Solution<G> {
+    let mut actions = vec![];
+    if !G::is_terminal(state) {
+        G::generate_actions(state, &mut actions);
+    }
+
+    if actions.is_empty() {
+        return Solution {
+            utilities: G::compute_utilities(state),
+            actions: vec![],
+            state: state.clone(),
+        };
+    }
+
+    let player = G::player_to_move(state).to_index();
+    let mut best: Option<(G::A, Solution<G>)> = None;
+    for action in actions {
+        let child = solve::<G>(&G::apply(state.clone(), &action));
+        let better = best
+            .as_ref()
+            .map_or(true, |(_, b)| child.utilities[player] > b.utilities[player]);
+        if better {
+            best = Some((action, child));
+        }
+    }
+
+    let (action, mut solution) = best.unwrap();
+    solution.actions.insert(0, (action, player));
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, Move, TicTacToe};
+
+    #[test]
+    fn test_solve_ttt() {
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+
+        let solution = solve::<TicTacToe>(&state);
+        assert_eq!(solution.utilities, vec![1., -1.]);
+        assert_eq!(solution.actions, vec![(Move(2), 0)]);
+        assert!(TicTacToe::is_terminal(&solution.state));
+
+        // X X .
+        // . O .
+        // . . O
+        //
+        // X to move wins at once by completing the top row
+        let state = TicTacToe::apply(
+            [0, 4, 1].iter().fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(*i))
+            }),
+            &Move(8),
+        );
+        let solution = solve::<TicTacToe>(&state);
+        assert_eq!(solution.utilities, vec![1., -1.]);
+        assert_eq!(solution.actions, vec![(Move(2), 0)]);
+    }
+
+    #[test]
+    fn test_search_solve_threshold() {
+        use crate::strategies::mcts::{strategy, SearchConfig, TreeSearch};
+        use crate::strategies::Search;
+
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+
+        let mut ts: TreeSearch<TicTacToe, strategy::Ucb1> = TreeSearch::new().config(
+            SearchConfig::new()
+                .max_iterations(200)
+                .solve_threshold(9)
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), Move(2));
+        // Every trial ran to the end of the game
+        assert!(ts.trial.as_ref().unwrap().status.end_type.is_some());
+    }
+}
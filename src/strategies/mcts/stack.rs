@@ -4,9 +4,71 @@ use super::node::NodeState;
 use super::node::NodeStats;
 use super::search::TreeIndex;
 use crate::game::Action;
-use crate::util::Pairs;
-use crate::util::ReversePairs;
-use crate::util::ReversePairs2;
+
+/// Adjacent pairs of a path from the root, `(parent, child)`, in root to leaf
+/// order.
+pub struct Pairs<'a, T: 'a>(std::slice::Windows<'a, T>);
+
+impl<'a, T> Pairs<'a, T> {
+    pub fn new(stack: &'a [T]) -> Self {
+        Self(stack.windows(2))
+    }
+}
+
+impl<'a, T> Iterator for Pairs<'a, T> {
+    type Item = (&'a T, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|pair| (&pair[0], &pair[1]))
+    }
+}
+
+/// Adjacent pairs of a path from the root, `(parent, child)`, in leaf to root
+/// order.
+pub struct ReversePairs<'a, T: 'a>(std::iter::Rev<std::slice::Windows<'a, T>>);
+
+impl<'a, T> ReversePairs<'a, T> {
+    pub fn new(stack: &'a [T]) -> Self {
+        Self(stack.windows(2).rev())
+    }
+}
+
+impl<'a, T> Iterator for ReversePairs<'a, T> {
+    type Item = (&'a T, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|pair| (&pair[0], &pair[1]))
+    }
+}
+
+/// Every element of a path from leaf to root, with its parent. The root has no
+/// parent.
+pub struct WithParent<'a, T: 'a> {
+    stack: &'a [T],
+    index: usize,
+}
+
+impl<'a, T> WithParent<'a, T> {
+    pub fn new(stack: &'a [T]) -> Self {
+        Self {
+            stack,
+            index: stack.len(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for WithParent<'a, T> {
+    type Item = (Option<&'a T>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        let parent = self.index.checked_sub(1).map(|i| &self.stack[i]);
+        Some((parent, &self.stack[self.index]))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct NodeStack<A> {
@@ -38,8 +100,8 @@ impl<A: Action> NodeStack<A> {
         ReversePairs::new(&self.stack)
     }
 
-    pub fn reverse_pairs2(&self) -> ReversePairs2<'_, Id> {
-        ReversePairs2::new(&self.stack)
+    pub fn with_parent(&self) -> WithParent<'_, Id> {
+        WithParent::new(&self.stack)
     }
 
     pub fn root(&self) -> Id {
@@ -111,3 +173,45 @@ impl<A: Action> NodeStack<A> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs() {
+        let stack = vec![1, 2, 3];
+        assert_eq!(
+            Pairs::new(&stack).collect::<Vec<_>>(),
+            vec![(&1, &2), (&2, &3)]
+        );
+        assert_eq!(Pairs::new(&stack[..1]).next(), None);
+        assert_eq!(Pairs::<i32>::new(&[]).next(), None);
+    }
+
+    #[test]
+    fn test_reverse_pairs() {
+        let stack = vec![1, 2, 3, 4, 5];
+        let mut reverse_pairs = ReversePairs::new(&stack);
+
+        assert_eq!(reverse_pairs.next(), Some((&4, &5)));
+        assert_eq!(reverse_pairs.next(), Some((&3, &4)));
+        assert_eq!(reverse_pairs.next(), Some((&2, &3)));
+        assert_eq!(reverse_pairs.next(), Some((&1, &2)));
+        assert_eq!(reverse_pairs.next(), None);
+        assert_eq!(ReversePairs::<i32>::new(&[]).next(), None);
+    }
+
+    #[test]
+    fn test_with_parent() {
+        let stack = vec![1, 2, 3, 4, 5];
+        let mut with_parent = WithParent::new(&stack);
+
+        assert_eq!(with_parent.next(), Some((Some(&4), &5)));
+        assert_eq!(with_parent.next(), Some((Some(&3), &4)));
+        assert_eq!(with_parent.next(), Some((Some(&2), &3)));
+        assert_eq!(with_parent.next(), Some((Some(&1), &2)));
+        assert_eq!(with_parent.next(), Some((None, &1)));
+        assert_eq!(with_parent.next(), None);
+    }
+}
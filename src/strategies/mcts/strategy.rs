@@ -49,6 +49,51 @@ impl<G: Game> Strategy<G> for Ucb1Mast {
     }
 }
 
+// Vanilla UCT + NST. See `simulate::Nst`.
+#[derive(Clone, Default)]
+pub struct Ucb1Nst;
+
+impl<G: Game> Strategy<G> for Ucb1Nst {
+    type Select = select::Ucb1;
+    type Simulate = simulate::Nst<G>;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+
+    fn friendly_name() -> String {
+        "ucb1_nst".into()
+    }
+}
+
+// Vanilla UCT + LGRF-2. See `simulate::LastGoodReply`.
+#[derive(Clone, Default)]
+pub struct Ucb1Lgr;
+
+impl<G: Game> Strategy<G> for Ucb1Lgr {
+    type Select = select::Ucb1;
+    type Simulate = simulate::LastGoodReply<G>;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+
+    fn friendly_name() -> String {
+        "ucb1_lgr".into()
+    }
+}
+
+// Vanilla UCT, scoring leaves with a static evaluation. See `simulate::Evaluator`.
+#[derive(Clone, Default)]
+pub struct Ucb1Eval;
+
+impl<G: Game> Strategy<G> for Ucb1Eval {
+    type Select = select::Ucb1;
+    type Simulate = simulate::Evaluator<G>;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+
+    fn friendly_name() -> String {
+        "ucb1_eval".into()
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct Amaf;
 
@@ -190,3 +235,138 @@ impl<G: Game> Strategy<G> for RaveMastDm {
     type Backprop = backprop::Classic;
     type FinalAction = select::RobustChild;
 }
+
+// AlphaZero style PUCT, with the priors from `Game::action_priors`
+#[derive(Clone, Default)]
+pub struct Puct;
+
+impl<G: Game> Strategy<G> for Puct {
+    type Select = select::Puct;
+    type Simulate = simulate::Uniform;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+
+    fn friendly_name() -> String {
+        "puct".into()
+    }
+}
+
+// AlphaZero style PUCT, with the leaf values and priors from an
+// `eval::Evaluator`. See `eval::Network`.
+#[derive(Clone, Default)]
+pub struct AlphaZero;
+
+impl<G: Game> Strategy<G> for AlphaZero {
+    type Select = select::Puct;
+    type Simulate = eval::Network<G>;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::RobustChild;
+
+    fn friendly_name() -> String {
+        "alpha_zero".into()
+    }
+}
+
+// Maximum entropy tree search (MENTS)
+#[derive(Clone, Default)]
+pub struct Ments;
+
+impl<G: Game> Strategy<G> for Ments {
+    type Select = select::Boltzmann;
+    type Simulate = simulate::Uniform;
+    type Backprop = backprop::Soft;
+    type FinalAction = select::RobustChild;
+
+    fn friendly_name() -> String {
+        "ments".into()
+    }
+}
+
+// Decoupled UCT for simultaneous move games. See `select::Duct`.
+#[derive(Clone, Default)]
+pub struct Duct;
+
+impl<G: Game> Strategy<G> for Duct {
+    type Select = select::Duct;
+    type Simulate = simulate::Uniform;
+    type Backprop = backprop::Classic;
+    type FinalAction = select::Duct;
+
+    fn friendly_name() -> String {
+        "duct".into()
+    }
+
+    fn config() -> SearchConfig<G, Self> {
+        SearchConfig::new().final_action(select::Duct::robust())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The names of the strategies dispatched by `match_preset!`, as used by
+/// tournament manifests, test suites and `dynamic::preset`.
+pub const PRESETS: [&str; 10] = [
+    "ucb1",
+    "ucb1_dm",
+    "ucb1_mast",
+    "amaf",
+    "amaf_mast",
+    "ucb1_tuned",
+    "ucb1_tuned_mast",
+    "ucb1_tuned_dm",
+    "ucb1_tuned_dm_mast",
+    "rave_mast_dm",
+];
+
+// Evaluate `$found` with `$s` naming the strategy of the preset called
+// `$name`, or `$not_found` with the unknown name bound to `$other`.
+macro_rules! match_preset {
+    ($name:expr, $s:ident => $found:expr, $other:ident => $not_found:expr) => {{
+        use $crate::strategies::mcts::strategy;
+        match $name {
+            "ucb1" => {
+                type $s = strategy::Ucb1;
+                $found
+            }
+            "ucb1_dm" => {
+                type $s = strategy::Ucb1DM;
+                $found
+            }
+            "ucb1_mast" => {
+                type $s = strategy::Ucb1Mast;
+                $found
+            }
+            "amaf" => {
+                type $s = strategy::Amaf;
+                $found
+            }
+            "amaf_mast" => {
+                type $s = strategy::AmafMast;
+                $found
+            }
+            "ucb1_tuned" => {
+                type $s = strategy::Ucb1Tuned;
+                $found
+            }
+            "ucb1_tuned_mast" => {
+                type $s = strategy::Ucb1TunedMast;
+                $found
+            }
+            "ucb1_tuned_dm" => {
+                type $s = strategy::Ucb1TunedDM;
+                $found
+            }
+            "ucb1_tuned_dm_mast" => {
+                type $s = strategy::Ucb1TunedDMMast;
+                $found
+            }
+            "rave_mast_dm" => {
+                type $s = strategy::RaveMastDm;
+                $found
+            }
+            $other => $not_found,
+        }
+    }};
+}
+
+pub(crate) use match_preset;
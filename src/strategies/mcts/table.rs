@@ -1,27 +1,219 @@
 use super::index;
 use crate::zobrist::ZobristHashMap;
 
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+
+/// Which table entries a new edge may link to when its state is already in
+/// the table. Linking a node first reached at a very different depth mixes
+/// statistics gathered under different search conditions, which can distort
+/// them, particularly when symmetric states share a hash.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TranspositionLinking {
+    /// Any entry for the same state.
+    #[default]
+    Any,
+    /// Only entries created at the same depth below the root.
+    Depth,
+    /// Only entries created at a depth of the same parity, i.e. with the same
+    /// number of moves to the root modulo two.
+    Parity,
+}
+
+impl TranspositionLinking {
+    pub fn accepts(&self, entry_depth: usize, depth: usize) -> bool {
+        match self {
+            TranspositionLinking::Any => true,
+            TranspositionLinking::Depth => entry_depth == depth,
+            TranspositionLinking::Parity => entry_depth % 2 == depth % 2,
+        }
+    }
+}
+
+/// How a `TranspositionTable` decides an entry matches a state. Distinct
+/// states sharing a 64-bit hash are rare, but when one is taken for the other
+/// the search links to a node with the wrong actions and statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TableVerification {
+    /// Compare the stored state as well as the hash, so that a collision is
+    /// detected and the states are kept apart.
+    #[default]
+    Safe,
+    /// Compare only the hash. This avoids comparing states, which may be
+    /// costly, at the risk of an undetected collision.
+    Fast,
+}
+
+impl TableVerification {
+    // Whether an entry for `stored` matches `state`, given equal hashes
+    #[inline]
+    fn matches<S: Eq>(self, stored: &S, state: &S) -> bool {
+        self == TableVerification::Fast || stored == state
+    }
+}
+
+/// Which entry a bounded table gives up when a bucket is full. See
+/// `TableBounds`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Replacement {
+    /// Keep the entry nearer the root, whose node has usually seen more
+    /// visits, dropping the new entry if it is deeper.
+    #[default]
+    DepthPreferred,
+    /// Always replace the entry with the new one.
+    Recent,
+    /// Two entries per bucket: a depth-preferred one, and one which is always
+    /// replaced. An entry displaced from the first moves to the second.
+    TwoTier,
+}
+
+/// The size of a `TranspositionTable` and how it is kept within it. An
+/// unbounded table keeps an entry for every node it is given. A bounded table
+/// holds at most `capacity` entries in buckets of one entry, or two with
+/// `Replacement::TwoTier`, each shared by the hashes equal modulo the number
+/// of buckets. A node whose entry is dropped stays in the tree, but can no
+/// longer be found as a transposition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TableBounds {
+    pub capacity: usize,
+    pub replacement: Replacement,
+}
+
+impl Default for TableBounds {
+    fn default() -> Self {
+        Self {
+            capacity: usize::MAX,
+            replacement: Replacement::default(),
+        }
+    }
+}
+
+impl TableBounds {
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.capacity != usize::MAX
+    }
+
+    // The entries held per bucket
+    fn slots(&self) -> usize {
+        match (self.is_bounded(), self.replacement) {
+            (false, _) => usize::MAX,
+            (true, Replacement::TwoTier) => 2,
+            (true, _) => 1,
+        }
+    }
+
+    // The bucket holding the entries for hash `k`
+    #[inline]
+    fn bucket(&self, k: u64) -> u64 {
+        if self.is_bounded() {
+            k % (self.capacity / self.slots()).max(1) as u64
+        } else {
+            k
+        }
+    }
+}
+
+/// Lookup and insertion counts of a `TranspositionTable`, for tuning its
+/// bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TableStats {
+    pub reads: usize,
+    pub writes: usize,
+    // Lookups which found the state
+    pub hits: usize,
+    // Lookups which found nothing for the hash
+    pub misses: usize,
+    // Lookups which found only other states in the bucket: a different hash
+    // in a bounded table, or a hash collision
+    pub collisions: usize,
+    // States inserted with the hash of a different state already held. Only
+    // detected with `TableVerification::Safe`.
+    pub hash_collisions: usize,
+    // Entries overwritten by a newer one
+    pub replacements: usize,
+    // Entries currently held
+    pub entries: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TableEntry<S: Eq> {
     pub node_id: index::Id,
     pub state: S,
+    pub hash: u64,
+    // The depth below the root at which the node was created
+    pub depth: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct TranspositionTable<S: Eq> {
     pub table: ZobristHashMap<Vec<TableEntry<S>>>,
+    pub bounds: TableBounds,
+    pub verification: TableVerification,
     pub reads: usize,
     pub writes: usize,
     pub hits: usize,
+    pub misses: usize,
+    pub collisions: usize,
+    pub hash_collisions: usize,
+    pub replacements: usize,
+    entries: usize,
 }
 
 impl<S: Eq> Default for TranspositionTable<S> {
     fn default() -> Self {
+        Self::new(TableBounds::default())
+    }
+}
+
+impl<S: Eq> TranspositionTable<S> {
+    pub fn new(bounds: TableBounds) -> Self {
         Self {
             table: ZobristHashMap::default(),
+            bounds,
+            verification: TableVerification::default(),
             reads: 0,
             writes: 0,
             hits: 0,
+            misses: 0,
+            collisions: 0,
+            hash_collisions: 0,
+            replacements: 0,
+            entries: 0,
+        }
+    }
+
+    pub fn verification(mut self, verification: TableVerification) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    pub fn stats(&self) -> TableStats {
+        TableStats {
+            reads: self.reads,
+            writes: self.writes,
+            hits: self.hits,
+            misses: self.misses,
+            collisions: self.collisions,
+            hash_collisions: self.hash_collisions,
+            replacements: self.replacements,
+            entries: self.entries,
         }
     }
 }
@@ -29,51 +221,125 @@ impl<S: Eq> Default for TranspositionTable<S> {
 impl<S: Clone + Eq> TranspositionTable<S> {
     #[inline]
     pub fn clear(&mut self) {
-        self.table.clear();
-        self.reads = 0;
-        self.writes = 0;
-        self.hits = 0;
+        *self = Self::new(self.bounds).verification(self.verification);
     }
 
+    // The entries which may hold hash `k`
     #[inline]
-    pub fn get(&mut self, k: u64, state: S) -> Option<&TableEntry<S>> {
+    fn entries(&self, k: u64) -> impl Iterator<Item = &TableEntry<S>> {
+        self.table
+            .get(self.bounds.bucket(k))
+            .into_iter()
+            .flatten()
+            .filter(move |entry| entry.hash == k)
+    }
+
+    /// Every entry, bucket by bucket.
+    pub(crate) fn all_entries(&self) -> impl Iterator<Item = &TableEntry<S>> {
+        self.table.0.values().flatten()
+    }
+
+    /// Put back an entry taken from `all_entries`, without applying the
+    /// replacement policy.
+    pub(crate) fn restore(&mut self, entry: TableEntry<S>) {
+        let bucket = self.bounds.bucket(entry.hash);
+        self.table.entry(bucket).or_default().push(entry);
+        self.entries += 1;
+    }
+
+    // Count a lookup of hash `k` by whether it `found` the state
+    fn record_lookup(&mut self, k: u64, found: bool) {
         self.reads += 1;
-        let result = self.table.get(k);
-        if let Some(entries) = result {
+        if found {
             self.hits += 1;
-            for entry in entries {
-                if entry.state == state {
-                    return Some(entry);
-                }
-            }
-            return None;
+        } else if self
+            .table
+            .get(self.bounds.bucket(k))
+            .is_some_and(|e| !e.is_empty())
+        {
+            self.collisions += 1;
+        } else {
+            self.misses += 1;
         }
-        None
+    }
+
+    #[inline]
+    pub fn get(&mut self, k: u64, state: S) -> Option<&TableEntry<S>> {
+        let found = self.get_const(k, state.clone()).is_some();
+        self.record_lookup(k, found);
+        self.get_const(k, state)
     }
 
     #[inline]
     pub fn get_const(&self, k: u64, state: S) -> Option<&TableEntry<S>> {
-        let result = self.table.get(k);
-        if let Some(entries) = result {
-            for entry in entries {
-                if entry.state == state {
-                    return Some(entry);
-                }
-            }
-            return None;
-        }
-        None
+        self.entries(k)
+            .find(|entry| self.verification.matches(&entry.state, &state))
     }
 
+    /// The entry for `state` which a node at `depth` may link to under
+    /// `linking`.
+    #[inline]
+    pub fn get_linkable(
+        &mut self,
+        k: u64,
+        state: &S,
+        depth: usize,
+        linking: TranspositionLinking,
+    ) -> Option<&TableEntry<S>> {
+        let verification = self.verification;
+        let linkable = |entry: &&TableEntry<S>| {
+            verification.matches(&entry.state, state) && linking.accepts(entry.depth, depth)
+        };
+        let found = self.entries(k).any(|entry| linkable(&entry));
+        self.record_lookup(k, found);
+        self.entries(k).find(linkable)
+    }
+
+    /// Add an entry, unless the state is already present at the same depth.
+    /// A different state with the same hash is counted as a collision.
+    /// When the table is bounded, the entry may replace another, or be dropped,
+    /// according to the replacement policy.
     #[inline(always)]
-    pub fn insert(&mut self, k: u64, node_id: index::Id, state: S) {
-        if self.get(k, state.clone()).is_some() {
+    pub fn insert(&mut self, k: u64, node_id: index::Id, state: S, depth: usize) {
+        if self
+            .entries(k)
+            .any(|entry| self.verification.matches(&entry.state, &state) && entry.depth == depth)
+        {
             return;
         }
-        let entries = self.table.entry(k).or_default();
-        if !entries.is_empty() {
-            eprintln!("collision: key={k:0x} len={}!", entries.len() + 1);
+        if self.verification == TableVerification::Safe
+            && self.entries(k).any(|entry| entry.state != state)
+        {
+            self.hash_collisions += 1;
+            log::warn!("transposition table hash collision: key={k:0x}");
+        }
+        let slots = self.bounds.slots();
+        let replacement = self.bounds.replacement;
+        let entries = self.table.entry(self.bounds.bucket(k)).or_default();
+        let entry = TableEntry {
+            node_id,
+            state,
+            hash: k,
+            depth,
+        };
+        if entries.len() < slots {
+            entries.push(entry);
+            self.entries += 1;
+            self.writes += 1;
+            return;
+        }
+        match replacement {
+            Replacement::DepthPreferred if depth > entries[0].depth => return,
+            Replacement::DepthPreferred | Replacement::Recent => entries[0] = entry,
+            Replacement::TwoTier => {
+                if depth <= entries[0].depth {
+                    entries[1] = std::mem::replace(&mut entries[0], entry);
+                } else {
+                    entries[1] = entry;
+                }
+            }
         }
-        entries.push(TableEntry { node_id, state });
+        self.writes += 1;
+        self.replacements += 1;
     }
 }
@@ -0,0 +1,386 @@
+//! A testbed for playout policies, which measures how well a single playout
+//! predicts the outcome of a position without playing full games.
+//!
+//! Mid-game positions are sampled by random or MCTS play from the initial
+//! state and labelled with a reference result, either solved exactly or
+//! estimated by a deep search. Each `SimulateStrategy` is then scored on how
+//! often its playouts agree with the reference outcome for the player to move,
+//! and on the squared error of the utilities they predict.
+//!
+//! ```ignore
+//! let testbed = Testbed::<G>::generate(&TestbedConfig::default());
+//! println!("{}", testbed.evaluate("uniform", simulate::Uniform, 10, 0));
+//! println!("{}", testbed.evaluate("mast", simulate::Mast, 10, 0));
+//! ```
+
+use super::node::Proof;
+use super::simulate::SimulateStrategy;
+use super::{solver, strategy, SearchConfig, TreeSearch, TreeStats};
+use crate::game::{Game, PlayerIndex};
+use crate::strategies::Search;
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How the positions are reached from the initial state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Generator {
+    Random,
+    // MCTS play with this many iterations per move
+    Search(usize),
+}
+
+/// How the reference result of a position is found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reference {
+    // The game-theoretic result, by exhaustive search. Only for small games.
+    Solve,
+    // The root value of a UCB1 search with this many iterations
+    Search(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TestbedConfig {
+    pub positions: usize,
+    // Positions are sampled after a uniformly chosen number of moves in
+    // `min_ply..=max_ply`, skipping those where the game has ended.
+    pub min_ply: usize,
+    pub max_ply: usize,
+    pub generator: Generator,
+    pub reference: Reference,
+    // An estimated reference result counts as a draw when the player to move's
+    // value is within this margin of the best of the others
+    pub draw_margin: f64,
+    pub max_playout_depth: usize,
+    pub seed: u64,
+}
+
+impl Default for TestbedConfig {
+    fn default() -> Self {
+        Self {
+            positions: 100,
+            min_ply: 4,
+            max_ply: 20,
+            generator: Generator::Random,
+            reference: Reference::Search(10_000),
+            draw_margin: 0.1,
+            max_playout_depth: usize::MAX,
+            seed: 0,
+        }
+    }
+}
+
+impl TestbedConfig {
+    pub fn positions(mut self, positions: usize) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    pub fn plies(mut self, min_ply: usize, max_ply: usize) -> Self {
+        self.min_ply = min_ply;
+        self.max_ply = max_ply;
+        self
+    }
+
+    pub fn generator(mut self, generator: Generator) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    pub fn reference(mut self, reference: Reference) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    pub fn draw_margin(mut self, draw_margin: f64) -> Self {
+        self.draw_margin = draw_margin;
+        self
+    }
+
+    pub fn max_playout_depth(mut self, max_playout_depth: usize) -> Self {
+        self.max_playout_depth = max_playout_depth;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// A position and its reference result.
+#[derive(Clone, Debug)]
+pub struct Sample<G: Game> {
+    pub state: G::S,
+    pub ply: usize,
+    // The expected utilities, and whether they are exact
+    pub utilities: Vec<f64>,
+    pub exact: bool,
+    // The reference outcome for the player to move
+    pub outcome: Proof,
+}
+
+/// How one playout policy fared on a testbed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayoutReport {
+    pub name: String,
+    pub positions: usize,
+    pub playouts: usize,
+    // The fraction of playouts whose outcome for the player to move matched
+    // the reference outcome
+    pub accuracy: f64,
+    // The mean squared error of the player to move's utility
+    pub mse: f64,
+    pub average_depth: f64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for PlayoutReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: accuracy {:.1}%, mse {:.3}, depth {:.1}, {} playouts in {:.2?}",
+            self.name,
+            100. * self.accuracy,
+            self.mse,
+            self.average_depth,
+            self.playouts,
+            self.elapsed
+        )
+    }
+}
+
+pub struct Testbed<G: Game> {
+    pub samples: Vec<Sample<G>>,
+    pub max_playout_depth: usize,
+}
+
+impl<G: Game> Testbed<G>
+where
+    SearchConfig<G, strategy::Ucb1>: Default,
+{
+    /// Sample and label the positions described by `config`.
+    pub fn generate(config: &TestbedConfig) -> Self {
+        let mut rng = SmallRng::seed_from_u64(config.seed);
+        let mut samples = Vec::with_capacity(config.positions);
+        // Give up on plies which keep running into the end of the game
+        let mut attempts = 0;
+        while samples.len() < config.positions && attempts < 100 * config.positions.max(1) {
+            attempts += 1;
+            let ply = rng.gen_range(config.min_ply..=config.max_ply.max(config.min_ply));
+            let Some(state) = play::<G>(ply, config.generator, &mut rng) else {
+                continue;
+            };
+            let (utilities, exact) = label::<G>(&state, config.reference, rng.gen());
+            let player = G::player_to_move(&state).to_index();
+            let outcome = if exact {
+                Proof::of(&utilities, player)
+            } else {
+                estimated_outcome(&utilities, player, config.draw_margin)
+            };
+            samples.push(Sample {
+                state,
+                ply,
+                utilities,
+                exact,
+                outcome,
+            });
+        }
+        Self {
+            samples,
+            max_playout_depth: config.max_playout_depth,
+        }
+    }
+
+    /// Run `playouts` playouts of `simulate` from each position. Strategies
+    /// which evaluate positions directly are scored on their evaluations.
+    pub fn evaluate<S: SimulateStrategy<G>>(
+        &self,
+        name: &str,
+        mut simulate: S,
+        playouts: usize,
+        seed: u64,
+    ) -> PlayoutReport {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let stats = TreeStats::default();
+        let start = Instant::now();
+        let (mut agreements, mut squared_error, mut depth, mut count) = (0, 0., 0, 0);
+        for sample in &self.samples {
+            let player = G::player_to_move(&sample.state).to_index();
+            for _ in 0..playouts {
+                let utilities = match simulate.evaluate(&sample.state, &stats, player) {
+                    Some(utilities) => utilities,
+                    None => {
+                        let trial = simulate.playout(
+                            sample.state.clone(),
+                            self.max_playout_depth,
+                            &stats,
+                            player,
+                            &mut rng,
+                        );
+                        depth += trial.depth;
+                        trial.utilities
+                    }
+                };
+                if Proof::of(&utilities, player) == sample.outcome {
+                    agreements += 1;
+                }
+                squared_error += (utilities[player] - sample.utilities[player]).powi(2);
+                count += 1;
+            }
+        }
+        PlayoutReport {
+            name: name.to_string(),
+            positions: self.samples.len(),
+            playouts: count,
+            accuracy: agreements as f64 / count.max(1) as f64,
+            mse: squared_error / count.max(1) as f64,
+            average_depth: depth as f64 / count.max(1) as f64,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+// Play `ply` moves from the initial state, or `None` if the game ends first
+fn play<G: Game>(ply: usize, generator: Generator, rng: &mut SmallRng) -> Option<G::S>
+where
+    SearchConfig<G, strategy::Ucb1>: Default,
+{
+    let mut state = G::S::default();
+    let mut actions = vec![];
+    for _ in 0..ply {
+        if G::is_terminal(&state) {
+            return None;
+        }
+        let action = match generator {
+            Generator::Random => {
+                actions.clear();
+                G::generate_actions(&state, &mut actions);
+                actions[rng.gen_range(0..actions.len())].clone()
+            }
+            Generator::Search(iterations) => {
+                let config = SearchConfig::default()
+                    .max_iterations(iterations)
+                    .seed(rng.gen());
+                TreeSearch::<G, strategy::Ucb1>::default()
+                    .config(config)
+                    .choose_action(&state)
+            }
+        };
+        state = G::apply(state, &action);
+    }
+    (!G::is_terminal(&state)).then_some(state)
+}
+
+fn estimated_outcome(utilities: &[f64], player: usize, draw_margin: f64) -> Proof {
+    let best_other = (0..utilities.len())
+        .filter(|i| *i != player)
+        .map(|i| utilities[i])
+        .fold(f64::NEG_INFINITY, f64::max);
+    let margin = utilities[player] - best_other;
+    if margin.abs() <= draw_margin {
+        Proof::Draw
+    } else if margin > 0. {
+        Proof::Win
+    } else {
+        Proof::Loss
+    }
+}
+
+fn label<G: Game>(state: &G::S, reference: Reference, seed: u64) -> (Vec<f64>, bool)
+where
+    SearchConfig<G, strategy::Ucb1>: Default,
+{
+    match reference {
+        Reference::Solve => (solver::solve::<G>(state).utilities, true),
+        Reference::Search(iterations) => {
+            let mut ts = TreeSearch::<G, strategy::Ucb1>::default().config(
+                SearchConfig::default()
+                    .max_iterations(iterations)
+                    .seed(seed),
+            );
+            ts.search(state);
+            match ts.index.get(ts.root_id).proven_utilities() {
+                Some(utilities) => (utilities, true),
+                None => (
+                    (0..G::num_players())
+                        .map(|player| ts.root_stats.expected_score(player))
+                        .collect(),
+                    false,
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::TicTacToe;
+    use crate::strategies::mcts::simulate;
+
+    // Evaluates every position exactly
+    #[derive(Clone, Default)]
+    struct Oracle;
+
+    impl SimulateStrategy<TicTacToe> for Oracle {
+        fn evaluate(
+            &mut self,
+            state: &<TicTacToe as Game>::S,
+            _: &TreeStats<TicTacToe>,
+            _: usize,
+        ) -> Option<Vec<f64>> {
+            Some(solver::solve::<TicTacToe>(state).utilities)
+        }
+    }
+
+    #[test]
+    fn test_testbed() {
+        let config = TestbedConfig::default()
+            .positions(20)
+            .plies(2, 5)
+            .reference(Reference::Solve)
+            .seed(1);
+        let testbed = Testbed::<TicTacToe>::generate(&config);
+        assert_eq!(testbed.samples.len(), 20);
+        assert!(testbed.samples.iter().all(|s| s.exact));
+        assert!(testbed.samples.iter().all(|s| (2..=5).contains(&s.ply)));
+
+        let oracle = testbed.evaluate("oracle", Oracle, 3, 0);
+        assert_eq!(oracle.playouts, 60);
+        assert_eq!(oracle.accuracy, 1.);
+        assert_eq!(oracle.mse, 0.);
+
+        let uniform = testbed.evaluate("uniform", simulate::Uniform, 10, 0);
+        assert!(uniform.accuracy < 1.);
+        assert!(uniform.mse > 0.);
+        assert!(uniform.average_depth > 0.);
+        assert!(uniform.to_string().starts_with("uniform: accuracy"));
+
+        // Positions from MCTS play with a searched reference
+        let searched = Testbed::<TicTacToe>::generate(
+            &config
+                .generator(Generator::Search(100))
+                .reference(Reference::Search(1000)),
+        );
+        assert_eq!(searched.samples.len(), 20);
+        assert!(searched
+            .samples
+            .iter()
+            .all(|s| !TicTacToe::is_terminal(&s.state)));
+        let report = searched.evaluate("uniform", simulate::Uniform, 1, 0);
+        assert_eq!(report.playouts, 20);
+        assert!((0. ..=1.).contains(&report.accuracy));
+    }
+
+    #[test]
+    fn test_estimated_outcome() {
+        assert_eq!(estimated_outcome(&[0.05, -0.05], 0, 0.1), Proof::Draw);
+        assert_eq!(estimated_outcome(&[0.5, -0.5], 0, 0.1), Proof::Win);
+        assert_eq!(estimated_outcome(&[0.5, -0.5], 1, 0.1), Proof::Loss);
+        assert_eq!(estimated_outcome(&[0.2, 0.3, 0.6], 1, 0.1), Proof::Loss);
+    }
+}
@@ -0,0 +1,223 @@
+//! Recording searches so that they can be replayed exactly. See
+//! `SearchConfig::record_trace`.
+//!
+//! Each search recorded in the trace starts by reseeding the random number
+//! generator from itself, and notes the seed and the number of iterations it
+//! ran in each stage. A replay reseeds each search with the recorded seed and
+//! runs exactly the recorded iterations, ignoring time limits and early stops,
+//! so that a
+//! search limited by time can be reproduced bit for bit. This assumes the same
+//! configuration and the same sequence of positions from a fresh
+//! `TreeSearch`. Searches with `tree_parallel` or `eval_batch`, or with
+//! `auto_expand` under a time limit, are not reproducible, and book moves are
+//! not recorded.
+
+use super::{ConfigError, SearchConfig, Strategy, TreeSearch};
+use crate::game::Game;
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::hash::Hasher;
+
+/// One recorded search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracedSearch {
+    pub seed: u64,
+    // Iterations of the main loop, not counting `extra_iterations`
+    pub iterations: usize,
+    // Iterations of the second stage, see `SearchConfig::disagreement_budget`
+    #[serde(default)]
+    pub extra_iterations: usize,
+    // The zobrist hash of the root, to tell which position was searched
+    pub root_hash: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchTrace {
+    // See `SearchConfig::config_hash`
+    pub config_hash: u64,
+    pub searches: Vec<TracedSearch>,
+}
+
+/// The recording and replay state of a `TreeSearch`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tracer {
+    trace: SearchTrace,
+    replay: VecDeque<TracedSearch>,
+    // The seed of the search in progress
+    seed: u64,
+}
+
+impl<G, S> SearchConfig<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+{
+    /// A hash of the settings which affect the course of a search, to check
+    /// that a trace is replayed with the configuration it was recorded with.
+    /// The strategy components are only identified by their types, not their
+    /// parameters, and the random number generator, name, verbosity, progress
+    /// callback and book contents are left out.
+    pub fn config_hash(&self) -> u64 {
+        let settings = [
+            std::any::type_name::<S>().to_string(),
+            format!("{:?}", self.q_init),
+            format!("{:?}", self.expand_threshold),
+            format!("{:?}", self.max_playout_depth),
+            format!("{:?}", self.max_iterations),
+            format!("{:?}", self.max_time),
+            format!("{:?}", self.max_expansions),
+            format!("{:?}", self.max_applied_actions),
+            format!("{:?}", self.use_transpositions),
+            format!("{:?}", self.transposition_linking),
+            format!("{:?}", self.table_bounds),
+            format!("{:?}", self.table_verification),
+            format!("{:?}", self.ucd),
+            format!("{:?}", self.stats_decay),
+            format!("{:?}", self.solve_threshold),
+            format!("{:?}", self.warm_start_visits),
+            format!("{:?}", self.easy_move),
+            format!("{:?}", self.time_control),
+            format!("{:?}", self.sequential_halving),
+            format!("{:?}", self.disagreement_budget),
+            format!("{:?}", self.revalidate_edges),
+            format!("{:?}", self.normalize_scores),
+            format!("{:?}", self.adjudication),
+            format!("{:?}", self.sort_children),
+            format!("{:?}", self.record_trials),
+            format!("{:?}", self.reuse_tree),
+            format!("{:?}", self.zero_sum),
+            format!("{:?}", self.auto_expand),
+            format!("{:?}", self.tree_parallel),
+            format!("{:?}", self.max_nodes),
+            format!("{:?}", self.node_recycling),
+            format!("{:?}", self.opening_book.is_some()),
+            format!("{:?}", self.exploration_schedule),
+            format!("{:?}", self.epsilon_schedule),
+            format!("{:?}", self.root_filter.is_some()),
+            format!("{:?}", self.eval_batch),
+        ];
+        let mut hasher = FxHasher::default();
+        for setting in settings {
+            hasher.write(setting.as_bytes());
+            hasher.write_u8(0);
+        }
+        hasher.finish()
+    }
+}
+
+impl<G, S> TreeSearch<G, S>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default,
+    G::S: std::fmt::Display,
+{
+    /// The searches recorded so far with `record_trace`.
+    pub fn trace(&self) -> &SearchTrace {
+        &self.tracer.trace
+    }
+
+    /// Replay the searches of `trace` in the following calls to `search`, in
+    /// order. Fails if the configuration differs from the one recorded.
+    pub fn replay(&mut self, trace: SearchTrace) -> Result<(), ConfigError> {
+        let config_hash = self.config.config_hash();
+        if trace.config_hash != config_hash {
+            return Err(ConfigError::TraceMismatch {
+                recorded: trace.config_hash,
+                config: config_hash,
+            });
+        }
+        self.tracer = Tracer {
+            replay: trace.searches.into(),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Reseed the generator for a recorded or replayed search. Returns the
+    /// search to replay, if replaying.
+    pub(crate) fn start_trace(&mut self) -> Option<TracedSearch> {
+        let replayed = self.tracer.replay.pop_front();
+        if replayed.is_none() && !self.config.record_trace {
+            return None;
+        }
+        let seed = replayed.map_or_else(|| self.config.rng.gen(), |search| search.seed);
+        self.config.rng = SmallRng::seed_from_u64(seed);
+        self.tracer.seed = seed;
+        replayed
+    }
+
+    /// Record the search of `state` just run, if recording.
+    pub(crate) fn end_trace(&mut self, state: &G::S, iterations: usize, extra_iterations: usize) {
+        if !self.config.record_trace {
+            return;
+        }
+        self.tracer.trace.config_hash = self.config.config_hash();
+        self.tracer.trace.searches.push(TracedSearch {
+            seed: self.tracer.seed,
+            iterations,
+            extra_iterations,
+            root_hash: G::zobrist_hash(state),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::*;
+    use crate::strategies::mcts::strategy;
+    use crate::strategies::Search;
+
+    use std::time::Duration;
+
+    type TS = TreeSearch<TicTacToe, strategy::Ucb1>;
+
+    // Play a game, returning the root visits of each search
+    fn play(ts: &mut TS) -> Vec<Vec<(Move, u32)>> {
+        let mut state = HashedPosition::new();
+        let mut visits = vec![];
+        while !TicTacToe::is_terminal(&state) {
+            let action = ts.choose_action(&state);
+            visits.push(ts.root_visits());
+            state = TicTacToe::apply(state, &action);
+        }
+        visits
+    }
+
+    #[test]
+    fn test_replay() {
+        let config = || {
+            SearchConfig::default()
+                .max_time(Duration::from_millis(5))
+                .disagreement_budget(1.)
+                .reuse_tree(Default::default())
+                .record_trace(true)
+        };
+        let mut ts = TS::default().config(config());
+        let visits = play(&mut ts);
+        let trace = ts.trace().clone();
+        assert_eq!(trace.searches.len(), visits.len());
+
+        // A fresh search, seeded otherwise, replays the same searches
+        let json = serde_json::to_string(&trace).unwrap();
+        let mut replay = TS::default().config(config().seed(1));
+        replay.replay(serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(play(&mut replay), visits);
+        assert_eq!(replay.trace(), &trace);
+
+        let mut other = TS::default().config(config().max_playout_depth(3));
+        assert_eq!(
+            other.replay(trace.clone()),
+            Err(ConfigError::TraceMismatch {
+                recorded: trace.config_hash,
+                config: other.config.config_hash(),
+            })
+        );
+    }
+}
@@ -1,10 +1,34 @@
+pub mod bandit;
+pub mod decompose;
 pub mod flat_mc;
 pub mod human;
+pub mod ismcts;
 pub mod mcts;
 pub mod random;
 
 use crate::game::Game;
 
+/// A search's judgement that the game is decided before it has ended. See
+/// `mcts::Adjudication`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    // The player to move gives up the game
+    Resign,
+    // The player to move claims the game as won
+    ClaimWin,
+}
+
+/// Statistics of a single call to `choose_action`, accumulated over a game by
+/// `util::GameStats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchMetrics {
+    pub nodes: usize,
+    pub average_depth: f64,
+    pub elapsed: std::time::Duration,
+    // The expected score at the root for the player to move
+    pub value: f64,
+}
+
 pub trait Search: Sync + Send {
     type G: Game;
 
@@ -12,10 +36,37 @@ pub trait Search: Sync + Send {
 
     fn choose_action(&mut self, state: &<Self::G as Game>::S) -> <Self::G as Game>::A;
 
+    /// Do any expensive one-time setup, such as building tables or books,
+    /// before playing from `init`. Tournament drivers call this once per
+    /// strategy before the first game, so that the cost is not counted against
+    /// the first move.
+    #[allow(unused_variables)]
+    fn prepare(&mut self, init: &<Self::G as Game>::S) {}
+
+    /// Choose an action, along with a verdict if the search judges the game
+    /// to be decided. Strategies without adjudication never return one.
+    fn choose_action_with_verdict(
+        &mut self,
+        state: &<Self::G as Game>::S,
+    ) -> (<Self::G as Game>::A, Option<Verdict>) {
+        (self.choose_action(state), None)
+    }
+
     fn principle_variation(&self) -> Vec<<Self::G as Game>::A> {
         vec![]
     }
 
+    /// Statistics of the last search, if the search keeps them.
+    fn search_metrics(&self) -> Option<SearchMetrics> {
+        None
+    }
+
+    /// The root actions of the last search with their visit counts. Empty for
+    /// searches which don't build a tree.
+    fn root_visits(&self) -> Vec<(<Self::G as Game>::A, u32)> {
+        vec![]
+    }
+
     fn estimated_depth(&self) -> usize {
         0
     }
@@ -66,6 +117,991 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stats_decay() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1Mast>;
+        let mut ts =
+            TS::default().config(mcts::SearchConfig::default().max_iterations(100).seed(0));
+        ts.choose_action(&HashedPosition::new());
+
+        let before = ts.stats.history.actions.clone();
+        assert!(!before.is_empty());
+        let mut stats = ts.stats.clone();
+        stats.decay(0.5);
+        for (action, decayed) in stats.history.actions.iter() {
+            let stats = before.get(action).unwrap();
+            assert_eq!(
+                decayed.num_visits,
+                (stats.num_visits as f64 * 0.5).floor() as u32
+            );
+            let avg = stats.score / stats.num_visits as f64;
+            let decayed_avg = decayed.score / decayed.num_visits as f64;
+            assert!((avg - decayed_avg).abs() < 1e-9);
+        }
+
+        stats.decay(0.);
+        assert!(stats.history.is_empty());
+
+        // Small counts decay too, rather than rounding back up
+        let mut stats = ts.stats.clone();
+        for _ in 0..100 {
+            stats.decay(0.9);
+        }
+        assert!(stats.history.is_empty());
+    }
+
+    #[test]
+    fn test_warm_start() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(20)
+                .warm_start_visits(100)
+                .seed(0),
+        );
+
+        // The prior dominates a short search. Illegal actions are ignored.
+        ts.warm_start(&[(Move(4), 0.7), (Move(0), 0.2), (Move(9), 0.1)]);
+        assert_eq!(ts.choose_action(&HashedPosition::new()), Move(4));
+        let root = ts.index.get(ts.root_id);
+        let visits = |i: usize| root.edges()[i].stats.num_visits;
+        assert!(visits(4) >= 78);
+        assert!(visits(0) >= 22);
+        assert_eq!(ts.root_stats.num_visits, 100 + 20);
+
+        // The prior only applies to the next search
+        ts.choose_action(&HashedPosition::new());
+        assert_eq!(ts.root_stats.num_visits, 20);
+
+        // X X .
+        // . O .
+        // . . .
+        // A uniform prior credits no wins, so O still finds the only block and
+        // the other moves don't look any better than a draw
+        let state = [0, 4, 1].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+        let config = || mcts::SearchConfig::default().max_iterations(300).seed(0);
+        let mut ts = TS::default().config(config());
+        assert_eq!(ts.choose_action(&state), Move(2));
+        let uniform = (0..9).map(|i| (Move(i), 1.)).collect::<Vec<_>>();
+        let mut ts = TS::default().config(config().warm_start_visits(300));
+        ts.warm_start(&uniform);
+        assert_eq!(ts.choose_action(&state), Move(2));
+        let root = ts.index.get(ts.root_id);
+        assert!(root
+            .edges()
+            .iter()
+            .filter(|edge| edge.action != Move(2))
+            .all(|edge| edge.stats.expected_score(1) <= 0.));
+    }
+
+    #[test]
+    fn test_simulation_free() {
+        use crate::games::ttt::*;
+        use mcts::simulate::{EndType, SimulateStrategy};
+        use rand::rngs::SmallRng;
+
+        // Values the center for X and never plays out
+        #[derive(Clone, Default)]
+        struct Center;
+
+        impl SimulateStrategy<TicTacToe> for Center {
+            fn evaluate(
+                &mut self,
+                state: &HashedPosition,
+                _stats: &mcts::TreeStats<TicTacToe>,
+                _player: usize,
+            ) -> Option<Vec<f64>> {
+                match state.position.get(4) {
+                    Some(Piece::X) => Some(vec![1., -1.]),
+                    _ => Some(vec![-1., 1.]),
+                }
+            }
+
+            fn select_move<'a>(
+                &mut self,
+                _state: &HashedPosition,
+                _available: &'a [Move],
+                _stats: &mcts::TreeStats<TicTacToe>,
+                _player: usize,
+                _rng: &mut SmallRng,
+            ) -> &'a Move {
+                panic!("playout in simulation-free mode");
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct Ucb1Center;
+
+        impl mcts::Strategy<TicTacToe> for Ucb1Center {
+            type Select = mcts::select::Ucb1;
+            type Simulate = Center;
+            type Backprop = mcts::backprop::Classic;
+            type FinalAction = mcts::select::RobustChild;
+        }
+
+        let mut ts = mcts::TreeSearch::<TicTacToe, Ucb1Center>::default()
+            .config(mcts::SearchConfig::default().max_iterations(50).seed(0));
+        assert_eq!(ts.choose_action(&HashedPosition::new()), Move(4));
+
+        let trial = ts.trial.as_ref().unwrap();
+        assert!(trial.actions.is_empty());
+        assert!(matches!(trial.status.end_type, Some(EndType::Evaluated)));
+    }
+
+    #[test]
+    fn test_easy_move() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+        let config = || mcts::SearchConfig::default().max_iterations(5000).seed(0);
+
+        let mut ts = TS::default().config(config());
+        let result = ts.search(&state);
+        assert_eq!(result.action, Move(2));
+        assert_eq!(result.iterations, 5000);
+        assert!(!result.easy_move);
+
+        let mut ts = TS::default().config(config().easy_move(mcts::EasyMove::default()));
+        let result = ts.search(&state);
+        assert_eq!(result.action, Move(2));
+        assert!(result.iterations < 5000);
+        assert!(result.easy_move);
+    }
+
+    #[test]
+    fn test_stop_handle() {
+        use crate::games::ttt::*;
+        use std::time::Duration;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_time(Duration::from_secs(60))
+                .seed(0),
+        );
+        let handle = ts.stop_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            handle.stop();
+        });
+        let result = ts.search(&HashedPosition::new());
+        stopper.join().unwrap();
+        assert!(result.elapsed < Duration::from_secs(30));
+        assert!(result.iterations > 0);
+
+        // A stop made between searches ends the next one once the root is
+        // expanded, and is then cleared
+        ts.config.max_time = Duration::default();
+        ts.config.max_iterations = 100;
+        ts.stop_handle().stop();
+        assert!(ts.search(&HashedPosition::new()).iterations < 100);
+        assert_eq!(ts.search(&HashedPosition::new()).iterations, 100);
+    }
+
+    #[test]
+    fn test_lcb_final_action() {
+        use crate::games::ttt::*;
+        use mcts::dynamic::{DynSelect, Dynamic};
+        type TS = mcts::TreeSearch<TicTacToe, Dynamic>;
+
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::new()
+                .final_action(DynSelect::new(mcts::select::Lcb::default()))
+                .max_iterations(500)
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), Move(2));
+    }
+
+    #[test]
+    fn test_proofs() {
+        use crate::games::ttt::*;
+        use mcts::node::Proof;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        // X X .
+        // O O .
+        // . . .
+        let state = [0, 3, 1, 4].iter().fold(HashedPosition::new(), |state, i| {
+            TicTacToe::apply(state, &Move(*i))
+        });
+        let mut ts =
+            TS::default().config(mcts::SearchConfig::default().max_iterations(500).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+        assert_eq!(ts.root_proof(), Some(Proof::Win));
+        let root = ts.index.get(ts.root_id);
+        let edge = root.edges().iter().find(|e| e.action == Move(2)).unwrap();
+        assert_eq!(edge.proof(0), Some(Proof::Win));
+        assert_eq!(edge.proof(1), Some(Proof::Loss));
+
+        // X X .
+        // . O .
+        // X . O
+        //
+        // O to move cannot stop both threats
+        let state = [0, 4, 1, 8, 6]
+            .iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(*i))
+            });
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(2000)
+                .expand_threshold(0)
+                .seed(0),
+        );
+        _ = ts.choose_action(&state);
+        assert_eq!(ts.root_proof(), Some(Proof::Loss));
+
+        // Leaves solved exactly are proven as well
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(50)
+                .solve_threshold(10)
+                .seed(0),
+        );
+        _ = ts.choose_action(&state);
+        assert_eq!(ts.root_proof(), Some(Proof::Loss));
+
+        assert_eq!(Proof::of(&[0., 0.], 1), Proof::Draw);
+    }
+
+    #[test]
+    fn test_select_path() {
+        use crate::games::ttt::*;
+        use mcts::index::Id;
+        use mcts::node::Edge;
+        use mcts::select::{SelectContext, SelectStrategy};
+        use rand::rngs::SmallRng;
+
+        // Checks that the path replays from the root to the current state
+        #[derive(Clone, Default)]
+        struct CheckPath {
+            inner: mcts::select::Ucb1,
+            max_len: usize,
+        }
+
+        impl SelectStrategy<TicTacToe> for CheckPath {
+            type Score = f64;
+            type Aux = f64;
+
+            fn setup(&mut self, ctx: &SelectContext<'_, TicTacToe>) -> f64 {
+                self.inner.setup(ctx)
+            }
+
+            fn best_child(
+                &mut self,
+                ctx: &SelectContext<'_, TicTacToe>,
+                rng: &mut SmallRng,
+            ) -> usize {
+                assert_eq!(ctx.path.len() + 1, ctx.stack.len());
+                let state =
+                    ctx.path
+                        .iter()
+                        .fold(HashedPosition::new(), |state, (action, player)| {
+                            assert_eq!(TicTacToe::player_to_move(&state).to_index(), *player);
+                            TicTacToe::apply(state, action)
+                        });
+                assert_eq!(&state, ctx.state);
+                self.max_len = self.max_len.max(ctx.path.len());
+                self.inner.best_child(ctx, rng)
+            }
+
+            fn score_child(
+                &self,
+                ctx: &SelectContext<'_, TicTacToe>,
+                child_id: Id,
+                edge: &Edge<Move>,
+                aux: f64,
+            ) -> f64 {
+                self.inner.score_child(ctx, child_id, edge, aux)
+            }
+
+            fn unvisited_value(&self, ctx: &SelectContext<'_, TicTacToe>, aux: f64) -> f64 {
+                self.inner.unvisited_value(ctx, aux)
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct CheckPathStrategy;
+
+        impl mcts::Strategy<TicTacToe> for CheckPathStrategy {
+            type Select = CheckPath;
+            type Simulate = mcts::simulate::Uniform;
+            type Backprop = mcts::backprop::Classic;
+            type FinalAction = mcts::select::RobustChild;
+        }
+
+        let mut ts = mcts::TreeSearch::<TicTacToe, CheckPathStrategy>::default()
+            .config(mcts::SearchConfig::default().max_iterations(500).seed(0));
+        _ = ts.choose_action(&HashedPosition::new());
+        assert!(ts.config.select.max_len > 1);
+    }
+
+    #[test]
+    fn test_grave_ref() {
+        use crate::games::ttt::*;
+        use mcts::index::Id;
+        use mcts::node::Edge;
+        use mcts::select::{SelectContext, SelectStrategy};
+        use rand::rngs::SmallRng;
+
+        // Checks the reference tracked during descent against a walk back up
+        // the stack
+        #[derive(Clone)]
+        struct CheckRef {
+            inner: mcts::select::Rave,
+            checked: usize,
+        }
+
+        impl Default for CheckRef {
+            fn default() -> Self {
+                Self {
+                    inner: mcts::select::Rave::default().threshold(5),
+                    checked: 0,
+                }
+            }
+        }
+
+        impl SelectStrategy<TicTacToe> for CheckRef {
+            type Score = f64;
+            type Aux = f64;
+
+            fn setup(&mut self, ctx: &SelectContext<'_, TicTacToe>) -> f64 {
+                self.inner.setup(ctx)
+            }
+
+            fn best_child(
+                &mut self,
+                ctx: &SelectContext<'_, TicTacToe>,
+                rng: &mut SmallRng,
+            ) -> usize {
+                let expected = ctx
+                    .stack
+                    .reverse_pairs()
+                    .find(|(parent_id, child_id)| {
+                        ctx.stack
+                            .get_stats(ctx.index, ctx.root_stats, **parent_id, **child_id)
+                            .total_visits()
+                            >= 5
+                    })
+                    .map_or(ctx.stack.root(), |(_, child_id)| *child_id);
+                assert_eq!(ctx.grave_ref, expected);
+                self.checked += (expected != ctx.stack.root()) as usize;
+                self.inner.best_child(ctx, rng)
+            }
+
+            fn score_child(
+                &self,
+                ctx: &SelectContext<'_, TicTacToe>,
+                child_id: Id,
+                edge: &Edge<Move>,
+                aux: f64,
+            ) -> f64 {
+                self.inner.score_child(ctx, child_id, edge, aux)
+            }
+
+            fn unvisited_value(&self, ctx: &SelectContext<'_, TicTacToe>, aux: f64) -> f64 {
+                self.inner.unvisited_value(ctx, aux)
+            }
+
+            fn backprop_flags(&self) -> mcts::BackpropFlags {
+                SelectStrategy::<TicTacToe>::backprop_flags(&self.inner)
+            }
+
+            fn grave_threshold(&self) -> Option<u32> {
+                SelectStrategy::<TicTacToe>::grave_threshold(&self.inner)
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct CheckRefStrategy;
+
+        impl mcts::Strategy<TicTacToe> for CheckRefStrategy {
+            type Select = CheckRef;
+            type Simulate = mcts::simulate::Uniform;
+            type Backprop = mcts::backprop::Classic;
+            type FinalAction = mcts::select::RobustChild;
+        }
+
+        let mut ts = mcts::TreeSearch::<TicTacToe, CheckRefStrategy>::default()
+            .config(mcts::SearchConfig::default().max_iterations(1000).seed(0));
+        _ = ts.choose_action(&HashedPosition::new());
+        assert!(ts.config.select.checked > 0);
+    }
+
+    #[test]
+    fn test_normalize_scores() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+
+        // TicTacToe with utilities of +/-100
+        #[derive(Clone)]
+        struct Scaled;
+
+        impl Game for Scaled {
+            type S = HashedPosition;
+            type A = Move;
+            type P = Piece;
+
+            fn apply(state: HashedPosition, action: &Move) -> HashedPosition {
+                TicTacToe::apply(state, action)
+            }
+
+            fn generate_actions(state: &HashedPosition, actions: &mut Vec<Move>) {
+                TicTacToe::generate_actions(state, actions)
+            }
+
+            fn is_terminal(state: &HashedPosition) -> bool {
+                TicTacToe::is_terminal(state)
+            }
+
+            fn winner(state: &HashedPosition) -> Option<Piece> {
+                TicTacToe::winner(state)
+            }
+
+            fn player_to_move(state: &HashedPosition) -> Piece {
+                TicTacToe::player_to_move(state)
+            }
+
+            fn compute_utilities(state: &HashedPosition) -> Vec<f64> {
+                TicTacToe::compute_utilities(state)
+                    .into_iter()
+                    .map(|u| u * 100.)
+                    .collect()
+            }
+        }
+
+        fn root_visits<G: Game<S = HashedPosition>>(normalize: bool) -> Vec<u32> {
+            let mut ts = mcts::TreeSearch::<G, mcts::strategy::Ucb1>::default().config(
+                mcts::SearchConfig::default()
+                    .max_iterations(2000)
+                    .normalize_scores(normalize)
+                    .seed(0),
+            );
+            _ = ts.choose_action(&HashedPosition::new());
+            ts.index
+                .get(ts.root_id)
+                .edges()
+                .iter()
+                .map(|edge| edge.stats.num_visits)
+                .collect()
+        }
+
+        // Normalized, the same exploration constant behaves the same on both
+        let visits = root_visits::<TicTacToe>(true);
+        assert_eq!(root_visits::<Scaled>(true), visits);
+        // Unnormalized, the exploration term is swamped by the larger scores
+        assert_ne!(root_visits::<Scaled>(false), visits);
+
+        let mut bounds = mcts::ScoreBounds::default();
+        assert_eq!(bounds.normalize(5.), 5.);
+        bounds.update(&[-100., 100.]);
+        assert_eq!(bounds.normalize(50.), 0.5);
+        assert_eq!(bounds.scale(), 0.01);
+    }
+
+    #[test]
+    fn test_disagreement_budget() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let mut resolved = 0;
+        for seed in 0..20 {
+            let mut ts = TS::default().config(
+                mcts::SearchConfig::default()
+                    .max_iterations(20)
+                    .disagreement_budget(10.)
+                    .seed(seed),
+            );
+            let result = ts.search(&HashedPosition::new());
+            assert!(result.extra_iterations <= 200);
+            assert_eq!(result.iterations, 20 + result.extra_iterations);
+            if result.extra_iterations > 0 && result.extra_iterations < 200 {
+                assert!(!ts.root_disagreement(&HashedPosition::new()));
+                resolved += 1;
+            }
+        }
+        assert!(resolved > 0);
+
+        // The second stage stops at the work budget like the first
+        let mut unresolved = 0;
+        for seed in 0..20 {
+            let mut ts = TS::default().config(
+                mcts::SearchConfig::default()
+                    .max_expansions(30)
+                    .disagreement_budget(10.)
+                    .seed(seed),
+            );
+            let result = ts.search(&HashedPosition::new());
+            assert!(result.expansions <= 30);
+            if ts.root_disagreement(&HashedPosition::new()) {
+                unresolved += 1;
+            }
+        }
+        assert!(unresolved > 0);
+    }
+
+    #[test]
+    fn test_adjudication() {
+        use crate::games::ttt::*;
+        use crate::util::{round_robin_multiple, AnySearch, Verbosity};
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        // Thresholds outside the utility range force a verdict every search
+        let always_resign = mcts::Adjudication::default()
+            .resign_below(2.)
+            .claim_above(3.)
+            .consecutive(2)
+            .min_iterations(10);
+        let make = |adjudication: mcts::Adjudication| {
+            TS::default().config(
+                mcts::SearchConfig::default()
+                    .max_iterations(20)
+                    .adjudication(adjudication)
+                    .seed(0),
+            )
+        };
+
+        let state = HashedPosition::new();
+        let mut ts = make(always_resign);
+        assert_eq!(ts.search(&state).verdict, None);
+        assert_eq!(ts.search(&state).verdict, Some(Verdict::Resign));
+        assert_eq!(ts.search(&state).verdict, Some(Verdict::Resign));
+
+        // Searches below the minimum budget break the streak
+        ts.config.adjudication = Some(always_resign.min_iterations(100));
+        assert_eq!(ts.search(&state).verdict, None);
+        ts.config.adjudication = Some(always_resign);
+        assert_eq!(ts.search(&state).verdict, None);
+
+        let mut ts = make(always_resign.resign_below(-3.).claim_above(-2.));
+        ts.search(&state);
+        assert_eq!(ts.search(&state).verdict, Some(Verdict::ClaimWin));
+
+        // The resigning player loses every game before it ends
+        let mut strategies = vec![
+            AnySearch::new(make(always_resign.consecutive(1))),
+            AnySearch::new(TS::default().config(mcts::SearchConfig::default().max_iterations(20))),
+        ];
+        let results = round_robin_multiple::<TicTacToe, AnySearch<_>>(
+            &mut strategies,
+            2,
+            &state,
+            Verbosity::Silent,
+        );
+        assert_eq!(results[0].losses, 4);
+        assert_eq!(results[0].adjudicated, 4);
+        assert_eq!(results[1].wins, 4);
+    }
+
+    #[test]
+    fn test_adjudication_interleaved() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let always_resign = mcts::Adjudication::default()
+            .resign_below(2.)
+            .claim_above(3.)
+            .consecutive(2)
+            .min_iterations(10);
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(20)
+                .adjudication(always_resign)
+                .seed(0),
+        );
+        let play = |moves: &[u8]| {
+            moves.iter().fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(*i))
+            })
+        };
+
+        // Two games through one search, one move each in turn: neither
+        // continues the other's streak
+        assert_eq!(ts.search(&play(&[])).verdict, None);
+        assert_eq!(ts.search(&play(&[0, 3, 1])).verdict, None);
+        assert_eq!(ts.search(&play(&[4, 0])).verdict, Some(Verdict::Resign));
+        assert_eq!(
+            ts.search(&play(&[0, 3, 1, 4, 8])).verdict,
+            Some(Verdict::Resign)
+        );
+
+        // A new game starts a new streak
+        assert_eq!(ts.search(&play(&[2])).verdict, None);
+    }
+
+    #[test]
+    fn test_revalidate_edges() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let mut ts = TS::default().config(mcts::SearchConfig::default().max_iterations(100));
+        let empty = HashedPosition::new();
+        ts.choose_action(&empty);
+        let visits = |ts: &TS, action: Move| {
+            let root = ts.index.get(ts.root_id);
+            root.edges()
+                .iter()
+                .find(|edge| edge.action == action)
+                .map(|edge| edge.stats.num_visits)
+        };
+        let before = visits(&ts, Move(4)).unwrap();
+
+        // Corner taken: its edge is dropped and the rest keep their stats
+        let corner = TicTacToe::apply(TicTacToe::apply(empty, &Move(0)), &Move(8));
+        ts.revalidate_edges(ts.root_id, &corner);
+        assert_eq!(ts.index.get(ts.root_id).edges().len(), 7);
+        assert_eq!(visits(&ts, Move(0)), None);
+        assert_eq!(visits(&ts, Move(4)), Some(before));
+
+        // Corner free again: the edge returns unexplored
+        ts.revalidate_edges(ts.root_id, &empty);
+        assert_eq!(ts.index.get(ts.root_id).edges().len(), 9);
+        assert_eq!(visits(&ts, Move(0)), Some(0));
+        assert_eq!(visits(&ts, Move(4)), Some(before));
+    }
+
+    #[test]
+    fn test_work_budgets() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let mut ts = TS::default().config(mcts::SearchConfig::default().max_expansions(50).seed(0));
+        let result = ts.search(&HashedPosition::new());
+        assert_eq!(result.expansions, 50);
+        assert_eq!(result.expansions, ts.tree_metrics().num_nodes - 1);
+
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_applied_actions(500)
+                .seed(0),
+        );
+        let result = ts.search(&HashedPosition::new());
+        // The budget is checked between iterations, which apply at most 9
+        assert!((500..509).contains(&result.applied_actions));
+
+        // The root is always expanded, however small the budget
+        let mut ts =
+            TS::default().config(mcts::SearchConfig::default().max_applied_actions(1).seed(0));
+        ts.search(&HashedPosition::new());
+        assert!(ts.index.get(ts.root_id).is_expanded());
+    }
+
+    #[test]
+    fn test_tree_metrics() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_time(std::time::Duration::from_millis(20))
+                .seed(0),
+        );
+        let result = ts.search(&HashedPosition::new());
+        let metrics = ts.tree_metrics();
+        assert_eq!(metrics.iterations, result.iterations);
+        assert!(metrics.num_nodes > 1);
+        assert!(metrics.clock_reads > 0);
+        assert!(metrics.clock_reads < result.iterations as u64);
+
+        // Only visited edges have their statistics allocated
+        let memory = ts.memory_metrics();
+        assert_eq!(memory.num_nodes, metrics.num_nodes);
+        assert!(memory.allocated_edge_stats < memory.num_edges);
+        assert!(memory.bytes < memory.eager_bytes);
+    }
+
+    #[test]
+    fn test_transposition_linking() {
+        use crate::games::traffic_lights::*;
+        use mcts::table::{TranspositionLinking, TranspositionTable};
+        type TS = mcts::TreeSearch<TrafficLights, mcts::strategy::Ucb1>;
+
+        let state = HashedPosition::new();
+        let mut table = TranspositionTable::default();
+        table.insert(1, mcts::index::Id::invalid_id(), state, 2);
+        let mut linkable = |depth, linking| table.get_linkable(1, &state, depth, linking).is_some();
+        assert!(linkable(4, TranspositionLinking::Any));
+        assert!(!linkable(4, TranspositionLinking::Depth));
+        assert!(linkable(4, TranspositionLinking::Parity));
+        assert!(!linkable(3, TranspositionLinking::Parity));
+        assert!(linkable(2, TranspositionLinking::Depth));
+
+        // The number of moves played is a function of the board, so every
+        // transposition is at the same depth and the policies agree. Each
+        // finds the move the solver proves optimal.
+        let mut solver = Solver::new();
+        let mut state = HashedPosition::new();
+        for action in [Move(4), Move(12), Move(16)] {
+            state = TrafficLights::apply(state, &action);
+        }
+        let optimal = solver.optimal_moves(&state);
+        let mut legal = vec![];
+        TrafficLights::generate_actions(&state, &mut legal);
+        assert!(optimal.len() < legal.len());
+        let mut sizes = vec![];
+        for linking in [
+            TranspositionLinking::Any,
+            TranspositionLinking::Depth,
+            TranspositionLinking::Parity,
+        ] {
+            let mut ts = TS::default().config(
+                mcts::SearchConfig::default()
+                    .max_iterations(2000)
+                    .use_transpositions(true)
+                    .transposition_linking(linking)
+                    .seed(0),
+            );
+            assert!(optimal.contains(&ts.choose_action(&state)));
+            sizes.push(ts.index.len());
+        }
+        assert!(sizes.iter().all(|size| *size == sizes[0]));
+    }
+
+    #[test]
+    fn test_table_bounds() {
+        use crate::games::traffic_lights::*;
+        use mcts::table::{Replacement, TableBounds, TranspositionTable};
+        type TS = mcts::TreeSearch<TrafficLights, mcts::strategy::Ucb1>;
+
+        let id = mcts::index::Id::invalid_id();
+        let a = HashedPosition::new();
+        let b = TrafficLights::apply(a, &Move(0));
+        let c = TrafficLights::apply(a, &Move(1));
+        // A single bucket, so that every entry competes for it
+        let table = |replacement, capacity| {
+            let bounds = TableBounds::default().replacement(replacement);
+            TranspositionTable::new(bounds.capacity(capacity))
+        };
+
+        let mut depth_preferred = table(Replacement::DepthPreferred, 1);
+        depth_preferred.insert(1, id, a, 2);
+        depth_preferred.insert(2, id, b, 3);
+        assert!(depth_preferred.get(2, b).is_none());
+        depth_preferred.insert(3, id, c, 1);
+        assert!(depth_preferred.get(3, c).is_some());
+        assert!(depth_preferred.get(1, a).is_none());
+        assert_eq!(depth_preferred.len(), 1);
+
+        let mut recent = table(Replacement::Recent, 1);
+        recent.insert(1, id, a, 2);
+        recent.insert(2, id, b, 3);
+        assert!(recent.get(2, b).is_some());
+        assert!(recent.get(1, a).is_none());
+
+        let mut two_tier = table(Replacement::TwoTier, 2);
+        two_tier.insert(1, id, a, 2);
+        two_tier.insert(2, id, b, 3);
+        two_tier.insert(3, id, c, 1);
+        // `c` takes the depth-preferred entry, demoting `a` and dropping `b`
+        assert!(two_tier.get(3, c).is_some());
+        assert!(two_tier.get(1, a).is_some());
+        assert!(two_tier.get(2, b).is_none());
+        assert_eq!(two_tier.len(), 2);
+        let stats = two_tier.stats();
+        assert_eq!((stats.writes, stats.replacements), (3, 1));
+        assert_eq!((stats.reads, stats.hits, stats.collisions), (3, 2, 1));
+        assert_eq!(stats.misses, 0);
+
+        // A search stays within the bound, and still finds transpositions
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(2000)
+                .use_transpositions(true)
+                .table_bounds(TableBounds::default().capacity(64))
+                .seed(0),
+        );
+        ts.choose_action(&HashedPosition::new());
+        let stats = ts.tree_metrics().table;
+        assert!(stats.entries <= 64);
+        assert!(stats.hits > 0);
+        assert!(stats.replacements > 0);
+        assert_eq!(stats.reads, stats.hits + stats.misses + stats.collisions);
+    }
+
+    #[test]
+    fn test_table_verification() {
+        use crate::games::traffic_lights::*;
+        use mcts::table::{TableBounds, TableVerification, TranspositionTable};
+
+        let a = HashedPosition::new();
+        let b = TrafficLights::apply(a, &Move(0));
+        let table = |verification| {
+            let mut table =
+                TranspositionTable::new(TableBounds::default()).verification(verification);
+            // Both states under the same hash
+            table.insert(1, mcts::index::Id::invalid_id(), a, 0);
+            table.insert(1, mcts::index::Id::invalid_id(), b, 1);
+            table
+        };
+
+        // The collision is detected, and each state finds only its own entry
+        let mut safe = table(TableVerification::Safe);
+        assert_eq!(safe.get(1, b).map(|entry| entry.depth), Some(1));
+        assert_eq!(safe.get(1, a).map(|entry| entry.depth), Some(0));
+        assert_eq!(safe.stats().hash_collisions, 1);
+        assert_eq!(safe.len(), 2);
+
+        // Matching by hash alone takes `b` for `a`
+        let mut fast = table(TableVerification::Fast);
+        assert_eq!(fast.get(1, b).map(|entry| entry.depth), Some(0));
+        assert_eq!(fast.stats().hash_collisions, 0);
+
+        // Without collisions both modes find the same move
+        let action = |verification| {
+            let mut ts = mcts::TreeSearch::<TrafficLights, mcts::strategy::Ucb1>::default().config(
+                mcts::SearchConfig::default()
+                    .max_iterations(500)
+                    .use_transpositions(true)
+                    .table_verification(verification)
+                    .seed(0),
+            );
+            let action = ts.choose_action(&a);
+            assert_eq!(ts.tree_metrics().table.hash_collisions, 0);
+            action
+        };
+        assert_eq!(
+            action(TableVerification::Safe),
+            action(TableVerification::Fast)
+        );
+    }
+
+    #[test]
+    fn test_sort_children() {
+        use crate::games::ttt::*;
+        type TS = mcts::TreeSearch<TicTacToe, mcts::strategy::Ucb1>;
+
+        let is_sorted = |node: &mcts::node::Node<Move>| {
+            let children = node.children_by_visits();
+            children.len() == node.edges().len()
+                && children
+                    .windows(2)
+                    .all(|w| w[0].stats.num_visits >= w[1].stats.num_visits)
+        };
+
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(2000)
+                .sort_children(true)
+                .seed(0),
+        );
+        let result = ts.search(&HashedPosition::new());
+        let mut root = ts.index.get(ts.root_id).clone();
+        assert!(is_sorted(&root));
+        assert_eq!(root.children_by_visits()[0].action, result.action);
+
+        // A stale order is not used
+        let last = root.children_by_visits().last().unwrap().action;
+        let edge = root.edges_mut().iter_mut().find(|e| e.action == last);
+        edge.unwrap().stats.num_visits = 10000;
+        assert!(is_sorted(&root));
+        assert_eq!(root.children_by_visits()[0].action, last);
+        root.sort_children();
+        assert_eq!(root.children_by_visits()[0].action, last);
+
+        ts.sort_all_children();
+        mcts::render::render(&ts);
+    }
+
+    #[test]
+    fn test_record_trials() {
+        use crate::games::druid::*;
+        use mcts::simulate::EndType;
+        type TS = mcts::TreeSearch<Druid, mcts::strategy::Ucb1>;
+
+        let recording = mcts::TrialRecording::default().every(10).capacity(20);
+        let mut ts = TS::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(500)
+                .max_playout_depth(10)
+                .record_trials(recording)
+                .seed(0),
+        );
+        ts.search(&HashedState::default());
+        let trials = ts.recorded_trials();
+        assert_eq!(trials.len(), 20);
+        assert!(trials.iter().all(|trial| trial.actions.len() <= 10));
+        assert!(trials
+            .iter()
+            .any(|trial| trial.status.end_type == Some(EndType::TurnLimit)));
+        assert!(trials.iter().all(|trial| trial.utilities.len() == 2));
+
+        // Each search starts with an empty buffer
+        ts.config.max_iterations = 100;
+        ts.search(&HashedState::default());
+        assert_eq!(ts.take_recorded_trials().len(), 10);
+        assert!(ts.recorded_trials().is_empty());
+
+        assert!(mcts::SearchConfig::<Druid, mcts::strategy::Ucb1>::default()
+            .record_trials(recording.every(0))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_prepare() {
+        use crate::games::ttt::*;
+        use crate::util::{round_robin_records, AnySearch, Verbosity};
+        use random::Random;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct Counting(Random<TicTacToe>, Arc<AtomicUsize>);
+
+        impl Search for Counting {
+            type G = TicTacToe;
+
+            fn friendly_name(&self) -> String {
+                self.0.friendly_name()
+            }
+
+            fn choose_action(&mut self, state: &HashedPosition) -> Move {
+                self.0.choose_action(state)
+            }
+
+            fn prepare(&mut self, _: &HashedPosition) {
+                self.1.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn set_friendly_name(&mut self, name: &str) {
+                self.0.set_friendly_name(name)
+            }
+        }
+
+        let prepared = Arc::new(AtomicUsize::new(0));
+        let mut strategies = vec![
+            AnySearch::new(Counting(Random::new(), prepared.clone())),
+            AnySearch::new(Counting(Random::new(), prepared.clone())),
+        ];
+        round_robin_records::<TicTacToe>(
+            &mut strategies,
+            3,
+            &HashedPosition::new(),
+            Verbosity::Silent,
+            &[],
+            0,
+        );
+        // Once per strategy, not per game
+        assert_eq!(prepared.load(Ordering::SeqCst), 2);
+    }
+
     #[test]
     fn test_basics() {
         use crate::games::ttt::*;
@@ -151,4 +1187,597 @@ mod tests {
 
         println!("{:#?}", ts.index);
     }
+
+    #[test]
+    fn test_table_guided() {
+        use crate::games::traffic_lights::TrafficLights;
+        use crate::games::ttt::*;
+        use mcts::simulate::{SimulateStrategy, TableGuided};
+        use rand::rngs::SmallRng;
+        use rand_core::SeedableRng;
+
+        let state = HashedPosition::new();
+        let mut available = vec![];
+        TicTacToe::generate_actions(&state, &mut available);
+        let mut stats = mcts::TreeStats::<TicTacToe>::default();
+        stats
+            .guide
+            .insert(TicTacToe::zobrist_hash(&state), (Move(4), 20));
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut guided = TableGuided::<TicTacToe>::new().probability(1.);
+        for _ in 0..20 {
+            assert_eq!(
+                *guided.select_move(&state, &available, &stats, 0, &mut rng),
+                Move(4)
+            );
+        }
+        // Too few visits, so the choice is left to the inner strategy
+        let mut guided = guided.min_visits(21);
+        let choices = (0..20)
+            .map(|_| *guided.select_move(&state, &available, &stats, 0, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(choices.iter().any(|m| *m != Move(4)));
+
+        // The search maintains the guide for the strategy
+        #[derive(Clone, Default)]
+        struct Guided;
+
+        impl mcts::Strategy<TrafficLights> for Guided {
+            type Select = mcts::select::Ucb1;
+            type Simulate = TableGuided<TrafficLights>;
+            type Backprop = mcts::backprop::Classic;
+            type FinalAction = mcts::select::RobustChild;
+        }
+
+        let mut ts = mcts::TreeSearch::<TrafficLights, Guided>::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(500)
+                .use_transpositions(true)
+                .seed(0),
+        );
+        ts.choose_action(&Default::default());
+        assert!(!ts.stats.guide.is_empty());
+        assert!(ts.stats.guide.values().all(|(_, visits)| *visits <= 500));
+    }
+
+    // TicTacToe, giving only a scalar reward for the first player
+    #[derive(Clone)]
+    struct Rewarded;
+
+    impl crate::game::Game for Rewarded {
+        type S = crate::games::ttt::HashedPosition;
+        type A = crate::games::ttt::Move;
+        type P = crate::games::ttt::Piece;
+
+        fn apply(state: Self::S, action: &Self::A) -> Self::S {
+            crate::games::ttt::TicTacToe::apply(state, action)
+        }
+
+        fn generate_actions(state: &Self::S, actions: &mut Vec<Self::A>) {
+            crate::games::ttt::TicTacToe::generate_actions(state, actions)
+        }
+
+        fn is_terminal(state: &Self::S) -> bool {
+            crate::games::ttt::TicTacToe::is_terminal(state)
+        }
+
+        fn winner(state: &Self::S) -> Option<Self::P> {
+            crate::games::ttt::TicTacToe::winner(state)
+        }
+
+        fn player_to_move(state: &Self::S) -> Self::P {
+            crate::games::ttt::TicTacToe::player_to_move(state)
+        }
+
+        fn zero_sum_reward(state: &Self::S) -> Option<f64> {
+            Some(match crate::games::ttt::TicTacToe::winner(state) {
+                Some(crate::games::ttt::Piece::X) => 1.,
+                Some(crate::games::ttt::Piece::O) => -1.,
+                None => 0.,
+            })
+        }
+    }
+
+    #[test]
+    fn test_zero_sum_reward() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+
+        // X wins on the top row
+        let state = [0, 3, 1, 4, 2]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        assert_eq!(Rewarded::compute_utilities(&state), vec![1., -1.]);
+        assert_eq!(
+            Rewarded::compute_utilities(&state),
+            TicTacToe::compute_utilities(&state)
+        );
+        assert!(mcts::is_zero_sum(&[0.1 + 0.2, -0.3]));
+        assert!(!mcts::is_zero_sum(&[1., 0.]));
+
+        let mut ts = mcts::TreeSearch::<Rewarded, mcts::strategy::Ucb1>::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(1000)
+                .zero_sum(true)
+                .seed(0),
+        );
+        _ = ts.choose_action(&HashedPosition::new());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "do not sum to zero")]
+    fn test_zero_sum_violation() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+
+        // Every outcome is worth an extra point to both players
+        #[derive(Clone)]
+        struct Shifted;
+
+        impl Game for Shifted {
+            type S = HashedPosition;
+            type A = Move;
+            type P = Piece;
+
+            fn apply(state: HashedPosition, action: &Move) -> HashedPosition {
+                TicTacToe::apply(state, action)
+            }
+
+            fn generate_actions(state: &HashedPosition, actions: &mut Vec<Move>) {
+                TicTacToe::generate_actions(state, actions)
+            }
+
+            fn is_terminal(state: &HashedPosition) -> bool {
+                TicTacToe::is_terminal(state)
+            }
+
+            fn winner(state: &HashedPosition) -> Option<Piece> {
+                TicTacToe::winner(state)
+            }
+
+            fn player_to_move(state: &HashedPosition) -> Piece {
+                TicTacToe::player_to_move(state)
+            }
+
+            fn compute_utilities(state: &HashedPosition) -> Vec<f64> {
+                Rewarded::compute_utilities(state)
+                    .into_iter()
+                    .map(|u| u + 1.)
+                    .collect()
+            }
+        }
+
+        let mut ts = mcts::TreeSearch::<Shifted, mcts::strategy::Ucb1>::default().config(
+            mcts::SearchConfig::default()
+                .max_iterations(100)
+                .zero_sum(true)
+                .seed(0),
+        );
+        _ = ts.choose_action(&HashedPosition::new());
+    }
+
+    #[test]
+    fn test_auto_expand() {
+        use crate::games::breakthrough::Breakthrough;
+
+        type G = Breakthrough<6, 6>;
+        let num_nodes = |auto_expand: Option<mcts::AutoExpand>| {
+            let mut config = mcts::SearchConfig::default().max_iterations(10_000).seed(0);
+            config.auto_expand = auto_expand;
+            let mut ts = mcts::TreeSearch::<G, mcts::strategy::Ucb1>::default().config(config);
+            _ = ts.choose_action(&Default::default());
+            ts.tree_metrics()
+        };
+
+        let fixed = num_nodes(None);
+        assert!(fixed.num_nodes > 9000);
+        assert_eq!(fixed.expand_threshold, 1);
+
+        let tuned = num_nodes(Some(mcts::AutoExpand::default().max_nodes(2000)));
+        assert!(tuned.expand_threshold > 1);
+        assert!(
+            tuned.num_nodes < 3000,
+            "{} nodes for a budget of 2000",
+            tuned.num_nodes
+        );
+        assert!(tuned.num_nodes > 1000);
+    }
+
+    #[test]
+    fn test_puct() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+        use crate::strategies::mcts::{search::action_priors, TreeSearch};
+
+        // A prior favouring the corner furthest from the first square
+        #[derive(Clone)]
+        struct Prioritized;
+
+        impl Game for Prioritized {
+            type S = HashedPosition;
+            type A = Move;
+            type P = Piece;
+
+            fn apply(state: HashedPosition, action: &Move) -> HashedPosition {
+                TicTacToe::apply(state, action)
+            }
+
+            fn generate_actions(state: &HashedPosition, actions: &mut Vec<Move>) {
+                TicTacToe::generate_actions(state, actions)
+            }
+
+            fn is_terminal(state: &HashedPosition) -> bool {
+                TicTacToe::is_terminal(state)
+            }
+
+            fn winner(state: &HashedPosition) -> Option<Piece> {
+                TicTacToe::winner(state)
+            }
+
+            fn player_to_move(state: &HashedPosition) -> Piece {
+                TicTacToe::player_to_move(state)
+            }
+
+            fn action_priors(_: &HashedPosition, actions: &[Move]) -> Option<Vec<f64>> {
+                Some(
+                    actions
+                        .iter()
+                        .map(|m| if *m == Move(8) { 8. } else { 1. })
+                        .collect(),
+                )
+            }
+        }
+
+        let empty = HashedPosition::new();
+        let mut actions = vec![];
+        TicTacToe::generate_actions(&empty, &mut actions);
+        assert_eq!(
+            action_priors::<TicTacToe>(&empty, &actions),
+            vec![1. / 9.; 9]
+        );
+        let priors = action_priors::<Prioritized>(&empty, &actions);
+        assert_eq!(priors[8], 0.5);
+        assert_eq!(priors[0], 1. / 16.);
+
+        // The uniform prior still finds the win
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let mut ts = TreeSearch::<TicTacToe, mcts::strategy::Puct>::default()
+            .config(mcts::SearchConfig::default().max_iterations(1000).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // A short search follows a strong prior
+        let mut ts = TreeSearch::<Prioritized, mcts::strategy::Puct>::default()
+            .config(mcts::SearchConfig::default().max_iterations(50).seed(0));
+        _ = ts.choose_action(&empty);
+        let visits = ts.root_visits();
+        let most_visited = visits.iter().max_by_key(|(_, n)| *n).unwrap();
+        assert_eq!(most_visited.0, Move(8));
+    }
+
+    #[test]
+    fn test_evaluator() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+        use crate::strategies::mcts::{simulate, solver, strategy, SearchConfig, TreeSearch};
+
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let exact = simulate::Evaluator::<TicTacToe>::new(|state, _| {
+            solver::solve::<TicTacToe>(state).utilities
+        });
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1Eval>::default().config(
+            SearchConfig::default()
+                .simulate(exact)
+                .max_iterations(200)
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // By default, a game without a heuristic scores leaves as draws
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1Eval>::default().config(
+            SearchConfig::default()
+                .simulate(simulate::Evaluator::default())
+                .max_iterations(100)
+                .record_trials(mcts::TrialRecording::default())
+                .seed(0),
+        );
+        _ = ts.choose_action(&HashedPosition::new());
+        let evaluated = ts
+            .recorded_trials()
+            .iter()
+            .filter(|trial| trial.status.end_type == Some(simulate::EndType::Evaluated))
+            .collect::<Vec<_>>();
+        assert!(!evaluated.is_empty());
+        assert!(evaluated
+            .iter()
+            .all(|trial| trial.actions.is_empty() && trial.utilities == vec![0., 0.]));
+    }
+
+    #[test]
+    fn test_nst() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+        use crate::strategies::mcts::history::ngram_context;
+        use crate::strategies::mcts::{simulate, strategy, ConfigError, SearchConfig, TreeSearch};
+
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1Nst>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // Sequences of every length up to the maximum were recorded
+        let ngrams = &ts.stats.history.ngrams;
+        assert!(!ngrams.is_empty());
+        assert!(ngrams
+            .get(ngram_context::<Move>(&[]), 0, &Move(2))
+            .is_some());
+        assert!((0..9)
+            .flat_map(|a| (0..9).map(move |b| (a, b)))
+            .any(|(a, b)| ngrams
+                .get(ngram_context(&[Move(a), Move(b)]), 0, &Move(2))
+                .is_some()));
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1Nst>::default()
+                .simulate(simulate::Nst::default().max_n(4))
+                .validate(),
+            Err(ConfigError::NGramLengthOutOfRange(4))
+        );
+    }
+
+    #[test]
+    fn test_last_good_reply() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+        use crate::strategies::mcts::simulate::SimulateStrategy;
+        use crate::strategies::mcts::{
+            backprop, select, simulate, strategy, SearchConfig, TreeSearch,
+        };
+
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1Lgr>::default()
+            .config(SearchConfig::default().max_iterations(1000).seed(0));
+        assert_eq!(ts.choose_action(&state), Move(2));
+        assert!(!ts.stats.history.replies.is_empty());
+
+        // LGR-1, without forgetting, over a MAST fallback
+        #[derive(Clone, Default)]
+        struct Ucb1LgrMast;
+
+        impl mcts::Strategy<TicTacToe> for Ucb1LgrMast {
+            type Select = select::Ucb1;
+            type Simulate = simulate::LastGoodReply<TicTacToe, simulate::Mast>;
+            type Backprop = backprop::Classic;
+            type FinalAction = select::RobustChild;
+        }
+
+        let lgr1 = simulate::LastGoodReply::default()
+            .lgr2(false)
+            .forgetting(false);
+        let flags = SimulateStrategy::<TicTacToe>::backprop_flags(&lgr1);
+        assert!(flags.reply() && flags.global() && !flags.forget_reply());
+        let mut ts = TreeSearch::<TicTacToe, Ucb1LgrMast>::default().config(
+            SearchConfig::default()
+                .simulate(lgr1)
+                .max_iterations(1000)
+                .seed(0),
+        );
+        assert_eq!(ts.choose_action(&state), Move(2));
+        let history = &ts.stats.history;
+        assert!(!history.replies.is_empty() && !history.actions.is_empty());
+    }
+
+    #[test]
+    fn test_mercy() {
+        use crate::game::Game;
+        use crate::games::gonnect::Gonnect;
+        use crate::strategies::mcts::simulate::{EndType, Mercy};
+        use crate::strategies::mcts::{backprop, select, simulate, SearchConfig, TreeSearch};
+
+        #[derive(Clone, Default)]
+        struct Ucb1Mercy;
+
+        impl mcts::Strategy<Gonnect<5>> for Ucb1Mercy {
+            type Select = select::Ucb1;
+            type Simulate = Mercy<Gonnect<5>>;
+            type Backprop = backprop::Classic;
+            type FinalAction = select::RobustChild;
+        }
+
+        fn search<S>() -> Vec<simulate::Trial<Gonnect<5>>>
+        where
+            S: mcts::Strategy<Gonnect<5>>,
+            mcts::SearchConfig<Gonnect<5>, S>: Default,
+        {
+            let mut ts = TreeSearch::<Gonnect<5>, S>::default().config(
+                SearchConfig::default()
+                    .max_iterations(200)
+                    .max_playout_depth(100)
+                    .record_trials(mcts::TrialRecording::default())
+                    .seed(0),
+            );
+            _ = ts.choose_action(&Default::default());
+            ts.take_recorded_trials()
+        }
+        let depth = |trials: &[simulate::Trial<_>]| trials.iter().map(|t| t.depth).sum::<usize>();
+
+        let trials = search::<Ucb1Mercy>();
+        let stopped = trials
+            .iter()
+            .filter(|trial| trial.status.end_type == Some(EndType::Mercy))
+            .collect::<Vec<_>>();
+        assert!(!stopped.is_empty());
+        for trial in stopped {
+            let winner = Gonnect::<5>::playout_cutoff(&trial.state).unwrap();
+            assert_eq!(trial.utilities[winner.to_index()], 1.);
+        }
+        // Decided playouts end sooner
+        assert!(depth(&trials) < depth(&search::<mcts::strategy::Ucb1>()));
+    }
+
+    #[test]
+    fn test_ucd() {
+        use crate::game::Game;
+        use crate::games::ttt::*;
+        use crate::strategies::mcts::select::SelectContext;
+        use crate::strategies::mcts::stack::NodeStack;
+        use crate::strategies::mcts::{strategy, ConfigError, SearchConfig, TreeSearch, Ucd};
+
+        let state = [0, 3, 1, 4]
+            .into_iter()
+            .fold(HashedPosition::new(), |state, i| {
+                TicTacToe::apply(state, &Move(i))
+            });
+        let config = SearchConfig::default()
+            .max_iterations(2000)
+            .use_transpositions(true)
+            .ucd(Ucd::default())
+            .seed(0);
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default().config(config);
+        assert_eq!(ts.choose_action(&state), Move(2));
+
+        // Every edge into a transposed node sees the node's shared statistics
+        let root = HashedPosition::new();
+        let mut ts = TreeSearch::<TicTacToe, strategy::Ucb1>::default().config(
+            SearchConfig::default()
+                .max_iterations(3000)
+                .use_transpositions(true)
+                .ucd(Ucd::default())
+                .seed(0),
+        );
+        _ = ts.choose_action(&root);
+        let stack = NodeStack::new(vec![ts.root_id]);
+        let ctx = SelectContext {
+            q_init: Default::default(),
+            stack: &stack,
+            path: &[],
+            grave_ref: ts.root_id,
+            root_stats: &ts.root_stats,
+            state: &root,
+            player: 0,
+            index: &ts.index,
+            table: &ts.table,
+            history: &ts.stats.history,
+            use_transpositions: true,
+            ucd: ts.config.ucd,
+            score_bounds: None,
+        };
+        let incoming = ts
+            .index
+            .iter()
+            .filter(|node| node.is_expanded())
+            .flat_map(|node| node.edges())
+            .filter(|edge| edge.stats.num_visits > 0)
+            .filter_map(|edge| edge.node_id.map(|id| (id, edge)))
+            .collect::<Vec<_>>();
+        let transposed = incoming
+            .iter()
+            .find(|(id, edge)| {
+                ts.index.get(*id).is_expanded()
+                    && incoming.iter().any(|(other, e)| {
+                        other == id && e.stats.num_visits != edge.stats.num_visits
+                    })
+            })
+            .expect("a node with several parents");
+        let parents = incoming
+            .iter()
+            .filter(|(id, _)| *id == transposed.0)
+            .map(|(_, edge)| ctx.adapted_stats(edge, 1))
+            .collect::<Vec<_>>();
+        assert!(parents.windows(2).all(|pair| pair[0] == pair[1]));
+        // At depth zero each edge keeps its own
+        assert_ne!(
+            ctx.adapted_stats(transposed.1, 0),
+            ctx.adapted_stats(transposed.1, 1)
+        );
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, strategy::Ucb1>::default()
+                .ucd(Ucd::new(1, 1, 4))
+                .validate(),
+            Err(ConfigError::InvalidUcd(Ucd::new(1, 1, 4)))
+        );
+    }
+
+    #[test]
+    fn test_cutoff() {
+        use crate::games::ttt::*;
+        use crate::strategies::mcts::simulate::{Cutoff, EndType};
+        use crate::strategies::mcts::{backprop, select, simulate, ConfigError, SearchConfig};
+
+        #[derive(Clone, Default)]
+        struct Ucb1Cutoff;
+
+        impl mcts::Strategy<TicTacToe> for Ucb1Cutoff {
+            type Select = select::Ucb1;
+            type Simulate = Cutoff<TicTacToe>;
+            type Backprop = backprop::Classic;
+            type FinalAction = select::RobustChild;
+        }
+
+        let cutoff = Cutoff::new(2, |_, player| {
+            if player == 0 {
+                vec![0.5, -0.5]
+            } else {
+                vec![-0.5, 0.5]
+            }
+        });
+        let mut ts = mcts::TreeSearch::<TicTacToe, Ucb1Cutoff>::default().config(
+            SearchConfig::default()
+                .simulate(cutoff)
+                .max_iterations(500)
+                .record_trials(mcts::TrialRecording::default())
+                .seed(0),
+        );
+        _ = ts.choose_action(&HashedPosition::new());
+        let trials = ts.recorded_trials();
+        assert!(trials.iter().all(|trial| trial.depth <= 2));
+        let (cut, ended): (Vec<_>, Vec<_>) = trials
+            .iter()
+            .partition(|trial| trial.status.end_type == Some(EndType::TurnLimit));
+        assert!(!cut.is_empty() && !ended.is_empty());
+        assert!(cut.iter().all(|trial| trial.utilities == vec![0.5, -0.5]));
+        assert!(ended
+            .iter()
+            .all(|trial| trial.utilities == TicTacToe::compute_utilities(&trial.state)));
+
+        // A shorter max_playout_depth takes precedence
+        let mut cutoff = Cutoff::<TicTacToe>::default();
+        let trial = simulate::SimulateStrategy::playout(
+            &mut cutoff,
+            HashedPosition::new(),
+            1,
+            &Default::default(),
+            0,
+            &mut rand_core::SeedableRng::seed_from_u64(0),
+        );
+        assert_eq!(trial.depth, 1);
+        assert_eq!(trial.utilities, vec![0., 0.]);
+
+        assert_eq!(
+            SearchConfig::<TicTacToe, Ucb1Cutoff>::default()
+                .simulate(Cutoff::default().depth(0))
+                .validate(),
+            Err(ConfigError::ZeroCutoffDepth)
+        );
+    }
 }
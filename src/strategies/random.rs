@@ -18,6 +18,11 @@ impl<G: Game> Random<G> {
             game_type: PhantomData,
         }
     }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        self
+    }
 }
 
 impl<G: Game> Default for Random<G> {
@@ -0,0 +1,316 @@
+//! Batch evaluation of test positions, for strategy regression suites.
+//!
+//! A suite file lists one position per line as an optional id, the moves
+//! leading to the position from the initial state, and optionally the
+//! expected best moves after a `|`. Blank lines and lines starting with `#`
+//! are ignored.
+//!
+//! ```text
+//! # ttt
+//! block: (0,0) (1,1) (1,0) | (2,0)
+//! (0,0) (1,1)
+//! ```
+//!
+//! Moves are matched against the notation of the legal actions, ignoring
+//! whitespace, so any game which implements `Game::notation` can be used.
+//! Positions which cannot be reached by moves from the initial state can be
+//! built directly with `Position::new`.
+
+use crate::game::{Game, PlayerIndex};
+use crate::strategies::mcts::strategy::match_preset;
+use crate::strategies::mcts::{SearchConfig, Strategy, TreeSearch};
+use crate::tournament::{self, ManifestError, Participant};
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SuiteError {
+    Io(std::io::Error),
+    // The line number (starting at 1) and a description of the problem
+    Parse(usize, String),
+    Manifest(ManifestError),
+}
+
+impl fmt::Display for SuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuiteError::Io(err) => write!(f, "io error: {err}"),
+            SuiteError::Parse(line, message) => write!(f, "line {line}: {message}"),
+            SuiteError::Manifest(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for SuiteError {}
+
+impl From<std::io::Error> for SuiteError {
+    fn from(err: std::io::Error) -> Self {
+        SuiteError::Io(err)
+    }
+}
+
+impl From<ManifestError> for SuiteError {
+    fn from(err: ManifestError) -> Self {
+        SuiteError::Manifest(err)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct Position<G: Game> {
+    pub id: String,
+    pub state: G::S,
+    // Expected best moves; empty if the position only records the search
+    pub best: Vec<G::A>,
+}
+
+impl<G: Game> Position<G> {
+    pub fn new(id: &str, state: G::S) -> Self {
+        Self {
+            id: id.to_string(),
+            state,
+            best: vec![],
+        }
+    }
+
+    pub fn best(mut self, best: Vec<G::A>) -> Self {
+        self.best = best;
+        self
+    }
+}
+
+fn compact(s: &str) -> String {
+    s.split_whitespace().collect()
+}
+
+fn parse_move<G: Game>(state: &G::S, token: &str) -> Option<G::A> {
+    let mut actions = Vec::new();
+    G::generate_actions(state, &mut actions);
+    actions
+        .into_iter()
+        .find(|action| compact(&G::notation(state, action)) == token)
+}
+
+/// Split a line into move tokens. Notation may contain spaces (e.g. "(0, 1)"),
+/// so a token continues until its brackets are balanced.
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut depth = 0i32;
+    for c in s.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        if c.is_whitespace() {
+            if depth <= 0 && !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(c);
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Parse a suite, replaying each position's moves from `init`.
+pub fn parse<G: Game>(text: &str, init: &G::S) -> Result<Vec<Position<G>>, SuiteError> {
+    let mut positions = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_no = i + 1;
+
+        let (id, rest) = match line.split_once(':') {
+            Some((id, rest)) if !id.contains(['(', '[', '{']) => (id.trim().to_string(), rest),
+            _ => (format!("{line_no}"), line),
+        };
+        let (moves, best) = match rest.split_once('|') {
+            Some((moves, best)) => (moves, Some(best)),
+            None => (rest, None),
+        };
+
+        let mut state = init.clone();
+        for token in tokenize(moves) {
+            let Some(action) = parse_move::<G>(&state, &token) else {
+                return Err(SuiteError::Parse(line_no, format!("illegal move: {token}")));
+            };
+            state = G::apply(state, &action);
+        }
+
+        let mut expected = Vec::new();
+        for token in best.map(tokenize).unwrap_or_default() {
+            let Some(action) = parse_move::<G>(&state, &token) else {
+                return Err(SuiteError::Parse(
+                    line_no,
+                    format!("illegal best move: {token}"),
+                ));
+            };
+            expected.push(action);
+        }
+
+        if G::is_terminal(&state) {
+            return Err(SuiteError::Parse(line_no, "position is terminal".into()));
+        }
+        positions.push(Position::new(&id, state).best(expected));
+    }
+    Ok(positions)
+}
+
+pub fn load<G: Game>(path: &Path, init: &G::S) -> Result<Vec<Position<G>>, SuiteError> {
+    parse(&std::fs::read_to_string(path)?, init)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The search result for one position. Moves are given in notation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub id: String,
+    pub action: String,
+    // The mean utility at the root for the player to move
+    pub value: f64,
+    pub pv: Vec<String>,
+    pub iterations: usize,
+    // Whether the chosen move was one of the expected best moves, if any were
+    // given
+    pub correct: Option<bool>,
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = match self.correct {
+            Some(true) => " ok",
+            Some(false) => " FAIL",
+            None => "",
+        };
+        write!(
+            f,
+            "{}: {} value={:.3} pv={}{mark}",
+            self.id,
+            self.action,
+            self.value,
+            self.pv.join(" ")
+        )
+    }
+}
+
+/// The number of positions where the chosen move was one of the expected
+/// moves, and the number of positions with expected moves.
+pub fn score(evaluations: &[Evaluation]) -> (usize, usize) {
+    let checked = evaluations.iter().filter_map(|e| e.correct);
+    let total = checked.clone().count();
+    (checked.filter(|correct| *correct).count(), total)
+}
+
+/// Search each position with a fresh tree and the given configuration.
+pub fn run<G, S>(positions: &[Position<G>], config: &SearchConfig<G, S>) -> Vec<Evaluation>
+where
+    G: Game,
+    S: Strategy<G>,
+    SearchConfig<G, S>: Default + Clone,
+{
+    positions
+        .iter()
+        .map(|position| {
+            let mut search = TreeSearch::<G, S>::new().config(config.clone());
+            let result = search.search(&position.state);
+            let player = G::player_to_move(&position.state).to_index();
+
+            let mut state = position.state.clone();
+            let mut pv = Vec::new();
+            for action in &search.pv {
+                pv.push(G::notation(&state, action));
+                state = G::apply(state, action);
+            }
+
+            Evaluation {
+                id: position.id.clone(),
+                action: G::notation(&position.state, &result.action),
+                value: search.root_stats.expected_score(player),
+                pv,
+                iterations: result.iterations,
+                correct: (!position.best.is_empty())
+                    .then(|| position.best.contains(&result.action)),
+            }
+        })
+        .collect()
+}
+
+fn run_with<G, S>(
+    positions: &[Position<G>],
+    participant: &Participant,
+) -> Result<Vec<Evaluation>, SuiteError>
+where
+    G: Game,
+    S: Strategy<G>,
+    G::S: fmt::Display,
+{
+    let (config, _) = tournament::configure::<G, S>(participant)?;
+    Ok(run(positions, &config))
+}
+
+/// Run the suite with a participant described as in a tournament manifest.
+pub fn run_participant<G>(
+    positions: &[Position<G>],
+    participant: &Participant,
+) -> Result<Vec<Evaluation>, SuiteError>
+where
+    G: Game,
+    G::S: fmt::Display,
+{
+    match_preset!(
+        participant.preset.as_str(),
+        S => run_with::<G, S>(positions, participant),
+        preset => Err(ManifestError::UnknownPreset(preset.into()).into())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::{HashedPosition, Move, TicTacToe};
+
+    #[test]
+    fn test_suite() {
+        let text = "
+            # X to move in both
+            win: (0, 0) (0, 1) (1, 1) (0, 2) | (2, 2)
+            (0,0) (1,0)
+        ";
+        let positions = parse::<TicTacToe>(text, &HashedPosition::new()).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].id, "win");
+        assert_eq!(positions[0].best, vec![Move(8)]);
+        assert_eq!(positions[1].id, "4");
+        assert!(positions[1].best.is_empty());
+
+        assert!(matches!(
+            parse::<TicTacToe>("(0,0) (0,0)", &HashedPosition::new()),
+            Err(SuiteError::Parse(1, _))
+        ));
+
+        let participant = Participant {
+            name: "ucb1".into(),
+            preset: "ucb1".into(),
+            seed: Some(1),
+            max_iterations: Some(2000),
+            ..Default::default()
+        };
+        let evaluations = run_participant(&positions, &participant).unwrap();
+        assert_eq!(evaluations[0].action, "(2, 2)");
+        assert_eq!(evaluations[0].pv.first().unwrap(), "(2, 2)");
+        assert!(evaluations[0].value > 0.5);
+        assert_eq!(evaluations[1].correct, None);
+        assert_eq!(score(&evaluations), (1, 1));
+    }
+}
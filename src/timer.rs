@@ -1,39 +1,102 @@
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread::sleep;
-use std::thread::spawn;
 use std::time::Duration;
 use std::time::Instant;
 
+// Reading the clock on every iteration is a measurable cost for fast games,
+// so the deadline is only checked every `interval` iterations. The interval is
+// adapted to the observed iteration rate to aim for one clock read per
+// `CHECK_PERIOD` (or a hundredth of the budget, if shorter).
+const CHECK_PERIOD: Duration = Duration::from_millis(1);
+const MAX_INTERVAL: u32 = 1 << 16;
+
 #[derive(Clone)]
 pub struct Timer {
     start_time: Instant,
-    timeout: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    target: Duration,
+    last_check: Instant,
+    interval: u32,
+    countdown: u32,
+    clock_reads: u64,
 }
 
 impl Timer {
     pub fn new() -> Self {
+        let now = Instant::now();
         Self {
-            start_time: Instant::now(),
-            timeout: Arc::new(AtomicBool::new(false)),
+            start_time: now,
+            deadline: None,
+            target: CHECK_PERIOD,
+            last_check: now,
+            interval: 1,
+            countdown: 1,
+            clock_reads: 0,
         }
     }
 
+    /// Start timing a search. A zero duration means no deadline.
     pub fn start(&mut self, duration: Duration) {
-        self.timeout = if duration == Duration::default() {
-            Arc::new(AtomicBool::new(false))
-        } else {
-            timeout_signal(duration)
-        };
-        self.start_time = Instant::now();
+        let now = Instant::now();
+        self.start_time = now;
+        self.last_check = now;
+        self.deadline = (duration != Duration::default()).then(|| now + duration);
+        self.target = CHECK_PERIOD.min(duration / 100);
+        self.interval = 1;
+        self.countdown = 1;
+        self.clock_reads = 0;
+    }
+
+    /// Move the deadline to `duration` after the start, if there is one.
+    pub fn extend(&mut self, duration: Duration) {
+        if self.deadline.is_some() {
+            self.deadline = Some(self.start_time + duration);
+        }
+    }
+
+    /// The time from the start to the deadline, if there is one.
+    pub fn budget(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.duration_since(self.start_time))
     }
 
     pub fn elapsed(&self) -> Duration {
         Instant::now().duration_since(self.start_time)
     }
 
-    pub fn done(&self) -> bool {
-        self.timeout.load(std::sync::atomic::Ordering::Relaxed)
+    /// Call once per iteration. Returns true once the deadline has passed.
+    #[inline]
+    pub fn done(&mut self) -> bool {
+        let Some(deadline) = self.deadline else {
+            return false;
+        };
+        self.countdown -= 1;
+        if self.countdown > 0 {
+            return false;
+        }
+
+        let now = Instant::now();
+        self.clock_reads += 1;
+        let per_iteration = now.duration_since(self.last_check) / self.interval;
+        self.interval = if per_iteration.is_zero() {
+            (self.interval * 2).min(MAX_INTERVAL)
+        } else {
+            (self.target.as_nanos() / per_iteration.as_nanos()).clamp(1, MAX_INTERVAL as u128)
+                as u32
+        };
+        self.countdown = self.interval;
+        self.last_check = now;
+        now >= deadline
+    }
+
+    /// The number of times the clock was read by `done` in this search.
+    pub fn clock_reads(&self) -> u64 {
+        self.clock_reads
+    }
+
+    /// The current number of iterations between clock reads.
+    pub fn interval(&self) -> u32 {
+        self.interval
     }
 }
 
@@ -43,14 +106,48 @@ impl Default for Timer {
     }
 }
 
-pub(super) fn timeout_signal(dur: Duration) -> Arc<AtomicBool> {
-    // Theoretically we could include an async runtime to do this and use
-    // fewer threads, but the stdlib implementation is only a few lines...
-    let signal = Arc::new(AtomicBool::new(false));
-    let signal2 = signal.clone();
-    spawn(move || {
-        sleep(dur);
-        signal2.store(true, std::sync::atomic::Ordering::Relaxed);
-    });
-    signal
+/// A cloneable handle to interrupt a running search from another thread, for
+/// example on a "stop" command. See `TreeSearch::stop_handle`.
+#[derive(Clone, Debug, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the search to return at its next check.
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer() {
+        let mut timer = Timer::new();
+        timer.start(Duration::default());
+        assert!((0..1000).all(|_| !timer.done()));
+        assert_eq!(timer.clock_reads(), 0);
+
+        timer.start(Duration::from_millis(20));
+        let mut iterations = 0u64;
+        while !timer.done() {
+            iterations += 1;
+        }
+        assert!(timer.elapsed() >= Duration::from_millis(20));
+        // Cheap iterations are checked in batches
+        assert!(timer.clock_reads() < iterations / 10);
+    }
 }
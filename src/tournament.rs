@@ -0,0 +1,835 @@
+//! Reproducible tournaments described by a manifest.
+//!
+//! A manifest lists the participants by strategy preset along with any
+//! configuration overrides and seeds. Running the manifest produces a results
+//! manifest which records the fully resolved configuration of each participant
+//! alongside its results, so the experiment can be shared and repeated.
+//!
+//! The optional `scoring` field selects how terminal utilities are shaped for
+//! games which support it, and defaults to `"win_loss"`. The optional
+//! `variety` field samples the opening moves of every game (see
+//! `util::Variety`), and can be overridden per participant. The moves chosen
+//! are listed in the results with each game. The results also split each
+//! participant's record by seat, estimate the first-move advantage, and rate
+//! the participants with a cross-table of their pairwise results (see
+//! `util::Ratings`).
+//!
+//! The optional `openings` field plays every pairing from a number of start
+//! positions, each reached by random moves from the initial state (see
+//! `util::RandomOpenings`), so that the results are not dominated by a single
+//! line. The results are then also broken down by opening.
+//!
+//! The optional `format` field pairs the participants as a `"round_robin"`,
+//! the default, a `"gauntlet"` of the first participant against each of the
+//! others, or a `"swiss"` tournament of `rounds` rounds (see `util::Format`),
+//! since a full round robin grows quadratically with the participants. The
+//! optional `concurrency` field limits how many games are played at once.
+//!
+//! ```json
+//! {
+//!   "rounds": 10,
+//!   "format": "gauntlet",
+//!   "concurrency": 4,
+//!   "scoring": "margin",
+//!   "variety": { "plies": 2, "top_n": 3, "temperature": 1.0 },
+//!   "openings": { "plies": 2, "count": 8 },
+//!   "participants": [
+//!     { "name": "ucb1", "preset": "ucb1", "seed": 1, "max_iterations": 1000 },
+//!     { "name": "rave", "preset": "rave_mast_dm", "q_init": "Win" }
+//!   ]
+//! }
+//! ```
+
+use crate::game::{Game, Scoring};
+use crate::strategies::mcts::node::QInit;
+use crate::strategies::mcts::strategy::{self, match_preset};
+use crate::strategies::mcts::{ConfigError, SearchConfig, Strategy, TreeSearch};
+use crate::util::{
+    tournament_records, AnySearch, ColorBalance, Format, GameRecord, GameStats, OpeningResult,
+    RandomOpenings, Ratings, SeatResult, Variety, Verbosity,
+};
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnknownPreset(String),
+    InvalidQInit(String),
+    Config(String, ConfigError),
+    UnsupportedScoring(String, Scoring),
+    ThreadPool(rayon::ThreadPoolBuildError),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io(err) => write!(f, "io error: {err}"),
+            ManifestError::Json(err) => write!(f, "json error: {err}"),
+            ManifestError::UnknownPreset(preset) => write!(f, "unknown preset: {preset}"),
+            ManifestError::InvalidQInit(q_init) => write!(f, "invalid q_init: {q_init}"),
+            ManifestError::Config(name, err) => write!(f, "invalid config for {name}: {err}"),
+            ManifestError::UnsupportedScoring(game, scoring) => {
+                write!(f, "{game} does not support {scoring:?} scoring")
+            }
+            ManifestError::ThreadPool(err) => write!(f, "thread pool error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl From<std::io::Error> for ManifestError {
+    fn from(err: std::io::Error) -> Self {
+        ManifestError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ManifestError {
+    fn from(err: serde_json::Error) -> Self {
+        ManifestError::Json(err)
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for ManifestError {
+    fn from(err: rayon::ThreadPoolBuildError) -> Self {
+        ManifestError::ThreadPool(err)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The names accepted in the `preset` field of a participant.
+pub const PRESETS: &[&str] = &strategy::PRESETS;
+
+/// A participant as described in the manifest. Any field left unset uses the
+/// preset's default, adjusted by the game's recommended config hints.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Participant {
+    pub name: String,
+    pub preset: String,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub expand_threshold: Option<u32>,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default)]
+    pub max_playout_depth: Option<usize>,
+    #[serde(default)]
+    pub max_time_ms: Option<u64>,
+    #[serde(default)]
+    pub q_init: Option<String>,
+    #[serde(default)]
+    pub use_transpositions: Option<bool>,
+    #[serde(default)]
+    pub stats_decay: Option<f64>,
+    // Overrides the manifest's opening variety
+    #[serde(default)]
+    pub variety: Option<Variety>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub rounds: usize,
+    #[serde(default)]
+    pub format: Format,
+    // The most games played at once, or one per core if unset
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    #[serde(default)]
+    pub scoring: Scoring,
+    #[serde(default)]
+    pub variety: Option<Variety>,
+    #[serde(default)]
+    pub openings: Option<RandomOpenings>,
+    pub participants: Vec<Participant>,
+}
+
+impl Manifest {
+    pub fn from_json(json: &str) -> Result<Self, ManifestError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// The configuration a participant actually played with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedConfig {
+    pub name: String,
+    pub preset: String,
+    pub seed: u64,
+    pub expand_threshold: u32,
+    pub max_iterations: usize,
+    pub max_playout_depth: usize,
+    pub max_time_ms: u64,
+    pub q_init: String,
+    pub use_transpositions: bool,
+    pub stats_decay: f64,
+    #[serde(default)]
+    pub variety: Variety,
+}
+
+impl ResolvedConfig {
+    /// The equivalent manifest entry, with every field set.
+    pub fn participant(&self) -> Participant {
+        Participant {
+            name: self.name.clone(),
+            preset: self.preset.clone(),
+            seed: Some(self.seed),
+            expand_threshold: Some(self.expand_threshold),
+            max_iterations: Some(self.max_iterations),
+            max_playout_depth: Some(self.max_playout_depth),
+            max_time_ms: Some(self.max_time_ms),
+            q_init: Some(self.q_init.clone()),
+            use_transpositions: Some(self.use_transpositions),
+            stats_decay: Some(self.stats_decay),
+            variety: Some(self.variety),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParticipantResult {
+    pub config: ResolvedConfig,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    // Wins and losses decided by resignation or a win claim
+    #[serde(default)]
+    pub adjudicated: usize,
+    // Search statistics over every game played
+    #[serde(default)]
+    pub stats: GameStats,
+    // Results moving first and second
+    #[serde(default)]
+    pub seats: [SeatResult; 2],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResultsManifest {
+    pub game: String,
+    pub rounds: usize,
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    #[serde(default)]
+    pub scoring: Scoring,
+    pub results: Vec<ParticipantResult>,
+    #[serde(default)]
+    pub games: Vec<GameRecord>,
+    // The first-move advantage over all games
+    #[serde(default)]
+    pub color_balance: ColorBalance,
+    // Elo ratings fitted to all games, and the pairwise results
+    #[serde(default)]
+    pub ratings: Ratings,
+    #[serde(default)]
+    pub openings: Option<RandomOpenings>,
+    // The results from each opening, by `GameRecord::opening`
+    #[serde(default)]
+    pub opening_results: Vec<OpeningResult>,
+}
+
+impl ResultsManifest {
+    /// A manifest which replays this tournament exactly.
+    pub fn manifest(&self) -> Manifest {
+        Manifest {
+            rounds: self.rounds,
+            format: self.format,
+            concurrency: self.concurrency,
+            scoring: self.scoring,
+            variety: None,
+            openings: self.openings,
+            participants: self
+                .results
+                .iter()
+                .map(|result| result.config.participant())
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, ManifestError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ManifestError> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub(crate) fn configure<G, S>(
+    participant: &Participant,
+) -> Result<(SearchConfig<G, S>, ResolvedConfig), ManifestError>
+where
+    G: Game,
+    S: Strategy<G>,
+{
+    let mut config = SearchConfig::<G, S>::default_for().name(&participant.name);
+    if let Some(expand_threshold) = participant.expand_threshold {
+        config = config.expand_threshold(expand_threshold);
+    }
+    if let Some(max_iterations) = participant.max_iterations {
+        config = config.max_iterations(max_iterations);
+    }
+    if let Some(max_playout_depth) = participant.max_playout_depth {
+        config = config.max_playout_depth(max_playout_depth);
+    }
+    if let Some(max_time_ms) = participant.max_time_ms {
+        config = config.max_time(Duration::from_millis(max_time_ms));
+    }
+    if let Some(q_init) = &participant.q_init {
+        config = config.q_init(
+            QInit::from_str(q_init).map_err(|_| ManifestError::InvalidQInit(q_init.clone()))?,
+        );
+    }
+    if let Some(use_transpositions) = participant.use_transpositions {
+        config = config.use_transpositions(use_transpositions);
+    }
+    if let Some(stats_decay) = participant.stats_decay {
+        config = config.stats_decay(stats_decay);
+    }
+
+    // Always seed explicitly so that the seed can be recorded
+    let seed = participant.seed.unwrap_or_else(rand::random);
+    config = config.seed(seed);
+
+    config
+        .validate()
+        .map_err(|err| ManifestError::Config(participant.name.clone(), err))?;
+
+    let resolved = ResolvedConfig {
+        name: participant.name.clone(),
+        preset: participant.preset.clone(),
+        seed,
+        expand_threshold: config.expand_threshold,
+        max_iterations: config.max_iterations,
+        max_playout_depth: config.max_playout_depth,
+        max_time_ms: config.max_time.as_millis() as u64,
+        q_init: format!("{:?}", config.q_init),
+        use_transpositions: config.use_transpositions,
+        stats_decay: config.stats_decay,
+        variety: participant.variety.unwrap_or_default(),
+    };
+
+    Ok((config, resolved))
+}
+
+fn make<'a, G, S>(
+    participant: &Participant,
+) -> Result<(AnySearch<'a, G>, ResolvedConfig), ManifestError>
+where
+    G: Game + 'a,
+    S: Strategy<G> + 'a,
+    G::S: fmt::Display,
+{
+    let (config, resolved) = configure::<G, S>(participant)?;
+    let search: TreeSearch<G, S> = TreeSearch::new().config(config);
+    Ok((AnySearch::new(search), resolved))
+}
+
+/// Construct a participant from its manifest entry.
+pub fn participant<'a, G>(
+    participant: &Participant,
+) -> Result<(AnySearch<'a, G>, ResolvedConfig), ManifestError>
+where
+    G: Game + 'a,
+    G::S: fmt::Display,
+{
+    match_preset!(
+        participant.preset.as_str(),
+        S => make::<G, S>(participant),
+        preset => Err(ManifestError::UnknownPreset(preset.into()))
+    )
+}
+
+/// Run a round robin tournament as described by the manifest, from `init`
+/// under the manifest's scoring.
+pub fn run<G>(
+    game: &str,
+    manifest: &Manifest,
+    init: &G::S,
+    verbose: Verbosity,
+) -> Result<ResultsManifest, ManifestError>
+where
+    G: Game,
+    G::S: fmt::Display,
+{
+    let init = &G::with_scoring(init.clone(), manifest.scoring)
+        .ok_or_else(|| ManifestError::UnsupportedScoring(game.into(), manifest.scoring))?;
+    let (mut strategies, configs): (Vec<_>, Vec<_>) = manifest
+        .participants
+        .iter()
+        .map(|p| Participant {
+            variety: p.variety.or(manifest.variety),
+            ..p.clone()
+        })
+        .map(|p| participant::<G>(&p))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+
+    // Derived from the recorded seeds, so the sampled openings replay too
+    let variety = configs.iter().map(|c| c.variety).collect::<Vec<_>>();
+    let seed = configs
+        .iter()
+        .fold(0u64, |acc, c| acc.wrapping_mul(31).wrapping_add(c.seed));
+    let (openings, moves): (Vec<_>, Vec<_>) = match manifest.openings {
+        Some(openings) => openings.generate::<G>(init, seed).into_iter().unzip(),
+        None => (vec![init.clone()], vec![vec![]]),
+    };
+    let mut play = || {
+        tournament_records::<G>(
+            &mut strategies,
+            manifest.format,
+            manifest.rounds,
+            &openings,
+            verbose,
+            &variety,
+            seed,
+        )
+    };
+    let (results, games) = match manifest.concurrency {
+        Some(concurrency) => rayon::ThreadPoolBuilder::new()
+            .num_threads(concurrency)
+            .build()?
+            .install(play),
+        None => play(),
+    };
+    let mut opening_results = OpeningResult::from_records(&games, openings.len(), configs.len());
+    for (result, moves) in opening_results.iter_mut().zip(moves) {
+        result.moves = moves;
+    }
+
+    let names = configs.iter().map(|c| c.name.clone()).collect();
+    Ok(ResultsManifest {
+        game: game.into(),
+        rounds: manifest.rounds,
+        format: manifest.format,
+        concurrency: manifest.concurrency,
+        scoring: manifest.scoring,
+        results: configs
+            .into_iter()
+            .zip(results)
+            .map(|(config, result)| ParticipantResult {
+                config,
+                wins: result.wins,
+                losses: result.losses,
+                draws: result.draws,
+                adjudicated: result.adjudicated,
+                stats: result.stats,
+                seats: result.seats,
+            })
+            .collect(),
+        color_balance: ColorBalance::from_records(&games),
+        ratings: Ratings::from_records(names, &games),
+        games,
+        openings: manifest.openings,
+        opening_results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::gonnect::Gonnect;
+    use crate::games::ttt::TicTacToe;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let manifest = Manifest::from_json(
+            r#"{
+                "rounds": 1,
+                "participants": [
+                    { "name": "a", "preset": "ucb1", "seed": 1, "max_iterations": 50 },
+                    { "name": "b", "preset": "rave_mast_dm", "max_iterations": 50, "q_init": "Win" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let results =
+            run::<TicTacToe>("ttt", &manifest, &Default::default(), Verbosity::Silent).unwrap();
+        assert_eq!(results.results.len(), 2);
+        let games: usize = results
+            .results
+            .iter()
+            .map(|r| r.wins + r.losses + r.draws)
+            .sum();
+        assert_eq!(games, 4);
+        assert_eq!(results.games.len(), 2);
+
+        // Each participant made at least two moves in each of its two games
+        for result in &results.results {
+            assert!(result.stats.moves >= 4);
+            assert!(result.stats.nodes_per_move() > 1.);
+            assert!(result.stats.average_depth() > 0.);
+        }
+        let moves: usize = results
+            .games
+            .iter()
+            .map(|g| g.stats[0].moves)
+            .sum::<usize>()
+            + results
+                .games
+                .iter()
+                .map(|g| g.stats[1].moves)
+                .sum::<usize>();
+        assert_eq!(
+            moves,
+            results.results.iter().map(|r| r.stats.moves).sum::<usize>()
+        );
+
+        // Each participant played once with each color
+        for result in &results.results {
+            assert_eq!(result.seats[0].games(), 1);
+            assert_eq!(result.seats[1].games(), 1);
+            assert_eq!(result.seats[0].wins + result.seats[1].wins, result.wins);
+        }
+        assert_eq!(results.color_balance.games(), 2);
+        assert!(results.color_balance.is_balanced());
+
+        let a = &results.results[0].config;
+        assert_eq!(a.seed, 1);
+        assert_eq!(a.max_iterations, 50);
+        assert_eq!(results.results[1].config.q_init, "Win");
+        assert_eq!(results.scoring, Scoring::WinLoss);
+
+        // The resolved manifest reproduces the configs exactly
+        let json = results.to_json().unwrap();
+        let parsed: ResultsManifest = serde_json::from_str(&json).unwrap();
+        for (p, r) in parsed.manifest().participants.iter().zip(&results.results) {
+            let (_, resolved) = participant::<TicTacToe>(p).unwrap();
+            assert_eq!(resolved, r.config);
+        }
+    }
+
+    #[test]
+    fn test_color_balance() {
+        let record = |players, winner| GameRecord {
+            players,
+            winner,
+            ..Default::default()
+        };
+        let mut records = vec![
+            record([0, 1], Some(0)),
+            record([1, 0], Some(1)),
+            record([0, 2], None),
+            record([2, 0], Some(0)),
+        ];
+        let balance = ColorBalance::from_records(&records);
+        assert_eq!(balance.first_wins, 2);
+        assert_eq!(balance.second_wins, 1);
+        assert_eq!(balance.draws, 1);
+        assert!(balance.is_balanced());
+        assert!((balance.first_move_advantage() - 0.125).abs() < 1e-9);
+        assert!(balance.first_move_elo() > 0.);
+
+        records.push(record([2, 1], None));
+        assert_eq!(
+            ColorBalance::from_records(&records).unbalanced,
+            vec![[2, 1]]
+        );
+    }
+
+    #[test]
+    fn test_ratings() {
+        let record = |players, winner| GameRecord {
+            players,
+            winner,
+            ..Default::default()
+        };
+        // 0 beats 1 three games to one, and 1 and 2 draw every game
+        let mut records = vec![];
+        for (players, winner) in [
+            ([0, 1], Some(0)),
+            ([1, 0], Some(0)),
+            ([0, 1], Some(0)),
+            ([1, 0], Some(1)),
+            ([1, 2], None),
+            ([2, 1], None),
+        ] {
+            records.push(record(players, winner));
+        }
+        let names = vec!["a".into(), "b".into(), "c".into()];
+        let ratings = Ratings::from_records(names, &records);
+        assert_eq!(ratings.cross_table[0][1].wins, 3);
+        assert_eq!(ratings.cross_table[1][0].losses, 3);
+        assert_eq!(ratings.cross_table[1][2].draws, 2);
+        assert_eq!(ratings.cross_table[0][2].games(), 0);
+        assert_eq!(ratings.ranking()[0], 0);
+        assert!(ratings.elo.iter().sum::<f64>().abs() < 1e-6);
+        assert!((ratings.elo[1] - ratings.elo[2]).abs() < 1e-6);
+
+        // With the virtual draw, a is expected to score 3.5 of 5 against b
+        let p = 1. / (1. + 10f64.powf((ratings.elo[1] - ratings.elo[0]) / 400.));
+        assert!((p - 0.7).abs() < 1e-6);
+        // The fewer games, the wider the interval
+        assert!(ratings.error[2] > ratings.error[1]);
+
+        let table = ratings.to_string();
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().nth(1).unwrap().contains(" 3-1-0 "));
+    }
+
+    #[test]
+    fn test_openings() {
+        let manifest = Manifest::from_json(
+            r#"{
+                "rounds": 1,
+                "openings": { "plies": 2, "count": 3 },
+                "participants": [
+                    { "name": "a", "preset": "ucb1", "seed": 1, "max_iterations": 50 },
+                    { "name": "b", "preset": "ucb1", "seed": 2, "max_iterations": 50 },
+                    { "name": "c", "preset": "ucb1", "seed": 3, "max_iterations": 50 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let results =
+            run::<TicTacToe>("ttt", &manifest, &Default::default(), Verbosity::Silent).unwrap();
+        assert_eq!(results.games.len(), 3 * 6);
+        assert_eq!(results.opening_results.len(), 3);
+        for (opening, result) in results.opening_results.iter().enumerate() {
+            assert_eq!(result.moves.len(), 2);
+            // Every pairing is played with both colors from each opening
+            assert_eq!(result.color_balance.games(), 6);
+            assert!(result.color_balance.is_balanced());
+            assert!(result.results.iter().all(|r| r.games() == 4));
+            assert_eq!(
+                results
+                    .games
+                    .iter()
+                    .filter(|game| game.opening == opening)
+                    .count(),
+                6
+            );
+        }
+        let lines = results
+            .opening_results
+            .iter()
+            .map(|result| &result.moves)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(lines.len(), 3);
+        let wins: usize = results
+            .opening_results
+            .iter()
+            .map(|result| result.results[0].wins)
+            .sum();
+        assert_eq!(wins, results.results[0].wins);
+        assert_eq!(results.manifest().openings, manifest.openings);
+
+        // Without openings, every game is played from the initial state
+        let openings = RandomOpenings { plies: 0, count: 3 };
+        assert_eq!(
+            openings.generate::<TicTacToe>(&Default::default(), 0).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_formats() {
+        let manifest = |format, participants: usize, rounds| {
+            let participants = (0..participants)
+                .map(|i| {
+                    format!(
+                        r#"{{ "name": "{i}", "preset": "ucb1", "seed": {i}, "max_iterations": 20 }}"#
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            Manifest::from_json(&format!(
+                r#"{{
+                    "rounds": {rounds},
+                    "format": "{format}",
+                    "concurrency": 2,
+                    "participants": [{participants}]
+                }}"#
+            ))
+            .unwrap()
+        };
+        let pairing = |game: &GameRecord| {
+            let [i, j] = game.players;
+            [i.min(j), i.max(j)]
+        };
+
+        let gauntlet = manifest("gauntlet", 4, 1);
+        assert_eq!(gauntlet.format, Format::Gauntlet);
+        let results =
+            run::<TicTacToe>("ttt", &gauntlet, &Default::default(), Verbosity::Silent).unwrap();
+        assert_eq!(results.games.len(), 6);
+        assert!(results.games.iter().all(|game| pairing(game)[0] == 0));
+        assert!(results.color_balance.is_balanced());
+        assert_eq!(
+            results.results[0].wins + results.results[0].losses + results.results[0].draws,
+            6
+        );
+        assert_eq!(results.manifest().concurrency, Some(2));
+
+        // Nobody meets twice in two rounds of four
+        let results = run::<TicTacToe>(
+            "ttt",
+            &manifest("swiss", 4, 2),
+            &Default::default(),
+            Verbosity::Silent,
+        )
+        .unwrap();
+        assert_eq!(results.games.len(), 8);
+        assert!(results.color_balance.is_balanced());
+        let pairings = results
+            .games
+            .iter()
+            .map(pairing)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(pairings.len(), 4);
+
+        // With an odd number, a different participant sits out each round
+        let results = run::<TicTacToe>(
+            "ttt",
+            &manifest("swiss", 5, 3),
+            &Default::default(),
+            Verbosity::Silent,
+        )
+        .unwrap();
+        assert_eq!(results.games.len(), 3 * 4);
+        let byes = (0..3)
+            .map(|round| {
+                (0..5)
+                    .find(|i| {
+                        !results
+                            .games
+                            .iter()
+                            .any(|game| game.round == round && game.players.contains(i))
+                    })
+                    .unwrap()
+            })
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(byes.len(), 3);
+    }
+
+    #[test]
+    fn test_game_stats() {
+        use crate::strategies::SearchMetrics;
+        use std::time::Duration;
+
+        let mut stats = GameStats::default();
+        for (value, ms) in [(0., 10), (0.5, 20), (0.2, 30)] {
+            stats.record(&SearchMetrics {
+                nodes: 100,
+                average_depth: 4.,
+                elapsed: Duration::from_millis(ms),
+                value,
+            });
+        }
+        assert_eq!(stats.moves, 3);
+        assert_eq!(stats.nodes_per_move(), 100.);
+        assert_eq!(stats.average_depth(), 4.);
+        assert!((stats.ms_per_move() - 20.).abs() < 1e-9);
+        assert!((stats.average_swing() - 0.4).abs() < 1e-9);
+        assert_eq!(stats.max_swing, 0.5);
+
+        let total = stats + stats;
+        assert_eq!(total.moves, 6);
+        assert_eq!(total.max_swing, 0.5);
+    }
+
+    #[test]
+    fn test_variety() {
+        let manifest = Manifest::from_json(
+            r#"{
+                "rounds": 4,
+                "variety": { "plies": 3, "top_n": 9, "temperature": 1.0 },
+                "participants": [
+                    { "name": "a", "preset": "ucb1", "seed": 1, "max_iterations": 50 },
+                    { "name": "b", "preset": "ucb1", "seed": 2, "max_iterations": 50,
+                      "variety": { "plies": 0, "top_n": 1, "temperature": 0.0 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let results =
+            run::<TicTacToe>("ttt", &manifest, &Default::default(), Verbosity::Silent).unwrap();
+        assert_eq!(results.games.len(), 8);
+        assert_eq!(results.results[0].config.variety.plies, 3);
+        assert_eq!(results.results[1].config.variety.plies, 0);
+
+        // Only the first participant's moves within the first three plies
+        // are sampled
+        for game in &results.games {
+            let expected = if game.players[0] == 0 { 2 } else { 1 };
+            assert_eq!(game.forced.len(), expected);
+        }
+        let first_moves = results
+            .games
+            .iter()
+            .filter(|game| game.players[0] == 0)
+            .map(|game| &game.forced[0])
+            .collect::<std::collections::HashSet<_>>();
+        assert!(first_moves.len() > 1);
+
+        let rng = &mut rand_core::SeedableRng::seed_from_u64(0);
+        let visits = [(0, 10), (1, 30), (2, 20), (3, 0)];
+        let greedy = Variety::default().temperature(0.);
+        assert_eq!(greedy.sample(&visits, rng), Some(1));
+        assert_eq!(Variety::default().top_n(1).sample(&visits, rng), Some(1));
+        assert!((0..100).all(|_| Variety::default().sample(&visits, rng) != Some(3)));
+        assert_eq!(Variety::default().sample::<usize>(&[], rng), None);
+    }
+
+    #[test]
+    fn test_manifest_errors() {
+        let bad_preset = Participant {
+            name: "x".into(),
+            preset: "nope".into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            participant::<TicTacToe>(&bad_preset),
+            Err(ManifestError::UnknownPreset(_))
+        ));
+
+        let bad_config = Participant {
+            name: "x".into(),
+            preset: "ucb1".into(),
+            max_playout_depth: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            participant::<TicTacToe>(&bad_config),
+            Err(ManifestError::Config(_, ConfigError::ZeroPlayoutDepth))
+        ));
+
+        // Margin scoring is applied where the game supports it
+        let margin = Manifest::from_json(
+            r#"{
+                "rounds": 1,
+                "scoring": "margin",
+                "participants": [
+                    { "name": "a", "preset": "ucb1", "max_iterations": 10 },
+                    { "name": "b", "preset": "ucb1", "max_iterations": 10 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            run::<TicTacToe>("ttt", &margin, &Default::default(), Verbosity::Silent),
+            Err(ManifestError::UnsupportedScoring(game, Scoring::Margin)) if game == "ttt"
+        ));
+        let results =
+            run::<Gonnect<4>>("gonnect", &margin, &Default::default(), Verbosity::Silent).unwrap();
+        assert_eq!(results.scoring, Scoring::Margin);
+    }
+}
@@ -0,0 +1,368 @@
+//! Hyperparameter tuning by simultaneous perturbation stochastic
+//! approximation (SPSA), without the external toolchain `demo/hyper.rs`
+//! relies on.
+//!
+//! The tuned values are a vector of `Param`, each with a range. Every
+//! iteration perturbs all of them at once in a random direction, plays pairs
+//! of games between the two perturbed configurations, and moves the values
+//! along the direction in proportion to the score. As in Fishtest's tuner,
+//! the configurations play each other, so no baseline is needed.
+//!
+//! ```ignore
+//! let knobs = [Knob::ExplorationConstant, Knob::Epsilon, Knob::RaveThreshold];
+//! let result = Spsa::new().iterations(200).run::<G, _>(
+//!     &Knob::params(&knobs),
+//!     |values, seed| {
+//!         let config = Knob::config::<G>(&knobs, values).max_iterations(1000);
+//!         AnySearch::new(TreeSearch::new().config(config.seed(seed)))
+//!     },
+//!     &init,
+//!     Verbosity::Verbose,
+//! );
+//! println!("{result}");
+//! ```
+//!
+//! `Knob` maps the usual knobs onto the `RaveMastDm` preset. Any other
+//! configuration can be tuned by building it from the values in the closure.
+
+use crate::game::Game;
+use crate::sprt::{play, random_opening};
+use crate::strategies::mcts::node::QInit;
+use crate::strategies::mcts::select::{RaveSchedule, RaveUcb};
+use crate::strategies::mcts::{simulate, strategy, SearchConfig};
+use crate::strategies::Search;
+use crate::util::{AnySearch, Verbosity};
+
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand_core::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A tuned value and the range it is kept in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: String,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Param {
+    pub fn new(name: &str, value: f64, min: f64, max: f64) -> Self {
+        assert!(min < max, "empty range for {name}");
+        Self {
+            name: name.into(),
+            value: value.clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        (value - self.min) / (self.max - self.min)
+    }
+
+    fn denormalize(&self, x: f64) -> f64 {
+        self.min + x.clamp(0., 1.) * (self.max - self.min)
+    }
+}
+
+/// The knobs of the `RaveMastDm` preset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Knob {
+    // The UCB1 exploration constant of RAVE
+    ExplorationConstant,
+    // Of the epsilon greedy playouts
+    Epsilon,
+    // The GRAVE threshold, rounded to an integer
+    RaveThreshold,
+    // The bias of the minimum MSE RAVE schedule
+    RaveBias,
+    // The reduction of a `QInit::ParentMinus` first play urgency
+    FpuReduction,
+}
+
+impl Knob {
+    /// The knob with its usual starting value and range.
+    pub fn param(&self) -> Param {
+        match self {
+            Knob::ExplorationConstant => Param::new("exploration_constant", 1., 0., 3.),
+            Knob::Epsilon => Param::new("epsilon", 0.1, 0., 1.),
+            Knob::RaveThreshold => Param::new("rave_threshold", 700., 0., 2000.),
+            Knob::RaveBias => Param::new("rave_bias", 0.1, 0., 1.),
+            Knob::FpuReduction => Param::new("fpu_reduction", 0.2, 0., 1.),
+        }
+    }
+
+    pub fn params(knobs: &[Knob]) -> Vec<Param> {
+        knobs.iter().map(Knob::param).collect()
+    }
+
+    /// Set the knob to `value`.
+    pub fn apply<G: Game>(
+        &self,
+        mut config: SearchConfig<G, strategy::RaveMastDm>,
+        value: f64,
+    ) -> SearchConfig<G, strategy::RaveMastDm> {
+        match self {
+            Knob::ExplorationConstant => {
+                config.select.ucb = RaveUcb::Ucb1 {
+                    exploration_constant: value,
+                }
+            }
+            Knob::Epsilon => {
+                config.simulate = simulate::DecisiveMove::new()
+                    .inner(simulate::EpsilonGreedy::with_epsilon(value))
+            }
+            Knob::RaveThreshold => config.select.threshold = value.round() as u32,
+            Knob::RaveBias => config.select.schedule = RaveSchedule::MinMSE { bias: value },
+            Knob::FpuReduction => config.q_init = QInit::ParentMinus(value),
+        }
+        config
+    }
+
+    /// The default `RaveMastDm` configuration with each knob set to its value.
+    pub fn config<G: Game>(
+        knobs: &[Knob],
+        values: &[f64],
+    ) -> SearchConfig<G, strategy::RaveMastDm> {
+        knobs
+            .iter()
+            .zip(values)
+            .fold(SearchConfig::new(), |config, (knob, value)| {
+                knob.apply(config, *value)
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Spsa {
+    pub iterations: usize,
+    // Game pairs played between the perturbed configurations per iteration
+    pub pairs: usize,
+    // The step size and perturbation, as fractions of each range. Both decay
+    // with the iterations, by the exponents `alpha` and `gamma`.
+    pub a: f64,
+    pub c: f64,
+    pub alpha: f64,
+    pub gamma: f64,
+    // Random moves from the initial state to start each pair from
+    pub opening_plies: usize,
+    pub seed: u64,
+}
+
+impl Default for Spsa {
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            pairs: 4,
+            a: 0.05,
+            c: 0.1,
+            alpha: 0.602,
+            gamma: 0.101,
+            opening_plies: 0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TuneResult {
+    // The tuned values
+    pub params: Vec<Param>,
+    // The values after each iteration
+    pub history: Vec<Vec<f64>>,
+}
+
+impl TuneResult {
+    pub fn values(&self) -> Vec<f64> {
+        self.params.iter().map(|param| param.value).collect()
+    }
+}
+
+impl fmt::Display for TuneResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for param in &self.params {
+            writeln!(f, "{:<25} {:.6}", param.name, param.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Spsa {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn pairs(mut self, pairs: usize) -> Self {
+        self.pairs = pairs;
+        self
+    }
+
+    pub fn a(mut self, a: f64) -> Self {
+        self.a = a;
+        self
+    }
+
+    pub fn c(mut self, c: f64) -> Self {
+        self.c = c;
+        self
+    }
+
+    pub fn opening_plies(mut self, opening_plies: usize) -> Self {
+        self.opening_plies = opening_plies;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Tune `params` by self-play from `init`, with `make` building a search
+    /// from the values and a seed.
+    pub fn run<'a, G, F>(
+        &self,
+        params: &[Param],
+        make: F,
+        init: &G::S,
+        verbose: Verbosity,
+    ) -> TuneResult
+    where
+        G: Game + Clone,
+        F: Fn(&[f64], u64) -> AnySearch<'a, G>,
+    {
+        assert!(self.pairs > 0, "SPSA needs games to score an iteration");
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let mut x = params
+            .iter()
+            .map(|param| param.normalize(param.value))
+            .collect::<Vec<_>>();
+        let values = |x: &[f64]| {
+            params
+                .iter()
+                .zip(x)
+                .map(|(param, x)| param.denormalize(*x))
+                .collect::<Vec<_>>()
+        };
+        // The usual stability constant, a tenth of the iterations
+        let stability = self.iterations as f64 / 10.;
+
+        let mut history = Vec::new();
+        for k in 0..self.iterations {
+            let a_k = self.a / (k as f64 + 1. + stability).powf(self.alpha);
+            let c_k = self.c / (k as f64 + 1.).powf(self.gamma);
+            let delta = x
+                .iter()
+                .map(|_| if rng.gen::<bool>() { 1. } else { -1. })
+                .collect::<Vec<f64>>();
+            let perturb = |sign: f64| {
+                x.iter()
+                    .zip(&delta)
+                    .map(|(x, delta)| x + sign * c_k * delta)
+                    .collect::<Vec<_>>()
+            };
+            let mut plus = make(&values(&perturb(1.)), rng.gen());
+            let mut minus = make(&values(&perturb(-1.)), rng.gen());
+            plus.prepare(init);
+            minus.prepare(init);
+
+            // The mean result of `plus`, from -1 to 1
+            let mut score = 0.;
+            for _ in 0..self.pairs {
+                let opening = random_opening::<G>(init, self.opening_plies, &mut rng);
+                for plus_seat in 0..2 {
+                    let mut players = [plus.clone(), minus.clone()];
+                    players.rotate_left(plus_seat);
+                    score += match play::<G>(&mut players, opening.clone()) {
+                        None => 0.,
+                        Some(seat) if seat == plus_seat => 1.,
+                        Some(_) => -1.,
+                    };
+                }
+            }
+            score /= 2. * self.pairs as f64;
+
+            for (x, delta) in x.iter_mut().zip(&delta) {
+                *x = (*x + a_k * score / (2. * c_k * delta)).clamp(0., 1.);
+            }
+            history.push(values(&x));
+            if verbose.verbose() {
+                let current = history.last().unwrap();
+                println!("iteration {k:>5}: score {score:+.3} values {current:.4?}");
+            }
+        }
+
+        TuneResult {
+            params: params
+                .iter()
+                .zip(values(&x))
+                .map(|(param, value)| Param {
+                    value,
+                    ..param.clone()
+                })
+                .collect(),
+            history,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::ttt::TicTacToe;
+    use crate::strategies::mcts::TreeSearch;
+
+    #[test]
+    fn test_knobs() {
+        let knobs = [
+            Knob::ExplorationConstant,
+            Knob::Epsilon,
+            Knob::RaveThreshold,
+            Knob::RaveBias,
+            Knob::FpuReduction,
+        ];
+        let config = Knob::config::<TicTacToe>(&knobs, &[0.5, 0.25, 99.6, 0.01, 0.3]);
+        assert!(matches!(
+            config.select.ucb,
+            RaveUcb::Ucb1 { exploration_constant } if exploration_constant == 0.5
+        ));
+        assert_eq!(config.select.threshold, 100);
+        assert!(matches!(config.select.schedule, RaveSchedule::MinMSE { bias } if bias == 0.01));
+        assert_eq!(config.q_init, QInit::ParentMinus(0.3));
+        assert!(config.validate().is_ok());
+
+        let param = Knob::Epsilon.param();
+        assert_eq!(param.denormalize(param.normalize(0.3)), 0.3);
+        assert_eq!(param.denormalize(2.), param.max);
+    }
+
+    #[test]
+    fn test_spsa() {
+        // More iterations are stronger, so tuning a budget should raise it
+        let params = [Param::new("budget", 0.5, 0., 1.)];
+        let make = |values: &[f64], seed| {
+            let iterations = 1 + (200. * values[0]) as usize;
+            AnySearch::new(
+                TreeSearch::<TicTacToe, strategy::Ucb1>::new()
+                    .config(SearchConfig::new().max_iterations(iterations).seed(seed)),
+            )
+        };
+        let result = Spsa::new()
+            .iterations(20)
+            .pairs(2)
+            .opening_plies(1)
+            .c(0.4)
+            .run::<TicTacToe, _>(&params, make, &Default::default(), Verbosity::Silent);
+        assert_eq!(result.history.len(), 20);
+        assert_eq!(result.params[0].name, "budget");
+        assert!(result.values()[0] > 0.5, "{result}");
+        assert!(result.values()[0] <= 1.);
+    }
+}
@@ -1,104 +1,25 @@
 use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use rand::Rng;
 
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::rngs::SmallRng;
+use rand_core::SeedableRng;
 
-use crate::game::{Game, PlayerIndex};
+use crate::display::{DiffStyle, StateDiff};
+use crate::game::{Game, PlayerId};
 use crate::strategies;
 
 use crate::strategies::random::Random;
-use crate::strategies::Search;
+use crate::strategies::{Search, SearchMetrics, Verdict};
 use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
 use std::ops::Add;
 use std::ops::AddAssign;
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-pub struct Pairs<'a, T: 'a> {
-    stack: &'a [T],
-    index: usize,
-}
-
-impl<'a, T> Pairs<'a, T> {
-    pub fn new(stack: &'a [T]) -> Self {
-        Self { stack, index: 0 }
-    }
-}
-
-impl<'a, T> Iterator for Pairs<'a, T> {
-    type Item = (&'a T, &'a T);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index < (self.stack.len() - 1) {
-            let result = Some((&self.stack[self.index], &self.stack[self.index + 1]));
-            self.index += 1;
-            result
-        } else {
-            None
-        }
-    }
-}
-
-pub struct ReversePairs<'a, T: 'a> {
-    stack: &'a [T],
-    index: usize,
-}
-
-impl<'a, T> ReversePairs<'a, T> {
-    pub fn new(stack: &'a [T]) -> Self {
-        Self {
-            stack,
-            index: stack.len(),
-        }
-    }
-}
-
-impl<'a, T> Iterator for ReversePairs<'a, T> {
-    type Item = (&'a T, &'a T);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= 2 {
-            self.index -= 1;
-            Some((&self.stack[self.index - 1], &self.stack[self.index]))
-        } else {
-            None
-        }
-    }
-}
-
-pub struct ReversePairs2<'a, T: 'a> {
-    stack: &'a [T],
-    index: usize,
-}
-
-impl<'a, T> ReversePairs2<'a, T> {
-    pub fn new(stack: &'a [T]) -> Self {
-        Self {
-            stack,
-            index: stack.len(),
-        }
-    }
-}
-
-impl<'a, T> Iterator for ReversePairs2<'a, T> {
-    type Item = (Option<&'a T>, &'a T);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index > 0 {
-            let first = if self.index > 1 {
-                Some(&self.stack[self.index - 2])
-            } else {
-                None
-            };
-            self.index -= 1;
-            Some((first, &self.stack[self.index]))
-        } else {
-            None
-        }
-    }
-}
-
 #[derive(Clone)]
 pub struct AnySearch<'a, G: Game + Clone>(pub Arc<Mutex<Box<dyn strategies::Search<G = G> + 'a>>>);
 
@@ -122,6 +43,17 @@ impl<'a, G: Game + Clone> strategies::Search for AnySearch<'a, G> {
         self.0.lock().unwrap().choose_action(state)
     }
 
+    fn prepare(&mut self, init: &<Self::G as Game>::S) {
+        self.0.lock().unwrap().prepare(init)
+    }
+
+    fn choose_action_with_verdict(
+        &mut self,
+        state: &<Self::G as Game>::S,
+    ) -> (<Self::G as Game>::A, Option<Verdict>) {
+        self.0.lock().unwrap().choose_action_with_verdict(state)
+    }
+
     fn estimated_depth(&self) -> usize {
         self.0.lock().unwrap().estimated_depth()
     }
@@ -167,11 +99,93 @@ pub(super) fn random_best<'a, T, F: Fn(&T) -> f64>(
     best
 }
 
+/// Search statistics for one player accumulated over a game, or over many
+/// games by adding them together.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameStats {
+    // Moves made by a search which reports `Search::search_metrics`
+    pub moves: usize,
+    pub total_nodes: usize,
+    // The sum of the average playout depth of each search
+    pub total_depth: f64,
+    pub total_ms: f64,
+    // The change in the root value between consecutive moves of the player
+    pub total_swing: f64,
+    pub max_swing: f64,
+    #[serde(skip)]
+    last_value: Option<f64>,
+}
+
+impl GameStats {
+    /// Add the metrics of the player's next move.
+    pub fn record(&mut self, metrics: &SearchMetrics) {
+        self.moves += 1;
+        self.total_nodes += metrics.nodes;
+        self.total_depth += metrics.average_depth;
+        self.total_ms += metrics.elapsed.as_secs_f64() * 1000.;
+        if let Some(last) = self.last_value {
+            let swing = (metrics.value - last).abs();
+            self.total_swing += swing;
+            self.max_swing = self.max_swing.max(swing);
+        }
+        self.last_value = Some(metrics.value);
+    }
+
+    pub fn nodes_per_move(&self) -> f64 {
+        self.total_nodes as f64 / self.moves.max(1) as f64
+    }
+
+    pub fn average_depth(&self) -> f64 {
+        self.total_depth / self.moves.max(1) as f64
+    }
+
+    pub fn ms_per_move(&self) -> f64 {
+        self.total_ms / self.moves.max(1) as f64
+    }
+
+    pub fn average_swing(&self) -> f64 {
+        self.total_swing / self.moves.saturating_sub(1).max(1) as f64
+    }
+}
+
+impl AddAssign for GameStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.moves += rhs.moves;
+        self.total_nodes += rhs.total_nodes;
+        self.total_depth += rhs.total_depth;
+        self.total_ms += rhs.total_ms;
+        self.total_swing += rhs.total_swing;
+        self.max_swing = self.max_swing.max(rhs.max_swing);
+        // Swings are not measured across games
+        self.last_value = None;
+    }
+}
+
+impl Add for GameStats {
+    type Output = Self;
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
 /// Play a complete, new game with players using the two provided strategies.
 ///
 /// Returns `None` if the game ends in a draw, or `Some(0)`, `Some(1)` if the
 /// first or second strategy won, respectively.
 pub fn battle_royale<G, S1, S2>(s1: &mut S1, s2: &mut S2) -> Option<usize>
+where
+    G: Game,
+    G::S: Default + Clone,
+    S1: strategies::Search<G = G>,
+    S2: strategies::Search<G = G>,
+{
+    battle_royale_stats(s1, s2).0
+}
+
+/// As `battle_royale`, also returning the search statistics of each strategy
+/// over the game.
+pub fn battle_royale_stats<G, S1, S2>(s1: &mut S1, s2: &mut S2) -> (Option<usize>, [GameStats; 2])
 where
     G: Game,
     G::S: Default + Clone,
@@ -180,23 +194,58 @@ where
 {
     let mut state = G::S::default();
     let mut strategies: [&mut dyn strategies::Search<G = G>; 2] = [s1, s2];
-    let mut s = 0;
+    strategies.iter_mut().for_each(|s| s.prepare(&state));
+    let mut stats = [GameStats::default(); 2];
+    let mut s = PlayerId(0);
     loop {
         if G::is_terminal(&state) {
-            let current_player = G::player_to_move(&state);
-            let winner = G::winner(&state);
-            return winner.map(|p| {
-                if current_player.to_index() == p.to_index() {
-                    s
+            let current_player = G::player_id(&state);
+            let winner = G::winner_id(&state).map(|p| {
+                if current_player == p {
+                    s.index()
                 } else {
-                    1 - s
+                    s.opponent().index()
                 }
             });
+            return (winner, stats);
         }
-        let strategy = &mut strategies[s];
+        let strategy = &mut strategies[s.index()];
         let m = strategy.choose_action(&state);
+        if let Some(metrics) = strategy.search_metrics() {
+            stats[s.index()].record(&metrics);
+        }
         state = G::apply(state, &m);
-        s = 1 - s;
+        s = s.opponent();
+    }
+}
+
+/// Wins, losses and draws from one seat in the turn order.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatResult {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl SeatResult {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+}
+
+impl AddAssign for SeatResult {
+    fn add_assign(&mut self, rhs: Self) {
+        self.wins += rhs.wins;
+        self.losses += rhs.losses;
+        self.draws += rhs.draws;
+    }
+}
+
+impl Add for SeatResult {
+    type Output = Self;
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
     }
 }
 
@@ -205,6 +254,11 @@ pub struct Result {
     pub wins: usize,
     pub losses: usize,
     pub draws: usize,
+    // Wins and losses decided by resignation or a win claim rather than play
+    pub adjudicated: usize,
+    pub stats: GameStats,
+    // The same results split by whether the strategy moved first or second
+    pub seats: [SeatResult; 2],
 }
 
 impl Add for Result {
@@ -214,6 +268,9 @@ impl Add for Result {
             wins: self.wins + rhs.wins,
             losses: self.losses + rhs.losses,
             draws: self.draws + rhs.draws,
+            adjudicated: self.adjudicated + rhs.adjudicated,
+            stats: self.stats + rhs.stats,
+            seats: [self.seats[0] + rhs.seats[0], self.seats[1] + rhs.seats[1]],
         }
     }
 }
@@ -223,6 +280,10 @@ impl AddAssign for Result {
         self.wins += rhs.wins;
         self.losses += rhs.losses;
         self.draws += rhs.draws;
+        self.adjudicated += rhs.adjudicated;
+        self.stats += rhs.stats;
+        self.seats[0] += rhs.seats[0];
+        self.seats[1] += rhs.seats[1];
     }
 }
 
@@ -245,14 +306,34 @@ where
 {
     let mut i = 0;
     let mut state = G::S::default();
+    let mut stats = vec![GameStats::default(); G::num_players()];
+    let style = DiffStyle::from_env();
     println!("[{i}] state:\n{state}");
     while !G::is_terminal(&state) {
         let action = search.choose_action(&state);
+        if let Some(metrics) = search.search_metrics() {
+            stats[G::player_id(&state).index()].record(&metrics);
+        }
+        let notation = G::notation(&state, &action);
+        let previous = state.clone();
         state = G::apply(state, &action);
         i += 1;
-        println!("[{i}] state:\n{state}");
+        println!(
+            "[{i}] {notation}:\n{}",
+            StateDiff::new(Some(&previous), &state, style)
+        );
     }
     println!("winner: {:?}", G::winner(&state));
+    for (player, stats) in stats.iter().enumerate().filter(|(_, s)| s.moves > 0) {
+        println!(
+            "player {player}: {:.0} nodes/move, {:.1} ms/move, depth {:.1}, swing {:.2} (max {:.2})",
+            stats.nodes_per_move(),
+            stats.ms_per_move(),
+            stats.average_depth(),
+            stats.average_swing(),
+            stats.max_swing,
+        );
+    }
 }
 
 pub fn random_play<G: Game>()
@@ -263,24 +344,645 @@ where
     self_play(Random::<G>::new())
 }
 
-/// Play a round-robin tournament with the provided strategies.
-fn round_robin<G>(
+/// Play `games` random games and check that the game keeps the invariants
+/// the search relies on, panicking with a description of the first violation:
+/// actions are generated exactly when the state is not terminal, utilities
+/// have an entry per player, equal states hash alike, and dense action
+/// indices round trip. With `notation`, `parse_action` must also invert
+/// `notation`.
+pub fn check_conformance<G: Game>(games: usize, seed: u64, notation: bool)
+where
+    G::S: std::fmt::Display,
+{
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut actions = Vec::new();
+    for _ in 0..games {
+        let mut state = G::S::default();
+        let mut plies = 0;
+        loop {
+            actions.clear();
+            G::generate_actions(&state, &mut actions);
+            let terminal = G::is_terminal(&state);
+            assert_eq!(
+                terminal,
+                actions.is_empty(),
+                "is_terminal disagrees with generate_actions after {plies} plies:\n{state}"
+            );
+            assert_eq!(
+                G::zobrist_hash(&state),
+                G::zobrist_hash(&state.clone()),
+                "zobrist_hash differs for equal states:\n{state}"
+            );
+            if terminal {
+                break;
+            }
+            for action in &actions {
+                if G::max_actions() > 0 {
+                    let index = G::action_index(&state, action);
+                    assert!(
+                        index < G::max_actions(),
+                        "action index {index} out of range"
+                    );
+                    assert_eq!(
+                        G::action_from_index(&state, index),
+                        *action,
+                        "action_from_index does not invert action_index"
+                    );
+                }
+                if notation {
+                    let text = G::notation(&state, action);
+                    assert_eq!(
+                        G::parse_action(&state, &text).as_ref(),
+                        Some(action),
+                        "parse_action does not invert notation for {text}"
+                    );
+                }
+            }
+            let action = &actions[rng.gen_range(0..actions.len())];
+            state = G::apply(state, action);
+            plies += 1;
+        }
+        assert_eq!(G::compute_utilities(&state).len(), G::num_players());
+    }
+}
+
+/// The rating difference implied by a mean score (wins plus half the draws,
+/// over games played) under the Elo model. Infinite for a score of 0 or 1.
+pub fn elo_difference(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+/// Count the states reached by every sequence of `depth` actions from
+/// `state`, for checking a move generator against known counts. Lines which
+/// reach a terminal state in fewer than `depth` actions are not counted.
+pub fn perft<G: Game>(state: &G::S, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if G::is_terminal(state) {
+        return 0;
+    }
+    let mut actions = Vec::new();
+    G::generate_actions(state, &mut actions);
+    if depth == 1 {
+        return actions.len() as u64;
+    }
+    actions
+        .iter()
+        .map(|action| perft::<G>(&G::apply(state.clone(), action), depth - 1))
+        .sum()
+}
+
+/// `perft` split by the first action, to narrow down where a move generator
+/// disagrees with a reference.
+pub fn perft_divide<G: Game>(state: &G::S, depth: usize) -> Vec<(G::A, u64)> {
+    if depth == 0 || G::is_terminal(state) {
+        return vec![];
+    }
+    let mut actions = Vec::new();
+    G::generate_actions(state, &mut actions);
+    actions
+        .into_iter()
+        .map(|action| {
+            let count = perft::<G>(&G::apply(state.clone(), &action), depth - 1);
+            (action, count)
+        })
+        .collect()
+}
+
+/// The number of distinct states, by `Game::zobrist_hash`, reached by
+/// sequences of `depth` actions from `state`. Each distinct state is expanded
+/// once, so this stays cheap in games with many transpositions. Only
+/// meaningful for games which implement `zobrist_hash`.
+pub fn perft_unique<G: Game>(state: &G::S, depth: usize) -> u64 {
+    let mut frontier = FxHashMap::default();
+    frontier.insert(G::zobrist_hash(state), state.clone());
+    let mut actions = Vec::new();
+    for _ in 0..depth {
+        let mut next = FxHashMap::default();
+        for state in frontier.values().filter(|state| !G::is_terminal(state)) {
+            actions.clear();
+            G::generate_actions(state, &mut actions);
+            for action in &actions {
+                let child = G::apply(state.clone(), action);
+                next.entry(G::zobrist_hash(&child)).or_insert(child);
+            }
+        }
+        frontier = next;
+    }
+    frontier.len() as u64
+}
+
+/// Opening variety for games between deterministic strategies, which would
+/// otherwise play the same game every time. For the first `plies` moves of
+/// each game, the move is sampled from the `top_n` most visited root actions
+/// of the player's search, with probability proportional to
+/// `visits^(1/temperature)`. A temperature of zero always plays the most
+/// visited. Strategies which don't report root visits play uniformly at
+/// random instead.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Variety {
+    pub plies: usize,
+    pub top_n: usize,
+    pub temperature: f64,
+}
+
+impl Default for Variety {
+    fn default() -> Self {
+        Self {
+            plies: 0,
+            top_n: 3,
+            temperature: 1.,
+        }
+    }
+}
+
+impl Variety {
+    pub fn plies(mut self, plies: usize) -> Self {
+        self.plies = plies;
+        self
+    }
+
+    pub fn top_n(mut self, top_n: usize) -> Self {
+        self.top_n = top_n;
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Sample from the most visited actions, or `None` if none were visited.
+    pub fn sample<A: Clone>(&self, visits: &[(A, u32)], rng: &mut SmallRng) -> Option<A> {
+        let mut top = visits.iter().filter(|(_, n)| *n > 0).collect::<Vec<_>>();
+        top.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+        top.truncate(self.top_n.max(1));
+        let (_, most) = top.first()?;
+        if self.temperature <= 0. {
+            return Some(top[0].0.clone());
+        }
+        // Relative to the most visited, so that large counts can't overflow
+        let weights = top
+            .iter()
+            .map(|(_, n)| (*n as f64 / *most as f64).powf(1. / self.temperature));
+        let dist = WeightedIndex::new(weights).ok()?;
+        Some(top[dist.sample(rng)].0.clone())
+    }
+}
+
+/// A game played in a round robin.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub round: usize,
+    // The index of the start position. See `round_robin_openings`.
+    #[serde(default)]
+    pub opening: usize,
+    // Indices of the strategies, in turn order
+    pub players: [usize; 2],
+    // The opening moves chosen for variety, in notation
+    pub forced: Vec<String>,
+    // The index of the winning strategy, or `None` for a draw
+    pub winner: Option<usize>,
+    pub adjudicated: bool,
+    // Search statistics of each player, in turn order
+    pub stats: [GameStats; 2],
+}
+
+/// The first player's results over a set of games, and a check that every
+/// pairing was played equally often with the colors reversed. Any tournament
+/// format should keep its pairings balanced, so that the first-move advantage
+/// of games with strong tempo effects does not favor one strategy.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColorBalance {
+    pub first_wins: usize,
+    pub second_wins: usize,
+    pub draws: usize,
+    // Pairings of strategy indices, in turn order, played more often than the
+    // reverse pairing
+    pub unbalanced: Vec<[usize; 2]>,
+}
+
+impl ColorBalance {
+    pub fn from_records(records: &[GameRecord]) -> Self {
+        let mut balance = Self::default();
+        let mut pairings: FxHashMap<[usize; 2], i64> = FxHashMap::default();
+        for record in records {
+            match record.winner {
+                None => balance.draws += 1,
+                Some(winner) if winner == record.players[0] => balance.first_wins += 1,
+                Some(_) => balance.second_wins += 1,
+            }
+            let [first, second] = record.players;
+            let (key, count) = if first < second {
+                ([first, second], 1)
+            } else {
+                ([second, first], -1)
+            };
+            *pairings.entry(key).or_default() += count;
+        }
+        balance.unbalanced = pairings
+            .into_iter()
+            .filter(|(_, count)| *count != 0)
+            .map(|([a, b], count)| if count > 0 { [a, b] } else { [b, a] })
+            .collect();
+        balance.unbalanced.sort_unstable();
+        balance
+    }
+
+    pub fn games(&self) -> usize {
+        self.first_wins + self.second_wins + self.draws
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.unbalanced.is_empty()
+    }
+
+    /// The first player's mean score (wins plus half the draws, over games
+    /// played) less one half. Positive when moving first is an advantage.
+    pub fn first_move_advantage(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.;
+        }
+        (self.first_wins as f64 + 0.5 * self.draws as f64) / self.games() as f64 - 0.5
+    }
+
+    /// The first-move advantage as a rating difference.
+    pub fn first_move_elo(&self) -> f64 {
+        elo_difference(0.5 + self.first_move_advantage())
+    }
+}
+
+/// One strategy's results against another.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadToHead {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl AddAssign for HeadToHead {
+    fn add_assign(&mut self, rhs: Self) {
+        self.wins += rhs.wins;
+        self.losses += rhs.losses;
+        self.draws += rhs.draws;
+    }
+}
+
+impl Add for HeadToHead {
+    type Output = Self;
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl HeadToHead {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Wins plus half the draws, over games played.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+}
+
+/// Elo ratings fitted to a set of games, with a cross-table of the pairwise
+/// results.
+///
+/// The ratings maximize the likelihood of the results under the Bradley-Terry
+/// model, counting a draw as half a win and half a loss. As in BayesElo, each
+/// pairing which was played gets a virtual draw, so that ratings stay finite
+/// when a strategy wins or loses every game. The confidence intervals are
+/// approximate, from the curvature of the likelihood in each rating alone.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ratings {
+    pub names: Vec<String>,
+    // Ratings averaging zero, and the half width of their 95% confidence
+    // intervals
+    pub elo: Vec<f64>,
+    pub error: Vec<f64>,
+    // The results of each strategy (row) against each other (column)
+    pub cross_table: Vec<Vec<HeadToHead>>,
+}
+
+impl Ratings {
+    const PRIOR_DRAWS: f64 = 1.;
+
+    pub fn from_records(names: Vec<String>, records: &[GameRecord]) -> Self {
+        let n = names.len();
+        let mut cross_table = vec![vec![HeadToHead::default(); n]; n];
+        for record in records {
+            let [a, b] = record.players;
+            match record.winner {
+                None => {
+                    cross_table[a][b].draws += 1;
+                    cross_table[b][a].draws += 1;
+                }
+                Some(winner) => {
+                    let loser = if winner == a { b } else { a };
+                    cross_table[winner][loser].wins += 1;
+                    cross_table[loser][winner].losses += 1;
+                }
+            }
+        }
+
+        // Games and points with the virtual draws
+        let games = |i: usize, j: usize| {
+            let played = cross_table[i][j].games() as f64;
+            if played > 0. {
+                played + Self::PRIOR_DRAWS
+            } else {
+                0.
+            }
+        };
+        let points = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let result = cross_table[i][j];
+                        let prior = if result.games() > 0 {
+                            0.5 * Self::PRIOR_DRAWS
+                        } else {
+                            0.
+                        };
+                        result.wins as f64 + 0.5 * result.draws as f64 + prior
+                    })
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+
+        // Minorization-maximization (Hunter, 2004) of the strengths
+        let mut gamma = vec![1.; n];
+        for _ in 0..1000 {
+            let next = (0..n)
+                .map(|i| {
+                    let denominator = (0..n)
+                        .map(|j| games(i, j) / (gamma[i] + gamma[j]))
+                        .sum::<f64>();
+                    if denominator > 0. {
+                        points[i] / denominator
+                    } else {
+                        1.
+                    }
+                })
+                .collect::<Vec<_>>();
+            let change = next
+                .iter()
+                .zip(&gamma)
+                .map(|(a, b)| (a / b).ln().abs())
+                .fold(0., f64::max);
+            gamma = next;
+            if change < 1e-9 {
+                break;
+            }
+        }
+
+        let scale = 400. / std::f64::consts::LN_10;
+        let mut elo = gamma.iter().map(|g| scale * g.ln()).collect::<Vec<_>>();
+        let mean = elo.iter().sum::<f64>() / n.max(1) as f64;
+        elo.iter_mut().for_each(|r| *r -= mean);
+        let error = (0..n)
+            .map(|i| {
+                let information = (0..n)
+                    .map(|j| {
+                        let p = gamma[i] / (gamma[i] + gamma[j]);
+                        games(i, j) * p * (1. - p)
+                    })
+                    .sum::<f64>();
+                1.96 * scale / information.sqrt()
+            })
+            .collect();
+
+        Self {
+            names,
+            elo,
+            error,
+            cross_table,
+        }
+    }
+
+    /// Strategy indices from the highest rated.
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking = (0..self.elo.len()).collect::<Vec<_>>();
+        ranking.sort_by(|a, b| self.elo[*b].total_cmp(&self.elo[*a]));
+        ranking
+    }
+}
+
+impl std::fmt::Display for Ratings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranking = self.ranking();
+        write!(
+            f,
+            "{:>4} {:<25} {:>6} {:>6} {:>6} {:>6} |",
+            "rank", "name", "elo", "+/-", "games", "score"
+        )?;
+        for rank in 1..=ranking.len() {
+            write!(f, " {rank:^9}")?;
+        }
+        writeln!(f)?;
+        for (rank, i) in (1..).zip(&ranking) {
+            let total = self.cross_table[*i]
+                .iter()
+                .fold(HeadToHead::default(), |acc, r| acc + *r);
+            write!(
+                f,
+                "{rank:>4} {:<25} {:>6.0} {:>6.0} {:>6} {:>5.1}% |",
+                self.names[*i],
+                self.elo[*i],
+                self.error[*i],
+                total.games(),
+                100. * total.score(),
+            )?;
+            // Wins, losses and draws against each opponent, in rank order
+            for j in &ranking {
+                let result = self.cross_table[*i][*j];
+                if i == j {
+                    write!(f, " {:^9}", "-")?;
+                } else {
+                    let cell = format!("{}-{}-{}", result.wins, result.losses, result.draws);
+                    write!(f, " {cell:^9}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Start positions for a tournament, reached by `plies` moves of the
+/// `Random` strategy from the initial state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RandomOpenings {
+    pub plies: usize,
+    pub count: usize,
+}
+
+impl RandomOpenings {
+    /// Up to `count` distinct openings, each with the moves leading to it in
+    /// notation. Fewer are returned if the game has too few lines of `plies`
+    /// moves.
+    pub fn generate<G: Game>(&self, init: &G::S, seed: u64) -> Vec<(G::S, Vec<String>)> {
+        let mut random = Random::<G>::new().seed(seed);
+        let mut seen = FxHashSet::default();
+        let mut openings = Vec::new();
+        // Give up after some duplicates in a row
+        let mut attempts = 0;
+        while openings.len() < self.count && attempts < 100 {
+            let mut state = init.clone();
+            let mut moves = Vec::new();
+            for _ in 0..self.plies {
+                if G::is_terminal(&state) {
+                    break;
+                }
+                let action = random.choose_action(&state);
+                moves.push(G::notation(&state, &action));
+                state = G::apply(state, &action);
+            }
+            if seen.insert(G::zobrist_hash(&state)) {
+                openings.push((state, moves));
+                attempts = 0;
+            } else {
+                attempts += 1;
+            }
+        }
+        openings
+    }
+}
+
+/// The results of a tournament from one start position.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpeningResult {
+    // The moves leading to the opening, if known
+    pub moves: Vec<String>,
+    // The results of each strategy, by index
+    pub results: Vec<HeadToHead>,
+    pub color_balance: ColorBalance,
+}
+
+impl OpeningResult {
+    /// Split the games of a tournament between `num_strategies` strategies by
+    /// opening.
+    pub fn from_records(
+        records: &[GameRecord],
+        num_openings: usize,
+        num_strategies: usize,
+    ) -> Vec<Self> {
+        (0..num_openings)
+            .map(|opening| {
+                let games = records
+                    .iter()
+                    .filter(|record| record.opening == opening)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let mut results = vec![HeadToHead::default(); num_strategies];
+                for game in &games {
+                    for player in game.players {
+                        match game.winner {
+                            None => results[player].draws += 1,
+                            Some(winner) if winner == player => results[player].wins += 1,
+                            Some(_) => results[player].losses += 1,
+                        }
+                    }
+                }
+                Self {
+                    moves: Vec::new(),
+                    results,
+                    color_balance: ColorBalance::from_records(&games),
+                }
+            })
+            .collect()
+    }
+}
+
+/// How the strategies of a tournament are paired. Each pairing is played
+/// from every opening with both colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    // Every strategy plays every other each round
+    #[default]
+    RoundRobin,
+    // The first strategy plays each of the others each round
+    Gauntlet,
+    // Each round pairs strategies with similar standings which have not met
+    // yet, if possible. With an odd number of strategies, the lowest ranked
+    // one without a bye sits the round out.
+    Swiss,
+}
+
+impl Format {
+    // The pairings of a round, in the order they are played, and the strategy
+    // given a bye, if any. `points` are the Swiss standings, `met` the
+    // pairings played so far and `byes` the strategies which have had a bye.
+    fn pairings(
+        &self,
+        num_strategies: usize,
+        points: &[f64],
+        met: &FxHashSet<[usize; 2]>,
+        byes: &[bool],
+    ) -> (Vec<(usize, usize)>, Option<usize>) {
+        match self {
+            Format::RoundRobin => (
+                (0..num_strategies)
+                    .flat_map(|i| (0..num_strategies).map(move |j| (i, j)))
+                    .filter(|(i, j)| i != j)
+                    .collect(),
+                None,
+            ),
+            Format::Gauntlet => (
+                (1..num_strategies).flat_map(|j| [(0, j), (j, 0)]).collect(),
+                None,
+            ),
+            Format::Swiss => {
+                let mut order = (0..num_strategies).collect::<Vec<_>>();
+                order.sort_by(|a, b| points[*b].total_cmp(&points[*a]).then(a.cmp(b)));
+                let mut bye = None;
+                if order.len() % 2 == 1 {
+                    let index = order
+                        .iter()
+                        .rposition(|i| !byes[*i])
+                        .unwrap_or(order.len() - 1);
+                    bye = Some(order.remove(index));
+                }
+                let mut pairings = Vec::new();
+                while let Some(i) = (!order.is_empty()).then(|| order.remove(0)) {
+                    let j = order
+                        .iter()
+                        .position(|j| !met.contains(&[i.min(*j), i.max(*j)]))
+                        .unwrap_or(0);
+                    let j = order.remove(j);
+                    pairings.extend([(i, j), (j, i)]);
+                }
+                (pairings, bye)
+            }
+        }
+    }
+}
+
+/// Play one round of a tournament: each of the `pairings` from each opening.
+fn play_round<G>(
     strategies: &mut [AnySearch<'_, G>],
-    init: &G::S,
+    pairings: &[(usize, usize)],
+    openings: &[G::S],
     verbose: Verbosity,
-) -> Vec<Result>
+    round: usize,
+    variety: &[Variety],
+    seed: u64,
+) -> (Vec<Result>, Vec<GameRecord>)
 where
     G: Game + Clone,
     G::S: Sync,
 {
     let mut pairs = Vec::new();
-    for i in 0..strategies.len() {
-        for j in 0..strategies.len() {
-            if i != j {
-                pairs.push((i, j));
-            }
+    for opening in 0..openings.len() {
+        for (i, j) in pairings {
+            pairs.push((opening, *i, *j));
         }
     }
+    let num_pairs = pairs.len();
 
     let mp = if verbose.verbose() {
         MultiProgress::new()
@@ -303,9 +1005,10 @@ where
 
     let counter: AtomicU32 = AtomicU32::new(0);
 
-    let results = pairs
+    let (results, mut records) = pairs
         .into_par_iter()
-        .map(|(i, j)| {
+        .enumerate()
+        .map(|(game, (opening, i, j))| {
             counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
 
             let mut results = vec![Result::default(); strategies.len()];
@@ -317,53 +1020,126 @@ where
             let vs_str = format!("{:>25} | {:<25}", si.friendly_name(), sj.friendly_name());
             pb.set_message(format!("{:^53}", vs_str));
 
-            let mut strat = [si, sj];
+            let strat = [si, sj];
             let players = [i, j];
+            let mut rng =
+                SmallRng::seed_from_u64(seed.wrapping_add((round * num_pairs + game) as u64));
+            let mut forced = Vec::new();
+            let mut stats = [GameStats::default(); 2];
             let mut current;
             let mut depth = 0;
-            let mut state = init.clone();
+            let mut state = openings[opening].clone();
+            let mut adjudicated = None;
             loop {
-                current = G::player_to_move(&state).to_index();
+                current = G::player_id(&state);
                 if G::is_terminal(&state) {
                     break;
                 }
 
-                let action = strat[current].choose_action(&state);
-                pb.set_length(depth + strat[current].estimated_depth() as u64);
+                let variety = variety
+                    .get(players[current.index()])
+                    .copied()
+                    .unwrap_or_default();
+                let (action, verdict) = {
+                    // Hold the lock so that the root visits are from this search
+                    let mut search = strat[current.index()].0.lock().unwrap();
+                    let (action, verdict) = search.choose_action_with_verdict(&state);
+                    if let Some(metrics) = search.search_metrics() {
+                        stats[current.index()].record(&metrics);
+                    }
+                    if depth < variety.plies as u64 {
+                        let sampled = variety.sample(&search.root_visits(), &mut rng);
+                        (
+                            sampled.unwrap_or_else(|| random_action::<G>(&state, &mut rng)),
+                            None,
+                        )
+                    } else {
+                        (action, verdict)
+                    }
+                };
+                match verdict {
+                    Some(Verdict::Resign) => adjudicated = Some(current.opponent()),
+                    Some(Verdict::ClaimWin) => adjudicated = Some(current),
+                    None => {}
+                }
+                if adjudicated.is_some() {
+                    break;
+                }
+                if depth < variety.plies as u64 {
+                    forced.push(G::notation(&state, &action));
+                }
+                pb.set_length(depth + strat[current.index()].estimated_depth() as u64);
                 state = G::apply(state, &action);
                 pb.inc(1);
                 depth += 1;
             }
 
-            match G::winner(&state) {
+            let winner = adjudicated.or_else(|| G::winner_id(&state));
+            results[i].stats += stats[0];
+            results[j].stats += stats[1];
+            for (seat, player) in players.iter().enumerate() {
+                let result = &mut results[*player].seats[seat];
+                match winner {
+                    None => result.draws += 1,
+                    Some(p) if p.index() == seat => result.wins += 1,
+                    Some(_) => result.losses += 1,
+                }
+            }
+            match winner {
                 None => {
                     results[i].draws += 1;
                     results[j].draws += 1;
                 }
                 Some(p) => {
-                    let winner = players[p.to_index()];
-                    let loser = players[1 - p.to_index()];
+                    let winner = players[p.index()];
+                    let loser = players[p.opponent().index()];
 
                     results[winner].wins += 1;
                     results[loser].losses += 1;
+                    if adjudicated.is_some() {
+                        results[winner].adjudicated += 1;
+                        results[loser].adjudicated += 1;
+                    }
                 }
             }
+            let record = GameRecord {
+                round,
+                opening,
+                players,
+                forced,
+                winner: winner.map(|p| players[p.index()]),
+                adjudicated: adjudicated.is_some(),
+                stats,
+            };
             pb.finish();
             mp.remove(&pb);
             pb_overall.inc(1);
             counter.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-            results
+            (results, vec![(game, record)])
         })
-        .reduce_with(|acc, x| {
-            acc.into_iter()
+        .reduce_with(|(acc, mut acc_records), (x, records)| {
+            acc_records.extend(records);
+            let acc = acc
+                .into_iter()
                 .zip(x.iter())
                 .map(|(r1, r2)| r1 + *r2)
-                .collect()
+                .collect();
+            (acc, acc_records)
         })
-        .unwrap_or_else(|| panic!());
+        .unwrap_or_else(|| (vec![Result::default(); strategies.len()], vec![]));
 
     assert_eq!(counter.load(std::sync::atomic::Ordering::SeqCst), 0);
-    results
+    records.sort_by_key(|(game, _)| *game);
+    (
+        results,
+        records.into_iter().map(|(_, record)| record).collect(),
+    )
+}
+
+fn random_action<G: Game>(state: &G::S, rng: &mut SmallRng) -> G::A {
+    let mut actions = Vec::new();
+    G::generate_actions(state, &mut actions);
+    actions[rng.gen_range(0..actions.len())].clone()
 }
 
 /// Play a round-robin tournament multiple times with the provided strategies.
@@ -377,21 +1153,122 @@ where
     G: Game + Clone,
     S: strategies::Search<G = G>,
 {
+    round_robin_records::<G>(strategies, rounds, init, verbose, &[], 0).0
+}
+
+/// Play a round-robin tournament multiple times, with opening variety for
+/// each strategy (see `Variety`), and return a record of every game along
+/// with the results. Strategies without an entry in `variety` play without
+/// it. The moves sampled for variety are reproducible for a given `seed`.
+pub fn round_robin_records<G>(
+    strategies: &mut [AnySearch<'_, G>],
+    rounds: usize,
+    init: &G::S,
+    verbose: Verbosity,
+    variety: &[Variety],
+    seed: u64,
+) -> (Vec<Result>, Vec<GameRecord>)
+where
+    G: Game + Clone,
+{
+    round_robin_openings::<G>(
+        strategies,
+        rounds,
+        std::slice::from_ref(init),
+        verbose,
+        variety,
+        seed,
+    )
+}
+
+/// As `round_robin_records`, playing every pairing from each of `openings`
+/// in every round, so that the results are not dominated by a single line.
+/// Each game records the index of its opening; see `OpeningResult` for the
+/// results by opening.
+pub fn round_robin_openings<G>(
+    strategies: &mut [AnySearch<'_, G>],
+    rounds: usize,
+    openings: &[G::S],
+    verbose: Verbosity,
+    variety: &[Variety],
+    seed: u64,
+) -> (Vec<Result>, Vec<GameRecord>)
+where
+    G: Game + Clone,
+{
+    tournament_records::<G>(
+        strategies,
+        Format::RoundRobin,
+        rounds,
+        openings,
+        verbose,
+        variety,
+        seed,
+    )
+}
+
+/// As `round_robin_openings`, with the strategies paired by `format`. The
+/// games of a round are played in parallel on the current rayon thread pool,
+/// so install a smaller pool to limit how many are played at once.
+pub fn tournament_records<G>(
+    strategies: &mut [AnySearch<'_, G>],
+    format: Format,
+    rounds: usize,
+    openings: &[G::S],
+    verbose: Verbosity,
+    variety: &[Variety],
+    seed: u64,
+) -> (Vec<Result>, Vec<GameRecord>)
+where
+    G: Game + Clone,
+{
+    assert!(!openings.is_empty(), "a tournament needs an opening");
     let mut results = vec![Result::default(); strategies.len()];
+    let mut records = Vec::new();
+    // Swiss standings: a point for winning a pairing or a bye, half for a tie
+    let mut points = vec![0.; strategies.len()];
+    let mut met = FxHashSet::default();
+    let mut byes = vec![false; strategies.len()];
 
-    for _ in 0..rounds {
-        let new_results = round_robin::<G>(strategies, init, verbose);
+    strategies.iter_mut().for_each(|s| s.prepare(&openings[0]));
+    for round in 0..rounds {
+        let (pairings, bye) = format.pairings(strategies.len(), &points, &met, &byes);
+        if let Some(bye) = bye {
+            byes[bye] = true;
+            points[bye] += 1.;
+        }
+        let (new_results, new_records) = play_round::<G>(
+            strategies, &pairings, openings, verbose, round, variety, seed,
+        );
+        let mut scores = FxHashMap::<[usize; 2], f64>::default();
+        for record in &new_records {
+            let [i, j] = record.players;
+            let key = [i.min(j), i.max(j)];
+            met.insert(key);
+            // The score of the first of the pair
+            *scores.entry(key).or_default() += match record.winner {
+                None => 0.,
+                Some(winner) if winner == key[0] => 1.,
+                Some(_) => -1.,
+            };
+        }
+        for ([i, j], score) in scores {
+            let score = (score.signum() + 1.) / 2.;
+            points[i] += score;
+            points[j] += 1. - score;
+        }
+        records.extend(new_records);
         for (index, result) in new_results.iter().enumerate() {
             results[index] += *result;
         }
 
         verbose.verbose().then(|| {
-            println!("{:=<63}", "");
+            println!("{:=<95}", "");
             println!(
-                "{0:^25} | {1:^10} | {2:^10} | {3:^4}",
-                "match", "won", "lost", "draw"
+                "{0:^25} | {1:^10} | {2:^10} | {3:^4} | {4:^9} | {5:^7} | {6:^5}",
+                "match", "won", "lost", "draw", "nodes/mv", "ms/mv", "swing"
             );
-            println!("{:-<59}", "");
+            println!("{:-<95}", "");
 
             let mut copy = results.iter().enumerate().collect::<Vec<_>>();
             copy.sort_unstable_by_key(|x| (-(x.1.wins as i64), x.1.losses, x.1.draws));
@@ -400,18 +1277,54 @@ where
                 let total = results[index].wins + results[index].losses + results[index].draws;
                 let win_pct = 100. * results[index].wins as f64 / total as f64;
                 let loss_pct = 100. * results[index].losses as f64 / total as f64;
+                let stats = &results[index].stats;
                 println!(
-                    "{0:<25} | {1:>4} ({win_pct:2.0}%) | {2:>4} ({loss_pct:2.0}%) | {3:<4}",
+                    "{0:<25} | {1:>4} ({win_pct:2.0}%) | {2:>4} ({loss_pct:2.0}%) | {3:<4} | {4:>9.0} | {5:>7.1} | {6:>5.2}",
                     strategies[index].friendly_name(),
                     results[index].wins,
                     results[index].losses,
                     results[index].draws,
+                    stats.nodes_per_move(),
+                    stats.ms_per_move(),
+                    stats.average_swing(),
                 );
             }
+
+            let balance = ColorBalance::from_records(&records);
+            println!("{:-<95}", "");
+            println!(
+                "first player: {} won, {} lost, {} drawn, advantage {:+.1}% ({:+.0} elo)",
+                balance.first_wins,
+                balance.second_wins,
+                balance.draws,
+                100. * balance.first_move_advantage(),
+                balance.first_move_elo(),
+            );
+            println!("{:-<95}", "");
+            let names = strategies.iter().map(|s| s.friendly_name()).collect();
+            print!("{}", Ratings::from_records(names, &records));
         });
     }
 
-    results
+    debug_assert!(ColorBalance::from_records(&records).is_balanced());
+    (results, records)
+}
+
+/// Every position of a line of play from `state`, each headed by the move
+/// leading to it and with the cells it changed marked.
+pub fn replay_string<G: Game>(state: &G::S, actions: &[G::A], style: DiffStyle) -> String {
+    let mut state = state.clone();
+    let mut out = format!("[0] start:\n{state}");
+    for (i, action) in (1..).zip(actions) {
+        let notation = G::notation(&state, action);
+        let previous = state.clone();
+        state = G::apply(state, action);
+        out.push_str(&format!(
+            "[{i}] {notation}:\n{}",
+            StateDiff::new(Some(&previous), &state, style)
+        ));
+    }
+    out
 }
 
 pub(super) fn pv_string<G: Game>(path: &[G::A], state: &G::S) -> String {
@@ -426,33 +1339,3 @@ pub(super) fn pv_string<G: Game>(path: &[G::A], state: &G::S) -> String {
     }
     out
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_reverse_pairs() {
-        let stack = vec![1, 2, 3, 4, 5];
-        let mut reverse_pairs = ReversePairs::new(&stack);
-
-        assert_eq!(reverse_pairs.next(), Some((&4, &5)));
-        assert_eq!(reverse_pairs.next(), Some((&3, &4)));
-        assert_eq!(reverse_pairs.next(), Some((&2, &3)));
-        assert_eq!(reverse_pairs.next(), Some((&1, &2)));
-        assert_eq!(reverse_pairs.next(), None);
-    }
-
-    #[test]
-    fn test_first_element_iter() {
-        let stack = vec![1, 2, 3, 4, 5];
-        let mut first_element_iter = ReversePairs2::new(&stack);
-
-        assert_eq!(first_element_iter.next(), Some((Some(&4), &5)));
-        assert_eq!(first_element_iter.next(), Some((Some(&3), &4)));
-        assert_eq!(first_element_iter.next(), Some((Some(&2), &3)));
-        assert_eq!(first_element_iter.next(), Some((Some(&1), &2)));
-        assert_eq!(first_element_iter.next(), Some((None, &1)));
-        assert_eq!(first_element_iter.next(), None);
-    }
-}